@@ -0,0 +1,98 @@
+//! Connection to a Sova server, sending one request at a time.
+
+use std::{collections::VecDeque, time::Duration};
+
+use sova_core::{
+    Scene,
+    server::{
+        ServerMessage,
+        auth::Secret,
+        client::{ClientMessage, SovaClient},
+    },
+};
+use tokio::time;
+
+/// Time given to the server to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Connection {
+    client: SovaClient,
+    /// Messages received while waiting for the answer to a request
+    pending: VecDeque<ServerMessage>,
+    /// Languages available on the server
+    pub languages: Vec<String>,
+    /// Scene of the server when logging in
+    pub scene: Scene,
+}
+
+impl Connection {
+    /// Connects and logs in to a server at `HOST:PORT`.
+    pub async fn open(address: &str, name: String, token: Option<String>) -> Result<Self, String> {
+        let (ip, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Invalid address '{address}' (HOST:PORT)"))?;
+        let port: u16 = port.parse().map_err(|_| format!("Invalid port '{port}'"))?;
+        let mut client = SovaClient::new(ip.to_owned(), port);
+        let hello = async {
+            client.connect().await?;
+            client.send(ClientMessage::Login(name, token.map(Secret::from))).await?;
+            client.read().await
+        };
+        match hello
+            .await
+            .map_err(|e| format!("Cannot connect to {address}: {e}"))?
+        {
+            ServerMessage::Hello {
+                scene,
+                available_languages,
+                ..
+            } => Ok(Connection {
+                client,
+                pending: VecDeque::new(),
+                languages: available_languages,
+                scene,
+            }),
+            ServerMessage::ConnectionRefused(reason) => Err(format!("Connection refused: {reason}")),
+            msg => Err(format!("Unexpected answer: {msg:?}")),
+        }
+    }
+
+    /// Next message of the server, or `None` if there is none within the timeout.
+    pub async fn next(&mut self, timeout: Duration) -> Result<Option<ServerMessage>, String> {
+        match self.pending.pop_front() {
+            Some(msg) => Ok(Some(msg)),
+            None => self.receive(timeout).await,
+        }
+    }
+
+    async fn receive(&mut self, timeout: Duration) -> Result<Option<ServerMessage>, String> {
+        // Waiting for data does not consume it, unlike a partial read
+        match time::timeout(timeout, self.client.ready()).await {
+            Err(_) => Ok(None),
+            Ok(false) => Err("Disconnected from the server".to_owned()),
+            Ok(true) => self.client.read().await.map(Some).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Sends a request and waits for the server to accept it. The broadcasts received
+    /// meanwhile are kept for [`Connection::next`].
+    pub async fn request(&mut self, msg: ClientMessage) -> Result<(), String> {
+        self.client.send(msg).await.map_err(|e| e.to_string())?;
+        loop {
+            match self.receive(REQUEST_TIMEOUT).await? {
+                None => return Err("The server did not answer".to_owned()),
+                Some(ServerMessage::Success) => return Ok(()),
+                Some(
+                    ServerMessage::InternalError(e)
+                    | ServerMessage::PermissionDenied(e)
+                    | ServerMessage::ConnectionRefused(e),
+                ) => return Err(e),
+                Some(msg) => self.pending.push_back(msg),
+            }
+        }
+    }
+
+    pub async fn close(mut self) {
+        let _ = self.client.disconnect().await;
+    }
+}
@@ -0,0 +1,92 @@
+//! Layout of the scripts of a scene in a directory: the script of frame M of line N is
+//! stored in `line_N/frame_M.<language>`.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Whether a language can be used as an extension, without leaving the line directory.
+fn is_extension(lang: &str) -> bool {
+    !lang.is_empty()
+        && lang
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Path of the script of a frame, in a scene directory. `None` if the language cannot be
+/// used as an extension.
+pub fn script_path(dir: &Path, line_id: usize, frame_id: usize, lang: &str) -> Option<PathBuf> {
+    if !is_extension(lang) {
+        return None;
+    }
+    Some(
+        dir.join(format!("line_{line_id}"))
+            .join(format!("frame_{frame_id}.{lang}")),
+    )
+}
+
+/// Line, frame and language of a script file, from the last components of its path.
+pub fn script_position(path: &Path) -> Option<(usize, usize, String)> {
+    let line = path.parent()?.file_name()?.to_str()?;
+    let line_id = line.strip_prefix("line_")?.parse().ok()?;
+    let file = path.file_name()?.to_str()?;
+    let (frame, lang) = file.split_once('.')?;
+    let frame_id = frame.strip_prefix("frame_")?.parse().ok()?;
+    if !is_extension(lang) {
+        return None;
+    }
+    Some((line_id, frame_id, lang.to_owned()))
+}
+
+/// Script files of a scene directory.
+pub fn scan(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut scripts = Vec::new();
+    for line in dir.read_dir()? {
+        let line = line?.path();
+        if !line.is_dir() {
+            continue;
+        }
+        for file in line.read_dir()? {
+            let file = file?.path();
+            if file.is_file() && script_position(&file).is_some() {
+                scripts.push(file);
+            }
+        }
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_map_to_positions() {
+        let path = script_path(Path::new("set"), 2, 13, "bali").unwrap();
+        assert_eq!(path, Path::new("set/line_2/frame_13.bali"));
+        assert_eq!(script_position(&path), Some((2, 13, "bali".to_owned())));
+    }
+
+    #[test]
+    fn other_files_are_ignored() {
+        for path in [
+            "line_0/frame_0",
+            "line_0/frame_0.bali.swp",
+            "line_0/.frame_0.bali",
+            "lines/frame_0.bali",
+            "line_x/frame_0.bali",
+            "frame_0.bali",
+        ] {
+            assert_eq!(script_position(Path::new(path)), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn languages_stay_in_their_directory() {
+        for lang in ["", "../../etc/passwd", "bali/x", "bali.swp", "..\\x"] {
+            assert_eq!(script_path(Path::new("set"), 0, 0, lang), None, "{lang}");
+        }
+    }
+}
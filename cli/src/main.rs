@@ -0,0 +1,467 @@
+//! Command line client of Sova servers: sets frame scripts from files, follows a
+//! directory of scripts, controls the transport and dumps the scene.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use sova_core::{
+    compiler::CompilationState,
+    scene::script::Script,
+    schedule::{ActionTiming, SchedulerMessage},
+    server::{ServerMessage, client::ClientMessage},
+};
+
+use crate::connection::Connection;
+
+pub mod connection;
+pub mod layout;
+
+/// Time given to the server to report the compilation of a pushed script.
+const COMPILATION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Environment variable holding the login token, when not given on the command line.
+const TOKEN_VAR: &str = "SOVA_TOKEN";
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Sova command line client")]
+struct Cli {
+    /// Address of the server
+    #[arg(short, long, value_name = "HOST:PORT", default_value = "127.0.0.1:8080")]
+    server: String,
+
+    /// Client name on the server (unique among its clients)
+    #[arg(short, long)]
+    name: Option<String>,
+
+    /// Token to log in to the server with (user token or shared secret). It is visible to
+    /// the other users of the machine: prefer --token-file or the SOVA_TOKEN environment variable.
+    #[arg(short, long)]
+    token: Option<String>,
+
+    /// File holding the token to log in to the server with (on its first line)
+    #[arg(long, value_name = "FILE", conflicts_with = "token")]
+    token_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Set the script of a frame from a file
+    Push {
+        /// Script file, whose position defaults to its path (line_N/frame_M.<language>)
+        file: PathBuf,
+
+        /// Line of the frame
+        #[arg(short, long, requires = "frame")]
+        line: Option<usize>,
+
+        /// Frame in the line
+        #[arg(short, long, requires = "line")]
+        frame: Option<usize>,
+
+        /// Language of the script (defaults to the extension of the file)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// When the script replaces the current one
+        #[arg(long, value_enum, default_value_t = Timing::Now)]
+        at: Timing,
+    },
+    /// Push the scripts of a directory (line_N/frame_M.<language>) whenever they change
+    Watch {
+        /// Scene directory
+        dir: PathBuf,
+
+        /// Also push every script when starting
+        #[arg(long)]
+        sync: bool,
+
+        /// Delay between two scans of the directory, in milliseconds
+        #[arg(long, value_name = "MS", default_value_t = 200)]
+        interval: u64,
+
+        /// When the scripts replace the current ones
+        #[arg(long, value_enum, default_value_t = Timing::Now)]
+        at: Timing,
+    },
+    /// Start the transport
+    Play {
+        /// When the transport starts
+        #[arg(long, value_enum, default_value_t = Timing::Now)]
+        at: Timing,
+    },
+    /// Stop the transport
+    Stop {
+        /// When the transport stops
+        #[arg(long, value_enum, default_value_t = Timing::Now)]
+        at: Timing,
+    },
+    /// Set the tempo
+    Tempo {
+        bpm: f64,
+
+        /// When the tempo changes
+        #[arg(long, value_enum, default_value_t = Timing::Now)]
+        at: Timing,
+    },
+    /// Print the scene as JSON, or write its scripts to a directory
+    Dump {
+        /// Write the scripts to this directory (line_N/frame_M.<language>) instead
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Timing {
+    /// Immediately
+    Now,
+    /// At the next beat
+    Beat,
+    /// At the next phase (bar)
+    Phase,
+    /// When the line of the frame loops (line 0 for the transport and tempo)
+    Line,
+}
+
+impl Timing {
+    fn action_timing(self, line_id: usize) -> ActionTiming {
+        match self {
+            Timing::Now => ActionTiming::Immediate,
+            Timing::Beat => ActionTiming::AtNextBeat,
+            Timing::Phase => ActionTiming::AtNextPhase,
+            Timing::Line => ActionTiming::EndOfLine(line_id),
+        }
+    }
+}
+
+/// Token to log in with, from the command line, a file or the environment.
+fn login_token(cli: &Cli) -> Result<Option<String>, String> {
+    let token = if let Some(path) = &cli.token_file {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read '{}': {e}", path.display()))?;
+        content.lines().next().map(|line| line.trim().to_owned())
+    } else if cli.token.is_some() {
+        cli.token.clone()
+    } else {
+        std::env::var(TOKEN_VAR).ok()
+    };
+    if token.as_deref().is_some_and(str::is_empty) {
+        return Err("The login token is empty.".to_owned());
+    }
+    Ok(token)
+}
+
+/// Line and column (from 1) of a byte offset in a text.
+fn location(text: &str, offset: usize) -> (usize, usize) {
+    let before = text.get(..offset).unwrap_or(text);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
+
+/// Reports the compilation of a pushed script. Returns false if it failed.
+fn report(path: &Path, content: &str, state: &CompilationState) -> bool {
+    match state {
+        CompilationState::Error(error) => {
+            let (line, column) = location(content, error.from);
+            eprintln!("{}:{line}:{column}: {}", path.display(), error.info);
+            false
+        }
+        _ => {
+            println!("{}: ok", path.display());
+            true
+        }
+    }
+}
+
+/// A script pushed to the server, whose compilation is not reported yet.
+struct Pushed {
+    path: PathBuf,
+    content: String,
+    id: u64,
+}
+
+/// Sets the script of a frame from a file.
+async fn push(
+    connection: &mut Connection,
+    path: &Path,
+    position: (usize, usize),
+    lang: String,
+    at: Timing,
+) -> Result<Pushed, String> {
+    if !connection.languages.contains(&lang) {
+        return Err(format!(
+            "{}: unknown language '{lang}' (available: {})",
+            path.display(),
+            connection.languages.join(", ")
+        ));
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read '{}': {e}", path.display()))?;
+    let script = Script::new(content.clone(), lang);
+    let id = script.id();
+    let (line_id, frame_id) = position;
+    connection
+        .request(ClientMessage::SchedulerControl(SchedulerMessage::SetScript(
+            line_id,
+            frame_id,
+            script,
+            at.action_timing(line_id),
+        )))
+        .await
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(Pushed {
+        path: path.to_owned(),
+        content,
+        id,
+    })
+}
+
+/// Compilation state reported by a message for a pushed script, once done.
+fn compilation<'a>(msg: &'a ServerMessage, pushed: &Pushed) -> Option<&'a CompilationState> {
+    match msg {
+        ServerMessage::CompilationUpdate(_, _, id, state)
+            if *id == pushed.id
+                && !matches!(
+                    state,
+                    CompilationState::NotCompiled | CompilationState::Compiling
+                ) =>
+        {
+            Some(state)
+        }
+        _ => None,
+    }
+}
+
+/// Modification dates of the scripts of a directory.
+fn scan(dir: &Path) -> Result<HashMap<PathBuf, SystemTime>, String> {
+    let scripts =
+        layout::scan(dir).map_err(|e| format!("Cannot read '{}': {e}", dir.display()))?;
+    Ok(scripts
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect())
+}
+
+/// Pushes the scripts of a directory whenever they change, until the server disconnects.
+async fn watch(
+    connection: &mut Connection,
+    dir: &Path,
+    sync: bool,
+    interval: Duration,
+    at: Timing,
+) -> Result<(), String> {
+    let mut known = if sync { HashMap::new() } else { scan(dir)? };
+    let mut pushed: Vec<Pushed> = Vec::new();
+    println!("Watching {}", dir.display());
+    loop {
+        let scripts = scan(dir)?;
+        for (path, modified) in &scripts {
+            if known.get(path) == Some(modified) {
+                continue;
+            }
+            let Some((line_id, frame_id, lang)) = layout::script_position(path) else {
+                continue;
+            };
+            match push(connection, path, (line_id, frame_id), lang, at).await {
+                Ok(script) => {
+                    pushed.retain(|p| p.path != script.path);
+                    pushed.push(script);
+                }
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        known = scripts;
+
+        // Reports compilations while waiting for the next scan
+        let deadline = Instant::now() + interval;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            let Some(msg) = connection.next(timeout).await? else {
+                break;
+            };
+            if let Some(i) = pushed.iter().position(|p| compilation(&msg, p).is_some()) {
+                let script = pushed.remove(i);
+                if let Some(state) = compilation(&msg, &script) {
+                    report(&script.path, &script.content, state);
+                }
+            }
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let token = login_token(&cli)?;
+    let name = cli
+        .name
+        .unwrap_or_else(|| format!("sova-cli-{}", std::process::id()));
+    let mut connection = Connection::open(&cli.server, name, token).await?;
+    let result = match cli.command {
+        Command::Push {
+            file,
+            line,
+            frame,
+            lang,
+            at,
+        } => {
+            let detected = layout::script_position(&file);
+            let position = match (line, frame) {
+                (Some(line), Some(frame)) => (line, frame),
+                _ => detected
+                    .as_ref()
+                    .map(|(line, frame, _)| (*line, *frame))
+                    .ok_or_else(|| {
+                        format!(
+                            "Cannot find the frame of '{}': use --line and --frame",
+                            file.display()
+                        )
+                    })?,
+            };
+            let lang = lang
+                .or_else(|| detected.map(|(_, _, lang)| lang))
+                .or_else(|| {
+                    let ext = file.extension()?.to_str()?;
+                    Some(ext.to_owned())
+                })
+                .ok_or_else(|| format!("Unknown language of '{}': use --lang", file.display()))?;
+            let script = push(&mut connection, &file, position, lang, at).await?;
+            let deadline = Instant::now() + COMPILATION_TIMEOUT;
+            let mut compiled = None;
+            while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+                let Some(msg) = connection.next(timeout).await? else {
+                    break;
+                };
+                if let Some(state) = compilation(&msg, &script) {
+                    compiled = Some(report(&script.path, &script.content, state));
+                    break;
+                }
+            }
+            match compiled {
+                Some(true) => Ok(()),
+                Some(false) => Err("Compilation failed".to_owned()),
+                None => Err(format!(
+                    "{}: no compilation reported within {}s",
+                    script.path.display(),
+                    COMPILATION_TIMEOUT.as_secs()
+                )),
+            }
+        }
+        Command::Watch {
+            dir,
+            sync,
+            interval,
+            at,
+        } => {
+            watch(
+                &mut connection,
+                &dir,
+                sync,
+                Duration::from_millis(interval),
+                at,
+            )
+            .await
+        }
+        Command::Play { at } => {
+            connection
+                .request(ClientMessage::TransportStart(at.action_timing(0)))
+                .await
+        }
+        Command::Stop { at } => {
+            connection
+                .request(ClientMessage::TransportStop(at.action_timing(0)))
+                .await
+        }
+        Command::Tempo { bpm, at } => {
+            connection
+                .request(ClientMessage::SetTempo(bpm, at.action_timing(0)))
+                .await
+        }
+        Command::Dump { dir: None } => serde_json::to_string_pretty(&connection.scene)
+            .map(|json| println!("{json}"))
+            .map_err(|e| e.to_string()),
+        Command::Dump { dir: Some(dir) } => dump(&connection, &dir),
+    };
+    connection.close().await;
+    result
+}
+
+/// Writes the scripts of the scene to a directory.
+fn dump(connection: &Connection, dir: &Path) -> Result<(), String> {
+    let scene = &connection.scene;
+    for line_id in 0..scene.n_lines() {
+        let Some(line) = scene.line(line_id) else {
+            continue;
+        };
+        for frame_id in 0..line.n_frames() {
+            let Some(script) = line.frame(frame_id).map(|f| f.script()) else {
+                continue;
+            };
+            if script.content().is_empty() {
+                continue;
+            }
+            let lang = script.lang();
+            let path = connection
+                .languages
+                .iter()
+                .any(|known| known == lang)
+                .then(|| layout::script_path(dir, line_id, frame_id, lang))
+                .flatten();
+            let Some(path) = path else {
+                eprintln!("Frame {frame_id} of line {line_id}: unknown language '{lang}', skipped");
+                continue;
+            };
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Cannot create '{}': {e}", parent.display()))?;
+            }
+            fs::write(&path, script.content())
+                .map_err(|e| format!("Cannot write '{}': {e}", path.display()))?;
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("sova: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_start_at_one() {
+        let text = "(note 60)\n  (é oops)";
+        assert_eq!(location(text, 0), (1, 1));
+        assert_eq!(location(text, text.find("oops").unwrap()), (2, 6));
+    }
+
+    #[test]
+    fn tokens_are_read_from_files() {
+        let path = std::env::temp_dir().join(format!("sova-cli-token-{}", std::process::id()));
+        fs::write(&path, "t0k \nignored").unwrap();
+        let cli = Cli::parse_from(["sova", "--token-file", path.to_str().unwrap(), "play"]);
+        assert_eq!(login_token(&cli), Ok(Some("t0k".to_owned())));
+        fs::write(&path, "\n").unwrap();
+        assert!(login_token(&cli).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(login_token(&cli).is_err());
+        assert!(Cli::try_parse_from(["sova", "-t", "a", "--token-file", "f", "play"]).is_err());
+    }
+}
@@ -11,7 +11,7 @@ use std::{
     process::{Command, Stdio}, sync::Arc,
 };
 
-use crate::vm::Program;
+use crate::vm::{Program, SourceMap};
 
 mod compilation_error;
 pub use compilation_error::CompilationError;
@@ -42,6 +42,18 @@ pub trait Compiler: Send + Sync + std::fmt::Debug {
     /// * `Ok(Program)` if compilation is successful.
     /// * `Err(CompilationError)` if any error occurs during compilation.
     fn compile(&self, text: &str, args: &BTreeMap<String, String>) -> Result<Program, CompilationError>;
+
+    /// Compiles the given source code text into a [`Program`], with the [`SourceMap`] locating
+    /// its instructions in the text.
+    ///
+    /// By default, the instructions are not located.
+    fn compile_with_source_map(
+        &self,
+        text: &str,
+        args: &BTreeMap<String, String>,
+    ) -> Result<(Program, SourceMap), CompilationError> {
+        self.compile(text, args).map(|prog| (prog, SourceMap::default()))
+    }
 }
 
 /// A [`Compiler`] implementation that delegates compilation to an external executable.
@@ -1,15 +1,15 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{compiler::CompilationError, vm::{Program, variable::VariableValue}};
+use crate::{compiler::CompilationError, vm::{Program, SourceMap, variable::VariableValue}};
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub enum CompilationState {
     #[default]
     NotCompiled,
     Compiling,
-    Compiled(#[serde(skip)] Program),
+    Compiled(#[serde(skip)] Program, #[serde(skip)] Arc<SourceMap>),
     Parsed(#[serde(skip)] Option<VariableValue>),
     Error(CompilationError)
 }
@@ -17,14 +17,14 @@ pub enum CompilationState {
 impl CompilationState {
     pub fn is_compiled(&self) -> bool {
         match self {
-            CompilationState::Compiled(_) => true,
+            CompilationState::Compiled(..) => true,
             _ => false
         }
     }
 
     pub fn is_ok(&self) -> bool {
         match self {
-            CompilationState::Compiled(_) | CompilationState::Parsed(_)
+            CompilationState::Compiled(..) | CompilationState::Parsed(_)
                 => true,
             _ => false
         }
@@ -39,7 +39,7 @@ impl CompilationState {
 
     pub fn lightened(&self) -> Self {
         match self {
-            Self::Compiled(_) => Self::Compiled(Default::default()),
+            Self::Compiled(..) => Self::Compiled(Default::default(), Default::default()),
             _ => self.clone()
         }
     }
@@ -54,7 +54,14 @@ impl CompilationState {
 
     pub fn program(&self) -> Option<&Program> {
         match self {
-            CompilationState::Compiled(prog) => Some(prog),
+            CompilationState::Compiled(prog, _) => Some(prog),
+            _ => None
+        }
+    }
+
+    pub fn error(&self) -> Option<&CompilationError> {
+        match self {
+            CompilationState::Error(error) => Some(error),
             _ => None
         }
     }
@@ -72,7 +79,7 @@ impl Display for CompilationState {
         match self {
             CompilationState::NotCompiled => write!(f, "Not compiled"),
             CompilationState::Compiling => write!(f, "Compiling..."),
-            CompilationState::Compiled(_) => write!(f, "Compiled"),
+            CompilationState::Compiled(..) => write!(f, "Compiled"),
             CompilationState::Error(err) => write!(f, "Error: {err}"),
             CompilationState::Parsed(_) => write!(f, "Parsed"),
         }
@@ -24,8 +24,8 @@ use std::{
 
 use crate::{
     clock::{Clock, SyncTime}, vm::event::ConcreteEvent, log_eprintln, log_println, protocol::{
-        DeviceDirection, DeviceInfo, DeviceKind, ProtocolDevice, ProtocolMessage, TimedMessage, audio_engine_proxy::AudioEngineProxy, log::{LOG_NAME, LogMessage, Severity}, midi::{MIDIMessage, MIDIMessageType, MidiIn, MidiInterface, MidiOut}, osc::OSCOut
-    }
+        DeviceDirection, DeviceInfo, DeviceKind, ProtocolDevice, ProtocolMessage, TimedMessage, audio_engine_proxy::AudioEngineProxy, log::{LOG_NAME, LogMessage, LogOut, Severity}, midi::{MIDIMessage, MIDIMessageType, MidiIn, MidiInterface, MidiOut, MpeZone, NoteOverlap}, osc::OSCOut, superdirt::{DirtProfile, SuperDirtOut}, transform::SlotTransform
+    }, theory::{Tuning, TuningOutput}
 };
 
 use midir::{Ignore, MidiInput, MidiOutput};
@@ -47,6 +47,12 @@ pub struct DeviceMap {
     /// Maps user-assigned Slot IDs (1-N) to the system or virtual device name assigned to it.
     /// Slot 0 is implicitly the Log device and is not stored here.
     pub slot_assignments: Mutex<[Option<String> ; MAX_DEVICE_SLOTS]>,
+    /// Transformations applied to the events sent to each slot (1-N), if any.
+    pub slot_transforms: Mutex<BTreeMap<usize, SlotTransform>>,
+    /// Tuning of the scene, realized by each output device.
+    pub tuning: Mutex<Option<Tuning>>,
+    /// Tuning outputs chosen for devices, keyed by device name. Other devices use their default.
+    pub tuning_outputs: Mutex<BTreeMap<String, TuningOutput>>,
     /// Log device
     pub log_device: Arc<ProtocolDevice>,
     /// Optional handle to the system's MIDI input interface, managed by `midir`.
@@ -89,7 +95,10 @@ impl DeviceMap {
             input_connections: Default::default(),
             output_connections: Default::default(),
             slot_assignments: Default::default(),
-            log_device: Arc::new(ProtocolDevice::Log),
+            slot_transforms: Default::default(),
+            tuning: Default::default(),
+            tuning_outputs: Default::default(),
+            log_device: Arc::new(ProtocolDevice::Log(LogOut::default())),
             midi_in,
             midi_out,
             missing_devices: Default::default(),
@@ -243,10 +252,14 @@ impl DeviceMap {
             })
     }
 
-    fn map_event_to_device(device: &Arc<ProtocolDevice>, event: ConcreteEvent, date: SyncTime, clock: &Clock) 
-        -> Vec<TimedMessage>
-    {
-        let timed = device.translate_event(event, date, clock);
+    fn map_event_to_device(
+        device: &Arc<ProtocolDevice>,
+        event: ConcreteEvent,
+        date: SyncTime,
+        clock: &Clock,
+        tuning: Option<(&Tuning, TuningOutput)>,
+    ) -> Vec<TimedMessage> {
+        let timed = device.translate_event(event, date, clock, tuning);
         timed.into_iter().map(|(payload, time)| {
             ProtocolMessage {
                 device: Arc::clone(device),
@@ -292,7 +305,7 @@ impl DeviceMap {
         // Handle Log Device implicitly first
         if target_device_name == LOG_NAME {
             // generate_log_message now stores the event.
-            return Self::map_event_to_device(&self.log_device, event, date, clock);
+            return Self::map_event_to_device(&self.log_device, event, date, clock, None);
         }
 
         // Look up the device in connected outputs
@@ -317,7 +330,11 @@ impl DeviceMap {
             ];
         };
 
-        Self::map_event_to_device(&device, event, date, clock)
+        let tuning = self.tuning.lock().unwrap();
+        let tuning = tuning
+            .as_ref()
+            .map(|tuning| (tuning, self.tuning_output(target_device_name, &device)));
+        Self::map_event_to_device(&device, event, date, clock, tuning)
     }
 
     /// Maps a `ConcreteEvent` to `TimedMessage`s for a target device specified by its `target_slot_id`.
@@ -349,8 +366,12 @@ impl DeviceMap {
         clock: &Clock, // Pass clock through
     ) -> Vec<TimedMessage> {
         if target_slot_id == 0 {
-            return Self::map_event_to_device(&self.log_device, event, date, clock);
+            return Self::map_event_to_device(&self.log_device, event, date, clock, None);
         } else {
+            let event = match self.slot_transforms.lock().unwrap().get(&target_slot_id) {
+                Some(transform) => transform.apply(event),
+                None => event,
+            };
             // Look up the device name assigned to the slot ID (1-N)
             match self.get_name_for_slot(target_slot_id) {
                 Some(device_name) => {
@@ -376,6 +397,35 @@ impl DeviceMap {
         }
     }
 
+    pub fn get_slot_transform(&self, slot_id: usize) -> Option<SlotTransform> {
+        self.slot_transforms.lock().unwrap().get(&slot_id).cloned()
+    }
+
+    /// Sets (or removes, with None) the transformations applied to the events sent to a slot.
+    /// The transform belongs to the slot, so it stays in place when the slot is reassigned.
+    pub fn set_slot_transform(
+        &self,
+        slot_id: usize,
+        transform: Option<SlotTransform>,
+    ) -> Result<(), String> {
+        if slot_id == 0 || slot_id > MAX_DEVICE_SLOTS {
+            return Err(format!(
+                "Invalid slot ID: {}. Must be between 1 and {}.",
+                slot_id, MAX_DEVICE_SLOTS
+            ));
+        }
+        let mut transforms = self.slot_transforms.lock().unwrap();
+        match transform {
+            Some(transform) if !transform.is_identity() => {
+                transforms.insert(slot_id, transform);
+            }
+            _ => {
+                transforms.remove(&slot_id);
+            }
+        }
+        Ok(())
+    }
+
     pub fn map_event(
         &self,
         event: ConcreteEvent,
@@ -414,6 +464,7 @@ impl DeviceMap {
                                   device_ref_opt: Option<&ProtocolDevice>|
          -> DeviceInfo {
             let assigned_slot_id = self.get_slot_for_name(&name);
+            let transform = assigned_slot_id.and_then(|slot_id| self.get_slot_transform(slot_id));
 
             // Determine connection status based on presence in connected_map for outputs
             // For system ports discovered but not explicitly connected via Sova, this might show false.
@@ -424,6 +475,10 @@ impl DeviceMap {
                 Some(d) => Some(d.address()),
                 _ => None,
             };
+            let mpe = device_ref_opt.and_then(ProtocolDevice::mpe_zone);
+            let overlap = device_ref_opt.and_then(ProtocolDevice::note_overlap);
+            let tuning_output = self.tuning_outputs.lock().unwrap().get(&name).copied();
+            let dirt = device_ref_opt.and_then(ProtocolDevice::dirt_profile);
 
             DeviceInfo {
                 slot_id: assigned_slot_id,
@@ -432,6 +487,11 @@ impl DeviceMap {
                 direction,
                 is_connected,
                 address,
+                mpe,
+                overlap,
+                transform,
+                tuning_output,
+                dirt,
             }
         };
 
@@ -475,7 +535,7 @@ impl DeviceMap {
             // Determine kind and get device reference
             let kind = device_arc.kind();
 
-            if kind == DeviceKind::Midi || kind == DeviceKind::Osc {
+            if matches!(kind, DeviceKind::Midi | DeviceKind::Osc | DeviceKind::SuperDirt) {
                 // Insert or update the entry using create_device_info with the device reference
                 discovered_devices_map.insert(
                     name.clone(),
@@ -495,6 +555,13 @@ impl DeviceMap {
                     direction: DeviceDirection::Output,
                     is_connected: false,
                     address: None,
+                    mpe: None,
+                    overlap: None,
+                    tuning_output: None,
+                    dirt: None,
+                    transform: self
+                        .get_slot_for_name(missing_name)
+                        .and_then(|slot_id| self.get_slot_transform(slot_id)),
                 });
             }
         }
@@ -757,7 +824,57 @@ impl DeviceMap {
             "[✨] Creating OSC Output device: '{}' @ {}:{}",
             name, ip_str, port
         );
+        let osc_device = self.connect_osc_out(name, ip_str, port)?;
+        self.register_output_connection(name.to_string(), ProtocolDevice::OSCOutDevice(osc_device));
+        log_println!("[✅] Registered OSC Output device: '{}'", name);
+        Ok(())
+    }
+
+    /// Creates and registers a new SuperDirt output targeting a specific IP address and port
+    /// (SuperDirt listens on 127.0.0.1:57120 by default), with the default orbits and parameters.
+    ///
+    /// # Returns
+    /// - `Ok(())` on successful creation, connection (socket binding), and registration.
+    /// - `Err(String)` in the same cases as `create_osc_output_device`.
+    pub fn create_superdirt_device(
+        &self,
+        name: &str,
+        ip_str: &str,
+        port: u16,
+    ) -> Result<(), String> {
+        log_println!(
+            "[✨] Creating SuperDirt device: '{}' @ {}:{}",
+            name, ip_str, port
+        );
+        let osc_device = self.connect_osc_out(name, ip_str, port)?;
+        self.register_output_connection(
+            name.to_string(),
+            ProtocolDevice::SuperDirtDevice(SuperDirtOut::new(osc_device)),
+        );
+        log_println!("[✅] Registered SuperDirt device: '{}'", name);
+        Ok(())
+    }
 
+    /// Sets the orbits and default parameters of a connected SuperDirt output.
+    pub fn set_device_dirt_profile(&self, device_name: &str, profile: DirtProfile) -> Result<(), String> {
+        let device = self
+            .output_connections
+            .lock()
+            .unwrap()
+            .get(device_name)
+            .map(Arc::clone)
+            .ok_or_else(|| format!("Device '{}' not found or not connected.", device_name))?;
+        match &*device {
+            ProtocolDevice::SuperDirtDevice(dirt_out) => {
+                dirt_out.set_profile(profile).map_err(|e| e.to_string())
+            }
+            _ => Err(format!("Device '{}' is not a SuperDirt output.", device_name)),
+        }
+    }
+
+    /// Checks that an OSC output can be created with this name and target address,
+    /// then binds its local UDP socket.
+    fn connect_osc_out(&self, name: &str, ip_str: &str, port: u16) -> Result<OSCOut, String> {
         // Parse target IP and create SocketAddr
         let target_ip_addr = IpAddr::from_str(ip_str)
             .map_err(|e| format!("Invalid IP address format '{}': {}", ip_str, e))?;
@@ -774,22 +891,21 @@ impl DeviceMap {
                     log_eprintln!("[!] {}", err_msg);
                     return Err(err_msg);
                 }
-                // Check specifically for OSC address collision
-                if let ProtocolDevice::OSCOutDevice(osc_out) = &**device_arc
+                // Check specifically for OSC address collision (OSC and SuperDirt outputs)
+                if let Some(osc_out) = device_arc.osc_out()
+                    && osc_out.address == target_socket_addr
                 {
-                    if osc_out.address == target_socket_addr {
-                        let err_msg = format!(
-                            "Cannot create OSC device '{}': Another OSC device already targets address '{}'.",
-                            name, target_socket_addr
-                        );
-                        log_eprintln!("[!] {}", err_msg);
-                        return Err(err_msg);
-                    }
+                    let err_msg = format!(
+                        "Cannot create OSC device '{}': Another OSC device already targets address '{}'.",
+                        name, target_socket_addr
+                    );
+                    log_eprintln!("[!] {}", err_msg);
+                    return Err(err_msg);
                 }
             }
         } // Lock released here
 
-        // Create the OSCOutDevice instance
+        // Create the OSCOut instance
         let mut osc_device = OSCOut {
             name: name.to_string(),
             address: target_socket_addr,
@@ -804,10 +920,7 @@ impl DeviceMap {
                     "[✅] OSC Output device '{}' socket created successfully.",
                     name
                 );
-                // Register the now-connected device
-                self.register_output_connection(name.to_string(), ProtocolDevice::OSCOutDevice(osc_device));
-                log_println!("[✅] Registered OSC Output device: '{}'", name);
-                Ok(())
+                Ok(osc_device)
             }
             Err(e) => {
                 let err_msg = format!(
@@ -912,13 +1025,19 @@ impl DeviceMap {
         let output_connections = self.output_connections.lock().unwrap();
 
         output_connections.iter().filter_map(|(name, device_arc)| {
+            let slot_id = self.get_slot_for_name(name);
             Some(DeviceInfo {
-                slot_id: self.get_slot_for_name(name),
+                slot_id,
                 name: name.clone(),
                 kind: device_arc.kind(),
                 direction: DeviceDirection::Output,
                 is_connected: true,
                 address: Some(device_arc.address()),
+                mpe: device_arc.mpe_zone(),
+                overlap: device_arc.note_overlap(),
+                tuning_output: self.tuning_outputs.lock().unwrap().get(name).copied(),
+                transform: slot_id.and_then(|slot_id| self.get_slot_transform(slot_id)),
+                dirt: device_arc.dirt_profile(),
             })
         }).collect()
     }
@@ -928,6 +1047,7 @@ impl DeviceMap {
     /// - Clears existing virtual MIDI and OSC devices (physical devices are left alone)
     /// - Recreates virtual MIDI devices (kind = VirtualMidi)
     /// - Recreates OSC devices (kind = Osc, parses ip:port from address)
+    /// - Recreates SuperDirt devices (kind = SuperDirt) and their orbits and default parameters
     /// - Attempts to connect physical MIDI devices if present on system (kind = Midi)
     /// - Restores slot assignments from device.slot_id
     ///
@@ -960,6 +1080,7 @@ impl DeviceMap {
                     match &**device_arc {
                         ProtocolDevice::VirtualMIDIOutDevice(_) => Some(name.clone()),
                         ProtocolDevice::OSCOutDevice(_) => Some(name.clone()),
+                        ProtocolDevice::SuperDirtDevice(_) => Some(name.clone()),
                         _ => None,
                     }
                 })
@@ -976,6 +1097,8 @@ impl DeviceMap {
             for slot in assignments.iter_mut() {
                 *slot = None;
             }
+            self.slot_transforms.lock().unwrap().clear();
+            self.tuning_outputs.lock().unwrap().clear();
         }
 
         // Recreate devices
@@ -999,6 +1122,17 @@ impl DeviceMap {
                         missing.push(device.name.clone());
                     }
                 }
+                DeviceKind::SuperDirt => {
+                    if let Some((ip, port)) = device.address.as_ref().and_then(|a| parse_socket_addr(a)) {
+                        if let Err(e) = self.create_superdirt_device(&device.name, &ip, port) {
+                            log_eprintln!("[!] Failed to restore SuperDirt device '{}': {}", device.name, e);
+                            missing.push(device.name.clone());
+                        }
+                    } else {
+                        log_eprintln!("[!] Invalid SuperDirt address for '{}': {:?}", device.name, device.address);
+                        missing.push(device.name.clone());
+                    }
+                }
                 DeviceKind::Midi => {
                     // Physical MIDI - check if available on system
                     if system_midi_ports.contains(&device.name) {
@@ -1019,17 +1153,122 @@ impl DeviceMap {
                 _ => {} // Skip Log, AudioEngine, Other
             }
 
+            if device.mpe.is_some()
+                && let Err(e) = self.set_device_mpe(&device.name, device.mpe)
+            {
+                log_eprintln!("[!] Failed to restore MPE mode of '{}': {}", device.name, e);
+            }
+            if let Some(policy) = device.overlap
+                && let Err(e) = self.set_device_overlap(&device.name, policy)
+            {
+                log_eprintln!("[!] Failed to restore note overlap of '{}': {}", device.name, e);
+            }
+            if let Some(profile) = device.dirt
+                && let Err(e) = self.set_device_dirt_profile(&device.name, profile)
+            {
+                log_eprintln!("[!] Failed to restore SuperDirt profile of '{}': {}", device.name, e);
+            }
+            if device.tuning_output.is_some()
+                && let Err(e) = self.set_device_tuning_output(&device.name, device.tuning_output)
+            {
+                log_eprintln!("[!] Failed to restore tuning output of '{}': {}", device.name, e);
+            }
+
             // Restore slot assignment
             if let Some(slot_id) = device.slot_id {
                 if let Err(e) = self.assign_slot(slot_id, &device.name) {
                     log_eprintln!("[!] Failed to restore slot {} assignment: {}", slot_id, e);
                 }
+                if let Err(e) = self.set_slot_transform(slot_id, device.transform) {
+                    log_eprintln!("[!] Failed to restore slot {} transform: {}", slot_id, e);
+                }
             }
         }
 
         missing
     }
 
+    /// Enables (with the given zone) or disables MPE mode on a connected MIDI output.
+    pub fn set_device_mpe(&self, device_name: &str, zone: Option<MpeZone>) -> Result<(), String> {
+        self.with_midi_output(device_name, |midi_out| {
+            midi_out.set_mpe(zone).map_err(|e| e.to_string())
+        })
+    }
+
+    /// Sets the overlapping notes policy of a connected MIDI output.
+    pub fn set_device_overlap(&self, device_name: &str, policy: NoteOverlap) -> Result<(), String> {
+        self.with_midi_output(device_name, |midi_out| {
+            midi_out.set_note_overlap(policy);
+            Ok(())
+        })
+    }
+
+    /// Sets the tuning of the scene, applied to every output device.
+    pub fn set_tuning(&self, tuning: Option<Tuning>) {
+        *self.tuning.lock().unwrap() = tuning;
+    }
+
+    /// Tuning output of a device: the one chosen for it, or its default.
+    fn tuning_output(&self, device_name: &str, device: &ProtocolDevice) -> TuningOutput {
+        self.tuning_outputs
+            .lock()
+            .unwrap()
+            .get(device_name)
+            .copied()
+            .unwrap_or(device.tuning_outputs()[0])
+    }
+
+    /// Chooses how a connected output device realizes the tuning of the scene
+    /// (None for its default).
+    pub fn set_device_tuning_output(
+        &self,
+        device_name: &str,
+        output: Option<TuningOutput>,
+    ) -> Result<(), String> {
+        let device = self
+            .output_connections
+            .lock()
+            .unwrap()
+            .get(device_name)
+            .map(Arc::clone)
+            .ok_or_else(|| format!("Device '{}' not found or not connected.", device_name))?;
+        let mut outputs = self.tuning_outputs.lock().unwrap();
+        match output {
+            Some(output) if device.tuning_outputs().contains(&output) => {
+                outputs.insert(device_name.to_owned(), output);
+            }
+            Some(output) => {
+                return Err(format!(
+                    "Device '{}' does not support the {:?} tuning output.",
+                    device_name, output
+                ));
+            }
+            None => {
+                outputs.remove(device_name);
+            }
+        }
+        Ok(())
+    }
+
+    fn with_midi_output<T>(
+        &self,
+        device_name: &str,
+        f: impl FnOnce(&MidiOut) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let device = self
+            .output_connections
+            .lock()
+            .unwrap()
+            .get(device_name)
+            .map(Arc::clone)
+            .ok_or_else(|| format!("Device '{}' not found or not connected.", device_name))?;
+        match &*device {
+            ProtocolDevice::MIDIOutDevice(midi_out)
+            | ProtocolDevice::VirtualMIDIOutDevice(midi_out) => f(midi_out),
+            _ => Err(format!("Device '{}' is not a MIDI output.", device_name)),
+        }
+    }
+
     /// Sends the MIDI "All Notes Off" message (Control Change 123, Value 0)
     /// to all connected MIDI output devices (physical and virtual) on all 16 channels.
     ///
@@ -1,5 +1,6 @@
 pub mod bali;
 pub mod boinx;
+pub mod format;
 pub mod imp;
 // pub mod lua;
 // pub mod rhai;
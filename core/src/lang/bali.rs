@@ -1,8 +1,127 @@
 // BaLi, Basically a Lisp
+use std::sync::LazyLock;
+
 use lalrpop_util::lalrpop_mod;
 lalrpop_mod!(bali_grammar, "/lang/bali/bali_grammar.rs");
 
 pub mod bali_ast;
 mod bali_compiler;
+mod bali_printer;
+
+pub use bali_compiler::{BaliCompiler, parse_bali};
+pub use bali_printer::{format_bali, print_program};
+
+/// Grammar of the compiler, from which the forms are read.
+const BALI_GRAMMAR: &str = include_str!("bali/bali_grammar.lalrpop");
+
+/// Documentation of the Bali forms: (name, signature, description).
+const BALI_DOCS: &[(&str, &str, &str)] = &[
+    ("def", "(def name value)", "Defines a variable."),
+    ("fun", "(fun name args... effects... value)", "Declares a function."),
+    ("note", "(note n [context])", "Plays a MIDI note."),
+    ("prog", "(prog n [context])", "Sends a MIDI program change."),
+    ("control", "(control cc value [context])", "Sends a MIDI control change."),
+    ("at", "(at note value [context])", "Sends a MIDI polyphonic aftertouch."),
+    ("chanpress", "(chanpress value [context])", "Sends a MIDI channel pressure."),
+    ("bend", "(bend value [context])", "Sends a MIDI pitch bend, from 0 to 16383 (8192 being the center)."),
+    ("cc14", "(cc14 cc value [context])", "Sends a 14-bit MIDI control change (control 0 to 31)."),
+    ("nrpn", "(nrpn parameter value [context])", "Sends a MIDI NRPN message."),
+    ("rpn", "(rpn parameter value [context])", "Sends a MIDI RPN message."),
+    ("note-bend", "(note-bend note value [context])", "Sends a per-note pitch bend, in MPE mode."),
+    ("note-press", "(note-press note value [context])", "Sends a per-note pressure, in MPE mode."),
+    ("note-timbre", "(note-timbre note value [context])", "Sends a per-note timbre (CC 74), in MPE mode."),
+    ("ramp-cc", "(ramp-cc cc from to [curve] [res:ms] [context])", "Ramps a MIDI control over the duration."),
+    ("ramp-bend", "(ramp-bend from to [curve] [res:ms] [context])", "Ramps the MIDI pitch bend over the duration."),
+    ("ramp-osc", "(ramp-osc \"/address\" from to [curve] [res:ms] [context])", "Ramps an OSC value over the duration."),
+    ("osc", "(osc \"/address\" args... [context])", "Sends an OSC message."),
+    ("dirt", "(dirt \"sound\" :param value... [context])", "Plays a SuperDirt sound."),
+    ("seed", "(seed value)", "Reseeds the random stream of the frame."),
+    ("seq", "(seq [context] effects...)", "Runs effects one after the other."),
+    ("for", "(for condition [context] effects...)", "Runs effects while the condition holds."),
+    ("if", "(if condition [context] effects...)", "Runs effects if the condition holds."),
+    ("loop", "(loop n [timing] [:neg :rev :step sh:n] [context] statements...)", "Repeats statements n times over the frame (or the timing)."),
+    ("eucloop", "(eucloop beats steps [timing] [:neg :rev :step sh:n] [context] statements...)", "Repeats statements following a euclidean rhythm."),
+    ("binloop", "(binloop n steps [timing] [:neg :rev :step sh:n] [context] statements...)", "Repeats statements following the binary digits of n."),
+    ("ramp", "(ramp name steps min max distribution [timing] [context] statements...)", "Repeats statements with a variable going from min to max."),
+    ("spread", "(spread [timing] [context] statements...)", "Spreads statements evenly over the frame (or the timing)."),
+    ("pick", "(pick index [context] statements...)", "Runs the statement at the index."),
+    ("alt", "(alt [context] statements...)", "Runs one statement per frame execution, in turn."),
+    ("with", "(with [context] statements...)", "Runs statements together, with a context (dev:, ch:, v:, dur:)."),
+    ("rand", "(rand [min] max)", "A random number between min (0 by default) and max."),
+    ("scale", "(scale value old-min old-max new-min new-max)", "Maps a value from a range to another."),
+    ("clamp", "(clamp value min max)", "Clamps a value into a range."),
+    ("min", "(min a b)", "The smallest of two values."),
+    ("max", "(max a b)", "The largest of two values."),
+    ("quantize", "(quantize value step)", "Rounds a value to a multiple of the step."),
+    ("degree", "(degree scale root degree)", "The note at a degree of a scale."),
+    ("snap", "(snap scale root note)", "The closest note of a scale."),
+    ("dchord", "(dchord scale root degree voices)", "The chord of stacked thirds on a degree of a scale."),
+    ("chord", "(chord name root)", "The notes of a chord built on a root."),
+    ("invert", "(invert chord n)", "A chord inverted n times (down if n is negative)."),
+    ("voicelead", "(voicelead from to)", "The voicing of a chord closest to the previous one."),
+    ("arp", "(arp chord pattern octaves)", "The notes of a chord in the order of an arpeggio (up, down, updown...)."),
+    ("nth", "(nth vec index)", "The element at an index of a vec, wrapping around."),
+    ("sine", "(sine speed)", "A sine oscillator, between 0 and 1."),
+    ("saw", "(saw speed)", "A sawtooth oscillator, between 0 and 1."),
+    ("isaw", "(isaw speed)", "An inverted sawtooth oscillator, between 0 and 1."),
+    ("triangle", "(triangle speed)", "A triangle oscillator, between 0 and 1."),
+    ("randstep", "(randstep speed)", "A random value held for each step."),
+    ("lfo", "(lfo name)", "The current value of a scene modulator."),
+    ("ccin", "(ccin cc [context])", "The last value received for a MIDI control."),
+    ("and", "(and a b)", "Holds if both conditions hold."),
+    ("or", "(or a b)", "Holds if one of the conditions holds."),
+    ("not", "(not a)", "Holds if the condition does not hold."),
+    ("lt", "(lt a b)", "Holds if a is lower than b."),
+    ("leq", "(leq a b)", "Holds if a is lower than or equal to b."),
+    ("gt", "(gt a b)", "Holds if a is greater than b."),
+    ("geq", "(geq a b)", "Holds if a is greater than or equal to b."),
+];
+
+static BALI_FORMS: LazyLock<Vec<(&str, &str, &str)>> = LazyLock::new(|| {
+    let mut forms: Vec<(&str, &str, &str)> = Vec::new();
+    for name in grammar_forms() {
+        if forms.iter().all(|(known, _, _)| *known != name) {
+            let doc = BALI_DOCS.iter().find(|(documented, _, _)| *documented == name);
+            forms.push(doc.copied().unwrap_or((name, "", "")));
+        }
+    }
+    forms
+});
+
+/// Names of the forms in the grammar, tokens such as `"(note"`.
+fn grammar_forms() -> impl Iterator<Item = &'static str> {
+    BALI_GRAMMAR.split("\"(").skip(1).filter_map(|token| {
+        let name = &token[..token.find('"')?];
+        let is_name = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        is_name.then_some(name)
+    })
+}
+
+/// Bali forms accepted by the compiler (signature and description), for completion and
+/// documentation.
+pub fn bali_forms() -> &'static [(&'static str, &'static str, &'static str)] {
+    &BALI_FORMS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-pub use bali_compiler::BaliCompiler;
+    #[test]
+    fn every_form_is_documented() {
+        for (name, signature, description) in bali_forms() {
+            assert!(
+                !signature.is_empty() && !description.is_empty(),
+                "Form '{name}' is not documented"
+            );
+        }
+        for (name, _, _) in BALI_DOCS {
+            assert!(
+                bali_forms().iter().any(|(form, _, _)| form == name),
+                "Form '{name}' is documented but not in the grammar"
+            );
+        }
+        assert!(bali_forms().iter().any(|(name, _, _)| *name == "note-bend"));
+    }
+}
@@ -4,7 +4,7 @@ use crate::lang::bali::bali_ast::constants::{
 };
 use crate::log_println;
 use crate::vm::{
-    EnvironmentFunc, Instruction, Program,
+    EnvironmentFunc, Instruction,
     control_asm::{ControlASM, DEFAULT_CHAN, DEFAULT_DEVICE},
     event::Event,
     variable::Variable,
@@ -19,6 +19,7 @@ pub mod abstract_statement;
 pub mod args;
 pub mod bali_context;
 pub mod boolean;
+pub mod code;
 pub mod concrete_fraction;
 pub mod constants;
 pub mod effect;
@@ -35,6 +36,7 @@ pub mod variable_generators;
 
 pub use bali_context::BaliContext;
 pub use boolean::BooleanExpression;
+pub use code::Code;
 pub use concrete_fraction::ConcreteFraction;
 pub use effect::Effect;
 pub use expression::Expression;
@@ -50,8 +52,8 @@ pub use variable_generators::{
     AltVariableGenerator, ChoiceVariableGenerator, LocalChoiceVariableGenerator,
 };
 
-pub fn bali_as_asm(prog: BaliProgram) -> Result<Program, String> {
-    let mut res: Program = Vec::new();
+pub fn bali_as_asm(prog: BaliProgram) -> Result<Code, String> {
+    let mut res = Code::default();
 
     if prog.is_empty() {
         return Ok(res);
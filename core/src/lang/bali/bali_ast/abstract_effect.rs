@@ -1,6 +1,7 @@
 use crate::lang::bali::bali_ast::{
-    BaliContext, Effect, TopLevelEffect, args::AbstractArg, args::ConcreteArg,
+    BaliContext, Effect, Expression, TopLevelEffect, args::AbstractArg, args::ConcreteArg,
 };
+use crate::protocol::{midi::NoteExpression, ramp::RampKind};
 
 #[derive(Debug, Clone)]
 pub enum EffectType {
@@ -13,10 +14,17 @@ pub enum EffectType {
 
     Aftertouch,
     ChannelPressure,
+    PitchBend,
+    ControlChange14,
+    Nrpn,
+    Rpn,
+    NoteExpression(NoteExpression),
     For,
     If,
     Pick,
     Choice,
+    Seed,
+    Ramp(RampKind),
 }
 
 pub struct AbstractEffect {
@@ -83,6 +91,43 @@ impl AbstractEffect {
                     Effect::ChannelPressure(concrete_args[0].to_expression(), BaliContext::new()),
                     BaliContext::new(),
                 ),
+                EffectType::PitchBend => TopLevelEffect::Effect(
+                    Effect::PitchBend(concrete_args[0].to_expression(), BaliContext::new()),
+                    BaliContext::new(),
+                ),
+                EffectType::ControlChange14 => TopLevelEffect::Effect(
+                    Effect::ControlChange14(
+                        concrete_args[1].to_expression(),
+                        concrete_args[0].to_expression(),
+                        BaliContext::new(),
+                    ),
+                    BaliContext::new(),
+                ),
+                EffectType::Nrpn => TopLevelEffect::Effect(
+                    Effect::Nrpn(
+                        concrete_args[1].to_expression(),
+                        concrete_args[0].to_expression(),
+                        BaliContext::new(),
+                    ),
+                    BaliContext::new(),
+                ),
+                EffectType::Rpn => TopLevelEffect::Effect(
+                    Effect::Rpn(
+                        concrete_args[1].to_expression(),
+                        concrete_args[0].to_expression(),
+                        BaliContext::new(),
+                    ),
+                    BaliContext::new(),
+                ),
+                EffectType::NoteExpression(expression) => TopLevelEffect::Effect(
+                    Effect::NoteExpression(
+                        expression,
+                        concrete_args[1].to_expression(),
+                        concrete_args[0].to_expression(),
+                        BaliContext::new(),
+                    ),
+                    BaliContext::new(),
+                ),
                 EffectType::Osc => {
                     let mut concrete_args = concrete_args;
                     let addr = concrete_args.pop().unwrap();
@@ -134,6 +179,28 @@ impl AbstractEffect {
                     inside_effects,
                     BaliContext::new(),
                 ),
+                EffectType::Ramp(kind) => {
+                    let param = match &concrete_args[4] {
+                        ConcreteArg::Literal(v) => Box::new(Expression::Value(v.clone())),
+                        arg => arg.to_expression(),
+                    };
+                    TopLevelEffect::Effect(
+                        Effect::Ramp(
+                            kind,
+                            param,
+                            concrete_args[3].to_expression(),
+                            concrete_args[2].to_expression(),
+                            concrete_args[1].to_value(),
+                            concrete_args[0].to_expression(),
+                            BaliContext::new(),
+                        ),
+                        BaliContext::new(),
+                    )
+                }
+                EffectType::Seed => TopLevelEffect::Effect(
+                    Effect::Seed(concrete_args[0].to_expression()),
+                    BaliContext::new(),
+                ),
                 //_ => todo!()
             };
             return effect;
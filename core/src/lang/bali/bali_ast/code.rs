@@ -0,0 +1,78 @@
+use crate::vm::{Instruction, Program, SourceSpan};
+
+/// Instructions generated from a part of a script, with the span of the source text of each,
+/// when known.
+#[derive(Debug, Clone, Default)]
+pub struct Code {
+    instructions: Vec<Instruction>,
+    spans: Vec<Option<SourceSpan>>,
+}
+
+impl Code {
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    pub fn push(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+        self.spans.push(None);
+    }
+
+    /// Attaches a span to the instructions which do not have a (narrower) one yet.
+    pub fn with_span(mut self, span: SourceSpan) -> Self {
+        for instruction_span in self.spans.iter_mut() {
+            instruction_span.get_or_insert(span);
+        }
+        self
+    }
+
+    /// The instructions, without their spans.
+    pub fn into_program(self) -> Program {
+        self.instructions
+    }
+
+    /// The instructions, and their spans.
+    pub fn into_parts(self) -> (Program, Vec<Option<SourceSpan>>) {
+        (self.instructions, self.spans)
+    }
+}
+
+impl From<Vec<Instruction>> for Code {
+    fn from(instructions: Vec<Instruction>) -> Self {
+        let spans = vec![None; instructions.len()];
+        Code {
+            instructions,
+            spans,
+        }
+    }
+}
+
+impl Extend<Instruction> for Code {
+    fn extend<T: IntoIterator<Item = Instruction>>(&mut self, iter: T) {
+        for instruction in iter {
+            self.push(instruction);
+        }
+    }
+}
+
+impl Extend<(Instruction, Option<SourceSpan>)> for Code {
+    fn extend<T: IntoIterator<Item = (Instruction, Option<SourceSpan>)>>(&mut self, iter: T) {
+        for (instruction, span) in iter {
+            self.instructions.push(instruction);
+            self.spans.push(span);
+        }
+    }
+}
+
+impl IntoIterator for Code {
+    type Item = (Instruction, Option<SourceSpan>);
+    type IntoIter = std::iter::Zip<std::vec::IntoIter<Instruction>, std::vec::IntoIter<Option<SourceSpan>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instructions.into_iter().zip(self.spans)
+    }
+}
@@ -7,6 +7,7 @@ use crate::{
         function::FunctionContent,
         value::Value,
     },
+    protocol::{midi::NoteExpression, ramp::RampKind},
     vm::{Instruction, control_asm::ControlASM, event::Event, variable::Variable},
 };
 
@@ -23,6 +24,23 @@ pub enum Effect {
 
     Aftertouch(Box<Expression>, Box<Expression>, BaliContext),
     ChannelPressure(Box<Expression>, BaliContext),
+    PitchBend(Box<Expression>, BaliContext),
+    ControlChange14(Box<Expression>, Box<Expression>, BaliContext),
+    Nrpn(Box<Expression>, Box<Expression>, BaliContext),
+    Rpn(Box<Expression>, Box<Expression>, BaliContext),
+    /// NoteExpression(expression, note, value, context), per note in MPE mode
+    NoteExpression(NoteExpression, Box<Expression>, Box<Expression>, BaliContext),
+    Seed(Box<Expression>),
+    /// Ramp(kind, parameter, from, to, curve, resolution in ms (0 for the default), context)
+    Ramp(
+        RampKind,
+        Box<Expression>,
+        Box<Expression>,
+        Box<Expression>,
+        Value,
+        Box<Expression>,
+        BaliContext,
+    ),
     Nop,
 }
 
@@ -61,25 +79,12 @@ impl Effect {
 
                 res.extend(context.emit_velocity(&velocity_var, DEFAULT_VELOCITY, functions));
                 res.extend(context.emit_channel(&chan_var, functions));
-
-                if let Some(ref d) = context.duration {
-                    res.extend(d.as_asm(functions));
-                } else {
-                    res.extend(
-                        Fraction {
-                            numerator: Box::new(Expression::Value(Value::Number(1))),
-                            denominator: Box::new(Expression::Value(Value::Number(
-                                DEFAULT_DURATION,
-                            ))),
-                        }
-                        .as_asm(functions),
-                    );
-                }
-                res.push(Instruction::Control(ControlASM::Pop(duration_var.clone())));
-                res.push(Instruction::Control(ControlASM::FloatAsFrames(
-                    duration_var.clone(),
-                    duration_time_var.clone(),
-                )));
+                res.extend(Self::emit_duration(
+                    &context,
+                    &duration_var,
+                    &duration_time_var,
+                    functions,
+                ));
 
                 res.extend(context.emit_device(&target_device_id_var, functions));
 
@@ -209,6 +214,74 @@ impl Effect {
                 };
                 res.push(Instruction::Effect(event, 0.0.into()));
             }
+            Effect::PitchBend(value_expr, c) => {
+                let context = c.update(&context);
+                let bend_value_var = Variable::Instance("_bend_value".to_owned());
+
+                res.extend(value_expr.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(bend_value_var.clone())));
+
+                res.extend(context.emit_channel(&chan_var, functions));
+                res.extend(context.emit_device(&target_device_id_var, functions));
+
+                res.push(Instruction::Effect(
+                    Event::MidiPitchBend(
+                        bend_value_var,
+                        chan_var.clone(),
+                        target_device_id_var.clone(),
+                    ),
+                    0.0.into(),
+                ));
+            }
+            Effect::ControlChange14(param_expr, value_expr, c)
+            | Effect::Nrpn(param_expr, value_expr, c)
+            | Effect::Rpn(param_expr, value_expr, c) => {
+                let context = c.update(&context);
+                let param_var = Variable::Instance("_hires_param".to_owned());
+                let hires_value_var = Variable::Instance("_hires_value".to_owned());
+
+                res.extend(param_expr.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(param_var.clone())));
+                res.extend(value_expr.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(hires_value_var.clone())));
+
+                res.extend(context.emit_channel(&chan_var, functions));
+                res.extend(context.emit_device(&target_device_id_var, functions));
+
+                let (chan, device) = (chan_var.clone(), target_device_id_var.clone());
+                let event = match self {
+                    Effect::ControlChange14(..) => {
+                        Event::MidiControl14(param_var, hires_value_var, chan, device)
+                    }
+                    Effect::Nrpn(..) => Event::MidiNrpn(param_var, hires_value_var, chan, device),
+                    _ => Event::MidiRpn(param_var, hires_value_var, chan, device),
+                };
+                res.push(Instruction::Effect(event, 0.0.into()));
+            }
+            Effect::NoteExpression(expression, note_expr, value_expr, c) => {
+                let context = c.update(&context);
+                let expr_note_var = Variable::Instance("_expr_note".to_owned());
+                let expr_value_var = Variable::Instance("_expr_value".to_owned());
+
+                res.extend(note_expr.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(expr_note_var.clone())));
+                res.extend(value_expr.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(expr_value_var.clone())));
+
+                res.extend(context.emit_channel(&chan_var, functions));
+                res.extend(context.emit_device(&target_device_id_var, functions));
+
+                res.push(Instruction::Effect(
+                    Event::MidiNoteExpression(
+                        *expression,
+                        expr_note_var,
+                        expr_value_var,
+                        chan_var.clone(),
+                        target_device_id_var.clone(),
+                    ),
+                    0.0.into(),
+                ));
+            }
             Effect::Aftertouch(note_expr, value_expr, c) => {
                 let context = c.update(&context);
                 let at_note_var = Variable::Instance("_at_note".to_owned());
@@ -232,6 +305,70 @@ impl Effect {
                     0.0.into(),
                 ));
             }
+            Effect::Ramp(kind, param, from, to, curve, resolution, c) => {
+                let context = c.update(&context);
+                let ramp_param_var = Variable::Instance("_ramp_param".to_owned());
+                let ramp_from_var = Variable::Instance("_ramp_from".to_owned());
+                let ramp_to_var = Variable::Instance("_ramp_to".to_owned());
+                let ramp_curve_var = Variable::Instance("_ramp_curve".to_owned());
+                let ramp_res_var = Variable::Instance("_ramp_res".to_owned());
+
+                match param.as_ref() {
+                    Expression::Value(Value::String(s)) => {
+                        res.push(Instruction::Control(ControlASM::Mov(
+                            Variable::Constant(s.clone().into()),
+                            ramp_param_var.clone(),
+                        )));
+                    }
+                    _ => {
+                        res.extend(param.as_asm(functions));
+                        res.push(Instruction::Control(ControlASM::Pop(ramp_param_var.clone())));
+                    }
+                }
+                res.extend(from.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(ramp_from_var.clone())));
+                res.extend(to.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(ramp_to_var.clone())));
+                res.push(curve.as_asm());
+                res.push(Instruction::Control(ControlASM::Pop(ramp_curve_var.clone())));
+                res.extend(resolution.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(ramp_res_var.clone())));
+                res.push(Instruction::Control(ControlASM::Mul(
+                    ramp_res_var.clone(),
+                    1000.into(),
+                    ramp_res_var.clone(),
+                )));
+
+                res.extend(context.emit_channel(&chan_var, functions));
+                res.extend(Self::emit_duration(
+                    &context,
+                    &duration_var,
+                    &duration_time_var,
+                    functions,
+                ));
+                res.extend(context.emit_device(&target_device_id_var, functions));
+
+                res.push(Instruction::Effect(
+                    Event::Ramp {
+                        kind: *kind,
+                        param: ramp_param_var,
+                        from: ramp_from_var,
+                        to: ramp_to_var,
+                        duration: duration_time_var.clone(),
+                        curve: ramp_curve_var,
+                        resolution: ramp_res_var,
+                        channel: chan_var.clone(),
+                        device_id: target_device_id_var.clone(),
+                    },
+                    0.0.into(),
+                ));
+            }
+            Effect::Seed(seed_expr) => {
+                let seed_var = Variable::Instance("_seed".to_owned());
+                res.extend(seed_expr.as_asm(functions));
+                res.push(Instruction::Control(ControlASM::Pop(seed_var.clone())));
+                res.push(Instruction::Control(ControlASM::Seed(seed_var)));
+            }
             Effect::ChannelPressure(value_expr, c) => {
                 let context = c.update(&context);
                 let chanpress_value_var = Variable::Instance("_chanpress_value".to_owned());
@@ -257,4 +394,31 @@ impl Effect {
 
         res
     }
+
+    /// Stores the duration of the context (or the default one) as frames in `duration_time_var`.
+    fn emit_duration(
+        context: &BaliContext,
+        duration_var: &Variable,
+        duration_time_var: &Variable,
+        functions: &HashMap<String, FunctionContent>,
+    ) -> Vec<Instruction> {
+        let mut res = Vec::new();
+        if let Some(ref d) = context.duration {
+            res.extend(d.as_asm(functions));
+        } else {
+            res.extend(
+                Fraction {
+                    numerator: Box::new(Expression::Value(Value::Number(1))),
+                    denominator: Box::new(Expression::Value(Value::Number(DEFAULT_DURATION))),
+                }
+                .as_asm(functions),
+            );
+        }
+        res.push(Instruction::Control(ControlASM::Pop(duration_var.clone())));
+        res.push(Instruction::Control(ControlASM::FloatAsFrames(
+            duration_var.clone(),
+            duration_time_var.clone(),
+        )));
+        res
+    }
 }
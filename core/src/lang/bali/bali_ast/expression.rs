@@ -27,11 +27,20 @@ pub enum Expression {
     Min(Box<Expression>, Box<Expression>),
     Max(Box<Expression>, Box<Expression>),
     Quantize(Box<Expression>, Box<Expression>), // value, step
+    ScaleDegree(Box<Expression>, Box<Expression>, Box<Expression>), // scale, root, degree
+    ScaleSnap(Box<Expression>, Box<Expression>, Box<Expression>),   // scale, root, note
+    DegreeChord(Box<Expression>, Box<Expression>, Box<Expression>, Box<Expression>), // scale, root, degree, voices
+    Chord(Box<Expression>, Box<Expression>),    // chord, root
+    Invert(Box<Expression>, Box<Expression>),   // chord, inversion
+    VoiceLead(Box<Expression>, Box<Expression>), // previous chord, next chord
+    Arpeggio(Box<Expression>, Box<Expression>, Box<Expression>), // chord, arpeggio, octaves
+    Nth(Box<Expression>, Box<Expression>),      // vec, index
     Sine(Box<Expression>),                      // speed
     Saw(Box<Expression>),                       // speed
     Triangle(Box<Expression>),                  // speed
     ISaw(Box<Expression>),                      // speed (inverted saw)
     RandStep(Box<Expression>),                  // speed (random step LFO)
+    Modulator(Box<Expression>),                 // name of a scene modulator
     MidiCC(
         Box<Expression>,
         Option<Box<Expression>>,
@@ -62,6 +71,9 @@ impl Expression {
                 | Expression::Min(e1, e2)
                 | Expression::Max(e1, e2)
                 | Expression::Quantize(e1, e2)
+                | Expression::Chord(e1, e2)
+                | Expression::Invert(e1, e2)
+                | Expression::VoiceLead(e1, e2)
                 | Expression::RandomFrac(e1, e2) => {
                     let mut asm = e1.as_asm(&functions);
                     asm.extend(e2.as_asm(&functions));
@@ -96,6 +108,15 @@ impl Expression {
                         Expression::Quantize(_, _) => asm.push(Instruction::Control(
                             ControlASM::Quantize(var_1.clone(), var_2.clone(), var_out.clone()),
                         )),
+                        Expression::Chord(_, _) => asm.push(Instruction::Control(
+                            ControlASM::Chord(var_1.clone(), var_2.clone(), var_out.clone()),
+                        )),
+                        Expression::Invert(_, _) => asm.push(Instruction::Control(
+                            ControlASM::Invert(var_1.clone(), var_2.clone(), var_out.clone()),
+                        )),
+                        Expression::VoiceLead(_, _) => asm.push(Instruction::Control(
+                            ControlASM::VoiceLead(var_1.clone(), var_2.clone(), var_out.clone()),
+                        )),
                         Expression::RandomFrac(_, _) => {
                             asm.push(Instruction::Control(ControlASM::Mov(
                                 Variable::Environment(EnvironmentFunc::RandomDecInBounds(
@@ -195,6 +216,82 @@ impl Expression {
                     )));
                     asm
                 }
+                Expression::ScaleDegree(scale, root, n) | Expression::ScaleSnap(scale, root, n) => {
+                    let mut asm = scale.as_asm(functions);
+                    asm.extend(root.as_asm(functions));
+                    asm.extend(n.as_asm(functions));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_3.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_2.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_1.clone())));
+                    let op = match self {
+                        Expression::ScaleDegree(..) => ControlASM::ScaleDegree,
+                        _ => ControlASM::ScaleSnap,
+                    };
+                    asm.push(Instruction::Control(op(
+                        var_1.clone(),
+                        var_2.clone(),
+                        var_3.clone(),
+                        var_out.clone(),
+                    )));
+                    asm
+                }
+                Expression::DegreeChord(scale, root, degree, voices) => {
+                    let mut asm = scale.as_asm(functions);
+                    asm.extend(root.as_asm(functions));
+                    asm.extend(degree.as_asm(functions));
+                    asm.extend(voices.as_asm(functions));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_4.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_3.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_2.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_1.clone())));
+                    asm.push(Instruction::Control(ControlASM::DegreeChord(
+                        var_1.clone(),
+                        var_2.clone(),
+                        var_3.clone(),
+                        var_4.clone(),
+                        var_out.clone(),
+                    )));
+                    asm
+                }
+                Expression::Arpeggio(chord, arpeggio, octaves) => {
+                    let mut asm = chord.as_asm(functions);
+                    asm.extend(arpeggio.as_asm(functions));
+                    asm.extend(octaves.as_asm(functions));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_3.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_2.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_1.clone())));
+                    asm.push(Instruction::Control(ControlASM::Arpeggiate(
+                        var_1.clone(),
+                        var_2.clone(),
+                        var_3.clone(),
+                        var_out.clone(),
+                    )));
+                    asm
+                }
+                // Nth: the index wraps around the length of the vec
+                Expression::Nth(vec, index) => {
+                    let mut asm = vec.as_asm(functions);
+                    asm.extend(index.as_asm(functions));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_2.clone())));
+                    asm.push(Instruction::Control(ControlASM::Pop(var_1.clone())));
+                    asm.push(Instruction::Control(ControlASM::VecLen(var_1.clone(), var_3.clone())));
+                    asm.push(Instruction::Control(ControlASM::Max(
+                        var_3.clone(),
+                        1.into(),
+                        var_3.clone(),
+                    )));
+                    asm.push(Instruction::Control(ControlASM::Mod(
+                        var_2.clone(),
+                        var_3.clone(),
+                        var_2.clone(),
+                    )));
+                    asm.push(Instruction::Control(ControlASM::VecGet(
+                        var_1.clone(),
+                        var_2.clone(),
+                        var_out.clone(),
+                    )));
+                    asm
+                }
                 Expression::Sine(speed_expr)
                 | Expression::Saw(speed_expr)
                 | Expression::Triangle(speed_expr)
@@ -225,6 +322,15 @@ impl Expression {
                     }
                     asm
                 }
+                Expression::Modulator(name_expr) => {
+                    let mut asm = name_expr.as_asm(functions);
+                    asm.push(Instruction::Control(ControlASM::Pop(var_1.clone())));
+                    asm.push(Instruction::Control(ControlASM::Mov(
+                        Variable::Environment(EnvironmentFunc::Modulator(Box::new(var_1.clone()))),
+                        var_out.clone(),
+                    )));
+                    asm
+                }
                 // MidiCC: Evaluate control expression, pop into midi_cc_ctrl_var, execute GetMidiCCFromContext into var_out
                 Expression::MidiCC(ctrl_expr, device_expr_opt, channel_expr_opt) => {
                     let mut asm = Vec::new();
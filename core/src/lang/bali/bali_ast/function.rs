@@ -1,10 +1,10 @@
 use crate::lang::bali::bali_ast::{
     AltVariableGenerator, LocalChoiceVariableGenerator, bali_context::BaliContext,
-    constants::FUNCTION_PREFIX, expression::Expression, toplevel_effect::TopLevelEffect,
+    code::Code, constants::FUNCTION_PREFIX, expression::Expression, toplevel_effect::TopLevelEffect,
 };
 
 use crate::vm::{
-    Instruction,
+    Instruction, SourceSpan,
     control_asm::ControlASM,
     variable::{Variable, VariableValue},
 };
@@ -18,6 +18,14 @@ pub struct FunctionContent {
     pub function_program: Vec<TopLevelEffect>,
 }
 
+/// Call of a user function in the script, checked once every function is declared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arity: usize,
+    pub span: SourceSpan,
+}
+
 impl FunctionContent {
     pub fn as_asm(
         &self,
@@ -26,7 +34,7 @@ impl FunctionContent {
         local_alt_vars: &mut AltVariableGenerator,
         functions: &HashMap<String, FunctionContent>,
     ) -> Instruction {
-        let mut function_code = Vec::new();
+        let mut function_code = Code::default();
 
         // get arguments from the stack
         for arg in self.arg_list.clone().into_iter().rev() {
@@ -53,7 +61,7 @@ impl FunctionContent {
         let var_name = format!("{}{}", FUNCTION_PREFIX, function_name);
 
         Instruction::Control(ControlASM::Mov(
-            Variable::Constant(VariableValue::Func(function_code)),
+            Variable::Constant(VariableValue::Func(function_code.into_program())),
             Variable::Instance(var_name),
         ))
     }
@@ -12,6 +12,7 @@ use crate::lang::bali::bali_ast::{
     toplevel_effect::TopLevelEffect,
     value::Value,
 };
+use crate::protocol::ramp::RampCurve;
 use crate::vm::variable::Variable;
 use std::collections::HashMap;
 
@@ -150,13 +151,12 @@ impl Statement {
         res
     }
 
-    fn get_linear_distribution(start: i64, end: i64, steps: i64) -> Vec<i64> {
+    fn get_distribution(start: i64, end: i64, steps: i64, curve: RampCurve) -> Vec<i64> {
         let mut res = Vec::new();
 
-        let coeff = ((end - start) as f64) / ((steps - 1) as f64);
-
         for x in 0..steps {
-            let y = coeff * (x as f64) + (start as f64);
+            let progress = (x as f64) / ((steps - 1) as f64);
+            let y = ((end - start) as f64) * curve.apply(progress) + (start as f64);
             res.push(y as i64);
         }
 
@@ -296,11 +296,8 @@ impl Statement {
                     v = v.divbyint(granularity);
                 }
 
-                let ramp_values = match distribution.to_str().as_str() {
-                    "linear" => Self::get_linear_distribution(start, end, granularity),
-                    "exponential" => todo!(),
-                    _ => Self::get_linear_distribution(start, end, granularity),
-                };
+                let curve = RampCurve::from_name(distribution.to_str().as_str());
+                let ramp_values = Self::get_distribution(start, end, granularity, curve);
 
                 if let Value::Variable(var) = var {
                     for i in 0..granularity {
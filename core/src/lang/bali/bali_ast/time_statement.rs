@@ -1,5 +1,5 @@
 use crate::lang::bali::bali_ast::{
-    AltVariableGenerator, LocalChoiceVariableGenerator, bali_context::BaliContext,
+    AltVariableGenerator, LocalChoiceVariableGenerator, bali_context::BaliContext, code::Code,
     concrete_fraction::ConcreteFraction, constants::LOCAL_TARGET_VAR, function::FunctionContent,
     information::Information, toplevel_effect::TopLevelEffect,
 };
@@ -54,7 +54,7 @@ impl TimeStatement {
         local_alt_vars: &mut AltVariableGenerator,
         set_alt_variables: &mut Vec<bool>,
         functions: &HashMap<String, FunctionContent>,
-    ) -> Vec<Instruction> {
+    ) -> Code {
         match self {
             TimeStatement::At(t, x, context, infos)
             | TimeStatement::JustBefore(t, x, context, infos)
@@ -75,7 +75,7 @@ impl TimeStatement {
 
                 match current_info {
                     Information::Choice(current_choice) => {
-                        let mut res = Vec::new();
+                        let mut res = Code::default();
 
                         res.push(Instruction::Control(ControlASM::Mov(
                             (current_choice.position as i64).into(),
@@ -137,7 +137,7 @@ impl TimeStatement {
                         res
                     }
                     Information::Pick(current_pick) => {
-                        let mut res = Vec::new();
+                        let mut res = Code::default();
 
                         // if this is the first element (in time) of this pick, evaluate the pick expression and store the result
                         // in the pick variable
@@ -191,7 +191,7 @@ impl TimeStatement {
                         res
                     }
                     Information::Alt(current_alt) => {
-                        let mut res = Vec::new();
+                        let mut res = Code::default();
 
                         // if this is the first element (in time) of this alt, get the
                         // value of the frame variable, then increase it by one
@@ -239,7 +239,7 @@ impl TimeStatement {
                         res
                     }
                     Information::Ramp(current_ramp) => {
-                        let mut res = Vec::new();
+                        let mut res = Code::default();
 
                         // set the ramp variable
                         res.push(Instruction::Control(ControlASM::Mov(
@@ -2,9 +2,11 @@ use crate::{
     lang::bali::bali_ast::{
         AltVariableGenerator, LOCAL_ALT_VAR, LOCAL_PICK_VAR, LOCAL_TARGET_VAR,
         LocalChoiceVariableGenerator, bali_context::BaliContext, boolean::BooleanExpression,
-        effect::Effect, expression::Expression, function::FunctionContent,
+        code::Code, effect::Effect, expression::Expression, function::FunctionContent,
+    },
+    vm::{
+        EnvironmentFunc, Instruction, SourceSpan, control_asm::ControlASM, variable::Variable,
     },
-    vm::{EnvironmentFunc, Instruction, control_asm::ControlASM, variable::Variable},
 };
 
 use std::collections::HashMap;
@@ -19,9 +21,15 @@ pub enum TopLevelEffect {
     Effect(Effect, BaliContext),
     Pick(Box<Expression>, Vec<TopLevelEffect>, BaliContext),
     Alt(Vec<TopLevelEffect>, Variable, BaliContext),
+    /// Effect with the part of the script it comes from.
+    Spanned(Box<TopLevelEffect>, SourceSpan),
 }
 
 impl TopLevelEffect {
+    pub fn spanned(self, span: SourceSpan) -> TopLevelEffect {
+        TopLevelEffect::Spanned(Box::new(self), span)
+    }
+
     pub fn set_context(self, c: &BaliContext) -> TopLevelEffect {
         match self {
             TopLevelEffect::Seq(es, seq_context) => TopLevelEffect::Seq(es, seq_context.update(c)),
@@ -46,6 +54,7 @@ impl TopLevelEffect {
             TopLevelEffect::Alt(es, var, alt_context) => {
                 TopLevelEffect::Alt(es, var, alt_context.update(c))
             }
+            TopLevelEffect::Spanned(e, span) => TopLevelEffect::Spanned(Box::new(e.set_context(c)), span),
         }
     }
 
@@ -55,12 +64,12 @@ impl TopLevelEffect {
         local_choice_vars: &mut LocalChoiceVariableGenerator,
         local_alt_vars: &mut AltVariableGenerator,
         functions: &HashMap<String, FunctionContent>,
-    ) -> Vec<Instruction> {
+    ) -> Code {
         //let time_var = Variable::Instance("_time".to_owned());
         let bvar_out = Variable::Instance("_bres".to_owned());
         match self {
             TopLevelEffect::Seq(s, seq_context) | TopLevelEffect::With(s, seq_context) => {
-                let mut res = Vec::new();
+                let mut res = Code::default();
                 let context = seq_context.update(&context);
                 for i in 0..s.len() {
                     let to_add = s[i].as_asm(
@@ -74,7 +83,7 @@ impl TopLevelEffect {
                 res
             }
             TopLevelEffect::For(e, s, for_context) => {
-                let mut res = Vec::new();
+                let mut res = Code::default();
 
                 // Compute and add condition
                 let condition = e.as_asm(functions);
@@ -89,7 +98,7 @@ impl TopLevelEffect {
 
                 // Compute effects
                 let context = for_context.update(&context);
-                let mut effects = Vec::new();
+                let mut effects = Code::default();
                 for i in 0..s.len() {
                     let to_add = s[i].as_asm(
                         context.clone(),
@@ -116,7 +125,7 @@ impl TopLevelEffect {
                 res
             }
             TopLevelEffect::If(e, s, if_context) => {
-                let mut res = Vec::new();
+                let mut res = Code::default();
 
                 // Compute and add condition
                 let condition = e.as_asm(functions);
@@ -131,7 +140,7 @@ impl TopLevelEffect {
 
                 // Compute effects
                 let context = if_context.update(&context);
-                let mut effects = Vec::new();
+                let mut effects = Code::default();
                 for i in 0..s.len() {
                     let to_add = s[i].as_asm(
                         context.clone(),
@@ -154,7 +163,7 @@ impl TopLevelEffect {
                 res
             }
             TopLevelEffect::Choice(num_selected, num_selectable, es, choice_context) => {
-                let mut res = Vec::new();
+                let mut res = Code::default();
 
                 // If nothing is selected, generate no instructions
                 let num_selected = *num_selected;
@@ -260,7 +269,7 @@ impl TopLevelEffect {
                 let context = pick_context.update(&context);
 
                 // compute the position
-                let mut res = position.as_asm(functions);
+                let mut res = Code::from(position.as_asm(functions));
                 res.push(Instruction::Control(ControlASM::Pop(
                     LOCAL_PICK_VAR.clone(),
                 )));
@@ -323,7 +332,7 @@ impl TopLevelEffect {
                 res
             }
             TopLevelEffect::Alt(es, frame_variable, alt_context) => {
-                let mut res = Vec::new();
+                let mut res = Code::default();
 
                 // get context
                 let context = alt_context.update(&context);
@@ -355,7 +364,7 @@ impl TopLevelEffect {
                 let mut effect_progs = Vec::new();
                 let mut distance_to_end = 0;
                 for pos in 0..es.len() {
-                    effect_progs.push(Vec::new());
+                    effect_progs.push(Code::default());
 
                     let this_effect_prog = es[pos].as_asm(
                         context.clone(),
@@ -383,7 +392,7 @@ impl TopLevelEffect {
                 for prog in effect_progs.iter() {
                     distance_to_end -= prog.len() + 1;
 
-                    res.extend(prog.to_vec());
+                    res.extend(prog.clone());
                     res.push(Instruction::Control(ControlASM::RelJump(
                         distance_to_end as i64 + 1,
                     )));
@@ -400,8 +409,11 @@ impl TopLevelEffect {
             }
             TopLevelEffect::Effect(ef, effect_context) => {
                 let context = effect_context.update(&context);
-                ef.as_asm(context, functions)
+                ef.as_asm(context, functions).into()
             }
+            TopLevelEffect::Spanned(e, span) => e
+                .as_asm(context, local_choice_vars, local_alt_vars, functions)
+                .with_span(*span),
         }
     }
 }
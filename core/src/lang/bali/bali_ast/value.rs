@@ -57,7 +57,23 @@ impl Value {
             "A" | "B" | "C" | "D" | "W" | "X" | "Y" | "Z" => Variable::Global(name.to_string()),
             "T" => Variable::Environment(EnvironmentFunc::GetTempo),
             "R" => Variable::Environment(EnvironmentFunc::RandomUInt(128)),
-            _ => Variable::Instance(name.to_string()),
+            _ => match name.strip_prefix("line-") {
+                Some(line_var) if !line_var.is_empty() => Variable::Line(line_var.to_string()),
+                _ => Variable::Instance(name.to_string()),
+            },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_prefix_names_line_variables() {
+        assert_eq!(Value::as_variable("line-count"), Variable::Line("count".to_string()));
+        assert_eq!(Value::as_variable("line-"), Variable::Instance("line-".to_string()));
+        assert_eq!(Value::as_variable("count"), Variable::Instance("count".to_string()));
+        assert_eq!(Value::as_variable("A"), Variable::Global("A".to_string()));
+    }
+}
@@ -1,15 +1,90 @@
 use crate::compiler::{CompilationError, Compiler};
 use std::collections::BTreeMap;
 
-use crate::vm::{Program, debug_print};
+use crate::vm::{Program, SourceMap, debug_print};
 
 use crate::lang::bali::{
-    bali_ast::{AltVariableGenerator, bali_as_asm, constants::DEBUG_INSTRUCTIONS},
+    bali_ast::{
+        AltVariableGenerator, BaliProgram, bali_as_asm, constants::DEBUG_INSTRUCTIONS,
+        function::FunctionCall, get_functions,
+    },
     bali_grammar,
 };
 
 use lalrpop_util::ParseError;
 
+/// Parses a Bali script, reporting the position of syntax errors.
+pub fn parse_bali(script: &str) -> Result<BaliProgram, CompilationError> {
+    parse(script).map(|(prog, _)| prog)
+}
+
+/// Parses a Bali script, with the function calls it makes.
+fn parse(script: &str) -> Result<(BaliProgram, Vec<FunctionCall>), CompilationError> {
+    let mut alt_variables = AltVariableGenerator::new("_alt".to_string());
+    let mut calls = Vec::new();
+    bali_grammar::ProgramParser::new()
+        .parse(&mut alt_variables, &mut calls, script)
+        .map(|prog| (prog, calls))
+        .map_err(|parse_error| {
+            let mut from = 0;
+            let mut to = 0;
+            match parse_error {
+                ParseError::InvalidToken { location: loc }
+                | ParseError::UnrecognizedEof {
+                    location: loc,
+                    expected: _,
+                } => {
+                    from = loc;
+                    to = loc;
+                }
+                ParseError::UnrecognizedToken {
+                    token: (f, _, t),
+                    expected: _,
+                }
+                | ParseError::ExtraToken { token: (f, _, t) } => {
+                    from = f;
+                    to = t;
+                }
+                ParseError::User { error: _ } => {}
+            };
+            CompilationError {
+                lang: "BaLi".to_string(),
+                info: parse_error.to_string(),
+                from,
+                to,
+            }
+        })
+}
+
+/// Checks that every called function is declared, with as many arguments as given.
+fn check_calls(prog: &BaliProgram, calls: &[FunctionCall]) -> Result<(), CompilationError> {
+    let functions = get_functions(prog).map_err(|info| CompilationError {
+        lang: "BaLi".to_string(),
+        info,
+        from: 0,
+        to: 0,
+    })?;
+    for call in calls {
+        let info = match functions.get(&call.name) {
+            None => format!("Unknown function '{}'", call.name),
+            Some(f) if f.arg_list.len() != call.arity => format!(
+                "Function '{}' takes {} arguments but {} were given",
+                call.name,
+                f.arg_list.len(),
+                call.arity
+            ),
+            Some(_) => continue,
+        };
+        return Err(CompilationError {
+            lang: "BaLi".to_string(),
+            info,
+            from: call.span.from,
+            to: call.span.to,
+        });
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct BaliCompiler;
 impl Compiler for BaliCompiler {
@@ -20,57 +95,98 @@ impl Compiler for BaliCompiler {
     fn compile(
         &self,
         script: &str,
-        _args: &BTreeMap<String, String>,
+        args: &BTreeMap<String, String>,
     ) -> Result<Program, CompilationError> {
-        let mut alt_variables = AltVariableGenerator::new("_alt".to_string());
-        match bali_grammar::ProgramParser::new().parse(&mut alt_variables, script) {
-            Ok(parsed) => {
-                let res = bali_as_asm(parsed);
-                match res {
-                    Ok(res) => {
-                        // print program for debug
-                        if DEBUG_INSTRUCTIONS {
-                            debug_print(&res, "PROGRAM".to_string(), "".to_string());
-                        }
-                        Ok(res)
-                    }
-                    Err(info) => Err(CompilationError {
-                        lang: "BaLi".to_string(),
-                        info,
-                        from: 0,
-                        to: 0,
-                    }),
-                }
-            }
-            Err(parse_error) => {
-                let mut from = 0;
-                let mut to = 0;
-                match parse_error {
-                    ParseError::InvalidToken { location: loc }
-                    | ParseError::UnrecognizedEof {
-                        location: loc,
-                        expected: _,
-                    } => {
-                        from = loc;
-                        to = loc;
-                    }
-                    ParseError::UnrecognizedToken {
-                        token: (f, _, t),
-                        expected: _,
-                    }
-                    | ParseError::ExtraToken { token: (f, _, t) } => {
-                        from = f;
-                        to = t;
-                    }
-                    ParseError::User { error: _ } => {}
-                };
-                Err(CompilationError {
-                    lang: "BaLi".to_string(),
-                    info: parse_error.to_string(),
-                    from,
-                    to,
-                })
-            }
+        self.compile_with_source_map(script, args)
+            .map(|(prog, _)| prog)
+    }
+
+    fn compile_with_source_map(
+        &self,
+        script: &str,
+        _args: &BTreeMap<String, String>,
+    ) -> Result<(Program, SourceMap), CompilationError> {
+        let (prog, calls) = parse(script)?;
+        check_calls(&prog, &calls)?;
+        let (res, spans) = bali_as_asm(prog)
+            .map_err(|info| CompilationError {
+                lang: "BaLi".to_string(),
+                info,
+                from: 0,
+                to: 0,
+            })?
+            .into_parts();
+        // print program for debug
+        if DEBUG_INSTRUCTIONS {
+            debug_print(&res, "PROGRAM".to_string(), "".to_string());
         }
+        Ok((res, SourceMap::new(script, spans)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{Instruction, SourcePosition, SourceSpan, control_asm::ControlASM};
+
+    fn compile(script: &str) -> Result<Program, CompilationError> {
+        BaliCompiler.compile(script, &BTreeMap::new())
+    }
+
+    #[test]
+    fn calls_are_checked_with_positions() {
+        let script = "(fun f x (+ x 1))\n(note (g 60))";
+        let error = compile(script).unwrap_err();
+        assert_eq!(error.info, "Unknown function 'g'");
+        assert_eq!(&script[error.from..error.to], "(g 60)");
+
+        let script = "(fun f x (+ x 1))\n(note (f 60 2))";
+        let error = compile(script).unwrap_err();
+        assert_eq!(&script[error.from..error.to], "(f 60 2)");
+
+        assert!(compile("(fun f x (+ x 1))\n(note (f 60))").is_ok());
+    }
+
+    #[test]
+    fn theory_forms_compile_to_their_ops() {
+        let prog = compile(
+            "(note (nth (arp (invert (chord \"m7\" 57) 1) \"updown\" 2) 3))\n\
+             (note (nth (voicelead (dchord \"major\" 60 0 3) (dchord \"major\" 60 4 3)) 0))",
+        )
+        .unwrap();
+        let has = |matches: fn(&ControlASM) -> bool| {
+            prog.iter()
+                .any(|instruction| matches!(instruction, Instruction::Control(op) if matches(op)))
+        };
+        assert!(has(|op| matches!(op, ControlASM::Chord(..))));
+        assert!(has(|op| matches!(op, ControlASM::Invert(..))));
+        assert!(has(|op| matches!(op, ControlASM::Arpeggiate(..))));
+        assert!(has(|op| matches!(op, ControlASM::DegreeChord(..))));
+        assert!(has(|op| matches!(op, ControlASM::VoiceLead(..))));
+        assert!(has(|op| matches!(op, ControlASM::VecGet(..))));
+
+        // Chords are values like any other, and may be given as arguments to functions
+        assert!(compile("(fun up c (invert c 1))\n(note (nth (up (chord \"maj\" 60)) 0))").is_ok());
+    }
+
+    #[test]
+    fn effects_are_located() {
+        let script = "(note 60)\n(for (lt 1 2) (note 62))";
+        let (prog, source_map) = BaliCompiler
+            .compile_with_source_map(script, &BTreeMap::new())
+            .unwrap();
+        let spans: Vec<SourceSpan> = (0..prog.len())
+            .filter_map(|position| source_map.span(position))
+            .collect();
+        let texts: Vec<&str> = spans.iter().map(|s| &script[s.from..s.to]).collect();
+        assert!(texts.contains(&"(note 60)"));
+        assert!(texts.contains(&"(note 62)"));
+        assert!(texts.contains(&"(for (lt 1 2) (note 62))"));
+
+        let positions: Vec<SourcePosition> = (0..prog.len())
+            .filter_map(|position| source_map.position(position))
+            .collect();
+        assert!(positions.contains(&SourcePosition { line: 1, column: 1 }));
+        assert!(positions.contains(&SourcePosition { line: 2, column: 15 }));
     }
 }
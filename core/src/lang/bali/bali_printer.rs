@@ -0,0 +1,685 @@
+//! Printing of Bali programs back to source, in a canonical layout: forms are kept on one
+//! line when they fit, otherwise their content is indented below their head.
+//!
+//! The parser desugars some forms (argument lists, `<a b>` alternatives, `{a b}` choices,
+//! contexts), so the printed source is the desugared program, parsed back to the same
+//! program. Comments, which only follow top-level forms, are kept by [`format_bali`].
+
+use crate::compiler::CompilationError;
+use crate::lang::bali::{
+    bali_ast::{
+        BaliContext, BaliProgram, BooleanExpression, ConcreteFraction, Effect, Expression,
+        LoopContext, Statement, TimingInformation, TopLevelEffect, Value,
+    },
+    parse_bali,
+};
+use crate::protocol::{midi::NoteExpression, ramp::RampKind};
+
+/// Width of the printed lines, beyond which forms are broken.
+const WIDTH: usize = 80;
+const INDENT: usize = 2;
+
+/// Prints a program, one top-level statement per line.
+pub fn print_program(program: &BaliProgram) -> String {
+    print(program, &[])
+}
+
+/// Formats a script by printing its program, keeping its comments after the top-level
+/// forms they follow.
+pub fn format_bali(script: &str) -> Result<String, CompilationError> {
+    let program = parse_bali(script)?;
+    Ok(print(&program, &comments(script)))
+}
+
+/// A comment of a script.
+struct Comment {
+    /// Number of top-level forms before the comment
+    after: usize,
+    /// Whether the comment is on the line of the end of the form before it
+    trailing: bool,
+    text: String,
+}
+
+/// Comments of a script, which the grammar only accepts between top-level forms.
+fn comments(script: &str) -> Vec<Comment> {
+    let mut res = Vec::new();
+    let (mut depth, mut forms, mut in_string, mut trailing) = (0usize, 0, false, false);
+    let mut chars = script.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' => depth += 1,
+            ')' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    forms += 1;
+                    trailing = true;
+                }
+            }
+            '\n' => trailing = false,
+            ';' => {
+                let text = script[i..].lines().next().unwrap_or_default();
+                res.push(Comment {
+                    after: forms,
+                    trailing,
+                    text: text.trim_end().to_owned(),
+                });
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+/// Prints a program, with the comments following its top-level statements.
+fn print(program: &BaliProgram, comments: &[Comment]) -> String {
+    let mut out = String::new();
+    let mut comments = comments.iter().peekable();
+    for (i, s) in program.iter().enumerate() {
+        let docs = block(std::slice::from_ref(s));
+        for doc in docs.iter() {
+            doc.write(0, &mut out);
+            out.push('\n');
+        }
+        while let Some(comment) = comments.next_if(|c| c.after <= i + 1) {
+            if comment.trailing && !docs.is_empty() {
+                out.pop();
+                out.push(' ');
+            }
+            out.push_str(&comment.text);
+            out.push('\n');
+        }
+    }
+    for comment in comments {
+        out.push_str(&comment.text);
+        out.push('\n');
+    }
+    out
+}
+
+/// A printed form, laid out once its indentation is known.
+enum Doc {
+    Atom(String),
+    /// Head of the form (opening parenthesis, name and arguments), and content
+    Form(String, Vec<Doc>),
+}
+
+impl Doc {
+    fn flat(&self) -> String {
+        match self {
+            Doc::Atom(atom) => atom.clone(),
+            Doc::Form(head, content) => {
+                let mut res = head.clone();
+                for doc in content {
+                    res.push(' ');
+                    res.push_str(&doc.flat());
+                }
+                res.push(')');
+                res
+            }
+        }
+    }
+
+    fn write(&self, indent: usize, out: &mut String) {
+        let flat = self.flat();
+        match self {
+            Doc::Form(head, content)
+                if !content.is_empty() && indent + flat.chars().count() > WIDTH =>
+            {
+                out.push_str(head);
+                for doc in content {
+                    out.push('\n');
+                    out.extend(std::iter::repeat_n(' ', indent + INDENT));
+                    doc.write(indent + INDENT, out);
+                }
+                out.push(')');
+            }
+            _ => out.push_str(&flat),
+        }
+    }
+}
+
+fn head(name: &str, args: Vec<String>, context: &BaliContext) -> String {
+    let mut res = format!("({name}");
+    for arg in args.into_iter().chain(context_args(context)) {
+        res.push(' ');
+        res.push_str(&arg);
+    }
+    res
+}
+
+fn atom(name: &str, args: Vec<String>, context: &BaliContext) -> Doc {
+    Doc::Atom(head(name, args, context) + ")")
+}
+
+/// Wraps a form which cannot hold a context in a `with`, if the context is not empty.
+fn wrap(doc: Doc, context: &BaliContext) -> Doc {
+    if context_args(context).is_empty() {
+        doc
+    } else {
+        Doc::Form(head("with", Vec::new(), context), vec![doc])
+    }
+}
+
+fn context_args(context: &BaliContext) -> Vec<String> {
+    [
+        ("dev:", &context.device),
+        ("ch:", &context.channel),
+        ("v:", &context.velocity),
+        ("dur:", &context.duration),
+    ]
+    .into_iter()
+    .filter_map(|(key, e)| e.as_ref().map(|e| format!("{key}{}", expression(e))))
+    .collect()
+}
+
+fn loop_context_args(lc: &LoopContext) -> Vec<String> {
+    let mut res = Vec::new();
+    if lc.negate {
+        res.push(":neg".to_string());
+    }
+    if lc.reverse {
+        res.push(":rev".to_string());
+    }
+    if lc.step_time {
+        res.push(":step".to_string());
+    }
+    if let Some(shift) = lc.shift {
+        res.push(format!("sh:{shift}"));
+    }
+    res
+}
+
+/// Statements of a program or of a `with`, where the groupings without context are
+/// spliced as their statements are run at the same time.
+fn block(statements: &[Statement]) -> Vec<Doc> {
+    let mut docs = Vec::new();
+    for s in statements {
+        match s {
+            Statement::With(inner, c) if context_args(c).is_empty() => docs.extend(block(inner)),
+            s => docs.push(statement(s, &BaliContext::new())),
+        }
+    }
+    docs
+}
+
+fn effect_block(effects: &[TopLevelEffect]) -> Vec<Doc> {
+    let mut docs = Vec::new();
+    for e in effects {
+        match e {
+            TopLevelEffect::With(inner, c) if context_args(c).is_empty() => {
+                docs.extend(effect_block(inner))
+            }
+            TopLevelEffect::Spanned(inner, _) => {
+                docs.extend(effect_block(std::slice::from_ref(inner)))
+            }
+            e => docs.push(top_level_effect(e, &BaliContext::new())),
+        }
+    }
+    docs
+}
+
+fn statements(statements: &[Statement]) -> Vec<Doc> {
+    statements
+        .iter()
+        .map(|s| statement(s, &BaliContext::new()))
+        .collect()
+}
+
+fn effects(effects: &[TopLevelEffect]) -> Vec<Doc> {
+    effects
+        .iter()
+        .map(|e| top_level_effect(e, &BaliContext::new()))
+        .collect()
+}
+
+/// Timing of a loop, omitted when it is the default one (the whole frame).
+fn loop_timing(t: &TimingInformation) -> Option<String> {
+    match t {
+        TimingInformation::PositionRelative(f) if f.signe * f.numerator == f.denominator => None,
+        t => Some(timing(t)),
+    }
+}
+
+/// Prints a statement, with the context of the `with` forms it was wrapped in.
+fn statement(s: &Statement, above: &BaliContext) -> Doc {
+    match s {
+        Statement::AfterFrac(t, ss, c) => {
+            Doc::Form(head(">", vec![timing(t)], &c.update(above)), statements(ss))
+        }
+        Statement::BeforeFrac(t, ss, c) => {
+            Doc::Form(head("<", vec![timing(t)], &c.update(above)), statements(ss))
+        }
+        Statement::Loop(it, t, ss, lc, c) => {
+            let mut args = vec![it.to_string()];
+            args.extend(loop_timing(t));
+            args.extend(loop_context_args(lc));
+            Doc::Form(head("loop", args, &c.update(above)), statements(ss))
+        }
+        Statement::Euclidean(beats, steps, lc, t, ss, c) => {
+            let mut args = vec![beats.to_string(), steps.to_string()];
+            args.extend(loop_timing(t));
+            args.extend(loop_context_args(lc));
+            Doc::Form(head("eucloop", args, &c.update(above)), statements(ss))
+        }
+        Statement::Binary(it, steps, lc, t, ss, c) => {
+            let mut args = vec![it.to_string(), steps.to_string()];
+            args.extend(loop_timing(t));
+            args.extend(loop_context_args(lc));
+            Doc::Form(head("binloop", args, &c.update(above)), statements(ss))
+        }
+        Statement::Spread(t, ss, lc, c) => {
+            let mut args: Vec<String> = loop_timing(t).into_iter().collect();
+            args.extend(loop_context_args(lc));
+            Doc::Form(head("spread", args, &c.update(above)), statements(ss))
+        }
+        Statement::Ramp(name, gran, min, max, distrib, lc, t, ss, c) => {
+            let mut args = vec![
+                value(name),
+                gran.to_string(),
+                min.to_string(),
+                max.to_string(),
+                value(distrib),
+            ];
+            args.extend(loop_timing(t));
+            args.extend(loop_context_args(lc));
+            Doc::Form(head("ramp", args, &c.update(above)), statements(ss))
+        }
+        Statement::After(es, c) => Doc::Form(head(">>", Vec::new(), &c.update(above)), effects(es)),
+        Statement::Before(es, c) => {
+            Doc::Form(head("<<", Vec::new(), &c.update(above)), effects(es))
+        }
+        // Kept in a `seq`, as the parser reads the statement of the same name first
+        Statement::Effect(e) if has_statement_form(e) => Doc::Form(
+            head("seq", Vec::new(), &BaliContext::new()),
+            vec![top_level_effect(e, above)],
+        ),
+        Statement::Effect(e) => top_level_effect(e, above),
+        Statement::With(ss, c) => {
+            let c = c.update(above);
+            match ss.as_slice() {
+                [s] => statement(s, &c),
+                ss => Doc::Form(head("with", Vec::new(), &c), block(ss)),
+            }
+        }
+        Statement::Choice(num, _, ss, c) => {
+            let args = if *num == 1 { Vec::new() } else { vec![num.to_string()] };
+            Doc::Form(head("?", args, &c.update(above)), statements(ss))
+        }
+        Statement::Pick(position, ss, c) => Doc::Form(
+            head("pick", vec![expression(position)], &c.update(above)),
+            statements(ss),
+        ),
+        Statement::Alt(ss, _, c) => {
+            Doc::Form(head("alt", Vec::new(), &c.update(above)), statements(ss))
+        }
+        Statement::FunctionDeclaration(name, args, es, e) => {
+            let mut content = effects(es);
+            content.push(Doc::Atom(expression(e)));
+            Doc::Form(
+                head("fun", [name].into_iter().chain(args).map(value).collect(), &BaliContext::new()),
+                content,
+            )
+        }
+    }
+}
+
+/// Whether an effect is printed as a form which is also a statement.
+fn has_statement_form(e: &TopLevelEffect) -> bool {
+    match e {
+        TopLevelEffect::Choice(..) | TopLevelEffect::Pick(..) | TopLevelEffect::Alt(..) => true,
+        TopLevelEffect::With(es, _) if es.len() == 1 => has_statement_form(&es[0]),
+        TopLevelEffect::Spanned(e, _) => has_statement_form(e),
+        _ => false,
+    }
+}
+
+fn top_level_effect(e: &TopLevelEffect, above: &BaliContext) -> Doc {
+    match e {
+        TopLevelEffect::Seq(es, c) => Doc::Form(head("seq", Vec::new(), &c.update(above)), effects(es)),
+        TopLevelEffect::With(es, c) => {
+            let c = c.update(above);
+            match es.as_slice() {
+                [e] => top_level_effect(e, &c),
+                es => Doc::Form(head("with", Vec::new(), &c), effect_block(es)),
+            }
+        }
+        TopLevelEffect::For(cond, es, c) => Doc::Form(
+            head("for", vec![boolean(cond)], &c.update(above)),
+            effects(es),
+        ),
+        TopLevelEffect::If(cond, es, c) => Doc::Form(
+            head("if", vec![boolean(cond)], &c.update(above)),
+            effects(es),
+        ),
+        TopLevelEffect::Choice(num, _, es, c) => {
+            let args = if *num == 1 { Vec::new() } else { vec![num.to_string()] };
+            Doc::Form(head("?", args, &c.update(above)), effects(es))
+        }
+        TopLevelEffect::Effect(e, c) => effect(e, &c.update(above)),
+        TopLevelEffect::Pick(position, es, c) => Doc::Form(
+            head("pick", vec![expression(position)], &c.update(above)),
+            effects(es),
+        ),
+        TopLevelEffect::Alt(es, _, c) => {
+            Doc::Form(head("alt", Vec::new(), &c.update(above)), effects(es))
+        }
+        TopLevelEffect::Spanned(e, _) => top_level_effect(e, above),
+    }
+}
+
+fn effect(e: &Effect, above: &BaliContext) -> Doc {
+    match e {
+        Effect::Definition(name, e) => wrap(
+            atom("def", vec![value(name), expression(e)], &BaliContext::new()),
+            above,
+        ),
+        Effect::Seed(e) => wrap(atom("seed", vec![expression(e)], &BaliContext::new()), above),
+        Effect::Nop => wrap(Doc::Atom("()".to_string()), above),
+        Effect::Note(n, c) => atom("note", vec![expression(n)], &c.update(above)),
+        Effect::ProgramChange(p, c) => atom("prog", vec![expression(p)], &c.update(above)),
+        Effect::ControlChange(ctrl, v, c) => atom(
+            "control",
+            vec![expression(ctrl), expression(v)],
+            &c.update(above),
+        ),
+        Effect::Aftertouch(note, v, c) => atom(
+            "at",
+            vec![expression(note), expression(v)],
+            &c.update(above),
+        ),
+        Effect::ChannelPressure(v, c) => atom("chanpress", vec![expression(v)], &c.update(above)),
+        Effect::PitchBend(v, c) => atom("bend", vec![expression(v)], &c.update(above)),
+        Effect::ControlChange14(ctrl, v, c) => atom(
+            "cc14",
+            vec![expression(ctrl), expression(v)],
+            &c.update(above),
+        ),
+        Effect::Nrpn(param, v, c) => atom(
+            "nrpn",
+            vec![expression(param), expression(v)],
+            &c.update(above),
+        ),
+        Effect::Rpn(param, v, c) => atom(
+            "rpn",
+            vec![expression(param), expression(v)],
+            &c.update(above),
+        ),
+        Effect::NoteExpression(kind, note, v, c) => {
+            let name = match kind {
+                NoteExpression::Bend => "note-bend",
+                NoteExpression::Pressure => "note-press",
+                NoteExpression::Timbre => "note-timbre",
+            };
+            atom(name, vec![expression(note), expression(v)], &c.update(above))
+        }
+        Effect::Ramp(kind, param, from, to, curve, resolution, c) => {
+            let (name, mut args) = match kind {
+                RampKind::MidiControl => ("ramp-cc", vec![expression(param)]),
+                RampKind::PitchBend => ("ramp-bend", Vec::new()),
+                RampKind::Osc => ("ramp-osc", vec![expression(param)]),
+            };
+            args.push(expression(from));
+            args.push(expression(to));
+            if !matches!(curve, Value::String(s) if s == "linear") {
+                args.push(value(curve));
+            }
+            if !matches!(resolution.as_ref(), Expression::Value(Value::Number(0))) {
+                args.push(format!("res:{}", expression(resolution)));
+            }
+            atom(name, args, &c.update(above))
+        }
+        Effect::Osc(addr, values, c) => {
+            let args = std::iter::once(value(addr))
+                .chain(values.iter().map(expression))
+                .collect();
+            atom("osc", args, &c.update(above))
+        }
+        Effect::Dirt(sound, params, c) => {
+            let mut args = vec![value(sound)];
+            for (key, e) in params {
+                args.push(format!(":{key}"));
+                args.push(expression(e));
+            }
+            atom("dirt", args, &c.update(above))
+        }
+    }
+}
+
+fn call(name: &str, args: &[&Expression]) -> String {
+    let mut res = format!("({name}");
+    for arg in args {
+        res.push(' ');
+        res.push_str(&expression(arg));
+    }
+    res.push(')');
+    res
+}
+
+fn expression(e: &Expression) -> String {
+    match e {
+        Expression::Addition(a, b) => call("+", &[a, b]),
+        Expression::Multiplication(a, b) => call("*", &[a, b]),
+        Expression::Subtraction(a, b) => call("-", &[a, b]),
+        Expression::Division(a, b) => call("/", &[a, b]),
+        Expression::Modulo(a, b) => call("%", &[a, b]),
+        Expression::Function(name, args) => {
+            let args: Vec<&Expression> = args.iter().map(|a| a.as_ref()).collect();
+            call(name, &args)
+        }
+        Expression::RandomFrac(min, max) => match min.as_ref() {
+            Expression::Value(Value::Number(0)) => call("rand", &[max]),
+            _ => call("rand", &[min, max]),
+        },
+        Expression::Scale(v, old_min, old_max, new_min, new_max) => {
+            call("scale", &[v, old_min, old_max, new_min, new_max])
+        }
+        Expression::Clamp(v, min, max) => call("clamp", &[v, min, max]),
+        Expression::Min(a, b) => call("min", &[a, b]),
+        Expression::Max(a, b) => call("max", &[a, b]),
+        Expression::Quantize(v, step) => call("quantize", &[v, step]),
+        Expression::ScaleDegree(scale, root, degree) => call("degree", &[scale, root, degree]),
+        Expression::ScaleSnap(scale, root, note) => call("snap", &[scale, root, note]),
+        Expression::DegreeChord(scale, root, degree, voices) => {
+            call("dchord", &[scale, root, degree, voices])
+        }
+        Expression::Chord(chord, root) => call("chord", &[chord, root]),
+        Expression::Invert(chord, inversion) => call("invert", &[chord, inversion]),
+        Expression::VoiceLead(from, to) => call("voicelead", &[from, to]),
+        Expression::Arpeggio(chord, arpeggio, octaves) => call("arp", &[chord, arpeggio, octaves]),
+        Expression::Nth(vec, index) => call("nth", &[vec, index]),
+        Expression::Sine(speed) => call("sine", &[speed]),
+        Expression::Saw(speed) => call("saw", &[speed]),
+        Expression::Triangle(speed) => call("triangle", &[speed]),
+        Expression::ISaw(speed) => call("isaw", &[speed]),
+        Expression::RandStep(speed) => call("randstep", &[speed]),
+        Expression::Modulator(name) => call("lfo", &[name]),
+        Expression::MidiCC(ctrl, device, channel) => {
+            let mut res = format!("(ccin {}", expression(ctrl));
+            if let Some(device) = device {
+                res.push_str(&format!(" dev:{}", expression(device)));
+            }
+            if let Some(channel) = channel {
+                res.push_str(&format!(" ch:{}", expression(channel)));
+            }
+            res.push(')');
+            res
+        }
+        Expression::Value(v) => value(v),
+    }
+}
+
+fn boolean(b: &BooleanExpression) -> String {
+    let (name, a, b) = match b {
+        BooleanExpression::And(a, b) => return format!("(and {} {})", boolean(a), boolean(b)),
+        BooleanExpression::Or(a, b) => return format!("(or {} {})", boolean(a), boolean(b)),
+        BooleanExpression::Not(a) => return format!("(not {})", boolean(a)),
+        BooleanExpression::Lower(a, b) => ("lt", a, b),
+        BooleanExpression::LowerOrEqual(a, b) => ("leq", a, b),
+        BooleanExpression::Greater(a, b) => ("gt", a, b),
+        BooleanExpression::GreaterOrEqual(a, b) => ("geq", a, b),
+        BooleanExpression::Equal(a, b) => ("==", a, b),
+        BooleanExpression::Different(a, b) => ("!=", a, b),
+    };
+    call(name, &[a, b])
+}
+
+fn value(v: &Value) -> String {
+    match v {
+        Value::Number(n) => n.to_string(),
+        Value::Decimal(d) => d.clone(),
+        Value::Variable(name) => name.clone(),
+        Value::String(s) => format!("\"{s}\""),
+    }
+}
+
+fn timing(t: &TimingInformation) -> String {
+    match t {
+        TimingInformation::PositionRelative(f) => fraction(f),
+        TimingInformation::FrameRelative(f) => format!("{}:f", fraction(f)),
+    }
+}
+
+/// Prints a fraction as an integer, a decimal if it has an exact (positive) one, or with `//`.
+fn fraction(f: &ConcreteFraction) -> String {
+    let numerator = f.signe * f.numerator;
+    if f.denominator == 1 {
+        return numerator.to_string();
+    }
+    decimal(numerator, f.denominator).unwrap_or_else(|| format!("(// {numerator} {})", f.denominator))
+}
+
+fn decimal(numerator: i64, denominator: i64) -> Option<String> {
+    // Negative decimals between -1 and 0 lose their sign when parsed
+    if numerator < 0 || denominator <= 0 {
+        return None;
+    }
+    let mut scale: i64 = 1;
+    let mut digits = 0;
+    while scale % denominator != 0 {
+        scale = scale.checked_mul(10)?;
+        digits += 1;
+    }
+    let scaled = numerator.checked_mul(scale / denominator)?;
+    let scaled = format!("{scaled:0>width$}", width = digits + 1);
+    let (int_part, dec_part) = scaled.split_at(scaled.len() - digits);
+    Some(format!("{int_part}.{dec_part}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::bali::{bali_ast::bali_as_asm, parse_bali};
+    use crate::vm::Instruction;
+
+    fn parse(script: &str) -> BaliProgram {
+        parse_bali(script).unwrap_or_else(|e| panic!("{script}: {}", e.info))
+    }
+
+    fn reformat(script: &str) -> String {
+        print_program(&parse(script))
+    }
+
+    /// Instructions a program compiles to, without the spans of their sources.
+    fn compiled(program: BaliProgram) -> Vec<Instruction> {
+        bali_as_asm(program).unwrap().into_program()
+    }
+
+    #[test]
+    fn printed_programs_parse_back_to_themselves() {
+        let scripts = [
+            "(note 60)",
+            "(note 60 dev:1 ch:2 v:100 dur:(/ 1 4))",
+            "(> 0.25 (note 60)) (< (1 // 3):f v:80 (prog 4))",
+            "(loop 4 (note [60 64 67]))",
+            "(loop 3 2 :rev sh:1 ch:2 (note 60) (control 7 (rand 20 100)))",
+            "(eucloop 3 8 :neg (dirt \"bd\" :n 3 :speed (sine 2)))",
+            "(binloop 5 4 :step (note <60 62 64>))",
+            "(ramp x 8 0 127 \"exp\" 0.5:f (control 1 x))",
+            "(spread (note 60) (note {62 64}) (note [65!2]))",
+            "(spread (note <60 62>) (with ch:2 (seq (? (note 1) (note 2)))))",
+            "(with dev:2 (note 60) (with (note 61) (note 62)))",
+            "(with ch:3 (>> (note 60) (with v:10 (note 61) (prog 2))))",
+            "(def x (+ 1 (* 2 y))) (seed 42) ()",
+            "(with dur:2 (def x 1))",
+            "(fun f a b (def y (- a b)) (/ y 2)) (note (f 1 2))",
+            "(? (note 60) (note 62)) (? 2 ch:1 (note 60) (note 62) (note 64))",
+            "(pick (% x 3) (note 60) (note 62)) (alt (note 60) (note 62))",
+            "(>> (seq (note 60) (note 62)) (for (lt i 4) (def i (+ i 1))))",
+            "(>> (if (and (gt x 1) (not (== y 2))) (note 60)) (? (note 1) (note 2)))",
+            "(ramp-cc 74 0 127) (ramp-bend 0 16383 \"sine\") (ramp-osc \"/a\" 0 1 v:1)",
+            "(ramp-cc 74 0 127 \"exp\" res:5) (ramp-osc \"/a\" 0 1 res:(* 2 x) ch:2)",
+            "(osc \"/trig\" 1 2.5 x) (at 60 20) (chanpress 30) (bend 8192)",
+            "(cc14 1 1000) (nrpn 10 20) (rpn 0 2) (note-bend 60 9000) (note-timbre 60 64)",
+            "(note (scale (saw 1) 0 1 (clamp x 0 10) (min 1 (max 2 3))))",
+            "(note (degree \"major\" 60 (quantize (triangle 2) 1)) ch:(ccin 1 dev:2 ch:3))",
+            "(note (snap \"minor\" 60 (isaw (randstep (lfo \"mod\")))))",
+            "(note (nth (arp (invert (chord \"m7\" 57) 1) \"updown\" 2) x))",
+            "(note (nth (voicelead (dchord \"major\" 60 0 3) (dchord \"major\" 60 3 4)) 2))",
+            "(loop 16 (dirt \"superpiano\" :n (rand 60 84) :velocity 0.5 :sustain 2 :room 0.3 :size 0.8))",
+        ];
+        for script in scripts {
+            let program = parse(script);
+            let printed = print_program(&program);
+            let reparsed = parse(&printed);
+            assert_eq!(compiled(reparsed.clone()), compiled(program), "{script}");
+            assert_eq!(print_program(&reparsed), printed, "{script}");
+        }
+    }
+
+    #[test]
+    fn defaults_are_omitted() {
+        assert_eq!(reformat("(loop 4 1 (note 60))"), "(loop 4 (note 60))\n");
+        assert_eq!(reformat("(ramp-cc 1 0 10 \"linear\")"), "(ramp-cc 1 0 10)\n");
+        assert_eq!(reformat("(ramp-bend 0 1 res:0)"), "(ramp-bend 0 1)\n");
+        assert_eq!(reformat("(note (rand 0 10))"), "(note (rand 10))\n");
+        assert_eq!(reformat("(> (// 1 2) (note 60))"), "(> 0.5 (note 60))\n");
+        assert_eq!(reformat("(< (// 1 3):f (note 60))"), "(< (// 1 3):f (note 60))\n");
+    }
+
+    #[test]
+    fn contexts_are_moved_into_forms() {
+        assert_eq!(
+            reformat("(with ch:1 (note 60 v:90)) (loop [2 3] (note 60))"),
+            "(note 60 ch:1 v:90)\n(loop 2 (note 60))\n(loop 3 (note 60))\n"
+        );
+        assert_eq!(
+            reformat("(note 60) ; a comment\n(note 62)"),
+            "(note 60)\n(note 62)\n"
+        );
+    }
+
+    #[test]
+    fn comments_are_kept_after_their_forms() {
+        let script = "(with ch:1 (note 60)) ; a \"comment\" (\n(dirt \"a;b(\" :n 1)\n  ; below\n(note 62)";
+        let formatted = format_bali(script).unwrap();
+        assert_eq!(
+            formatted,
+            "(note 60 ch:1) ; a \"comment\" (\n(dirt \"a;b(\" :n 1)\n; below\n(note 62)\n"
+        );
+        assert_eq!(format_bali(&formatted).unwrap(), formatted);
+        assert!(format_bali("(note 60").is_err());
+    }
+
+    #[test]
+    fn long_forms_are_broken() {
+        let printed = reformat(
+            "(loop 4 (note 60 ch:1 v:100 dur:0.25) (note 64 ch:1 v:100 dur:0.25) (note 67 ch:1))",
+        );
+        assert_eq!(
+            printed,
+            "(loop 4\n  (note 60 ch:1 v:100 dur:0.25)\n  (note 64 ch:1 v:100 dur:0.25)\n  (note 67 ch:1))\n"
+        );
+    }
+}
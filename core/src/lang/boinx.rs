@@ -1,9 +1,14 @@
 use std::{cmp, collections::{HashMap, VecDeque}, mem};
 
 use crate::{
-    clock::{NEVER, SyncTime, TimeSpan}, compiler::CompilationState, scene::script::Script, vm::{
+    clock::{NEVER, SyncTime, TimeSpan}, compiler::CompilationState, scene::script::Script,
+    protocol::{
+        midi::NoteExpression,
+        ramp::{DEFAULT_RAMP_RESOLUTION, Ramp, RampCurve, RampTarget},
+    },
+    vm::{
         EvaluationContext,
-        event::ConcreteEvent,
+        event::{ConcreteEvent, to_14_bits},
         interpreter::{Interpreter, InterpreterFactory},
         variable::VariableValue,
     }
@@ -16,8 +21,13 @@ mod position;
 use ast::*;
 pub use position::*;
 
+pub use ast::funcs::BOINX_FUNCTIONS;
 pub use parser::parse_boinx;
 
+/// Keys of the maps describing MIDI events other than notes, see [`BoinxLine::midi_event`].
+const MIDI_EVENT_KEYS: [&str; 7] =
+    ["bend", "cc14", "nrpn", "rpn", "note_bend", "note_press", "note_timbre"];
+
 /// Represents a single Line of execution in Boinx, with a starting date, and a timespan.
 pub struct BoinxLine {
     pub start_date: SyncTime,
@@ -73,6 +83,12 @@ impl BoinxLine {
                 let channel = channel.as_integer(ctx.clock, ctx.frame_len) as u64;
                 Some(ConcreteEvent::MidiNote(*n as u64, 90, channel, dur, device))
             }
+            BoinxItem::ArgMap(map) if map.contains_key("ramp") => {
+                Self::ramp_event(ctx, map, dur, device, channel)
+            }
+            BoinxItem::ArgMap(map) if MIDI_EVENT_KEYS.iter().any(|k| map.contains_key(*k)) => {
+                Self::midi_event(ctx, map, device, channel)
+            }
             BoinxItem::ArgMap(map) => {
                 let mut map : HashMap<String, VariableValue> = 
                     map.iter().filter_map(|(key, value)| {
@@ -97,6 +113,83 @@ impl BoinxLine {
         }
     }
 
+    /// Builds the pitch bend, 14-bit CC, NRPN, RPN or per note expression event described
+    /// by a map produced by the function of the same name.
+    fn midi_event(
+        ctx: &mut EvaluationContext,
+        map: &HashMap<String, BoinxItem>,
+        device: usize,
+        channel: &VariableValue,
+    ) -> Option<ConcreteEvent> {
+        let get = |key: &str| {
+            map.get(key)
+                .cloned()
+                .map(|i| to_14_bits(VariableValue::from(i).as_integer(ctx.clock, ctx.frame_len)))
+        };
+        let channel = channel.as_integer(ctx.clock, ctx.frame_len) as u64;
+        if let Some(bend) = get("bend") {
+            return Some(ConcreteEvent::MidiPitchBend(bend, channel, device));
+        }
+        let value = get("value")?;
+        if let Some(control) = get("cc14") {
+            Some(ConcreteEvent::MidiControl14(control, value, channel, device))
+        } else if let Some(param) = get("nrpn") {
+            Some(ConcreteEvent::MidiNrpn(param, value, channel, device))
+        } else if let Some(param) = get("rpn") {
+            Some(ConcreteEvent::MidiRpn(param, value, channel, device))
+        } else {
+            let (note, expression) = [
+                ("note_bend", NoteExpression::Bend),
+                ("note_press", NoteExpression::Pressure),
+                ("note_timbre", NoteExpression::Timbre),
+            ]
+            .into_iter()
+            .find_map(|(key, expression)| get(key).map(|note| (note, expression)))?;
+            Some(ConcreteEvent::MidiNoteExpression(note, expression, value, channel, device))
+        }
+    }
+
+    /// Builds a ramp lasting `dur` from the map produced by the `ramp` function.
+    /// A numeric target is a CC number, `"bend"` the pitch bend, any other string an OSC address.
+    /// The optional resolution is given in milliseconds.
+    fn ramp_event(
+        ctx: &mut EvaluationContext,
+        map: &HashMap<String, BoinxItem>,
+        dur: SyncTime,
+        device: usize,
+        channel: &VariableValue,
+    ) -> Option<ConcreteEvent> {
+        let get = |key: &str| map.get(key).cloned().map(VariableValue::from);
+        let from = get("from")?.as_float(ctx.clock, ctx.frame_len);
+        let to = get("to")?.as_float(ctx.clock, ctx.frame_len);
+        let curve = get("curve")
+            .map(|c| RampCurve::from_name(&c.as_str(ctx.clock, ctx.frame_len)))
+            .unwrap_or_default();
+        let resolution = get("res")
+            .map(|r| (r.as_float(ctx.clock, ctx.frame_len) * 1000.0) as SyncTime)
+            .filter(|r| *r > 0)
+            .unwrap_or(DEFAULT_RAMP_RESOLUTION);
+        let target = match get("ramp")? {
+            VariableValue::Str(s) if s == "bend" => RampTarget::PitchBend {
+                channel: channel.as_integer(ctx.clock, ctx.frame_len) as u64,
+            },
+            VariableValue::Str(address) => RampTarget::OscFloat { address },
+            control => RampTarget::MidiControl {
+                control: control.as_integer(ctx.clock, ctx.frame_len) as u64,
+                channel: channel.as_integer(ctx.clock, ctx.frame_len) as u64,
+            },
+        };
+        let ramp = Ramp {
+            target,
+            from,
+            to,
+            duration: dur,
+            curve,
+            resolution,
+        };
+        Some(ConcreteEvent::Ramp(ramp, device))
+    }
+
     pub fn get_targets(
         &self,
         ctx: &mut EvaluationContext,
@@ -1,9 +1,12 @@
-use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+use rand::{Rng, seq::SliceRandom};
 
 use crate::{
     clock::TimeSpan,
     lang::boinx::ast::BoinxItem,
     log_warn,
+    theory::{self, Arpeggio, Chord, Scale},
     vm::{EvaluationContext, variable::VariableValue},
 };
 
@@ -18,133 +21,380 @@ fn unpack_if_one(mut args: Vec<BoinxItem>) -> Vec<BoinxItem> {
     }
 }
 
-pub fn execute_boinx_function(
-    ctx: &EvaluationContext,
-    name: &str,
-    mut args: Vec<BoinxItem>,
-) -> BoinxItem {
+/// Notes of an item, sequences and chords being flattened.
+fn item_notes(ctx: &mut EvaluationContext, item: BoinxItem) -> Vec<i64> {
     use BoinxItem::*;
-    match name {
-        "choice" => {
-            args = unpack_if_one(args);
-            let i = rand::random_range(0..args.len());
-            args.remove(i)
-        }
-        "shuffle" => {
-            args = unpack_if_one(args);
-            args.shuffle(&mut rand::rng());
-            Sequence(args)
-        }
-        "rev" => {
-            args = unpack_if_one(args);
-            args = args.into_iter().rev().collect();
-            Sequence(args)
-        }
-        "range" => {
-            let (i1, i2) = if args.len() >= 2 {
-                let mut iter = args.into_iter();
-                let a = VariableValue::from(iter.next().unwrap());
-                let b = VariableValue::from(iter.next().unwrap());
-                let a = a.as_integer(ctx.clock, ctx.frame_len);
-                let b = b.as_integer(ctx.clock, ctx.frame_len);
-                (a, b)
-            } else {
-                let a = VariableValue::from(args.pop().unwrap());
-                let a = a.as_integer(ctx.clock, ctx.frame_len);
-                (0, a)
-            };
-            Sequence((i1..i2).map(|i| Note(i)).collect())
-        }
-        "randrange" => {
-            let (i1, i2) = if args.len() >= 2 {
-                let mut iter = args.into_iter();
-                let a = VariableValue::from(iter.next().unwrap());
-                let b = VariableValue::from(iter.next().unwrap());
-                let a = a.as_float(ctx.clock, ctx.frame_len);
-                let b = b.as_float(ctx.clock, ctx.frame_len);
-                (a, b)
-            } else {
-                let a = VariableValue::from(args.pop().unwrap());
-                let a = a.as_float(ctx.clock, ctx.frame_len);
-                (0.0, a)
-            };
-            Number(rand::random_range(i1..i2))
-        }
-        "irandrange" => {
-            let (i1, i2) = if args.len() >= 2 {
-                let mut iter = args.into_iter();
-                let a = VariableValue::from(iter.next().unwrap());
-                let b = VariableValue::from(iter.next().unwrap());
-                let a = a.as_integer(ctx.clock, ctx.frame_len);
-                let b = b.as_integer(ctx.clock, ctx.frame_len);
-                (a, b)
-            } else {
-                let a = VariableValue::from(args.pop().unwrap());
-                let a = a.as_integer(ctx.clock, ctx.frame_len);
-                (0, a)
-            };
-            Note(rand::random_range(i1..i2))
-        }
-        "after" => {
-            if args.len() > 1 {
-                log_warn!("Too many arguments for 'after' function, taking only last !");
-            }
-            let dur = match args.pop().unwrap() {
-                Duration(d) => d,
-                Number(f) => TimeSpan::Frames(f),
+    match item {
+        Note(i) => vec![i],
+        Sequence(items) | Simultaneous(items) => items
+            .into_iter()
+            .flat_map(|i| item_notes(ctx, i))
+            .collect(),
+        item => vec![VariableValue::from(item).as_integer(ctx.clock, ctx.frame_len)],
+    }
+}
+
+/// First note of an item.
+fn item_note(ctx: &mut EvaluationContext, item: BoinxItem) -> i64 {
+    item_notes(ctx, item).first().copied().unwrap_or_default()
+}
+
+/// Scale given by name, or as a sequence of semitones from the root.
+fn item_scale(ctx: &mut EvaluationContext, item: BoinxItem) -> Scale {
+    match item {
+        BoinxItem::Str(name) => Scale::from_name(&name).unwrap_or_else(|| {
+            log_warn!("Unknown scale '{name}' ! Using major");
+            Scale::Major
+        }),
+        item => Scale::Custom(
+            item_notes(ctx, item)
+                .into_iter()
+                .map(|i| i.rem_euclid(12) as u8)
+                .collect(),
+        ),
+    }
+}
+
+/// Chord given by name, or as a sequence of semitones from the root.
+fn item_chord(ctx: &mut EvaluationContext, item: BoinxItem) -> Chord {
+    match item {
+        BoinxItem::Str(name) => Chord::from_name(&name).unwrap_or_else(|| {
+            log_warn!("Unknown chord '{name}' ! Using major");
+            Chord::Major
+        }),
+        item => Chord::Custom(
+            item_notes(ctx, item)
+                .into_iter()
+                .map(|i| i.clamp(0, 127) as u8)
+                .collect(),
+        ),
+    }
+}
+
+fn notes_item(notes: Vec<i64>) -> Vec<BoinxItem> {
+    notes.into_iter().map(BoinxItem::Note).collect()
+}
+
+/// A single note, or a sequence if there are several.
+fn note_or_sequence(notes: Vec<i64>) -> BoinxItem {
+    if notes.len() == 1 {
+        BoinxItem::Note(notes[0])
+    } else {
+        BoinxItem::Sequence(notes_item(notes))
+    }
+}
+
+/// Defines the Boinx functions from their names, signatures, descriptions and bodies, so
+/// that the documentation lists exactly the functions the evaluation knows.
+macro_rules! boinx_functions {
+    (
+        $ctx:ident, $name:ident, $args:ident;
+        $( $($fname:literal ($sig:literal, $doc:literal))|+ => $body:block )*
+    ) => {
+        /// Boinx functions (signature and description), for completion and documentation.
+        pub const BOINX_FUNCTIONS: &[(&str, &str, &str)] = &[$($(($fname, $sig, $doc),)+)*];
+
+        pub fn execute_boinx_function(
+            $ctx: &mut EvaluationContext,
+            $name: &str,
+            mut $args: Vec<BoinxItem>,
+        ) -> BoinxItem {
+            use BoinxItem::*;
+            match $name {
+                $($($fname)|+ => $body)*
                 _ => {
-                    log_warn!("Argument for 'after' is not a duration !");
-                    TimeSpan::default()
+                    log_warn!("Boinx function '{}' does not exist !", $name);
+                    Mute
                 }
-            };
-            Sequence(vec![WithDuration(Box::new(Mute), dur), Placeholder])
+            }
+        }
+    };
+}
+
+boinx_functions! {
+    ctx, name, args;
+    "choice"("choice(items)", "One of the items, picked at random.") => {
+        args = unpack_if_one(args);
+        let i = ctx.rng.random_range(0..args.len());
+        args.remove(i)
+    }
+    "shuffle"("shuffle(items)", "The items in a random order.") => {
+        args = unpack_if_one(args);
+        args.shuffle(ctx.rng);
+        Sequence(args)
+    }
+    "rev"("rev(items)", "The items in reverse order.") => {
+        args = unpack_if_one(args);
+        args = args.into_iter().rev().collect();
+        Sequence(args)
+    }
+    "range"("range([start,] end)", "The integers from start (0 by default) to end, excluded.") => {
+        let (i1, i2) = if args.len() >= 2 {
+            let mut iter = args.into_iter();
+            let a = VariableValue::from(iter.next().unwrap());
+            let b = VariableValue::from(iter.next().unwrap());
+            let a = a.as_integer(ctx.clock, ctx.frame_len);
+            let b = b.as_integer(ctx.clock, ctx.frame_len);
+            (a, b)
+        } else {
+            let a = VariableValue::from(args.pop().unwrap());
+            let a = a.as_integer(ctx.clock, ctx.frame_len);
+            (0, a)
+        };
+        Sequence((i1..i2).map(Note).collect())
+    }
+    "randrange"("randrange([min,] max)", "A random number between min (0 by default) and max.") => {
+        let (i1, i2) = if args.len() >= 2 {
+            let mut iter = args.into_iter();
+            let a = VariableValue::from(iter.next().unwrap());
+            let b = VariableValue::from(iter.next().unwrap());
+            let a = a.as_float(ctx.clock, ctx.frame_len);
+            let b = b.as_float(ctx.clock, ctx.frame_len);
+            (a, b)
+        } else {
+            let a = VariableValue::from(args.pop().unwrap());
+            let a = a.as_float(ctx.clock, ctx.frame_len);
+            (0.0, a)
+        };
+        Number(ctx.rng.random_range(i1..i2))
+    }
+    "irandrange"("irandrange([min,] max)", "A random integer between min (0 by default) and max, excluded.") => {
+        let (i1, i2) = if args.len() >= 2 {
+            let mut iter = args.into_iter();
+            let a = VariableValue::from(iter.next().unwrap());
+            let b = VariableValue::from(iter.next().unwrap());
+            let a = a.as_integer(ctx.clock, ctx.frame_len);
+            let b = b.as_integer(ctx.clock, ctx.frame_len);
+            (a, b)
+        } else {
+            let a = VariableValue::from(args.pop().unwrap());
+            let a = a.as_integer(ctx.clock, ctx.frame_len);
+            (0, a)
+        };
+        Note(ctx.rng.random_range(i1..i2))
+    }
+    "after"("after(duration)", "Waits for the duration before the item.") => {
+        if args.len() > 1 {
+            log_warn!("Too many arguments for 'after' function, taking only last !");
         }
-        "secs" => {
-            if args.len() > 1 {
-                log_warn!("Too many arguments for 'secs' function ! Taking only last !");
+        let dur = match args.pop().unwrap() {
+            Duration(d) => d,
+            Number(f) => TimeSpan::Frames(f),
+            _ => {
+                log_warn!("Argument for 'after' is not a duration !");
+                TimeSpan::default()
             }
-            let dur = match args.pop().unwrap() {
-                Duration(d) => d,
-                Number(f) => TimeSpan::Frames(f),
-                _ => {
-                    log_warn!("Argument for 'after' is not a duration !");
-                    TimeSpan::default()
-                }
-            };
-            Number(dur.as_secs(ctx.clock, ctx.frame_len))
+        };
+        Sequence(vec![WithDuration(Box::new(Mute), dur), Placeholder])
+    }
+    "secs"("secs(duration)", "The duration in seconds.") => {
+        if args.len() > 1 {
+            log_warn!("Too many arguments for 'secs' function ! Taking only last !");
         }
-        "len" => {
-            if args.len() <= 1 {
-                log_warn!("Too few arguments for 'len' ! Ignoring");
+        let dur = match args.pop().unwrap() {
+            Duration(d) => d,
+            Number(f) => TimeSpan::Frames(f),
+            _ => {
+                log_warn!("Argument for 'after' is not a duration !");
+                TimeSpan::default()
             }
-            let dur = match args.pop().unwrap() {
-                Duration(d) => d,
-                Number(f) => TimeSpan::Frames(f),
-                _ => {
-                    log_warn!("Argument for 'len' is not a duration !");
-                    TimeSpan::default()
-                }
-            };
-            WithDuration(Box::new(Simultaneous(args)), dur)
+        };
+        Number(dur.as_secs(ctx.clock, ctx.frame_len))
+    }
+    "len"("len(items, duration)", "The items played together for the duration.") => {
+        if args.len() <= 1 {
+            log_warn!("Too few arguments for 'len' ! Ignoring");
         }
-        "at" => {
-            if args.len() <= 1 {
-                log_warn!("Too few arguments for 'at' ! Ignoring");
+        let dur = match args.pop().unwrap() {
+            Duration(d) => d,
+            Number(f) => TimeSpan::Frames(f),
+            _ => {
+                log_warn!("Argument for 'len' is not a duration !");
+                TimeSpan::default()
             }
-            let index = match args.pop().unwrap() {
-                Note(i) => i as usize,
-                Number(f) => f as usize,
-                _ => {
-                    log_warn!("Argument for 'at' is not an index !");
-                    0
-                }
-            };
-            let mut args = unpack_if_one(args);
-            args.swap_remove(index % args.len())
+        };
+        WithDuration(Box::new(Simultaneous(args)), dur)
+    }
+    "at"("at(items, index)", "The item at the index, wrapping around.") => {
+        if args.len() <= 1 {
+            log_warn!("Too few arguments for 'at' ! Ignoring");
+        }
+        let index = match args.pop().unwrap() {
+            Note(i) => i as usize,
+            Number(f) => f as usize,
+            _ => {
+                log_warn!("Argument for 'at' is not an index !");
+                0
+            }
+        };
+        let mut args = unpack_if_one(args);
+        args.swap_remove(index % args.len())
+    }
+    "seed"("seed(value)", "Reseeds the random stream of the frame.") => {
+        if args.len() > 1 {
+            log_warn!("Too many arguments for 'seed' function ! Taking only last !");
+        }
+        let Some(seed) = args.pop() else {
+            log_warn!("Missing argument for 'seed' function !");
+            return Mute;
+        };
+        let seed = VariableValue::from(seed).as_integer(ctx.clock, ctx.frame_len);
+        ctx.rng.reseed(seed as u64);
+        Mute
+    }
+    "lfo"("lfo(name)", "The current value of the scene modulator.") => {
+        let Some(modulator) = args.pop() else {
+            log_warn!("Missing modulator name for 'lfo' function !");
+            return Mute;
+        };
+        let modulator = VariableValue::from(modulator).as_str(ctx.clock, ctx.frame_len);
+        let beat = ctx.clock.beat_at_date(ctx.logic_date);
+        match ctx.modulators.get(&modulator) {
+            Some(m) => Number(m.value_at(beat)),
+            None => {
+                log_warn!("Modulator '{modulator}' does not exist !");
+                Mute
+            }
+        }
+    }
+    "bend"("bend(value)", "A MIDI pitch bend, from 0 to 16383 (8192 being the center).") => {
+        let Some(value) = args.pop() else {
+            log_warn!("Missing value for 'bend' function !");
+            return Mute;
+        };
+        ArgMap(HashMap::from([("bend".to_owned(), value)]))
+    }
+    "cc14"("cc14(control, value)", "A 14-bit MIDI control change (control 0 to 31).")
+    | "nrpn"("nrpn(parameter, value)", "A MIDI NRPN message.")
+    | "rpn"("rpn(parameter, value)", "A MIDI RPN message.")
+    | "note_bend"("note_bend(note, value)", "A per-note pitch bend, in MPE mode.")
+    | "note_press"("note_press(note, value)", "A per-note pressure, in MPE mode.")
+    | "note_timbre"("note_timbre(note, value)", "A per-note timbre (CC 74), in MPE mode.")
+    => {
+        if args.len() < 2 {
+            log_warn!("'{name}' function needs a parameter and a value !");
+            return Mute;
+        }
+        let mut iter = args.into_iter();
+        ArgMap(HashMap::from([
+            (name.to_owned(), iter.next().unwrap()),
+            ("value".to_owned(), iter.next().unwrap()),
+        ]))
+    }
+    "ramp"("ramp(target, from, to[, curve[, res]])", "A ramp of a control, the pitch bend or an OSC value over the duration of the event, with a step every res ms.") => {
+        if args.len() < 3 {
+            log_warn!("'ramp' function needs a target, a start and an end value !");
+            return Mute;
+        }
+        let mut iter = args.into_iter();
+        let mut map = HashMap::new();
+        map.insert("ramp".to_owned(), iter.next().unwrap());
+        map.insert("from".to_owned(), iter.next().unwrap());
+        map.insert("to".to_owned(), iter.next().unwrap());
+        if let Some(curve) = iter.next() {
+            map.insert("curve".to_owned(), curve);
         }
-        _ => {
-            log_warn!("Boinx function '{name}' does not exist !");
-            BoinxItem::Mute
+        if let Some(resolution) = iter.next() {
+            map.insert("res".to_owned(), resolution);
+        }
+        ArgMap(map)
+    }
+    "scale"("scale(name[, root])", "The notes of a scale, given by name or semitones, from the root (60 by default).")
+    | "chord"("chord(name[, root])", "The notes of a chord, given by name or semitones, from the root (60 by default).")
+    => {
+        let mut iter = args.into_iter();
+        let Some(kind) = iter.next() else {
+            log_warn!("Missing {name} name for '{name}' function !");
+            return Mute;
+        };
+        let root = iter.next().map(|r| item_note(ctx, r)).unwrap_or(60);
+        if name == "scale" {
+            Sequence(notes_item(item_scale(ctx, kind).notes(root)))
+        } else {
+            Simultaneous(notes_item(item_chord(ctx, kind).notes(root)))
+        }
+    }
+    "degree"("degree(scale, root, degrees)", "The notes at the degrees of the scale.")
+    | "snap"("snap(scale, root, notes)", "The notes moved to the closest notes of the scale.")
+    => {
+        if args.len() < 3 {
+            log_warn!("'{name}' function needs a scale, a root and notes !");
+            return Mute;
+        }
+        let mut iter = args.into_iter();
+        let scale = item_scale(ctx, iter.next().unwrap());
+        let root = item_note(ctx, iter.next().unwrap());
+        let values: Vec<i64> = iter.flat_map(|i| item_notes(ctx, i)).collect();
+        let notes = values
+            .into_iter()
+            .map(|v| match name {
+                "degree" => scale.degree(v, root),
+                _ => scale.quantize(v, root.rem_euclid(12)),
+            })
+            .collect();
+        note_or_sequence(notes)
+    }
+    "degree_chord"("degree_chord(scale, root, degree[, voices])", "The chord built on a degree of the scale, with 3 voices by default.") => {
+        if args.len() < 3 {
+            log_warn!("'degree_chord' function needs a scale, a root and a degree !");
+            return Mute;
+        }
+        let mut iter = args.into_iter();
+        let scale = item_scale(ctx, iter.next().unwrap());
+        let root = item_note(ctx, iter.next().unwrap());
+        let degree = item_note(ctx, iter.next().unwrap());
+        let voices = iter.next().map(|v| item_note(ctx, v)).unwrap_or(3);
+        Simultaneous(notes_item(scale.degree_chord(degree, root, voices.max(0) as usize)))
+    }
+    "inv"("inv(chord, inversion)", "An inversion of the chord.") => {
+        if args.len() < 2 {
+            log_warn!("'inv' function needs a chord and an inversion !");
+            return Mute;
+        }
+        let inversion = args.pop().unwrap();
+        let inversion = item_note(ctx, inversion);
+        let chord: Vec<i64> = args.into_iter().flat_map(|i| item_notes(ctx, i)).collect();
+        Simultaneous(notes_item(theory::invert(&chord, inversion)))
+    }
+    "lead"("lead(previous, next)", "The next chord voiced closest to the previous one.") => {
+        if args.len() < 2 {
+            log_warn!("'lead' function needs a previous and a next chord !");
+            return Mute;
+        }
+        let mut iter = args.into_iter();
+        let from = item_notes(ctx, iter.next().unwrap());
+        let to = item_notes(ctx, iter.next().unwrap());
+        Simultaneous(notes_item(theory::voice_lead(&from, &to)))
+    }
+    "arp"("arp(chord[, mode[, octaves]])", "The chord arpeggiated (up by default) over octaves.") => {
+        let mut iter = args.into_iter();
+        let Some(chord) = iter.next() else {
+            log_warn!("Missing chord for 'arp' function !");
+            return Mute;
+        };
+        let chord = item_notes(ctx, chord);
+        let arpeggio = match iter.next() {
+            Some(Str(mode)) => Arpeggio::from_name(&mode).unwrap_or_else(|| {
+                log_warn!("Unknown arpeggio '{mode}' ! Using up");
+                Arpeggio::Up
+            }),
+            _ => Arpeggio::Up,
+        };
+        let octaves = iter.next().map(|o| item_note(ctx, o)).unwrap_or(1);
+        Sequence(notes_item(arpeggio.notes(&chord, octaves.max(1) as usize)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn functions_are_listed_once() {
+        for (i, (name, signature, _)) in BOINX_FUNCTIONS.iter().enumerate() {
+            assert!(signature.starts_with(&format!("{name}(")));
+            assert!(BOINX_FUNCTIONS[..i].iter().all(|(other, _, _)| other != name));
         }
+        assert!(BOINX_FUNCTIONS.iter().any(|(name, _, _)| *name == "note_timbre"));
     }
 }
@@ -1,5 +1,7 @@
 use std::{collections::BTreeSet, fmt::Display};
 
+use rand::Rng;
+
 use crate::{
     clock::TimeSpan, lang::boinx::ast::{BoinxArithmeticOp, BoinxCompo, BoinxItem}, log_eprintln, vm::{EvaluationContext, variable::Variable}
 };
@@ -26,7 +28,7 @@ impl Display for BoinxIdentQualif {
     }
 }
 
-pub fn env_func(name: &str, ctx: &EvaluationContext) -> BoinxItem {
+pub fn env_func(name: &str, ctx: &mut EvaluationContext) -> BoinxItem {
     use BoinxArithmeticOp::*;
     use BoinxItem::*;
     match name {
@@ -78,8 +80,8 @@ pub fn env_func(name: &str, ctx: &EvaluationContext) -> BoinxItem {
         "micros" => Duration(TimeSpan::Micros(ctx.logic_date)),
         "tempo" => Number(ctx.clock.tempo()),
         "quantum" => Number(ctx.clock.quantum()),
-        "rand" => Number(rand::random()),
-        "irand" => Note(rand::random()),
+        "rand" => Number(ctx.rng.random()),
+        "irand" => Note(ctx.rng.random()),
         _ if name.starts_with("seq") => {
             let value = &name[3..];
             if let Ok(n) = value.parse::<usize>() {
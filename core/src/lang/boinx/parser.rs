@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use pest::{
     Parser,
+    error::InputLocation,
     iterators::{Pair, Pairs},
     pratt_parser::PrattParser,
 };
@@ -12,7 +13,7 @@ use crate::{
     compiler::CompilationError,
     lang::boinx::ast::{
         BoinxArithmeticOp, BoinxCompo, BoinxCompoOp, BoinxCondition, BoinxConditionOp, BoinxIdent,
-        BoinxItem, BoinxOutput, BoinxProg, BoinxStatement,
+        BoinxItem, BoinxOutput, BoinxProg, BoinxStatement, funcs::BOINX_FUNCTIONS,
     },
 };
 
@@ -266,14 +267,58 @@ fn parse_prog(pairs: Pairs<Rule>) -> BoinxProg {
     BoinxProg(statements)
 }
 
+/// Checks that every called function exists, pointing to the call of the first unknown one.
+fn check_functions(pairs: &Pairs<Rule>) -> Result<(), CompilationError> {
+    for call in pairs.clone().flatten().filter(|p| p.as_rule() == Rule::func) {
+        let name = call.clone().into_inner().next().unwrap().as_str();
+        // Functions prefixed by '_' are evaluated along with the variables
+        let name = name.strip_prefix('_').unwrap_or(name);
+        if BOINX_FUNCTIONS.iter().any(|(known, _, _)| *known == name) {
+            continue;
+        }
+        let span = call.as_span();
+        return Err(CompilationError {
+            lang: "boinx".to_owned(),
+            info: format!("Unknown function '{name}'"),
+            from: span.start(),
+            to: span.end(),
+        });
+    }
+    Ok(())
+}
+
 pub fn parse_boinx(prog: &str) -> Result<BoinxProg, CompilationError> {
     match BoinxParser::parse(Rule::prog, prog) {
-        Ok(pairs) => Ok(parse_prog(pairs)),
-        Err(e) => Err(CompilationError {
-            lang: "boinx".to_owned(),
-            info: format!("Parsing error: {e}"),
-            from: 0,
-            to: 0,
-        }),
+        Ok(pairs) => {
+            check_functions(&pairs)?;
+            Ok(parse_prog(pairs))
+        }
+        Err(e) => {
+            let (from, to) = match e.location {
+                InputLocation::Pos(pos) => (pos, pos),
+                InputLocation::Span(span) => span,
+            };
+            Err(CompilationError {
+                lang: "boinx".to_owned(),
+                info: format!("Parsing error: {e}"),
+                from,
+                to,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_functions_are_located() {
+        let script = "a = choice(60, 62)\nrev(a _shuffle(1, 2) fliip(60))";
+        let error = parse_boinx(script).unwrap_err();
+        assert_eq!(error.info, "Unknown function 'fliip'");
+        assert_eq!(&script[error.from..error.to], "fliip(60)");
+
+        assert!(parse_boinx("a = choice(60, 62)\nrev(a _shuffle(1, 2))").is_ok());
     }
 }
@@ -0,0 +1,109 @@
+//! Formatting of the scripts. Bali scripts are printed back from their program in a
+//! canonical layout, keeping their comments. Boinx scripts are reindented following the
+//! depth of their brackets, with trailing whitespace removed: comments, sugar and line
+//! breaks are kept, so that formatting never changes what a script does.
+
+use crate::lang::bali::format_bali;
+
+/// Indentation of a level of brackets, unless the editor has its own.
+pub const INDENT: &str = "  ";
+
+/// Formats a script of a language, Boinx scripts being indented with `indent`.
+pub fn format_script(lang: &str, text: &str, indent: &str) -> Result<String, String> {
+    match lang {
+        "bali" => format_bali(text).map_err(|e| format!("Cannot format the script: {}", e.info)),
+        "boinx" => Ok(reindent(text, indent)),
+        _ => Err(format!("No formatter for language '{lang}'.")),
+    }
+}
+
+/// State of the scan of a Boinx script, kept from a line to the next.
+#[derive(Default)]
+struct Scan {
+    depth: usize,
+    /// Closing quote of the string being scanned
+    string: Option<char>,
+}
+
+impl Scan {
+    /// Scans a line, updating the depth of the brackets outside strings and comments.
+    fn line(&mut self, line: &str) {
+        let mut previous = ' ';
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match self.string {
+                Some(quote) if c == quote => self.string = None,
+                Some(_) => {}
+                None => match c {
+                    '/' if chars.peek() == Some(&'/') => return,
+                    '"' => self.string = Some('"'),
+                    // `'` also marks durations in beats, after a number
+                    '\'' if !previous.is_ascii_digit() && previous != '\'' => {
+                        self.string = Some('\'')
+                    }
+                    '(' | '[' | '{' => self.depth += 1,
+                    ')' | ']' | '}' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                },
+            }
+            previous = c;
+        }
+    }
+}
+
+/// Number of closing brackets starting a line, dedenting it.
+fn leading_closers(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| matches!(c, ')' | ']' | '}'))
+        .count()
+}
+
+/// Reindents a Boinx script, indenting each level of brackets with `indent`.
+pub fn reindent(text: &str, indent: &str) -> String {
+    let mut scan = Scan::default();
+    let mut formatted = String::with_capacity(text.len());
+    for line in text.lines() {
+        if scan.string.is_some() {
+            // Continuation of a string spanning several lines, kept as it is
+            formatted.push_str(line);
+        } else {
+            let trimmed = line.trim();
+            if !trimmed.is_empty() {
+                let level = scan.depth.saturating_sub(leading_closers(trimmed));
+                formatted.push_str(&indent.repeat(level));
+                formatted.push_str(trimmed.trim_end());
+            }
+        }
+        formatted.push('\n');
+        scan.line(line);
+    }
+    while formatted.ends_with("\n\n") {
+        formatted.pop();
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bali_is_printed_with_its_comments() {
+        let text = "(> 0   (loop 4\n(note 60)))  ; comment (\n(note 62)\n; on its own line";
+        assert_eq!(
+            format_script("bali", text, "\t").unwrap(),
+            "(> 0 (loop 4 (note 60))) ; comment (\n(note 62)\n; on its own line\n"
+        );
+        assert!(format_script("bali", "(note", "  ").is_err());
+        assert!(format_script("lua", "", "  ").is_err());
+    }
+
+    #[test]
+    fn boinx_durations_are_not_strings() {
+        let text = "{\n1' 'a(' [\nC4\n]\n}";
+        assert_eq!(
+            format_script("boinx", text, "\t").unwrap(),
+            "{\n\t1' 'a(' [\n\t\tC4\n\t]\n}\n"
+        );
+    }
+}
@@ -9,6 +9,7 @@ pub mod scene;
 pub mod schedule;
 pub mod server;
 pub mod util;
+pub mod theory;
 pub mod world;
 pub mod init;
 
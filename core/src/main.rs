@@ -9,6 +9,8 @@ use device_map::DeviceMap;
 use scene::Line;
 use scene::Scene;
 use schedule::SchedulerMessage;
+use server::auth::{AuthConfig, Role};
+use server::federation::MirrorConfig;
 use server::{ServerState, SovaCoreServer};
 use std::io::ErrorKind;
 use std::sync::Arc;
@@ -27,6 +29,7 @@ pub mod protocol;
 pub mod scene;
 pub mod schedule;
 pub mod server;
+pub mod theory;
 pub mod util;
 pub mod vm;
 pub mod world;
@@ -77,6 +80,99 @@ struct Cli {
     /// Initial quantum in beats
     #[arg(short, long, value_name = "BEATS", default_value_t = DEFAULT_QUANTUM)]
     quantum: f64,
+
+    /// Also accept WebSocket clients (JSON or MessagePack messages) on this port
+    #[arg(long, value_name = "PORT")]
+    websocket: Option<u16>,
+
+    /// Accept OSC remote control messages (/sova/...) on this UDP port
+    #[arg(long, value_name = "PORT")]
+    osc_control: Option<u16>,
+
+    /// Mirror lines of another server, read-only here: [NAME[:TOKEN]@]HOST:PORT/LINES,
+    /// LINES listing remote lines, optionally with their local line (e.g. 0=4,1=5). Repeatable.
+    /// A TOKEN written $VAR is read from the environment variable VAR: tokens on the command
+    /// line are visible to the other users of the machine.
+    #[arg(long, value_name = "MIRROR")]
+    mirror: Vec<MirrorConfig>,
+
+    /// File holding the token of the mirrors given without one (on its first line)
+    #[arg(long, value_name = "FILE", requires = "mirror")]
+    mirror_token_file: Option<std::path::PathBuf>,
+
+    /// JSON file of authentication settings (shared secret, user tokens and roles)
+    #[arg(long, value_name = "FILE")]
+    auth: Option<std::path::PathBuf>,
+
+    /// Shared secret clients must log in with. It is visible to the other users of the
+    /// machine: prefer --secret-file or the SOVA_SECRET environment variable.
+    #[arg(long, value_name = "SECRET")]
+    secret: Option<String>,
+
+    /// File holding the shared secret clients must log in with (on its first line)
+    #[arg(long, value_name = "FILE", conflicts_with = "secret")]
+    secret_file: Option<std::path::PathBuf>,
+
+    /// Role of the clients logging in without credentials (viewer, performer or admin).
+    /// They are refused if the server has credentials and this is not set.
+    #[arg(long, value_name = "ROLE")]
+    anonymous: Option<Role>,
+}
+
+/// Environment variable holding the shared secret, when not given on the command line.
+const SECRET_VAR: &str = "SOVA_SECRET";
+
+/// Builds the authentication settings from the command line.
+fn load_auth(cli: &Cli) -> Result<AuthConfig, String> {
+    let mut auth = match &cli.auth {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid authentication file '{}': {}", path.display(), e))?
+        }
+        None => AuthConfig::default(),
+    };
+    if let Some(path) = &cli.secret_file {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+        auth.secret = content.lines().next().map(|line| line.trim().to_owned());
+    } else if cli.secret.is_some() {
+        auth.secret = cli.secret.clone();
+    } else if let Ok(secret) = std::env::var(SECRET_VAR) {
+        auth.secret = Some(secret);
+    }
+    if auth.secret.as_deref().is_some_and(str::is_empty) {
+        return Err("The shared secret is empty.".to_owned());
+    }
+    if cli.anonymous.is_some() {
+        auth.anonymous = cli.anonymous;
+    }
+    Ok(auth)
+}
+
+/// Mirrors of the command line, with their tokens read from the environment or a file.
+fn load_mirrors(cli: &Cli) -> Result<Vec<MirrorConfig>, String> {
+    let file_token = match &cli.mirror_token_file {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+            let token = content.lines().next().map(|line| line.trim().to_owned());
+            if token.as_deref().is_none_or(str::is_empty) {
+                return Err(format!("The mirror token in '{}' is empty.", path.display()));
+            }
+            token
+        }
+        None => None,
+    };
+    let mut mirrors = cli.mirror.clone();
+    for mirror in mirrors.iter_mut() {
+        mirror.resolve_token(|name| std::env::var(name).ok())?;
+        if mirror.token.is_none() {
+            mirror.token = file_token.clone();
+        }
+    }
+    Ok(mirrors)
 }
 
 #[tokio::main]
@@ -91,6 +187,20 @@ async fn main() {
     // ======================================================================
     // Parse CLI arguments first
     let cli = Cli::parse();
+    let auth = match load_auth(&cli) {
+        Ok(auth) => auth,
+        Err(e) => {
+            eprintln!("[!] {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mirrors = match load_mirrors(&cli) {
+        Ok(mirrors) => mirrors,
+        Err(e) => {
+            eprintln!("[!] {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // ======================================================================
     // Initialize logger and immediately set up full mode for complete logging
@@ -200,9 +310,28 @@ async fn main() {
         update_sender.clone(),
         languages,
     );
+    if auth.is_open() {
+        log_println!("[!] No secret nor user tokens: every client has full access.");
+    } else {
+        log_println!(
+            "[+] Authentication required ({} user tokens, anonymous clients: {}).",
+            auth.users.len(),
+            auth.anonymous.map_or("refused".to_string(), |role| role.to_string())
+        );
+    }
+    let server_state = server_state.with_auth(auth);
 
     // Use parsed arguments
-    let server = SovaCoreServer::new(cli.ip, cli.port, server_state);
+    let mut server = SovaCoreServer::new(cli.ip, cli.port, server_state);
+    if let Some(port) = cli.websocket {
+        server = server.with_websocket(port);
+    }
+    if let Some(port) = cli.osc_control {
+        server = server.with_osc_control(port);
+    }
+    for mirror in mirrors {
+        server = server.with_mirror(mirror);
+    }
     log_println!(
         "[+] Starting Sova server on {}:{}...",
         server.ip,
@@ -7,6 +7,9 @@
 //! - `log`: Handles structures and logic for internal logging messages.
 //! - `midi`: Contains definitions related to the MIDI protocol
 //! - `osc`: Contains definitions for the Open Sound Control (OSC) protocol
+//! - `superdirt`: Defines the SuperDirt output, sending timestamped `/dirt/play` bundles.
+//! - `ramp`: Defines continuous parameter changes, expanded into timed MIDI or OSC messages.
+//! - `transform`: Defines the note transformations applied per device slot.
 //! - `payload`: Defines the `ProtocolPayload` enum which encapsulates protocol-specific
 //!   data (MIDI, OSC, Log).
 //! - `message`: Defines the `ProtocolMessage` and `TimedMessage` structs representing a
@@ -19,6 +22,9 @@
 pub mod log;
 pub mod midi;
 pub mod osc;
+pub mod ramp;
+pub mod superdirt;
+pub mod transform;
 
 pub mod audio_engine_proxy;
 
@@ -2,11 +2,14 @@ use crate::clock::{Clock, SyncTime};
 use crate::vm::event::ConcreteEvent;
 use crate::protocol::audio_engine_proxy::{AudioEnginePayload, AudioEngineProxy};
 use crate::protocol::error::ProtocolError;
-use crate::protocol::log;
-use crate::protocol::midi::{MIDIMessage, MidiIn};
+use crate::protocol::log::{self, LogOut};
+use crate::protocol::midi::{MidiIn, MpeZone, NoteOverlap};
+use crate::protocol::transform::SlotTransform;
+use crate::theory::{Tuning, TuningOutput};
 use crate::protocol::osc::{OSCMessage, OSCOut};
+use crate::protocol::superdirt::{DirtProfile, SuperDirtOut};
 use crate::protocol::{midi::MidiOut, payload::ProtocolPayload};
-use crate::{log_eprintln, LogMessage};
+use crate::log_eprintln;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug, Display};
 
@@ -20,6 +23,21 @@ pub struct DeviceInfo {
     pub direction: DeviceDirection,
     pub is_connected: bool,
     pub address: Option<String>,
+    /// MPE zone of a MIDI output in MPE mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mpe: Option<MpeZone>,
+    /// Overlapping notes policy of a MIDI output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlap: Option<NoteOverlap>,
+    /// Transformations applied to the events sent to the slot of the device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<SlotTransform>,
+    /// How the device realizes the tuning of the scene, if not its default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tuning_output: Option<TuningOutput>,
+    /// Orbits and default parameters of a SuperDirt output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dirt: Option<DirtProfile>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
@@ -27,6 +45,7 @@ pub enum DeviceKind {
     Midi,
     VirtualMidi,
     Osc,
+    SuperDirt,
     Log,
     AudioEngine,
     Missing,
@@ -40,6 +59,7 @@ impl Display for DeviceKind {
             DeviceKind::Midi => write!(f, "Midi"),
             DeviceKind::VirtualMidi => write!(f, "VirtualMidi"),
             DeviceKind::Osc => write!(f, "Osc"),
+            DeviceKind::SuperDirt => write!(f, "SuperDirt"),
             DeviceKind::Log => write!(f, "Log"),
             DeviceKind::AudioEngine => write!(f, "AudioEngine"),
             DeviceKind::Missing => write!(f, "Missing"),
@@ -72,7 +92,7 @@ impl Display for DeviceDirection {
 /// while output devices handle sending messages.
 pub enum ProtocolDevice {
     /// Internal logging device, typically writing to standard output.
-    Log,
+    Log(LogOut),
     /// A physical or virtual MIDI input device, wrapping a `MidiIn` handler.
     /// Access is shared and thread-safe via `Arc<Mutex<>>`.
     MIDIInDevice(MidiIn),
@@ -89,6 +109,8 @@ pub enum ProtocolDevice {
     OSCInDevice,
     /// An OSC output device targeting a specific network address.
     OSCOutDevice(OSCOut),
+    /// An OSC output sending timestamped `/dirt/play` bundles to SuperDirt.
+    SuperDirtDevice(SuperDirtOut),
     /// Internal audio engine (Sova) - no external connectivity required
     AudioEngine(AudioEngineProxy),
 }
@@ -125,7 +147,10 @@ impl ProtocolDevice {
             ProtocolDevice::OSCOutDevice(osc_out) => {
                 osc_out.connect()
             }
-            ProtocolDevice::Log => Ok(()), // Log device doesn't need connection
+            ProtocolDevice::SuperDirtDevice(dirt_out) => {
+                dirt_out.connect()
+            }
+            ProtocolDevice::Log(_) => Ok(()), // Log device doesn't need connection
             ProtocolDevice::AudioEngine { .. } => Ok(()), // AudioEngine doesn't need external connection
         }
     }
@@ -136,7 +161,8 @@ impl ProtocolDevice {
     /// - `MIDIOutDevice`/`VirtualMIDIOutDevice`: Sends MIDI bytes via `midir`.
     /// - `OSCOutDevice`: Encodes the `OSCMessage` into an OSC `OscBundle`
     ///   with a timestamp (`now + latency`) via `rosc` and sends it over the UDP socket.
-    /// - `Log`: Prints the `LogMessage` content to standard output.
+    /// - `Log`: Prints the `LogMessage` content to standard output, and the notes
+    ///   with their voice bookkeeping (see `LogOut`).
     /// - Input devices (`MIDIInDevice`, `OSCInDevice`): Returns an error as sending
     ///   to an input is not possible.
     ///
@@ -179,20 +205,16 @@ impl ProtocolDevice {
                 };
                 osc_out.send(crate_osc_msg)
             }
-            ProtocolDevice::Log => {
-                let ProtocolPayload::LOG(log_msg) = message else {
-                    return Err(ProtocolError(
-                        "Invalid message format for Log device!".to_owned(),
-                    ));
+            ProtocolDevice::SuperDirtDevice(dirt_out) => {
+                let ProtocolPayload::OSC(crate_osc_msg) = message else {
+                    return Err(ProtocolError(format!(
+                        "Invalid message format for SuperDirt device '{}'!",
+                        dirt_out.osc.name
+                    )));
                 };
-                // Simple stdout logging implementation
-                crate::log_println!("[LOG][{}] {}", log_msg.level, log_msg.msg);
-                if let Some(event) = log_msg.event {
-                    // Use debug formatting for the associated event if present
-                    crate::log_println!("    Associated Event: {:?}", event);
-                }
-                Ok(())
+                dirt_out.send(crate_osc_msg)
             }
+            ProtocolDevice::Log(log_out) => log_out.send(message),
             ProtocolDevice::AudioEngine(proxy) => {
                 let ProtocolPayload::AudioEngine(msg) = message else {
                     return Err(ProtocolError(
@@ -233,10 +255,11 @@ impl ProtocolDevice {
                     osc_out.name, osc_out.address
                 );
             }
-            ProtocolDevice::Log
+            ProtocolDevice::Log(_)
             | ProtocolDevice::MIDIInDevice(_)
             | ProtocolDevice::VirtualMIDIInDevice(_)
             | ProtocolDevice::OSCInDevice
+            | ProtocolDevice::SuperDirtDevice(_)
             | ProtocolDevice::AudioEngine { .. } => {
                 // No flushing mechanism for Log, AudioEngine, Control, or input devices
             }
@@ -254,7 +277,7 @@ impl ProtocolDevice {
     /// - `OSCInDevice`: Returns a placeholder string ("OSC_IN_ADDRESS_TBD").
     pub fn address(&self) -> String {
         match self {
-            ProtocolDevice::Log => log::LOG_NAME.to_string(), // Use constant if available
+            ProtocolDevice::Log(_) => log::LOG_NAME.to_string(), // Use constant if available
             ProtocolDevice::OSCInDevice => "OSC_IN_ADDRESS_TBD".to_string(), // Placeholder
             ProtocolDevice::MIDIInDevice(midi_in) 
             | ProtocolDevice::VirtualMIDIInDevice(midi_in) 
@@ -263,37 +286,91 @@ impl ProtocolDevice {
             | ProtocolDevice::VirtualMIDIOutDevice(midi_out) 
                 => midi_out.name.clone(),
             ProtocolDevice::OSCOutDevice(osc_out) => osc_out.address.to_string(),
+            ProtocolDevice::SuperDirtDevice(dirt_out) => dirt_out.osc.address.to_string(),
             ProtocolDevice::AudioEngine { .. } => "Internal".to_string(),
         }
     }
 
+    /// OSC connection of an OSC or SuperDirt output, None for other devices.
+    pub fn osc_out(&self) -> Option<&OSCOut> {
+        match self {
+            ProtocolDevice::OSCOutDevice(osc_out) => Some(osc_out),
+            ProtocolDevice::SuperDirtDevice(dirt_out) => Some(&dirt_out.osc),
+            _ => None,
+        }
+    }
+
+    /// Orbits and default parameters of a SuperDirt output, None for other devices.
+    pub fn dirt_profile(&self) -> Option<DirtProfile> {
+        match self {
+            ProtocolDevice::SuperDirtDevice(dirt_out) => Some(dirt_out.profile()),
+            _ => None,
+        }
+    }
+
+    /// MPE zone of a MIDI output in MPE mode, None otherwise.
+    pub fn mpe_zone(&self) -> Option<MpeZone> {
+        match self {
+            ProtocolDevice::MIDIOutDevice(midi_out)
+            | ProtocolDevice::VirtualMIDIOutDevice(midi_out) => midi_out.mpe_zone(),
+            _ => None,
+        }
+    }
+
+    /// Overlapping notes policy of a MIDI output, None for other devices.
+    pub fn note_overlap(&self) -> Option<NoteOverlap> {
+        match self {
+            ProtocolDevice::MIDIOutDevice(midi_out)
+            | ProtocolDevice::VirtualMIDIOutDevice(midi_out) => Some(midi_out.note_overlap()),
+            _ => None,
+        }
+    }
+
+    /// Tuning outputs supported by the device, the first one being its default.
+    pub fn tuning_outputs(&self) -> &'static [TuningOutput] {
+        match self {
+            ProtocolDevice::MIDIOutDevice(_) | ProtocolDevice::VirtualMIDIOutDevice(_) => {
+                &[TuningOutput::PitchBend, TuningOutput::Mts, TuningOutput::Off]
+            }
+            ProtocolDevice::OSCOutDevice(_) | ProtocolDevice::SuperDirtDevice(_) => {
+                &[TuningOutput::Note, TuningOutput::Frequency, TuningOutput::Off]
+            }
+            _ => &[TuningOutput::Off],
+        }
+    }
+
     pub fn kind(&self) -> DeviceKind {
         match self {
-            ProtocolDevice::Log => DeviceKind::Log,
+            ProtocolDevice::Log(_) => DeviceKind::Log,
             ProtocolDevice::MIDIInDevice(_) 
             | ProtocolDevice::MIDIOutDevice(_) => DeviceKind::Midi,
             ProtocolDevice::VirtualMIDIInDevice(_) 
             | ProtocolDevice::VirtualMIDIOutDevice(_) => DeviceKind::VirtualMidi,
             ProtocolDevice::OSCOutDevice(_) 
             | ProtocolDevice::OSCInDevice => DeviceKind::Osc,
+            ProtocolDevice::SuperDirtDevice(_) => DeviceKind::SuperDirt,
             ProtocolDevice::AudioEngine { .. } => DeviceKind::AudioEngine,
         }
     }
 
-    pub fn translate_event(&self, event: ConcreteEvent, date: SyncTime, clock: &Clock) 
-        -> Vec<(ProtocolPayload, SyncTime)> 
-    {
+    pub fn translate_event(
+        &self,
+        event: ConcreteEvent,
+        date: SyncTime,
+        clock: &Clock,
+        tuning: Option<(&Tuning, TuningOutput)>,
+    ) -> Vec<(ProtocolPayload, SyncTime)> {
         match self {
             ProtocolDevice::OSCOutDevice(out) => {
-                OSCMessage::generate_messages(out, event, date, clock)
+                OSCMessage::generate_messages(out, event, date, clock, tuning)
             }
-            ProtocolDevice::MIDIOutDevice(midi_out) | ProtocolDevice::VirtualMIDIOutDevice(midi_out)=> {
-                MIDIMessage::generate_messages(event, date, midi_out.epsilon)
+            ProtocolDevice::SuperDirtDevice(out) => {
+                out.generate_messages(event, date, clock, tuning)
             }
-            ProtocolDevice::Log => {
-                // Should be unreachable due to the initial check, but kept defensively.
-                LogMessage::generate_messages(event, date)
+            ProtocolDevice::MIDIOutDevice(midi_out) | ProtocolDevice::VirtualMIDIOutDevice(midi_out)=> {
+                midi_out.generate_messages(event, date, tuning)
             }
+            ProtocolDevice::Log(log_out) => log_out.generate_messages(event, date),
             ProtocolDevice::AudioEngine { .. } => {
                 AudioEnginePayload::generate_messages(event, date)
             }
@@ -329,12 +406,18 @@ impl From<OSCOut> for ProtocolDevice {
     }
 }
 
+impl From<SuperDirtOut> for ProtocolDevice {
+    fn from(value: SuperDirtOut) -> Self {
+        Self::SuperDirtDevice(value)
+    }
+}
+
 // Custom Debug implementation to avoid printing the full internal state
 // of handlers (MidiIn/Out, UdpSocket, MidiOutputConnection) which can be large.
 impl Debug for ProtocolDevice {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ProtocolDevice::Log => write!(f, "Log"),
+            ProtocolDevice::Log(_) => write!(f, "Log"),
             ProtocolDevice::OSCInDevice => write!(f, "OSCInDevice"),
             ProtocolDevice::MIDIInDevice(midi_in) 
             | ProtocolDevice::VirtualMIDIInDevice(midi_in) => {
@@ -347,6 +430,9 @@ impl Debug for ProtocolDevice {
             ProtocolDevice::OSCOutDevice(osc_out) => {
                 Debug::fmt(osc_out, f)
             }
+            ProtocolDevice::SuperDirtDevice(dirt_out) => {
+                Debug::fmt(dirt_out, f)
+            }
             ProtocolDevice::AudioEngine { ..}=> write!(f, "AudioEngine"),
         }
     }
@@ -356,7 +442,7 @@ impl Display for ProtocolDevice {
     /// Formats the device for display, typically using its name or type.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ProtocolDevice::Log => write!(f, "Log"),
+            ProtocolDevice::Log(_) => write!(f, "Log"),
             ProtocolDevice::OSCInDevice => write!(f, "OSCInDevice"),
             ProtocolDevice::MIDIInDevice(midi_in) 
             | ProtocolDevice::VirtualMIDIInDevice(midi_in) => {
@@ -368,6 +454,8 @@ impl Display for ProtocolDevice {
             }
             ProtocolDevice::OSCOutDevice(osc_out) 
                 => write!(f, "OSCOutDevice({})", osc_out.name),
+            ProtocolDevice::SuperDirtDevice(dirt_out)
+                => write!(f, "SuperDirtDevice({})", dirt_out.osc.name),
             ProtocolDevice::AudioEngine { .. } => write!(f, "AudioEngine"),
         }
     }
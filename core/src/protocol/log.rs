@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
 
 use crate::clock::SyncTime;
 use crate::vm::event::ConcreteEvent;
+use crate::protocol::error::ProtocolError;
+use crate::protocol::midi::{MIDIMessage, MIDIMessageType, VoiceTracker};
 use crate::protocol::payload::ProtocolPayload;
 
 /// Represents the severity level of a log message.
@@ -135,15 +138,39 @@ impl LogMessage {
 
     /// Creates a new `LogMessage` from a `ConcreteEvent` and severity level.
     ///
-    /// The message text is derived from the event's debug representation.
+    /// The message text is a readable description for 14-bit MIDI events,
+    /// and the event's debug representation otherwise.
     pub fn from_event(level: Severity, event: ConcreteEvent) -> Self {
         LogMessage {
             level,
             event: None,
-            msg: format!("{:?}", event),
+            msg: Self::describe(&event),
         }
     }
 
+    fn describe(event: &ConcreteEvent) -> String {
+        match event {
+            ConcreteEvent::MidiPitchBend(value, chan, _) => {
+                let bend = (*value as f64 - 8192.0) / 8192.0;
+                format!("PitchBend chan {chan} : {value} ({bend:+.3})")
+            }
+            ConcreteEvent::MidiControl14(control, value, chan, _) => {
+                format!("CC14 chan {chan} : {control}/{} = {value}", control + 32)
+            }
+            ConcreteEvent::MidiNrpn(param, value, chan, _) => {
+                format!("NRPN chan {chan} : {} ({param}) = {value}", Self::msb_lsb(*param))
+            }
+            ConcreteEvent::MidiRpn(param, value, chan, _) => {
+                format!("RPN chan {chan} : {} ({param}) = {value}", Self::msb_lsb(*param))
+            }
+            _ => format!("{:?}", event),
+        }
+    }
+
+    fn msb_lsb(value: u64) -> String {
+        format!("{}:{}", (value >> 7) & 0x7F, value & 0x7F)
+    }
+
     pub fn generate_messages(event: ConcreteEvent, date: SyncTime) 
         -> Vec<(ProtocolPayload, SyncTime)> 
     {
@@ -151,3 +178,116 @@ impl LogMessage {
     }
 
 }
+
+/// The internal log device.
+///
+/// Notes sent to the log are tracked as they would be on a MIDI output,
+/// so that the log reports the voice bookkeeping (overlaps, ignored NoteOffs).
+#[derive(Debug, Default)]
+pub struct LogOut {
+    pub voices: Mutex<VoiceTracker>,
+}
+
+impl LogOut {
+    /// Notes become a NoteOn and a NoteOff, logged when they are sent.
+    /// Other events are described right away.
+    pub fn generate_messages(&self, event: ConcreteEvent, date: SyncTime)
+        -> Vec<(ProtocolPayload, SyncTime)>
+    {
+        match event {
+            ConcreteEvent::MidiNote(..) => MIDIMessage::generate_messages(event, date, 0),
+            _ => LogMessage::generate_messages(event, date),
+        }
+    }
+
+    pub fn send(&self, message: ProtocolPayload) -> Result<(), ProtocolError> {
+        let log_msg = match message {
+            ProtocolPayload::LOG(log_msg) => log_msg,
+            ProtocolPayload::MIDI(midi_msg) => self.report(midi_msg),
+            _ => {
+                return Err(ProtocolError(
+                    "Invalid message format for Log device!".to_owned(),
+                ));
+            }
+        };
+        crate::log_println!("[LOG][{}] {}", log_msg.level, log_msg.msg);
+        if let Some(event) = log_msg.event {
+            crate::log_println!("    Associated Event: {:?}", event);
+        }
+        Ok(())
+    }
+
+    /// Describes a MIDI message, along with the voice bookkeeping of notes.
+    pub fn report(&self, message: MIDIMessage) -> LogMessage {
+        let channel = message.channel;
+        let mut voices = self.voices.lock().unwrap();
+        let msg = match message.payload {
+            MIDIMessageType::NoteOn { note, velocity } => {
+                let sent = voices.note_on(channel, note, velocity);
+                let overlap = if sent.len() == 1 {
+                    String::new()
+                } else {
+                    format!(", overlap ({})", voices.policy)
+                };
+                format!(
+                    "NoteOn chan {} : {note} vel {velocity}{overlap}, {} references, {} notes sounding",
+                    channel + 1,
+                    voices.count(channel, note),
+                    voices.sounding()
+                )
+            }
+            MIDIMessageType::NoteOff { note, velocity } => {
+                if voices.note_off(channel, note, velocity).is_empty() {
+                    format!(
+                        "NoteOff chan {} : {note} ignored, {} references left",
+                        channel + 1,
+                        voices.count(channel, note)
+                    )
+                } else {
+                    format!(
+                        "NoteOff chan {} : {note}, {} notes sounding",
+                        channel + 1,
+                        voices.sounding()
+                    )
+                }
+            }
+            _ => message.to_string(),
+        };
+        LogMessage::info(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(payload: MIDIMessageType) -> MIDIMessage {
+        MIDIMessage { payload, channel: 0 }
+    }
+
+    #[test]
+    fn notes_are_reported_with_their_voices() {
+        let log = LogOut::default();
+        let on = || note(MIDIMessageType::NoteOn { note: 60, velocity: 90 });
+        let off = || note(MIDIMessageType::NoteOff { note: 60, velocity: 0 });
+        assert_eq!(
+            log.report(on()).msg,
+            "NoteOn chan 1 : 60 vel 90, 1 references, 1 notes sounding"
+        );
+        assert_eq!(
+            log.report(on()).msg,
+            "NoteOn chan 1 : 60 vel 90, overlap (retrigger), 2 references, 1 notes sounding"
+        );
+        assert_eq!(log.report(off()).msg, "NoteOff chan 1 : 60 ignored, 1 references left");
+        assert_eq!(log.report(off()).msg, "NoteOff chan 1 : 60, 0 notes sounding");
+    }
+
+    #[test]
+    fn notes_are_logged_when_they_start_and_end() {
+        let log = LogOut::default();
+        let messages = log.generate_messages(ConcreteEvent::MidiNote(60, 90, 1, 100, 0), 1000);
+        let dates: Vec<SyncTime> = messages.iter().map(|(_, date)| *date).collect();
+        assert_eq!(dates, vec![1000, 1100]);
+        assert!(messages.iter().all(|(payload, _)| matches!(payload, ProtocolPayload::MIDI(_))));
+    }
+}
@@ -1,16 +1,22 @@
 use midir::{MidiInput, MidiOutput, MidiOutputConnection};
 
 use control_memory::MidiInMemory;
-use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display};
 use std::sync::{Arc, Mutex};
 
 mod control_memory;
 mod message;
 pub use message::*;
+mod mpe;
+pub use mpe::*;
+mod voices;
+pub use voices::*;
 
 use crate::clock::SyncTime;
 use crate::protocol::error::ProtocolError;
+use crate::protocol::payload::ProtocolPayload;
+use crate::theory::{Tuning, TuningOutput};
+use crate::vm::event::ConcreteEvent;
 
 mod midi_constants;
 pub use midi_constants::*;
@@ -44,18 +50,18 @@ pub trait MidiInterface {
 ///
 /// Wraps a `midir::MidiOutputConnection` within an `Arc<Mutex<Option<...>>>`
 /// to allow shared, thread-safe access and connection management.
-/// Also tracks active notes to prevent sending duplicate Note On messages.
+/// Also tracks the sounding notes to handle overlapping ones.
 pub struct MidiOut {
     /// The name assigned to this MIDI output client/connection.
     pub name: String,
     /// The underlying `midir` output connection, managed thread-safely.
     /// This field is not serialized.
     pub connection: Mutex<Option<MidiOutputConnection>>,
-    /// Tracks currently active notes per channel to avoid sending duplicate Note Ons.
-    /// Maps channel (u8) to a set of active notes (u8).
-    /// This field is not serialized and has a default initializer.
-    pub active_notes: Mutex<HashMap<u8, HashSet<u8>>>,
-    pub epsilon: SyncTime
+    /// Tracks the notes currently sounding, and applies the overlap policy of the device.
+    pub voices: Mutex<VoiceTracker>,
+    pub epsilon: SyncTime,
+    /// Member channels allocation when the device is in MPE mode.
+    pub mpe: Mutex<Option<MpeState>>,
 }
 
 impl Display for MidiOut {
@@ -75,18 +81,105 @@ impl Debug for MidiOut {
 }
 
 impl MidiOut {
+    /// Translates an event into MIDI messages, allocating member channels in MPE mode,
+    /// and retuning the notes with the tuning of the scene, if any.
+    /// Keys left unmapped by the tuning are not played.
+    pub fn generate_messages(
+        &self,
+        event: ConcreteEvent,
+        date: SyncTime,
+        tuning: Option<(&Tuning, TuningOutput)>,
+    ) -> Vec<(ProtocolPayload, SyncTime)> {
+        if let Some(state) = self.mpe.lock().unwrap().as_mut()
+            && let Some(res) = state.translate(&event, date, self.epsilon, tuning)
+        {
+            return res;
+        }
+        let range = DEFAULT_BEND_RANGE as f64;
+        match (tuning, event) {
+            (
+                Some((tuning, TuningOutput::PitchBend)),
+                ConcreteEvent::MidiNote(note, vel, chan, dur, device_id),
+            ) => {
+                let Some((sent_note, bend)) = tuning.bend(note as i64, range) else {
+                    return Vec::new();
+                };
+                let mut res = MIDIMessage::generate_messages(
+                    ConcreteEvent::MidiPitchBend(bend as u64, chan, device_id),
+                    date.saturating_sub(1),
+                    self.epsilon,
+                );
+                res.extend(MIDIMessage::generate_messages(
+                    ConcreteEvent::MidiNote(sent_note as u64, vel, chan, dur, device_id),
+                    date,
+                    self.epsilon,
+                ));
+                res
+            }
+            (
+                Some((tuning, TuningOutput::PitchBend)),
+                ConcreteEvent::MidiAftertouch(note, value, chan, device_id),
+            ) => match tuning.bend(note as i64, range) {
+                Some((sent_note, _)) => MIDIMessage::generate_messages(
+                    ConcreteEvent::MidiAftertouch(sent_note as u64, value, chan, device_id),
+                    date,
+                    self.epsilon,
+                ),
+                None => Vec::new(),
+            },
+            (Some((tuning, TuningOutput::Mts)), event @ ConcreteEvent::MidiNote(note, ..)) => {
+                let Some(data) = tuning.mts_data(note as i64) else {
+                    return Vec::new();
+                };
+                let mut res = vec![(
+                    MIDIMessage {
+                        payload: MIDIMessageType::SystemExclusive { data },
+                        channel: 0,
+                    }
+                    .into(),
+                    date.saturating_sub(1),
+                )];
+                res.extend(MIDIMessage::generate_messages(event, date, self.epsilon));
+                res
+            }
+            (_, event) => MIDIMessage::generate_messages(event, date, self.epsilon),
+        }
+    }
+
+    pub fn mpe_zone(&self) -> Option<MpeZone> {
+        self.mpe.lock().unwrap().as_ref().map(|state| state.zone)
+    }
+
+    /// Enables (with the given zone) or disables MPE mode, and sends the matching
+    /// configuration to the receiver if the device is connected.
+    pub fn set_mpe(&self, zone: Option<MpeZone>) -> Result<(), ProtocolError> {
+        let messages = match zone {
+            Some(zone) => zone.configuration_messages(),
+            None if self.mpe_zone().is_some() => MpeZone::release_messages(),
+            None => Vec::new(),
+        };
+        *self.mpe.lock().unwrap() = zone.map(MpeState::new);
+        if !self.is_connected() {
+            return Ok(());
+        }
+        for message in messages {
+            self.send(message)?;
+        }
+        Ok(())
+    }
+
     /// Sends a `MIDIMessage` through the connected output port.
     ///
     /// Converts the `MIDIMessage` to raw bytes and sends it via the `midir` connection.
-    /// Includes logic to prevent sending duplicate Note On messages for the same note/channel.
-    /// Automatically handles Note Off messages only if the corresponding Note On was tracked.
+    /// Note On and Note Off messages go through the voice bookkeeping first, which applies
+    /// the overlap policy of the device (see [`NoteOverlap`]) and may send several messages
+    /// or none at all. Overlaps are reported to the log at debug level.
     ///
     /// # Errors
     /// Returns `Err(MidiError)` if:
     /// - The connection Mutex is poisoned.
     /// - The `MidiOut` is not connected to a port.
     /// - The underlying `midir` connection fails to send the message.
-    /// - The `MIDIMessage` contains invalid SysEx data (see `to_bytes`).
     pub fn send(&self, message: MIDIMessage) -> Result<(), ProtocolError> {
         let mut connection_opt_guard = self
             .connection
@@ -99,41 +192,68 @@ impl MidiOut {
             );
         };
 
-        let mut active_notes_guard = self.active_notes.lock().unwrap();
-        let bytes = match message.payload {
+        let channel = message.channel;
+        let payloads = match message.payload {
             MIDIMessageType::NoteOn { note, velocity } => {
-                let channel_notes = active_notes_guard.entry(message.channel).or_default();
-                if channel_notes.contains(&note) {
-                    return Ok(());
-                    //connection.send(&vec![NOTE_OFF_MSG + message.channel, note, velocity]);
-                } else {
-                    channel_notes.insert(note);
+                let mut voices = self.voices.lock().unwrap();
+                let res = voices.note_on(channel, note, velocity);
+                if res.len() != 1 {
+                    crate::log_debug!(
+                        "[~] {}: note {} overlaps on channel {} ({}, {} references, {} notes sounding)",
+                        self.name, note, channel + 1, voices.policy,
+                        voices.count(channel, note), voices.sounding()
+                    );
                 }
-                vec![NOTE_ON_MSG + message.channel, note, velocity]
+                res
             }
             MIDIMessageType::NoteOff { note, velocity } => {
-                let channel_notes = active_notes_guard.entry(message.channel).or_default();
-                if channel_notes.contains(&note) {
-                    channel_notes.remove(&note);
-                    vec![NOTE_OFF_MSG + message.channel, note, velocity]
-                } else {
-                    return Ok(());
+                let mut voices = self.voices.lock().unwrap();
+                let res = voices.note_off(channel, note, velocity);
+                if res.is_empty() {
+                    crate::log_debug!(
+                        "[~] {}: note off {} on channel {} ignored ({} references left)",
+                        self.name, note, channel + 1, voices.count(channel, note)
+                    );
                 }
+                res
+            }
+            MIDIMessageType::ControlChange { control: ALL_NOTES_OFF_CC, .. } => {
+                self.voices.lock().unwrap().release_channel(channel);
+                vec![message.payload]
+            }
+            payload => vec![payload],
+        };
+
+        for payload in payloads {
+            connection
+                .send(&Self::raw_bytes(payload, channel))
+                .map_err(|e| ProtocolError(format!("Échec d'envoi du message MIDI : {}", e)))?;
+        }
+        Ok(())
+    }
+
+    fn raw_bytes(payload: MIDIMessageType, channel: u8) -> Vec<u8> {
+        match payload {
+            MIDIMessageType::NoteOn { note, velocity } => {
+                vec![NOTE_ON_MSG + channel, note, velocity]
+            }
+            MIDIMessageType::NoteOff { note, velocity } => {
+                vec![NOTE_OFF_MSG + channel, note, velocity]
             }
             MIDIMessageType::ControlChange { control, value } => {
-                vec![CONTROL_CHANGE_MSG + message.channel, control, value]
+                vec![CONTROL_CHANGE_MSG + channel, control, value]
             }
             MIDIMessageType::ProgramChange { program } => {
-                vec![PROGRAM_CHANGE_MSG + message.channel, program]
+                vec![PROGRAM_CHANGE_MSG + channel, program]
             }
             MIDIMessageType::Aftertouch { note, value } => {
-                vec![AFTERTOUCH_MSG + message.channel, note, value]
+                vec![AFTERTOUCH_MSG + channel, note, value]
             }
             MIDIMessageType::ChannelPressure { value } => {
-                vec![CHANNEL_PRESSURE_MSG + message.channel, value]
+                vec![CHANNEL_PRESSURE_MSG + channel, value]
             }
             MIDIMessageType::PitchBend { value } => vec![
-                PITCH_BEND_MSG + message.channel,
+                PITCH_BEND_MSG + channel,
                 (value & 0x7F) as u8,
                 (value >> 7) as u8,
             ],
@@ -149,11 +269,15 @@ impl MidiOut {
                 m
             }
             MIDIMessageType::Undefined(byte) => vec![byte],
-        };
+        }
+    }
+
+    pub fn note_overlap(&self) -> NoteOverlap {
+        self.voices.lock().unwrap().policy
+    }
 
-        connection
-            .send(&bytes)
-            .map_err(|e| format!("Échec d'envoi du message MIDI : {}", e).into())
+    pub fn set_note_overlap(&self, policy: NoteOverlap) {
+        self.voices.lock().unwrap().policy = policy;
     }
 
     /// Connects this `MidiOut` instance to a specific physical output port identified by its name.
@@ -256,8 +380,9 @@ impl MidiInterface for MidiOut {
         Ok(MidiOut {
             name,
             connection: Mutex::new(None),
-            active_notes: Mutex::new(HashMap::new()),
-            epsilon: DEFAULT_MIDI_EPSILON
+            voices: Mutex::new(VoiceTracker::default()),
+            epsilon: DEFAULT_MIDI_EPSILON,
+            mpe: Mutex::new(None),
         })
     }
 
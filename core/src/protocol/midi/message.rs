@@ -5,6 +5,7 @@ use crate::clock::SyncTime;
 use crate::vm::event::ConcreteEvent;
 use crate::protocol::error::ProtocolError;
 use crate::protocol::midi::midi_constants::*;
+use crate::protocol::midi::{MPE_TIMBRE_CC, NoteExpression};
 use crate::protocol::payload::ProtocolPayload;
 use crate::vm::variable::VariableValue;
 
@@ -113,6 +114,8 @@ impl MIDIMessage {
     /// Handles mapping various `ConcreteEvent::Midi*` variants to their corresponding
     /// MIDI message types (NoteOn/Off, CC, ProgramChange, etc.).
     /// Note durations are handled by scheduling a corresponding NoteOff message.
+    /// The NoteOn is delayed by `epsilon` and the NoteOff advanced by it, so that
+    /// back to back notes are released before being played again.
     /// MIDI channels are converted from 1-based (in `ConcreteEvent`) to 0-based (in `MIDIMessage`).
     /// System messages (Start, Stop, etc.) are sent on channel 0.
     pub fn generate_messages(
@@ -123,15 +126,8 @@ impl MIDIMessage {
         match event {
             ConcreteEvent::MidiNote(note, vel, chan, dur, _device_id) => {
                 let midi_chan = (chan.saturating_sub(1) % 16) as u8; // Convert to 0-based MIDI channel
-                vec![(
-                        MIDIMessage {
-                            payload: MIDIMessageType::NoteOff {
-                                note: note as u8,
-                                velocity: 0,
-                            },
-                            channel: midi_chan,
-                        }.into(), date
-                    ),
+                // Overlaps with a note already sounding are handled by the device (see `NoteOverlap`)
+                vec![
                     // NoteOn
                     (
                         MIDIMessage {
@@ -168,6 +164,68 @@ impl MIDIMessage {
                     ),
                 ]
             }
+            ConcreteEvent::MidiPitchBend(value, chan, _device_id) => {
+                let midi_chan = (chan.saturating_sub(1) % 16) as u8;
+                vec![
+                    (
+                        MIDIMessage {
+                            payload: MIDIMessageType::PitchBend {
+                                value: value.min(0x3FFF) as u16,
+                            },
+                            channel: midi_chan,
+                        }.into(), date
+                    ),
+                ]
+            }
+            ConcreteEvent::MidiControl14(control, value, chan, _device_id) => {
+                let control = (control % CONTROL_LSB_OFFSET as u64) as u8;
+                let (msb, lsb) = Self::split_14_bits(value);
+                Self::control_changes(chan, &[
+                    (control, msb),
+                    (control + CONTROL_LSB_OFFSET, lsb),
+                ], date)
+            }
+            ConcreteEvent::MidiNrpn(param, value, chan, _device_id) => {
+                let (param_msb, param_lsb) = Self::split_14_bits(param);
+                let (msb, lsb) = Self::split_14_bits(value);
+                Self::control_changes(chan, &[
+                    (NRPN_MSB_CC, param_msb),
+                    (NRPN_LSB_CC, param_lsb),
+                    (DATA_ENTRY_MSB_CC, msb),
+                    (DATA_ENTRY_LSB_CC, lsb),
+                ], date)
+            }
+            ConcreteEvent::MidiRpn(param, value, chan, _device_id) => {
+                let (param_msb, param_lsb) = Self::split_14_bits(param);
+                let (msb, lsb) = Self::split_14_bits(value);
+                // Deselecting the parameter afterwards keeps later data entries harmless
+                Self::control_changes(chan, &[
+                    (RPN_MSB_CC, param_msb),
+                    (RPN_LSB_CC, param_lsb),
+                    (DATA_ENTRY_MSB_CC, msb),
+                    (DATA_ENTRY_LSB_CC, lsb),
+                    (RPN_MSB_CC, RPN_NULL),
+                    (RPN_LSB_CC, RPN_NULL),
+                ], date)
+            }
+            ConcreteEvent::MidiNoteExpression(note, expression, value, chan, _device_id) => {
+                // Without MPE, expressions apply to the whole channel (pressure stays per note)
+                let midi_chan = (chan.saturating_sub(1) % 16) as u8;
+                let payload = match expression {
+                    NoteExpression::Bend => MIDIMessageType::PitchBend {
+                        value: value.min(0x3FFF) as u16,
+                    },
+                    NoteExpression::Pressure => MIDIMessageType::Aftertouch {
+                        note: note as u8,
+                        value: value.min(127) as u8,
+                    },
+                    NoteExpression::Timbre => MIDIMessageType::ControlChange {
+                        control: MPE_TIMBRE_CC,
+                        value: value.min(127) as u8,
+                    },
+                };
+                vec![(MIDIMessage { payload, channel: midi_chan }.into(), date)]
+            }
             ConcreteEvent::MidiProgram(program, chan, _device_id) => {
                 let midi_chan = (chan.saturating_sub(1) % 16) as u8;
                 vec![
@@ -269,6 +327,9 @@ impl MIDIMessage {
                     ),
                 ]
             }
+            ConcreteEvent::Ramp(ramp, _device_id) if ramp.is_midi() => {
+                vec![(ProtocolPayload::Ramp(ramp), date)]
+            }
             ConcreteEvent::Generic(args, duration, channel, _device_id) => {
                 let midi_chan = channel.parse::<u64>().unwrap_or(1).saturating_sub(1) % 16;
                 match args {
@@ -299,6 +360,33 @@ impl MIDIMessage {
         }
     }
 
+    /// Splits a 14-bit value (clamped to 16383) into its MSB and LSB (7 bits each).
+    fn split_14_bits(value: u64) -> (u8, u8) {
+        let value = value.min(0x3FFF);
+        ((value >> 7) as u8, (value & 0x7F) as u8)
+    }
+
+    /// Sends the given (control, value) pairs in order. The World queue does not keep the
+    /// insertion order of simultaneous messages, so each one is delayed by a microsecond.
+    fn control_changes(
+        chan: u64,
+        pairs: &[(u8, u8)],
+        date: SyncTime
+    ) -> Vec<(ProtocolPayload, SyncTime)> {
+        let midi_chan = (chan.saturating_sub(1) % 16) as u8;
+        pairs.iter().enumerate().map(|(i, (control, value))| {
+            (
+                MIDIMessage {
+                    payload: MIDIMessageType::ControlChange {
+                        control: *control,
+                        value: *value,
+                    },
+                    channel: midi_chan,
+                }.into(), date + i as SyncTime
+            )
+        }).collect()
+    }
+
 }
 
 /// Enumerates the supported types of MIDI message payloads.
@@ -406,3 +494,36 @@ impl Display for MIDIMessageType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controls(payloads: Vec<(ProtocolPayload, SyncTime)>) -> Vec<(u8, u8, SyncTime)> {
+        payloads
+            .into_iter()
+            .map(|(payload, date)| match payload {
+                ProtocolPayload::MIDI(MIDIMessage {
+                    payload: MIDIMessageType::ControlChange { control, value },
+                    ..
+                }) => (control, value, date),
+                _ => panic!("Unexpected payload"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nrpn_is_sent_in_order() {
+        let msgs = MIDIMessage::generate_messages(ConcreteEvent::MidiNrpn(300, 8193, 1, 1), 10, 0);
+        assert_eq!(
+            controls(msgs),
+            vec![(99, 2, 10), (98, 44, 11), (6, 64, 12), (38, 1, 13)]
+        );
+    }
+
+    #[test]
+    fn control_14_uses_lsb_controller() {
+        let msgs = MIDIMessage::generate_messages(ConcreteEvent::MidiControl14(7, 20000, 1, 1), 0, 0);
+        assert_eq!(controls(msgs), vec![(7, 127, 0), (39, 127, 1)]);
+    }
+}
@@ -12,3 +12,17 @@ pub const START_MSG: u8 = 0xFA;
 pub const STOP_MSG: u8 = 0xFC;
 pub const SYSTEM_EXCLUSIVE_MSG: u8 = 0xF0;
 pub const SYSTEM_EXCLUSIVE_END_MSG: u8 = 0xF7;
+
+// Controller numbers
+pub const ALL_NOTES_OFF_CC: u8 = 123;
+pub const CONTROL_LSB_OFFSET: u8 = 32;
+pub const DATA_ENTRY_MSB_CC: u8 = 6;
+pub const DATA_ENTRY_LSB_CC: u8 = 38;
+pub const NRPN_LSB_CC: u8 = 98;
+pub const NRPN_MSB_CC: u8 = 99;
+pub const RPN_LSB_CC: u8 = 100;
+pub const RPN_MSB_CC: u8 = 101;
+pub const RPN_NULL: u8 = 127;
+
+/// Usual pitch bend range of a receiver, in semitones, when nothing configured it.
+pub const DEFAULT_BEND_RANGE: u8 = 2;
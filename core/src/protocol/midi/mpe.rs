@@ -0,0 +1,286 @@
+//! MIDI Polyphonic Expression (MPE) output.
+//!
+//! In MPE mode, a `MidiOut` plays each note on its own member channel of the lower zone
+//! (master channel 1, members from channel 2), so that pitch bend, pressure and timbre can
+//! be sent per note. Scripts address these expressions by note number and never choose
+//! the channel themselves.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::SyncTime,
+    protocol::{
+        midi::{MIDIMessage, MIDIMessageType},
+        payload::ProtocolPayload,
+    },
+    theory::{Tuning, TuningOutput},
+    vm::event::ConcreteEvent,
+};
+
+/// Default pitch bend range of the member channels, in semitones (as advised by the MPE spec).
+pub const DEFAULT_MPE_BEND_RANGE: u8 = 48;
+/// Controller carrying the timbre (third dimension) of an MPE note.
+pub const MPE_TIMBRE_CC: u8 = 74;
+/// Registered parameter of the MPE Configuration Message.
+const MCM_RPN: u64 = 6;
+/// Registered parameter of the pitch bend sensitivity.
+const BEND_RANGE_RPN: u64 = 0;
+
+/// Per-note expression dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteExpression {
+    /// 14-bit pitch bend, 8192 being the center
+    Bend,
+    /// Pressure in `[0, 127]`
+    Pressure,
+    /// Timbre (CC74) in `[0, 127]`
+    Timbre,
+}
+
+/// MPE lower zone configuration of a MIDI output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MpeZone {
+    /// Number of member channels, in `[1, 15]`
+    pub members: u8,
+    /// Pitch bend range of the member channels, in semitones
+    #[serde(default = "default_bend_range")]
+    pub bend_range: u8,
+}
+
+fn default_bend_range() -> u8 {
+    DEFAULT_MPE_BEND_RANGE
+}
+
+impl Default for MpeZone {
+    fn default() -> Self {
+        MpeZone {
+            members: 15,
+            bend_range: DEFAULT_MPE_BEND_RANGE,
+        }
+    }
+}
+
+impl MpeZone {
+    fn member_count(&self) -> u8 {
+        self.members.clamp(1, 15)
+    }
+
+    /// Messages declaring the zone to the receiver (MPE Configuration Message on the master
+    /// channel, then the pitch bend range of each member channel).
+    pub fn configuration_messages(&self) -> Vec<MIDIMessage> {
+        let mut events = vec![ConcreteEvent::MidiRpn(
+            MCM_RPN,
+            (self.member_count() as u64) << 7,
+            1,
+            0,
+        )];
+        for channel in 2..=(self.member_count() as u64 + 1) {
+            events.push(ConcreteEvent::MidiRpn(
+                BEND_RANGE_RPN,
+                (self.bend_range.min(96) as u64) << 7,
+                channel,
+                0,
+            ));
+        }
+        Self::to_midi(events)
+    }
+
+    /// Messages closing the zone (MPE Configuration Message with no member channel).
+    pub fn release_messages() -> Vec<MIDIMessage> {
+        Self::to_midi(vec![ConcreteEvent::MidiRpn(MCM_RPN, 0, 1, 0)])
+    }
+
+    fn to_midi(events: Vec<ConcreteEvent>) -> Vec<MIDIMessage> {
+        events
+            .into_iter()
+            .flat_map(|event| MIDIMessage::generate_messages(event, 0, 0))
+            .filter_map(|(payload, _)| match payload {
+                ProtocolPayload::MIDI(msg) => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MemberChannel {
+    note: Option<u8>,
+    start: SyncTime,
+    end: SyncTime,
+    /// Pitch bend of the note at rest, away from 8192 when the note is retuned
+    center: u16,
+}
+
+/// Member channels allocation of a `MidiOut` in MPE mode.
+///
+/// Allocation works on the scheduled dates of the notes rather than on the moment they
+/// are sent, since notes are translated ahead of time.
+#[derive(Debug, Clone)]
+pub struct MpeState {
+    pub zone: MpeZone,
+    channels: Vec<MemberChannel>,
+}
+
+impl MpeState {
+    pub fn new(zone: MpeZone) -> Self {
+        MpeState {
+            zone,
+            channels: vec![MemberChannel::default(); zone.member_count() as usize],
+        }
+    }
+
+    /// Picks a member channel (0-based MIDI channel) for a note playing from `start` to `end`.
+    /// Free channels are preferred, the one released for the longest time first, so that
+    /// release tails are preserved. When every channel is busy, the note ending first is stolen.
+    pub fn allocate(&mut self, note: u8, start: SyncTime, end: SyncTime) -> u8 {
+        self.allocate_tuned(note, start, end, 8192)
+    }
+
+    /// Same as `allocate`, for a note whose pitch bend at rest is `center`.
+    fn allocate_tuned(&mut self, note: u8, start: SyncTime, end: SyncTime, center: u16) -> u8 {
+        let free = self
+            .channels
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.end <= start)
+            .min_by_key(|(_, c)| c.end);
+        let index = match free {
+            Some((i, _)) => i,
+            None => {
+                self.channels
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, c)| c.end)
+                    .map(|(i, _)| i)
+                    .unwrap_or_default()
+            }
+        };
+        self.channels[index] = MemberChannel {
+            note: Some(note),
+            start,
+            end,
+            center,
+        };
+        index as u8 + 1
+    }
+
+    /// Member channel (0-based MIDI channel) on which the given note is playing at `date`,
+    /// the most recent one if the note is played several times.
+    pub fn channel_of(&self, note: u8, date: SyncTime) -> Option<u8> {
+        self.channels
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.note == Some(note) && date <= c.end)
+            .max_by_key(|(_, c)| c.start)
+            .map(|(i, _)| i as u8 + 1)
+    }
+
+    /// Translates the events that behave differently in MPE mode, or returns None.
+    /// With a tuning, notes are retuned by the pitch bend of their member channel,
+    /// or by an MTS message. Per note expressions keep addressing the notes by key.
+    pub fn translate(
+        &mut self,
+        event: &ConcreteEvent,
+        date: SyncTime,
+        epsilon: SyncTime,
+        tuning: Option<(&Tuning, TuningOutput)>,
+    ) -> Option<Vec<(ProtocolPayload, SyncTime)>> {
+        match *event {
+            ConcreteEvent::MidiNote(note, vel, _chan, dur, device_id) => {
+                let mut res = Vec::new();
+                let (sent_note, center) = match tuning {
+                    Some((tuning, TuningOutput::PitchBend)) => {
+                        match tuning.bend(note as i64, self.zone.bend_range as f64) {
+                            Some((sent_note, center)) => (sent_note as u64, center),
+                            None => return Some(res),
+                        }
+                    }
+                    Some((tuning, TuningOutput::Mts)) => {
+                        let Some(data) = tuning.mts_data(note as i64) else {
+                            return Some(res);
+                        };
+                        res.push((
+                            MIDIMessage {
+                                payload: MIDIMessageType::SystemExclusive { data },
+                                channel: 0,
+                            }
+                            .into(),
+                            date.saturating_sub(1),
+                        ));
+                        (note, 8192)
+                    }
+                    _ => (note, 8192),
+                };
+                let channel = self.allocate_tuned(note as u8, date, date + dur, center);
+                // Reset the expression left by the previous note of this channel
+                res.push((
+                    MIDIMessage {
+                        payload: MIDIMessageType::PitchBend { value: center },
+                        channel,
+                    }
+                    .into(),
+                    date.saturating_sub(1),
+                ));
+                res.extend(MIDIMessage::generate_messages(
+                    ConcreteEvent::MidiNote(sent_note, vel, channel as u64 + 1, dur, device_id),
+                    date,
+                    epsilon,
+                ));
+                Some(res)
+            }
+            ConcreteEvent::MidiNoteExpression(note, expression, value, _chan, _device_id) => {
+                let Some(channel) = self.channel_of(note as u8, date) else {
+                    return Some(Vec::new());
+                };
+                // Bends are relative to the tuning of the note
+                let center = self.channels[channel as usize - 1].center as i64;
+                let payload = match expression {
+                    NoteExpression::Bend => MIDIMessageType::PitchBend {
+                        value: (value as i64 + center - 8192).clamp(0, 0x3FFF) as u16,
+                    },
+                    NoteExpression::Pressure => MIDIMessageType::ChannelPressure {
+                        value: value.min(127) as u8,
+                    },
+                    NoteExpression::Timbre => MIDIMessageType::ControlChange {
+                        control: MPE_TIMBRE_CC,
+                        value: value.min(127) as u8,
+                    },
+                };
+                Some(vec![(MIDIMessage { payload, channel }.into(), date)])
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_get_their_own_channel() {
+        let mut state = MpeState::new(MpeZone { members: 2, bend_range: 48 });
+        assert_eq!(state.allocate(60, 0, 100), 1);
+        assert_eq!(state.allocate(64, 10, 100), 2);
+        // Every channel busy: the note ending first is stolen
+        assert_eq!(state.allocate(67, 20, 200), 1);
+        assert_eq!(state.channel_of(67, 50), Some(1));
+        assert_eq!(state.channel_of(60, 50), None);
+        // The channel released first is reused first
+        assert_eq!(state.allocate(72, 250, 300), 2);
+    }
+
+    #[test]
+    fn configuration_declares_members() {
+        let msgs = MpeZone { members: 3, bend_range: 48 }.configuration_messages();
+        // RPN sequences are 6 control changes long: MCM then 3 bend ranges
+        assert_eq!(msgs.len(), 24);
+        assert_eq!(msgs[0].channel, 0);
+        assert_eq!(
+            msgs[2].payload,
+            MIDIMessageType::ControlChange { control: 6, value: 3 }
+        );
+        assert_eq!(msgs[6].channel, 1);
+    }
+}
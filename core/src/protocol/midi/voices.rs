@@ -0,0 +1,183 @@
+//! Bookkeeping of the notes sounding on a MIDI output, and handling of overlapping notes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::protocol::midi::MIDIMessageType;
+
+/// What a MIDI output does when a note starts while it is already sounding on the same
+/// channel (or, for legato, while another note is sounding on the channel).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteOverlap {
+    /// The note is stopped and started again. It is released by the last pending NoteOff.
+    #[default]
+    Retrigger,
+    /// The note keeps sounding, and is released by the last pending NoteOff.
+    Stack,
+    /// Monophonic channels : a new note starts before the previous ones are released,
+    /// and their pending NoteOffs are ignored.
+    Legato,
+}
+
+impl Display for NoteOverlap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoteOverlap::Retrigger => write!(f, "retrigger"),
+            NoteOverlap::Stack => write!(f, "stack"),
+            NoteOverlap::Legato => write!(f, "legato"),
+        }
+    }
+}
+
+/// Sounding notes of a MIDI output, indexed by (channel, note).
+#[derive(Debug, Clone, Default)]
+pub struct VoiceTracker {
+    pub policy: NoteOverlap,
+    /// Number of NoteOns not yet matched by a NoteOff
+    voices: HashMap<(u8, u8), u32>,
+    /// Pending NoteOffs of notes already released by a legato transition
+    orphan_offs: HashMap<(u8, u8), u32>,
+}
+
+impl VoiceTracker {
+    pub fn new(policy: NoteOverlap) -> Self {
+        VoiceTracker {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Number of references on the given note.
+    pub fn count(&self, channel: u8, note: u8) -> u32 {
+        self.voices.get(&(channel, note)).copied().unwrap_or_default()
+    }
+
+    /// Number of distinct notes sounding.
+    pub fn sounding(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Registers a NoteOn and returns the messages to actually send, in order.
+    pub fn note_on(&mut self, channel: u8, note: u8, velocity: u8) -> Vec<MIDIMessageType> {
+        let on = MIDIMessageType::NoteOn { note, velocity };
+        let count = self.voices.entry((channel, note)).or_default();
+        *count += 1;
+        if *count > 1 {
+            return match self.policy {
+                NoteOverlap::Retrigger => vec![MIDIMessageType::NoteOff { note, velocity: 0 }, on],
+                NoteOverlap::Stack | NoteOverlap::Legato => Vec::new(),
+            };
+        }
+        let mut res = vec![on];
+        if self.policy == NoteOverlap::Legato {
+            let previous: Vec<(u8, u32)> = self
+                .voices
+                .iter()
+                .filter(|((c, n), _)| *c == channel && *n != note)
+                .map(|((_, n), count)| (*n, *count))
+                .collect();
+            for (previous_note, count) in previous {
+                self.voices.remove(&(channel, previous_note));
+                *self.orphan_offs.entry((channel, previous_note)).or_default() += count;
+                res.push(MIDIMessageType::NoteOff {
+                    note: previous_note,
+                    velocity: 0,
+                });
+            }
+        }
+        res
+    }
+
+    /// Registers a NoteOff and returns the messages to actually send.
+    pub fn note_off(&mut self, channel: u8, note: u8, velocity: u8) -> Vec<MIDIMessageType> {
+        let key = (channel, note);
+        if let Some(orphans) = self.orphan_offs.get_mut(&key) {
+            *orphans -= 1;
+            if *orphans == 0 {
+                self.orphan_offs.remove(&key);
+            }
+            return Vec::new();
+        }
+        let Some(count) = self.voices.get_mut(&key) else {
+            return Vec::new();
+        };
+        *count -= 1;
+        if *count > 0 {
+            return Vec::new();
+        }
+        self.voices.remove(&key);
+        vec![MIDIMessageType::NoteOff { note, velocity }]
+    }
+
+    /// Forgets the notes sounding on a channel (e.g. after an All Notes Off),
+    /// their pending NoteOffs being ignored.
+    pub fn release_channel(&mut self, channel: u8) {
+        let released: Vec<((u8, u8), u32)> = self
+            .voices
+            .iter()
+            .filter(|((c, _), _)| *c == channel)
+            .map(|(key, count)| (*key, *count))
+            .collect();
+        for (key, count) in released {
+            self.voices.remove(&key);
+            *self.orphan_offs.entry(key).or_default() += count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on(note: u8) -> MIDIMessageType {
+        MIDIMessageType::NoteOn { note, velocity: 90 }
+    }
+
+    fn off(note: u8) -> MIDIMessageType {
+        MIDIMessageType::NoteOff { note, velocity: 0 }
+    }
+
+    #[test]
+    fn retrigger_restarts_and_keeps_last_off() {
+        let mut voices = VoiceTracker::new(NoteOverlap::Retrigger);
+        assert_eq!(voices.note_on(0, 60, 90), vec![on(60)]);
+        assert_eq!(voices.note_on(0, 60, 90), vec![off(60), on(60)]);
+        // The NoteOff of the first note no longer cuts the second one
+        assert_eq!(voices.note_off(0, 60, 0), vec![]);
+        assert_eq!(voices.count(0, 60), 1);
+        assert_eq!(voices.note_off(0, 60, 0), vec![off(60)]);
+        assert_eq!(voices.sounding(), 0);
+    }
+
+    #[test]
+    fn stack_holds_until_last_off() {
+        let mut voices = VoiceTracker::new(NoteOverlap::Stack);
+        assert_eq!(voices.note_on(0, 60, 90), vec![on(60)]);
+        assert_eq!(voices.note_on(0, 60, 90), vec![]);
+        assert_eq!(voices.note_on(1, 60, 90), vec![on(60)]);
+        assert_eq!(voices.note_off(0, 60, 0), vec![]);
+        assert_eq!(voices.note_off(0, 60, 0), vec![off(60)]);
+        assert_eq!(voices.count(1, 60), 1);
+    }
+
+    #[test]
+    fn legato_overlaps_and_ignores_orphan_offs() {
+        let mut voices = VoiceTracker::new(NoteOverlap::Legato);
+        assert_eq!(voices.note_on(0, 60, 90), vec![on(60)]);
+        assert_eq!(voices.note_on(0, 62, 90), vec![on(62), off(60)]);
+        // Pending NoteOff of the first note
+        assert_eq!(voices.note_off(0, 60, 0), vec![]);
+        assert_eq!(voices.note_on(0, 60, 90), vec![on(60), off(62)]);
+        assert_eq!(voices.note_off(0, 62, 0), vec![]);
+        assert_eq!(voices.note_off(0, 60, 0), vec![off(60)]);
+        assert_eq!(voices.sounding(), 0);
+    }
+
+    #[test]
+    fn unmatched_off_is_dropped() {
+        let mut voices = VoiceTracker::default();
+        assert_eq!(voices.note_off(0, 60, 0), vec![]);
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::HashMap;
 use std::fmt::Display;
 
 use rosc::OscTime;
 use serde::{Deserialize, Serialize};
 
-use crate::{clock::{Clock, SyncTime}, vm::{event::ConcreteEvent, variable::VariableValue}, protocol::{ProtocolPayload, osc::OSCOut}};
+use crate::{clock::{Clock, SyncTime}, vm::{event::ConcreteEvent, variable::VariableValue}, protocol::{ProtocolPayload, osc::OSCOut}, theory::{Tuning, TuningOutput}};
+
+/// Key of the SuperDirt note 0, SuperDirt notes being relative to C5.
+pub(crate) const DIRT_NOTE_OFFSET: i64 = 60;
 
 /// Represents a single OSC message, consisting of an address pattern and a list of arguments.
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -88,16 +92,67 @@ impl OSCMessage {
         }
     }
 
-    pub fn generate_messages(dev: &OSCOut, event: ConcreteEvent, date: SyncTime, clock: &Clock) 
-        -> Vec<(ProtocolPayload, SyncTime)>
-    {
-        let latency_micros = (dev.latency * 1_000_000.0) as u64;
-        let target_date = date + latency_micros;
-        
-        let timetag = match OscTime::try_from(clock.to_system_time(target_date)) {
-            Ok(t) => Some(t.into()),
-            _ => None
+    /// Retunes the `note` argument of a message, `key_offset` being the key of the note 0.
+    /// Returns false if the key is not mapped by the tuning.
+    pub(crate) fn retune(
+        args: &mut HashMap<String, VariableValue>,
+        tuning: (&Tuning, TuningOutput),
+        key_offset: i64,
+    ) -> bool {
+        let (tuning, output) = tuning;
+        let key = match args.get("note") {
+            Some(VariableValue::Integer(note)) => *note + key_offset,
+            Some(VariableValue::Float(note)) => note.round() as i64 + key_offset,
+            _ => return true,
         };
+        match output {
+            TuningOutput::Frequency => {
+                let Some(freq) = tuning.frequency(key) else {
+                    return false;
+                };
+                args.remove("note");
+                args.insert("freq".to_owned(), VariableValue::Float(freq));
+            }
+            TuningOutput::Note => {
+                let Some(note) = tuning.note(key) else {
+                    return false;
+                };
+                args.insert("note".to_owned(), VariableValue::Float(note - key_offset as f64));
+            }
+            _ => (),
+        }
+        true
+    }
+
+    /// Timetag of a message played at `date`, sent `latency` seconds in advance.
+    pub(crate) fn timetag_at(date: SyncTime, latency: f64, clock: &Clock) -> Option<(u32, u32)> {
+        let latency_micros = (latency * 1_000_000.0) as u64;
+        OscTime::try_from(clock.to_system_time(date + latency_micros))
+            .ok()
+            .map(Into::into)
+    }
+
+    pub fn generate_messages(
+        dev: &OSCOut,
+        mut event: ConcreteEvent,
+        date: SyncTime,
+        clock: &Clock,
+        tuning: Option<(&Tuning, TuningOutput)>,
+    ) -> Vec<(ProtocolPayload, SyncTime)> {
+        if let Some(tuning) = tuning {
+            let mapped = match &mut event {
+                ConcreteEvent::Dirt { args, .. } => Self::retune(args, tuning, DIRT_NOTE_OFFSET),
+                ConcreteEvent::Generic(VariableValue::Map(args), ..) => {
+                    Self::retune(args, tuning, 0)
+                }
+                _ => true,
+            };
+            if !mapped {
+                return Vec::new();
+            }
+        }
+
+        let timetag = Self::timetag_at(date, dev.latency, clock);
         match event {
             // Handle Generic OSC Event (pass-through)
             ConcreteEvent::Osc {
@@ -156,6 +211,9 @@ impl OSCMessage {
                     timetag: timetag
                 }.into(), date)]
             }
+            ConcreteEvent::Ramp(ramp, _device_id) if !ramp.is_midi() => {
+                vec![(ProtocolPayload::Ramp(ramp), date)]
+            }
             ConcreteEvent::Generic(args, duration, channel, _device_id) => {
                 let mut flat_args = Vec::new();
                 for (key, value) in args.as_map().into_iter() {
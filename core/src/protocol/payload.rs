@@ -1,7 +1,7 @@
 use crate::protocol::audio_engine_proxy::AudioEnginePayload;
 use crate::protocol::device::ProtocolDevice;
 use crate::protocol::message::ProtocolMessage;
-use crate::protocol::{log::LogMessage, midi::MIDIMessage, osc::OSCMessage};
+use crate::protocol::{log::LogMessage, midi::MIDIMessage, osc::OSCMessage, ramp::Ramp};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::sync::Arc;
@@ -16,6 +16,8 @@ pub enum ProtocolPayload {
     MIDI(MIDIMessage),
     LOG(LogMessage),
     AudioEngine(AudioEnginePayload),
+    /// Expanded by the `World` into MIDI or OSC messages before being sent
+    Ramp(Ramp),
 }
 
 impl ProtocolPayload {
@@ -40,6 +42,11 @@ impl Display for ProtocolPayload {
                 "AudioEngine: {} args",
                 m.args.len(),
             ),
+            ProtocolPayload::Ramp(r) => write!(
+                f,
+                "Ramp: {} -> {} in {}us",
+                r.from, r.to, r.duration
+            ),
         }
     }
 }
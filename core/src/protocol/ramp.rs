@@ -0,0 +1,228 @@
+//! Continuous parameter changes (ramps), expanded by the `World` into timed messages.
+//!
+//! A ramp is translated once for its target device, then split into steps when it reaches
+//! the `World`, so that a single script event produces a whole sweep.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::SyncTime,
+    protocol::{
+        midi::{MIDIMessage, MIDIMessageType},
+        osc::OSCMessage,
+        payload::ProtocolPayload,
+    },
+    vm::variable::VariableValue,
+};
+
+/// Default delay between two steps of a ramp (10ms).
+pub const DEFAULT_RAMP_RESOLUTION: SyncTime = 10_000;
+/// Smallest accepted delay between two steps of a ramp (1ms).
+pub const MIN_RAMP_RESOLUTION: SyncTime = 1_000;
+/// Largest number of steps a ramp is split into, long ramps getting a coarser resolution.
+pub const MAX_RAMP_STEPS: SyncTime = 10_000;
+
+/// Shape of the progression between the start and the end value of a ramp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RampCurve {
+    #[default]
+    Linear,
+    /// Slow start, fast end
+    Exponential,
+    /// Fast start, slow end
+    Logarithmic,
+    /// Slow start and end
+    Smooth,
+}
+
+impl RampCurve {
+    /// Parses a curve name, unknown names giving a linear curve.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "exp" | "exponential" => RampCurve::Exponential,
+            "log" | "logarithmic" => RampCurve::Logarithmic,
+            "smooth" => RampCurve::Smooth,
+            _ => RampCurve::Linear,
+        }
+    }
+
+    /// Maps a linear progression in `[0, 1]` to the curve progression in `[0, 1]`.
+    pub fn apply(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            RampCurve::Linear => x,
+            RampCurve::Exponential => x * x,
+            RampCurve::Logarithmic => 1.0 - (1.0 - x) * (1.0 - x),
+            RampCurve::Smooth => x * x * (3.0 - 2.0 * x),
+        }
+    }
+}
+
+/// Kind of parameter targeted by a ramp, used by languages before the target is fully known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RampKind {
+    MidiControl,
+    PitchBend,
+    Osc,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RampTarget {
+    /// MIDI control change, values are rounded and clamped to `[0, 127]`
+    MidiControl { control: u64, channel: u64 },
+    /// MIDI pitch bend, values are in `[-1, 1]` (0 being the center)
+    PitchBend { channel: u64 },
+    /// OSC message with a single float argument
+    OscFloat { address: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ramp {
+    pub target: RampTarget,
+    pub from: f64,
+    pub to: f64,
+    /// Total duration in microseconds
+    pub duration: SyncTime,
+    pub curve: RampCurve,
+    /// Delay between two steps in microseconds
+    pub resolution: SyncTime,
+}
+
+impl Ramp {
+    pub fn is_midi(&self) -> bool {
+        !matches!(self.target, RampTarget::OscFloat { .. })
+    }
+
+    /// Value of the ramp at the given progression in `[0, 1]`.
+    pub fn value_at(&self, progress: f64) -> f64 {
+        self.from + (self.to - self.from) * self.curve.apply(progress)
+    }
+
+    /// Dates (relative to the start of the ramp) and values of each step, both ends included.
+    /// There are at most `MAX_RAMP_STEPS + 1` steps, whatever the duration of the ramp.
+    pub fn steps(&self) -> Vec<(SyncTime, f64)> {
+        let n = (self.duration / self.resolution.max(MIN_RAMP_RESOLUTION)).clamp(1, MAX_RAMP_STEPS);
+        (0..=n)
+            .map(|i| {
+                let offset = (self.duration as u128 * i as u128 / n as u128) as SyncTime;
+                (offset, self.value_at(i as f64 / n as f64))
+            })
+            .collect()
+    }
+
+    fn payload(&self, value: f64) -> ProtocolPayload {
+        match &self.target {
+            RampTarget::MidiControl { control, channel } => MIDIMessage {
+                payload: MIDIMessageType::ControlChange {
+                    control: *control as u8,
+                    value: value.round().clamp(0.0, 127.0) as u8,
+                },
+                channel: (channel.saturating_sub(1) % 16) as u8,
+            }
+            .into(),
+            RampTarget::PitchBend { channel } => MIDIMessage {
+                payload: MIDIMessageType::PitchBend {
+                    value: (8192.0 + value.clamp(-1.0, 1.0) * 8192.0).round().min(16383.0) as u16,
+                },
+                channel: (channel.saturating_sub(1) % 16) as u8,
+            }
+            .into(),
+            RampTarget::OscFloat { address } => OSCMessage {
+                addr: address.clone(),
+                args: vec![VariableValue::Float(value)],
+                timetag: None,
+            }
+            .into(),
+        }
+    }
+
+    /// Expands the ramp into payloads, with their dates relative to the start of the ramp.
+    /// Consecutive steps giving the same message (e.g. the same CC value) are only sent once.
+    pub fn expand(&self) -> Vec<(ProtocolPayload, SyncTime)> {
+        let mut res: Vec<(ProtocolPayload, SyncTime)> = Vec::new();
+        for (offset, value) in self.steps() {
+            let payload = self.payload(value);
+            if res.last().is_some_and(|(last, _)| *last == payload) {
+                continue;
+            }
+            res.push((payload, offset));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cc_ramp(from: f64, to: f64, duration: SyncTime) -> Ramp {
+        Ramp {
+            target: RampTarget::MidiControl { control: 74, channel: 1 },
+            from,
+            to,
+            duration,
+            curve: RampCurve::Linear,
+            resolution: DEFAULT_RAMP_RESOLUTION,
+        }
+    }
+
+    #[test]
+    fn steps_cover_both_ends() {
+        let steps = cc_ramp(0.0, 100.0, 100_000).steps();
+        assert_eq!(steps.len(), 11);
+        assert_eq!(steps[0], (0, 0.0));
+        assert_eq!(steps[10], (100_000, 100.0));
+    }
+
+    #[test]
+    fn steps_are_capped() {
+        let hour = 3_600_000_000;
+        let steps = cc_ramp(0.0, 127.0, hour).steps();
+        assert_eq!(steps.len() as SyncTime, MAX_RAMP_STEPS + 1);
+        assert_eq!(steps.last(), Some(&(hour, 127.0)));
+        let steps = cc_ramp(0.0, 127.0, SyncTime::MAX).steps();
+        assert_eq!(steps.len() as SyncTime, MAX_RAMP_STEPS + 1);
+        assert_eq!(steps.last().map(|step| step.0), Some(SyncTime::MAX));
+    }
+
+    #[test]
+    fn expand_skips_repeated_values() {
+        let expanded = cc_ramp(10.0, 12.0, 1_000_000).expand();
+        assert_eq!(expanded.len(), 3);
+        assert_eq!(expanded[0].1, 0);
+    }
+
+    #[test]
+    fn curves_keep_bounds() {
+        for curve in [RampCurve::Linear, RampCurve::Exponential, RampCurve::Logarithmic, RampCurve::Smooth] {
+            assert_eq!(curve.apply(0.0), 0.0);
+            assert_eq!(curve.apply(1.0), 1.0);
+        }
+        assert!(RampCurve::Exponential.apply(0.5) < 0.5);
+        assert!(RampCurve::Logarithmic.apply(0.5) > 0.5);
+    }
+
+    #[test]
+    fn pitch_bend_is_centered() {
+        let ramp = Ramp {
+            target: RampTarget::PitchBend { channel: 1 },
+            from: -1.0,
+            to: 1.0,
+            duration: 0,
+            curve: RampCurve::Linear,
+            resolution: DEFAULT_RAMP_RESOLUTION,
+        };
+        let values: Vec<u16> = ramp
+            .expand()
+            .into_iter()
+            .map(|(payload, _)| match payload {
+                ProtocolPayload::MIDI(MIDIMessage { payload: MIDIMessageType::PitchBend { value }, .. }) => value,
+                _ => panic!("Unexpected payload"),
+            })
+            .collect();
+        assert_eq!(values, vec![0, 16383]);
+    }
+}
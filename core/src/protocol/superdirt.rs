@@ -0,0 +1,308 @@
+//! Output to SuperDirt, the sampler and synth host of TidalCycles.
+//!
+//! Events become `/dirt/play` messages, sent in OSC bundles timestamped with the date of
+//! the event (plus the latency of the device), so that SuperDirt plays them sample-accurately.
+//! Each message carries the `cps`, `cycle` and `delta` of the event, taken from the clock.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::{
+    clock::{Clock, SyncTime},
+    protocol::{
+        error::ProtocolError,
+        osc::{OSCMessage, OSCOut, DIRT_NOTE_OFFSET},
+        payload::ProtocolPayload,
+    },
+    theory::{Tuning, TuningOutput},
+    vm::{event::ConcreteEvent, variable::VariableValue},
+};
+
+/// Address of the SuperDirt play messages.
+pub const DIRT_PLAY: &str = "/dirt/play";
+/// Port SuperDirt listens on by default.
+pub const DEFAULT_DIRT_PORT: u16 = 57120;
+/// Number of orbits started by `SuperDirt.start` by default.
+pub const DEFAULT_DIRT_ORBITS: u8 = 12;
+
+fn default_orbits() -> u8 {
+    DEFAULT_DIRT_ORBITS
+}
+
+/// Orbits and default parameters of a SuperDirt output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirtProfile {
+    /// Number of orbits started in SuperDirt. Orbit arguments wrap around it.
+    #[serde(default = "default_orbits")]
+    pub orbits: u8,
+    /// Orbit of the events without `orbit` argument
+    #[serde(default)]
+    pub orbit: u8,
+    /// Parameters added to every event, unless the event sets them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, VariableValue>,
+}
+
+impl Default for DirtProfile {
+    fn default() -> Self {
+        DirtProfile {
+            orbits: DEFAULT_DIRT_ORBITS,
+            orbit: 0,
+            defaults: BTreeMap::new(),
+        }
+    }
+}
+
+impl DirtProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.orbits == 0 {
+            return Err("SuperDirt needs at least one orbit".to_owned());
+        }
+        if self.orbit >= self.orbits {
+            return Err(format!(
+                "Default orbit {} is out of the {} orbits",
+                self.orbit, self.orbits
+            ));
+        }
+        Ok(())
+    }
+
+    /// Orbit an `orbit` argument plays on, wrapped to the available orbits.
+    pub fn resolve_orbit(&self, orbit: Option<&VariableValue>) -> i64 {
+        let orbits = self.orbits.max(1) as i64;
+        match orbit {
+            Some(VariableValue::Integer(i)) => i.rem_euclid(orbits),
+            Some(VariableValue::Float(f)) => (f.round() as i64).rem_euclid(orbits),
+            _ => self.orbit as i64 % orbits,
+        }
+    }
+
+    /// Builds the `/dirt/play` message of an event: the defaults overridden by the
+    /// arguments of the event, its orbit, then `cps`, `cycle` and `delta`.
+    /// Arguments are sorted by name, so that messages are deterministic.
+    pub fn message(
+        &self,
+        args: HashMap<String, VariableValue>,
+        cps: f64,
+        cycle: f64,
+        delta: f64,
+    ) -> OSCMessage {
+        let mut params = self.defaults.clone();
+        params.extend(args);
+        let orbit = self.resolve_orbit(params.get("orbit"));
+        params.insert("orbit".to_owned(), VariableValue::Integer(orbit));
+        params.insert("cps".to_owned(), VariableValue::Float(cps));
+        params.insert("cycle".to_owned(), VariableValue::Float(cycle));
+        params.insert("delta".to_owned(), VariableValue::Float(delta));
+
+        let mut flat_args = Vec::with_capacity(params.len() * 2);
+        for (key, value) in params {
+            flat_args.push(VariableValue::Str(key));
+            flat_args.push(value);
+        }
+        OSCMessage::new(DIRT_PLAY.to_owned(), flat_args)
+    }
+}
+
+/// An OSC output talking to SuperDirt.
+pub struct SuperDirtOut {
+    pub osc: OSCOut,
+    pub profile: Mutex<DirtProfile>,
+}
+
+impl SuperDirtOut {
+    pub fn new(osc: OSCOut) -> Self {
+        SuperDirtOut {
+            osc,
+            profile: Mutex::new(DirtProfile::default()),
+        }
+    }
+
+    pub fn connect(&mut self) -> Result<(), ProtocolError> {
+        self.osc.connect()
+    }
+
+    pub fn send(&self, message: OSCMessage) -> Result<(), ProtocolError> {
+        self.osc.send(message)
+    }
+
+    pub fn profile(&self) -> DirtProfile {
+        self.profile.lock().unwrap().clone()
+    }
+
+    pub fn set_profile(&self, profile: DirtProfile) -> Result<(), ProtocolError> {
+        profile.validate().map_err(ProtocolError)?;
+        *self.profile.lock().unwrap() = profile;
+        Ok(())
+    }
+
+    /// Translates an event into timestamped `/dirt/play` messages. Dirt events last one
+    /// beat, generic events their own duration. Other events (raw OSC messages, ramps,
+    /// generic events sent to their own address and MIDI events) are mapped as by an
+    /// OSC output.
+    pub fn generate_messages(
+        &self,
+        mut event: ConcreteEvent,
+        date: SyncTime,
+        clock: &Clock,
+        tuning: Option<(&Tuning, TuningOutput)>,
+    ) -> Vec<(ProtocolPayload, SyncTime)> {
+        let played = match &event {
+            ConcreteEvent::Dirt { .. } => true,
+            ConcreteEvent::Generic(_, _, addr, _) => addr.is_empty(),
+            _ => false,
+        };
+        if !played {
+            return OSCMessage::generate_messages(&self.osc, event, date, clock, tuning);
+        }
+        if let Some(tuning) = tuning {
+            let mapped = match &mut event {
+                ConcreteEvent::Dirt { args, .. } => {
+                    OSCMessage::retune(args, tuning, DIRT_NOTE_OFFSET)
+                }
+                ConcreteEvent::Generic(VariableValue::Map(args), ..) => {
+                    OSCMessage::retune(args, tuning, DIRT_NOTE_OFFSET)
+                }
+                _ => true,
+            };
+            if !mapped {
+                return Vec::new();
+            }
+        }
+
+        let timetag = OSCMessage::timetag_at(date, self.osc.latency, clock);
+        let (args, duration) = match event {
+            ConcreteEvent::Generic(args, duration, _, _) => (args.as_map(), duration),
+            ConcreteEvent::Dirt { args, .. } => (args, clock.beats_to_micros(1.0)),
+            _ => return Vec::new(),
+        };
+
+        let cps = clock.tempo() / 60.0;
+        let cycle = clock.beat_at_date(date);
+        let delta = duration as f64 / 1_000_000.0;
+        let message = self
+            .profile
+            .lock()
+            .unwrap()
+            .message(args, cps, cycle, delta)
+            .at_date(timetag);
+        vec![(message.into(), date)]
+    }
+}
+
+impl fmt::Debug for SuperDirtOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuperDirtDevice")
+            .field("osc", &self.osc)
+            .field("profile", &self.profile())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockServer;
+    use rosc::{OscPacket, OscType};
+    use std::net::UdpSocket;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn defaults_and_orbits() {
+        let profile = DirtProfile {
+            orbits: 4,
+            orbit: 1,
+            defaults: BTreeMap::from([
+                ("gain".to_owned(), VariableValue::Float(0.8)),
+                ("s".to_owned(), VariableValue::Str("bd".to_owned())),
+            ]),
+        };
+        let args = HashMap::from([
+            ("s".to_owned(), VariableValue::Str("sn".to_owned())),
+            ("orbit".to_owned(), VariableValue::Integer(6)),
+        ]);
+        let message = profile.message(args, 0.5, 3.0, 2.0);
+        assert_eq!(message.addr, DIRT_PLAY);
+        let arg = |name: &str| {
+            let i = message
+                .args
+                .iter()
+                .position(|a| *a == VariableValue::Str(name.to_owned()))
+                .unwrap();
+            message.args[i + 1].clone()
+        };
+        assert_eq!(arg("s"), VariableValue::Str("sn".to_owned()));
+        assert_eq!(arg("gain"), VariableValue::Float(0.8));
+        assert_eq!(arg("orbit"), VariableValue::Integer(2));
+        assert_eq!(arg("cps"), VariableValue::Float(0.5));
+        assert_eq!(profile.resolve_orbit(None), 1);
+        assert!(DirtProfile { orbits: 2, orbit: 2, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn bundles_reach_a_udp_listener() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut out = SuperDirtOut::new(OSCOut {
+            name: "dirt".to_owned(),
+            address: listener.local_addr().unwrap(),
+            latency: 0.02,
+            socket: None,
+        });
+        out.connect().unwrap();
+
+        let args = HashMap::from([("s".to_owned(), VariableValue::Str("bd".to_owned()))]);
+        let message = out.profile().message(args, 1.0, 4.0, 1.0).at_date(Some((42, 7)));
+        out.send(message).unwrap();
+
+        let mut buf = [0u8; rosc::decoder::MTU];
+        let size = listener.recv(&mut buf).unwrap();
+        let (_, packet) = rosc::decoder::decode_udp(&buf[..size]).unwrap();
+        let OscPacket::Bundle(bundle) = packet else {
+            panic!("Expected a bundle");
+        };
+        assert_eq!((bundle.timetag.seconds, bundle.timetag.fractional), (42, 7));
+        let OscPacket::Message(message) = &bundle.content[0] else {
+            panic!("Expected a message");
+        };
+        assert_eq!(message.addr, DIRT_PLAY);
+        let orbit = message
+            .args
+            .iter()
+            .position(|a| *a == OscType::String("orbit".to_owned()))
+            .unwrap();
+        assert_eq!(message.args[orbit + 1], OscType::Int(0));
+    }
+
+    #[test]
+    fn other_events_are_mapped_as_osc() {
+        let clock = Clock::from(&Arc::new(ClockServer::new(120.0, 4.0)));
+        let out = SuperDirtOut::new(OSCOut {
+            name: "dirt".to_owned(),
+            address: "127.0.0.1:57120".parse().unwrap(),
+            latency: 0.0,
+            socket: None,
+        });
+        let address = |event: ConcreteEvent| -> Vec<String> {
+            out.generate_messages(event, 0, &clock, None)
+                .into_iter()
+                .map(|(payload, _)| match payload {
+                    ProtocolPayload::OSC(message) => message.addr,
+                    _ => panic!("Expected an OSC message"),
+                })
+                .collect()
+        };
+        assert_eq!(address(ConcreteEvent::MidiNote(60, 90, 1, 1000, 2)), vec!["/midi/noteon"]);
+        assert_eq!(address(ConcreteEvent::MidiControl(1, 64, 1, 2)), vec!["/midi/cc"]);
+        assert_eq!(address(ConcreteEvent::MidiProgram(3, 1, 2)), vec!["/midi/program"]);
+        let args = VariableValue::Map(HashMap::from([("freq".to_owned(), 440.0.into())]));
+        let generic = |addr: &str| ConcreteEvent::Generic(args.clone(), 1000, addr.to_owned(), 2);
+        assert_eq!(address(generic("/synth")), vec!["/synth"]);
+        assert_eq!(address(generic("")), vec![DIRT_PLAY]);
+    }
+}
@@ -0,0 +1,215 @@
+//! Note transformations applied per device slot, before events are translated for a device.
+//!
+//! They let a whole ensemble be transposed, quantized to a scale or rebalanced live,
+//! without editing the scripts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    protocol::ramp::{RampCurve, RampTarget},
+    theory::Scale,
+    vm::{event::ConcreteEvent, variable::VariableValue},
+};
+
+/// Scale the notes of a slot are moved to.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ScaleQuantize {
+    /// Pitch class of the root, in `[0, 11]` (0 is C)
+    pub root: u8,
+    pub scale: Scale,
+}
+
+/// Reshaping of the velocities of a slot. Velocities are passed through the curve,
+/// then scaled to `[min, max]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VelocityTransform {
+    #[serde(default)]
+    pub curve: RampCurve,
+    #[serde(default)]
+    pub min: u8,
+    #[serde(default = "default_max_velocity")]
+    pub max: u8,
+}
+
+fn default_max_velocity() -> u8 {
+    127
+}
+
+impl Default for VelocityTransform {
+    fn default() -> Self {
+        VelocityTransform {
+            curve: RampCurve::Linear,
+            min: 0,
+            max: 127,
+        }
+    }
+}
+
+impl VelocityTransform {
+    pub fn apply(&self, velocity: u64) -> u64 {
+        let x = velocity.min(127) as f64 / 127.0;
+        let (min, max) = (self.min.min(127) as f64, self.max.min(127) as f64);
+        (min + self.curve.apply(x) * (max - min)).round() as u64
+    }
+}
+
+/// Transformations of the events sent to a device slot. The default transform changes nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SlotTransform {
+    /// Semitones added to every note
+    #[serde(default)]
+    pub transpose: i64,
+    /// Scale the notes are quantized to, after transposition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantize: Option<ScaleQuantize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<VelocityTransform>,
+    /// Channel remapping (1-based), channels absent from the map are kept
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub channels: BTreeMap<u64, u64>,
+}
+
+impl SlotTransform {
+    pub fn is_identity(&self) -> bool {
+        *self == SlotTransform::default()
+    }
+
+    /// Transposes then quantizes a note, clamped to the MIDI range.
+    pub fn note(&self, note: u64) -> u64 {
+        let mut note = (note as i64).saturating_add(self.transpose);
+        if let Some(q) = &self.quantize {
+            note = q.scale.quantize(note, q.root as i64 % 12);
+        }
+        note.clamp(0, 127) as u64
+    }
+
+    pub fn velocity(&self, velocity: u64) -> u64 {
+        match &self.velocity {
+            Some(v) => v.apply(velocity),
+            None => velocity,
+        }
+    }
+
+    pub fn channel(&self, channel: u64) -> u64 {
+        self.channels.get(&channel).copied().unwrap_or(channel)
+    }
+
+    /// Applies the transform to an event. Notes are changed in MIDI note, aftertouch and
+    /// per note expression events, and in the `note` of generic events. Channels are
+    /// remapped in every MIDI channel event.
+    pub fn apply(&self, event: ConcreteEvent) -> ConcreteEvent {
+        match event {
+            ConcreteEvent::MidiNote(note, vel, chan, dur, dev) => ConcreteEvent::MidiNote(
+                self.note(note),
+                self.velocity(vel),
+                self.channel(chan),
+                dur,
+                dev,
+            ),
+            ConcreteEvent::MidiAftertouch(note, pressure, chan, dev) => {
+                ConcreteEvent::MidiAftertouch(self.note(note), pressure, self.channel(chan), dev)
+            }
+            ConcreteEvent::MidiNoteExpression(note, expression, value, chan, dev) => {
+                ConcreteEvent::MidiNoteExpression(
+                    self.note(note),
+                    expression,
+                    value,
+                    self.channel(chan),
+                    dev,
+                )
+            }
+            ConcreteEvent::MidiControl(control, value, chan, dev) => {
+                ConcreteEvent::MidiControl(control, value, self.channel(chan), dev)
+            }
+            ConcreteEvent::MidiProgram(program, chan, dev) => {
+                ConcreteEvent::MidiProgram(program, self.channel(chan), dev)
+            }
+            ConcreteEvent::MidiChannelPressure(pressure, chan, dev) => {
+                ConcreteEvent::MidiChannelPressure(pressure, self.channel(chan), dev)
+            }
+            ConcreteEvent::MidiPitchBend(value, chan, dev) => {
+                ConcreteEvent::MidiPitchBend(value, self.channel(chan), dev)
+            }
+            ConcreteEvent::MidiControl14(control, value, chan, dev) => {
+                ConcreteEvent::MidiControl14(control, value, self.channel(chan), dev)
+            }
+            ConcreteEvent::MidiNrpn(param, value, chan, dev) => {
+                ConcreteEvent::MidiNrpn(param, value, self.channel(chan), dev)
+            }
+            ConcreteEvent::MidiRpn(param, value, chan, dev) => {
+                ConcreteEvent::MidiRpn(param, value, self.channel(chan), dev)
+            }
+            ConcreteEvent::Ramp(mut ramp, dev) => {
+                match &mut ramp.target {
+                    RampTarget::MidiControl { channel, .. } | RampTarget::PitchBend { channel } => {
+                        *channel = self.channel(*channel);
+                    }
+                    RampTarget::OscFloat { .. } => (),
+                }
+                ConcreteEvent::Ramp(ramp, dev)
+            }
+            ConcreteEvent::Generic(VariableValue::Integer(note), dur, chan, dev) => {
+                let note = self.note(note.max(0) as u64) as i64;
+                ConcreteEvent::Generic(VariableValue::Integer(note), dur, chan, dev)
+            }
+            ConcreteEvent::Generic(VariableValue::Map(mut map), dur, chan, dev) => {
+                if let Some(VariableValue::Integer(note)) = map.get("note") {
+                    let note = self.note((*note).max(0) as u64) as i64;
+                    map.insert("note".to_owned(), VariableValue::Integer(note));
+                }
+                if let Some(VariableValue::Integer(vel)) = map.get("velocity") {
+                    let vel = self.velocity((*vel).max(0) as u64) as i64;
+                    map.insert("velocity".to_owned(), VariableValue::Integer(vel));
+                }
+                ConcreteEvent::Generic(VariableValue::Map(map), dur, chan, dev)
+            }
+            event => event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_are_transposed_then_quantized() {
+        let transform = SlotTransform {
+            transpose: 1,
+            quantize: Some(ScaleQuantize { root: 0, scale: Scale::Major }),
+            channels: BTreeMap::from([(1, 3)]),
+            ..Default::default()
+        };
+        assert_eq!(
+            transform.apply(ConcreteEvent::MidiNote(60, 100, 1, 10, 1)),
+            ConcreteEvent::MidiNote(60, 100, 3, 10, 1)
+        );
+        assert_eq!(transform.note(63), 64);
+        assert_eq!(transform.channel(2), 2);
+    }
+
+    #[test]
+    fn extreme_transpositions_are_clamped() {
+        let mut transform = SlotTransform {
+            transpose: i64::MAX,
+            quantize: Some(ScaleQuantize { root: 0, scale: Scale::Major }),
+            ..Default::default()
+        };
+        assert_eq!(transform.note(127), 127);
+        transform.transpose = i64::MIN;
+        assert_eq!(transform.note(0), 0);
+    }
+
+    #[test]
+    fn velocity_range() {
+        let velocity = VelocityTransform {
+            curve: RampCurve::Linear,
+            min: 40,
+            max: 100,
+        };
+        assert_eq!(velocity.apply(0), 40);
+        assert_eq!(velocity.apply(127), 100);
+        assert!(SlotTransform::default().is_identity());
+    }
+}
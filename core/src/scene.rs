@@ -2,17 +2,20 @@
 
 use crate::{
     clock::{Clock, NEVER, SyncTime},
-    vm::{PartialContext, event::ConcreteEvent, variable::VariableStore},
+    vm::{PartialContext, event::ConcreteEvent, random::SovaRng, variable::{VariableScope, VariableStore, VariableValue}},
     log_eprintln,
+    theory::Tuning,
 };
 use serde::{Deserialize, Serialize};
-use std::usize;
+use std::{collections::{BTreeMap, HashMap}, usize};
 mod frame;
 mod line;
+pub mod modulator;
 pub mod script;
 
 pub use frame::Frame;
 pub use line::Line;
+pub use modulator::Modulator;
 
 /// Represents a scene, which is a collection of [`Line`]s that can play concurrently.
 ///
@@ -25,6 +28,16 @@ pub struct Scene {
     /// Each `Line` runs concurrently within the scene's context.
     pub lines: Vec<Line>,
     pub vars: VariableStore,
+    /// Root seed of all random streams. Without seed, streams are seeded from system entropy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Named modulation sources, readable from every script.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub modulators: BTreeMap<String, Modulator>,
+    /// Microtonal tuning of the notes, realized by each output device. Without tuning,
+    /// notes are played in 12-TET.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tuning: Option<Tuning>,
 }
 
 impl Scene {
@@ -36,6 +49,9 @@ impl Scene {
         Scene {
             lines,
             vars: VariableStore::new(),
+            seed: None,
+            modulators: BTreeMap::new(),
+            tuning: None,
         }
     }
 
@@ -50,9 +66,98 @@ impl Scene {
         }
     }
 
+    /// Clears every variable store of the scene (global, line and frame), rewinds all lines
+    /// and restarts random streams.
     pub fn reset(&mut self) {
         self.lines.iter_mut().for_each(Line::reset);
         self.vars.clear();
+        self.reseed();
+    }
+
+    /// Restarts the random streams of every line from the scene seed,
+    /// so that playing a seeded scene always gives the same results.
+    pub fn reseed(&mut self) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        for (index, line) in self.lines.iter_mut().enumerate() {
+            line.reseed(SovaRng::derive(seed, index));
+        }
+    }
+
+    /// Restarts the random streams of a single line.
+    pub fn reseed_line(&mut self, index: usize) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        if let Some(line) = self.lines.get_mut(index) {
+            line.reseed(SovaRng::derive(seed, index));
+        }
+    }
+
+    /// Defines or replaces a modulator, or removes it if `modulator` is None.
+    pub fn set_modulator(&mut self, name: String, modulator: Option<Modulator>) {
+        match modulator {
+            Some(modulator) => {
+                self.modulators.insert(name, modulator);
+            }
+            None => {
+                self.modulators.remove(&name);
+            }
+        }
+    }
+
+    /// Collects the user visible variables of every scope holding at least one.
+    pub fn visible_variables(&self) -> HashMap<VariableScope, VariableStore> {
+        let mut res = HashMap::new();
+        let mut collect = |scope: VariableScope, store: &VariableStore| {
+            let visible: VariableStore = store.visible_vars().collect();
+            if !visible.is_empty() {
+                res.insert(scope, visible);
+            }
+        };
+        collect(VariableScope::Global, &self.vars);
+        for (line_id, line) in self.lines.iter().enumerate() {
+            collect(VariableScope::Line(line_id), &line.vars);
+            for (frame_id, frame) in line.frames.iter().enumerate() {
+                collect(VariableScope::Frame(line_id, frame_id), &frame.vars);
+            }
+        }
+        res
+    }
+
+    /// Every variable store of the scene, with its scope.
+    pub fn variable_stores_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (VariableScope, &mut VariableStore)> {
+        let lines = self.lines.iter_mut().enumerate().flat_map(|(line_id, line)| {
+            let frames = line.frames.iter_mut().enumerate().map(move |(frame_id, frame)| {
+                (VariableScope::Frame(line_id, frame_id), &mut frame.vars)
+            });
+            std::iter::once((VariableScope::Line(line_id), &mut line.vars)).chain(frames)
+        });
+        std::iter::once((VariableScope::Global, &mut self.vars)).chain(lines)
+    }
+
+    /// Applies variable changes (as broadcast by the scheduler) to the scene.
+    /// Changes targeting lines or frames that do not exist are ignored.
+    pub fn apply_variable_changes(&mut self, changes: &[(VariableScope, String, Option<VariableValue>)]) {
+        for (scope, name, value) in changes {
+            let store = match *scope {
+                VariableScope::Global => &mut self.vars,
+                VariableScope::Line(line_id) if line_id < self.n_lines() => {
+                    &mut self.line_mut(line_id).vars
+                }
+                VariableScope::Frame(line_id, frame_id) if self.has_frame(line_id, frame_id) => {
+                    &mut self.get_frame_mut(line_id, frame_id).vars
+                }
+                _ => continue,
+            };
+            match value {
+                Some(value) => {
+                    store.insert(name.clone(), value.clone());
+                }
+                None => {
+                    store.remove(name);
+                }
+            }
+        }
     }
 
     pub fn has_frame(&self, line_id: usize, frame_id: usize) -> bool {
@@ -174,15 +279,16 @@ impl Scene {
     pub fn update_executions<'a>(
         &'a mut self,
         mut partial: PartialContext<'a>,
-    ) -> (Vec<ConcreteEvent>, SyncTime) {
+    ) -> (Vec<(usize, ConcreteEvent)>, SyncTime) {
         let mut events = Vec::new();
         let mut next_wait = NEVER;
         partial.global_vars = Some(&mut self.vars);
+        partial.modulators = Some(&self.modulators);
         for (index, line) in self.lines.iter_mut().enumerate() {
             let mut partial_child = partial.child();
             partial_child.line_index = Some(index);
-            let (mut new_events, wait) = line.update_executions(partial_child);
-            events.append(&mut new_events);
+            let (new_events, wait) = line.update_executions(partial_child);
+            events.extend(new_events.into_iter().map(|event| (index, event)));
             next_wait = std::cmp::min(next_wait, wait)
         }
         (events, next_wait)
@@ -200,3 +306,54 @@ impl Scene {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_clears_every_variable_scope() {
+        let mut scene = Scene::new(vec![Line::new(vec![1.0, 1.0])]);
+        scene.vars.insert("A".to_string(), 1.into());
+        scene.line_mut(0).vars.insert("count".to_string(), 2.into());
+        scene.get_frame_mut(0, 1).vars.insert("x".to_string(), 3.into());
+        scene.reset();
+        assert!(scene.vars.is_empty());
+        assert!(scene.line(0).unwrap().vars.is_empty());
+        assert!(scene.get_frame(0, 1).unwrap().vars.is_empty());
+    }
+
+    #[test]
+    fn variable_changes_are_applied_to_existing_scopes() {
+        let mut scene = Scene::new(vec![Line::new(vec![1.0])]);
+        scene.line_mut(0).vars.insert("gone".to_string(), 1.into());
+        scene.apply_variable_changes(&[
+            (VariableScope::Global, "A".to_string(), Some(2.into())),
+            (VariableScope::Line(0), "gone".to_string(), None),
+            (VariableScope::Frame(0, 0), "x".to_string(), Some(3.into())),
+            (VariableScope::Line(1), "missing".to_string(), Some(4.into())),
+            (VariableScope::Frame(0, 1), "missing".to_string(), Some(5.into())),
+        ]);
+        assert_eq!(scene.vars.get("A"), Some(&2.into()));
+        assert!(scene.line(0).unwrap().vars.is_empty());
+        assert_eq!(scene.get_frame(0, 0).unwrap().vars.get("x"), Some(&3.into()));
+        assert_eq!(scene.n_lines(), 1);
+    }
+
+    #[test]
+    fn variable_stores_are_listed_with_their_scope() {
+        let mut scene = Scene::new(vec![Line::new(vec![1.0, 1.0]), Line::new(vec![1.0])]);
+        let scopes: Vec<VariableScope> = scene.variable_stores_mut().map(|(s, _)| s).collect();
+        assert_eq!(
+            scopes,
+            vec![
+                VariableScope::Global,
+                VariableScope::Line(0),
+                VariableScope::Frame(0, 0),
+                VariableScope::Frame(0, 1),
+                VariableScope::Line(1),
+                VariableScope::Frame(1, 0),
+            ]
+        );
+    }
+}
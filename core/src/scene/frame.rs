@@ -7,7 +7,7 @@ use crate::{
     compiler::CompilationState,
     vm::{
         PartialContext, event::ConcreteEvent, interpreter::InterpreterDirectory,
-        variable::VariableStore,
+        random::SovaRng, variable::VariableStore,
     },
     log_eprintln,
     scene::script::{Script, ScriptExecution},
@@ -42,6 +42,9 @@ pub struct Frame {
     script_has_changed: bool,
     #[serde(skip)]
     pub executions: Vec<ScriptExecution>,
+    /// Random stream used by the executions of this frame
+    #[serde(skip)]
+    pub rng: SovaRng,
 }
 
 impl Frame {
@@ -58,6 +61,7 @@ impl Frame {
             self.mark_script_changed();
         }
         self.executions = old.executions;
+        self.rng = old.rng;
     }
 
     pub fn mark_script_changed(&mut self) {
@@ -134,6 +138,7 @@ impl Frame {
         let mut new_executions = Vec::new();
         partial.frame_vars = Some(&mut self.vars);
         partial.frame_len = Some(self.duration);
+        partial.rng = Some(&mut self.rng);
         for exec in self.executions.iter_mut() {
             if !exec.is_ready(date) {
                 let wait = exec.remaining_before(date);
@@ -217,6 +222,7 @@ impl Default for Frame {
             vars: Default::default(),
             script_has_changed: false,
             executions: Default::default(),
+            rng: Default::default(),
         }
     }
 }
@@ -232,6 +238,7 @@ impl Clone for Frame {
             vars: Default::default(),
             script_has_changed: false,
             executions: Default::default(),
+            rng: self.rng.clone(),
         }
     }
 }
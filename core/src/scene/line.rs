@@ -1,6 +1,6 @@
 use crate::{
     clock::NEVER,
-    vm::{PartialContext, event::ConcreteEvent, interpreter::InterpreterDirectory},
+    vm::{PartialContext, event::ConcreteEvent, interpreter::InterpreterDirectory, random::SovaRng},
     scene::{Frame, script::Script},
     util::decimal_operations::precise_division,
 };
@@ -43,6 +43,9 @@ pub struct Line {
     /// If set, defines a custom total loop duration in beats for this line, overriding the calculated sum of its frames.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_length: Option<f64>,
+    /// If set, seeds the random streams of this line's frames, overriding the seed derived from the scene.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 
     // --- Runtime State (Not Serialized) ---
     /// The index of the currently active frame during playback.
@@ -121,14 +124,30 @@ impl Line {
         }
     }
 
-    pub fn reset(&mut self) {
+    /// Puts the line back at its starting position, keeping variables untouched.
+    pub fn rewind(&mut self) {
         self.current_iteration = 0;
         self.current_frame = 0;
         self.current_repetition = 0;
         self.frames_passed = 0;
         self.frames_executed = 0;
         self.last_trigger = NEVER;
+    }
+
+    /// Rewinds the line and clears its variables, as well as the variables of its frames.
+    pub fn reset(&mut self) {
+        self.rewind();
         self.vars.clear();
+        self.frames.iter_mut().for_each(Frame::reset);
+    }
+
+    /// Restarts the random streams of all frames. Each frame gets its own stream,
+    /// derived from the line seed if set, or from the given scene-derived seed otherwise.
+    pub fn reseed(&mut self, scene_seed: u64) {
+        let seed = self.seed.unwrap_or(scene_seed);
+        for (index, frame) in self.frames.iter_mut().enumerate() {
+            frame.rng.reseed(SovaRng::derive(seed, index));
+        }
     }
 
     pub fn configure(&mut self, other: &Line) {
@@ -136,6 +155,7 @@ impl Line {
         self.start_frame = other.start_frame;
         self.end_frame = other.end_frame;
         self.custom_length = other.custom_length;
+        self.seed = other.seed;
     }
 
     /// Returns light version without frames
@@ -518,6 +538,7 @@ impl Default for Line {
             start_frame: Default::default(),
             end_frame: Default::default(),
             custom_length: Default::default(),
+            seed: Default::default(),
             current_frame: Default::default(),
             current_iteration: Default::default(),
             current_repetition: Default::default(),
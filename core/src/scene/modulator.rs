@@ -0,0 +1,224 @@
+//! Tempo-synced modulation sources (LFOs and envelopes) shared by the whole scene.
+//!
+//! A modulator has no internal state : its value is a pure function of the Link beat,
+//! so every reader (scripts of any language, the scheduler streaming it to a device, a
+//! remote peer) sees the same value at the same beat.
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+use crate::vm::{event::ConcreteEvent, random::SovaRng, variable::VariableValue};
+use crate::protocol::osc::OSCMessage;
+
+/// Highest streaming rate accepted for a modulator output, in updates per second.
+pub const MAX_MODULATOR_RATE: f64 = 1000.0;
+
+/// Waveform of a [`Modulator`]. Every shape produces values in `[0, 1]` over one cycle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModulatorShape {
+    Sine,
+    Saw,
+    /// Decreasing saw
+    InvertedSaw,
+    Triangle,
+    /// Square wave, high during the given fraction of the cycle
+    Square(f64),
+    /// Random value held during each cycle, drawn from the given seed
+    SampleHold(u64),
+    /// ADSR envelope retriggered at each cycle. Durations are in beats, sustain is a level.
+    /// The gate closes `release` beats before the end of the cycle.
+    Envelope {
+        attack: f64,
+        decay: f64,
+        sustain: f64,
+        release: f64,
+    },
+}
+
+/// Where a modulator is continuously sent by the scheduler.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModulatorTarget {
+    /// MIDI control change, the value is rounded and clamped to `[0, 127]`
+    MidiControl { channel: u64, control: u64 },
+    /// OSC message with a single float argument
+    Osc { address: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModulatorOutput {
+    pub device: usize,
+    pub target: ModulatorTarget,
+    /// Updates per second
+    pub rate: f64,
+}
+
+impl ModulatorOutput {
+    /// Delay between two updates, in microseconds, or None if the output is disabled.
+    pub fn interval(&self) -> Option<u64> {
+        if self.rate <= 0.0 || self.rate.is_nan() {
+            return None;
+        }
+        Some((1_000_000.0 / self.rate.min(MAX_MODULATOR_RATE)) as u64)
+    }
+
+    pub fn event(&self, value: f64) -> ConcreteEvent {
+        match &self.target {
+            ModulatorTarget::MidiControl { channel, control } => {
+                let value = value.round().clamp(0.0, 127.0) as u64;
+                ConcreteEvent::MidiControl(*control, value, *channel, self.device)
+            }
+            ModulatorTarget::Osc { address } => ConcreteEvent::Osc {
+                message: OSCMessage {
+                    addr: address.clone(),
+                    args: vec![VariableValue::Float(value)],
+                    timetag: None,
+                },
+                device_id: self.device,
+            },
+        }
+    }
+}
+
+/// Named modulation source, tied to the Link beat.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Modulator {
+    pub shape: ModulatorShape,
+    /// Length of a cycle, in beats
+    pub period: f64,
+    /// Phase offset, in cycles
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default)]
+    pub min: f64,
+    #[serde(default = "default_max")]
+    pub max: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<ModulatorOutput>,
+}
+
+fn default_max() -> f64 {
+    1.0
+}
+
+impl Modulator {
+    pub fn new(shape: ModulatorShape, period: f64) -> Self {
+        Modulator {
+            shape,
+            period,
+            offset: 0.0,
+            min: 0.0,
+            max: 1.0,
+            output: None,
+        }
+    }
+
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Position in cycles at the given beat, offset included. A negative period runs backwards.
+    fn cycles(&self, beat: f64) -> f64 {
+        if self.period == 0.0 {
+            return self.offset;
+        }
+        beat / self.period + self.offset
+    }
+
+    /// Value of the modulator in `[0, 1]` at the given beat.
+    pub fn unit_value(&self, beat: f64) -> f64 {
+        let cycles = self.cycles(beat);
+        let phase = cycles.rem_euclid(1.0);
+        match &self.shape {
+            ModulatorShape::Sine => ((phase * 2.0 * PI).sin() + 1.0) / 2.0,
+            ModulatorShape::Saw => phase,
+            ModulatorShape::InvertedSaw => 1.0 - phase,
+            ModulatorShape::Triangle => 1.0 - (phase * 2.0 - 1.0).abs(),
+            ModulatorShape::Square(duty) => {
+                if phase < *duty { 1.0 } else { 0.0 }
+            }
+            ModulatorShape::SampleHold(seed) => {
+                let cycle = cycles.floor() as i64 as u64 as usize;
+                let value = SovaRng::derive(*seed, cycle);
+                (value >> 11) as f64 / (1u64 << 53) as f64
+            }
+            ModulatorShape::Envelope { attack, decay, sustain, release } => {
+                let t = phase * self.period.max(0.0);
+                let gate_end = self.period - release;
+                if t >= gate_end {
+                    if *release <= 0.0 {
+                        return 0.0;
+                    }
+                    sustain * (1.0 - (t - gate_end) / release)
+                } else if t < *attack {
+                    t / attack
+                } else if t < attack + decay {
+                    1.0 - (1.0 - sustain) * (t - attack) / decay
+                } else {
+                    *sustain
+                }
+            }
+        }
+    }
+
+    /// Value of the modulator at the given beat, scaled to `[min, max]`.
+    pub fn value_at(&self, beat: f64) -> f64 {
+        self.min + self.unit_value(beat) * (self.max - self.min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn phase_follows_beat() {
+        let saw = Modulator::new(ModulatorShape::Saw, 4.0);
+        assert!(close(saw.value_at(0.0), 0.0));
+        assert!(close(saw.value_at(1.0), 0.25));
+        assert!(close(saw.value_at(5.0), 0.25));
+        let sine = Modulator::new(ModulatorShape::Sine, 1.0).with_range(-1.0, 1.0);
+        assert!(close(sine.value_at(0.25), 1.0));
+        assert!(close(sine.value_at(10.75), -1.0));
+    }
+
+    #[test]
+    fn sample_hold_is_constant_within_cycle() {
+        let sh = Modulator::new(ModulatorShape::SampleHold(3), 2.0);
+        let a = sh.value_at(4.1);
+        assert!(close(a, sh.value_at(5.9)));
+        assert!(!close(a, sh.value_at(6.1)));
+        assert!((0.0..1.0).contains(&a));
+    }
+
+    #[test]
+    fn envelope_stages() {
+        let env = Modulator::new(
+            ModulatorShape::Envelope { attack: 1.0, decay: 1.0, sustain: 0.5, release: 1.0 },
+            4.0,
+        );
+        assert!(close(env.value_at(0.5), 0.5));
+        assert!(close(env.value_at(1.0), 1.0));
+        assert!(close(env.value_at(2.5), 0.5));
+        assert!(close(env.value_at(3.5), 0.25));
+        assert!(close(env.value_at(4.0), 0.0));
+    }
+
+    #[test]
+    fn midi_output_is_clamped() {
+        let output = ModulatorOutput {
+            device: 1,
+            target: ModulatorTarget::MidiControl { channel: 1, control: 74 },
+            rate: 50.0,
+        };
+        assert_eq!(output.interval(), Some(20_000));
+        assert_eq!(output.event(200.0), ConcreteEvent::MidiControl(74, 127, 1, 1));
+    }
+}
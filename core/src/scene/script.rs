@@ -94,7 +94,7 @@ impl Script {
     }
 
     pub fn set_program(&mut self, prog: Program) {
-        self.compiled = CompilationState::Compiled(prog)
+        self.compiled = CompilationState::Compiled(prog, Default::default())
     }
 
     pub fn set_error(&mut self, error: CompilationError) {
@@ -121,7 +121,7 @@ impl hash::Hash for Script {
 impl From<Program> for Script {
     fn from(compiled: Program) -> Self {
         Script {
-            compiled: CompilationState::Compiled(compiled),
+            compiled: CompilationState::Compiled(compiled, Default::default()),
             ..Default::default()
         }
     }
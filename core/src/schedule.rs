@@ -1,7 +1,7 @@
 use crate::{
     clock::{Clock, ClockServer, NEVER, SyncTime},
     device_map::DeviceMap,
-    vm::{LanguageCenter, PartialContext, variable::VariableStore},
+    vm::{LanguageCenter, PartialContext, RuntimeFault, variable::{VariableScope, VariableStore}},
     log_println,
     protocol::TimedMessage,
     scene::Scene,
@@ -10,22 +10,32 @@ use crate::{
 };
 
 use crossbeam_channel::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
-use std::{cmp::min, sync::Arc, thread::JoinHandle, time::Duration, usize};
+use std::{cmp::min, collections::HashMap, sync::Arc, thread::JoinHandle, time::Duration, usize};
 use thread_priority::{ThreadBuilder, ThreadPriority};
 
 pub mod playback;
 
 mod action_timing;
+mod emitted_event;
 mod message;
 mod notification;
 mod scheduler_actions;
 
 pub use action_timing::ActionTiming;
+pub use emitted_event::{EmittedEvent, EventKind, EventName};
 pub use message::SchedulerMessage;
 pub use notification::SovaNotification;
 
 pub const SCHEDULED_DRIFT: SyncTime = 30_000;
 pub const SCHEDULER_ACTIVE_WAITING_SWITCH: SyncTime = 100;
+/// Minimal delay between two variable changes broadcasts (~30fps)
+pub const VARIABLES_BROADCAST_INTERVAL: SyncTime = 33_000;
+/// Minimal delay between two emitted events broadcasts (~30fps)
+pub const EVENTS_BROADCAST_INTERVAL: SyncTime = 33_000;
+/// Maximal number of emitted events kept between two broadcasts
+const MAX_EMITTED_EVENTS: usize = 256;
+/// Maximal number of runtime faults kept between two broadcasts
+const MAX_RUNTIME_FAULTS: usize = 64;
 
 pub struct Scheduler {
     pub scene: Scene,
@@ -44,6 +54,20 @@ pub struct Scheduler {
     shutdown_requested: bool,
 
     scene_structure: Vec<Vec<f64>>,
+
+    variables_image: HashMap<VariableScope, VariableStore>,
+    /// Compare every variable store on the next broadcast, not only the modified ones
+    rescan_variables: bool,
+    last_variables_check: SyncTime,
+
+    /// Events emitted since the last broadcast
+    emitted_events: Vec<EmittedEvent>,
+    /// Runtime faults raised by the scripts since the last broadcast
+    runtime_faults: Vec<RuntimeFault>,
+    last_events_broadcast: SyncTime,
+
+    /// Date of the next update of each streamed modulator
+    modulator_updates: HashMap<String, SyncTime>,
 }
 
 impl Scheduler {
@@ -102,6 +126,13 @@ impl Scheduler {
             playback_manager: PlaybackManager::default(),
             shutdown_requested: false,
             scene_structure: Vec::new(),
+            variables_image: HashMap::new(),
+            rescan_variables: true,
+            last_variables_check: 0,
+            emitted_events: Vec::new(),
+            runtime_faults: Vec::new(),
+            last_events_broadcast: 0,
+            modulator_updates: HashMap::new(),
         }
     }
 
@@ -109,8 +140,10 @@ impl Scheduler {
         scene.make_consistent();
         scene.reset();
         self.scene = scene;
+        self.devices.set_tuning(self.scene.tuning.clone());
 
         self.scene_structure = self.scene.structure();
+        self.rescan_variables = true;
         self.languages
             .process_scene(&self.scene, self.feedback.clone());
 
@@ -140,6 +173,13 @@ impl Scheduler {
                     .update_notifier
                     .send(SovaNotification::QuantumChanged(quantum));
             }
+            SchedulerMessage::SetTuning(tuning, _) => {
+                self.scene.tuning = tuning.clone();
+                self.devices.set_tuning(tuning.clone());
+                let _ = self
+                    .update_notifier
+                    .send(SovaNotification::TuningChanged(tuning));
+            }
             SchedulerMessage::SetScene(scene, _) => {
                 self.change_scene(scene.clone());
                 let _ = self
@@ -167,6 +207,7 @@ impl Scheduler {
                     &self.feedback,
                 );
                 self.scene_structure = self.scene.structure();
+                self.rescan_variables = true;
             }
         }
     }
@@ -233,8 +274,19 @@ impl Scheduler {
         partial.clock = Some(&self.clock);
         partial.device_map = Some(&self.devices);
         partial.structure = Some(&self.scene_structure);
+        partial.faults = Some(&mut self.runtime_faults);
         let (events, wait) = self.scene.update_executions(partial);
-        for event in events {
+        let excess = self.runtime_faults.len().saturating_sub(MAX_RUNTIME_FAULTS);
+        self.runtime_faults.drain(..excess);
+        if !events.is_empty() {
+            let beat = self.clock.beat_at_date(date);
+            self.emitted_events.extend(events.iter().filter_map(|(line_id, event)| {
+                EmittedEvent::new(*line_id, event, beat, &self.clock)
+            }));
+            let excess = self.emitted_events.len().saturating_sub(MAX_EMITTED_EVENTS);
+            self.emitted_events.drain(..excess);
+        }
+        for (_, event) in events {
             for msg in self.devices.map_event(event, date, &self.clock) {
                 let _ = self.world_iface.send(msg);
             }
@@ -242,6 +294,115 @@ impl Scheduler {
         wait
     }
 
+    /// Sends the changes of visible variables (all scopes) since the last broadcast.
+    /// Changes are coalesced and sent at most once per `VARIABLES_BROADCAST_INTERVAL`.
+    /// Returns the delay before variables should be checked again.
+    fn broadcast_variable_changes(&mut self, date: SyncTime) -> SyncTime {
+        let elapsed = date.saturating_sub(self.last_variables_check);
+        if elapsed < VARIABLES_BROADCAST_INTERVAL {
+            return VARIABLES_BROADCAST_INTERVAL - elapsed;
+        }
+        self.last_variables_check = date;
+        // Only the stores modified since the last broadcast are compared, unless the scene
+        // changed and scopes may have moved
+        let rescan = std::mem::take(&mut self.rescan_variables);
+        let mut stale = if rescan {
+            std::mem::take(&mut self.variables_image)
+        } else {
+            HashMap::new()
+        };
+        let mut changes = Vec::new();
+        for (scope, store) in self.scene.variable_stores_mut() {
+            if !store.take_modified() && !rescan {
+                continue;
+            }
+            let image = if rescan {
+                stale.remove(&scope)
+            } else {
+                self.variables_image.remove(&scope)
+            };
+            let mut image = image.unwrap_or_default();
+            for (name, value) in store.diff(&image) {
+                if name.starts_with('_') {
+                    continue;
+                }
+                match &value {
+                    Some(value) => image.insert(name.clone(), value.clone()),
+                    None => image.remove(&name),
+                };
+                changes.push((scope, name, value));
+            }
+            if !image.is_empty() {
+                self.variables_image.insert(scope, image);
+            }
+        }
+        // Scopes which do not exist anymore
+        for (scope, previous) in stale {
+            changes.extend(previous.iter().map(|(k, _)| (scope, k.clone(), None)));
+        }
+        if changes.is_empty() {
+            return NEVER;
+        }
+        let _ = self
+            .update_notifier
+            .send(SovaNotification::VariablesChanged(changes));
+        VARIABLES_BROADCAST_INTERVAL
+    }
+
+    /// Sends the events emitted and the runtime faults raised since the last broadcast.
+    /// Both are coalesced and sent at most once per `EVENTS_BROADCAST_INTERVAL`.
+    /// Returns the delay before events should be checked again.
+    fn broadcast_emitted_events(&mut self, date: SyncTime) -> SyncTime {
+        if self.emitted_events.is_empty() && self.runtime_faults.is_empty() {
+            return NEVER;
+        }
+        let elapsed = date.saturating_sub(self.last_events_broadcast);
+        if elapsed < EVENTS_BROADCAST_INTERVAL {
+            return EVENTS_BROADCAST_INTERVAL - elapsed;
+        }
+        self.last_events_broadcast = date;
+        if !self.emitted_events.is_empty() {
+            let events = std::mem::take(&mut self.emitted_events);
+            let _ = self
+                .update_notifier
+                .send(SovaNotification::EventsEmitted(events));
+        }
+        if !self.runtime_faults.is_empty() {
+            let faults = std::mem::take(&mut self.runtime_faults);
+            let _ = self
+                .update_notifier
+                .send(SovaNotification::RuntimeFaults(faults));
+        }
+        NEVER
+    }
+
+    /// Sends the current value of every modulator having an output, each at its own rate.
+    /// Returns the delay before the next modulator update.
+    fn stream_modulators(&mut self, date: SyncTime) -> SyncTime {
+        self.modulator_updates
+            .retain(|name, _| self.scene.modulators.contains_key(name));
+        let beat = self.clock.beat_at_date(date);
+        let mut next_update = NEVER;
+        for (name, modulator) in self.scene.modulators.iter() {
+            let Some(output) = &modulator.output else {
+                continue;
+            };
+            let Some(interval) = output.interval() else {
+                continue;
+            };
+            let due = self.modulator_updates.entry(name.clone()).or_insert(date);
+            if *due <= date {
+                let event = output.event(modulator.value_at(beat));
+                for msg in self.devices.map_event(event, date, &self.clock) {
+                    let _ = self.world_iface.send(msg);
+                }
+                *due = date + interval;
+            }
+            next_update = min(next_update, *due - date);
+        }
+        next_update
+    }
+
     pub fn active_wait(&self, date: &mut SyncTime, target: SyncTime) {
         if target.saturating_sub(*date) > ACTIVE_WAITING_SWITCH_MICROS {
             return;
@@ -290,6 +451,8 @@ impl Scheduler {
             }
 
             if !self.playback_manager.state().is_playing() {
+                let variables_delay = self.broadcast_variable_changes(date);
+                self.next_wait = Some(min(variables_delay, self.next_wait.unwrap_or(NEVER)));
                 continue;
             }
 
@@ -326,7 +489,15 @@ impl Scheduler {
                     ));
             }
 
-            let next_delay = std::cmp::min(next_exec_delay, next_frame_delay);
+            let variables_delay = self.broadcast_variable_changes(date);
+            let modulators_delay = self.stream_modulators(date);
+            let events_delay = self.broadcast_emitted_events(date);
+
+            let next_delay = next_exec_delay
+                .min(next_frame_delay)
+                .min(variables_delay)
+                .min(modulators_delay)
+                .min(events_delay);
             if next_delay > 0 {
                 self.next_wait = Some(next_delay);
             } else {
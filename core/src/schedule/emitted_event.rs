@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    clock::Clock,
+    vm::{event::ConcreteEvent, variable::VariableValue},
+};
+
+/// Bytes kept of the names of sounds and addresses.
+const NAME_LEN: usize = 24;
+
+/// Name of a sound or an address, truncated so that it is summarized without allocating
+/// on the scheduler thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventName {
+    len: u8,
+    bytes: [u8; NAME_LEN],
+}
+
+impl EventName {
+    pub fn new(name: &str) -> Self {
+        let mut len = name.len().min(NAME_LEN);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; NAME_LEN];
+        bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+        EventName {
+            len: len as u8,
+            bytes,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = (self.len as usize).min(NAME_LEN);
+        std::str::from_utf8(&self.bytes[..len]).unwrap_or_default()
+    }
+}
+
+/// What an emitted event does. Clients build its label with `Display`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EventKind {
+    Note(i64),
+    /// Dirt or generic event, with its sound if named
+    Sound(Option<EventName>),
+    Osc(EventName),
+    Control(u64, u64),
+    Control14(u64, u64),
+    Nrpn(u64, u64),
+    Rpn(u64, u64),
+    Bend(u64),
+    Expression(u64),
+    Program(u64),
+    Aftertouch(u64, u64),
+    Pressure(u64),
+    Sysex,
+    Start,
+    Stop,
+    Reset,
+    Continue,
+    Clock,
+    Ramp,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::Note(note) => write!(f, "note {note}"),
+            EventKind::Sound(Some(sound)) => write!(f, "{}", sound.as_str()),
+            EventKind::Sound(None) => write!(f, "event"),
+            EventKind::Osc(addr) => write!(f, "{}", addr.as_str()),
+            EventKind::Control(control, value) => write!(f, "cc {control}={value}"),
+            EventKind::Control14(control, value) => write!(f, "cc14 {control}={value}"),
+            EventKind::Nrpn(param, value) => write!(f, "nrpn {param}={value}"),
+            EventKind::Rpn(param, value) => write!(f, "rpn {param}={value}"),
+            EventKind::Bend(value) => write!(f, "bend {value}"),
+            EventKind::Expression(note) => write!(f, "expression {note}"),
+            EventKind::Program(program) => write!(f, "program {program}"),
+            EventKind::Aftertouch(note, value) => write!(f, "aftertouch {note}={value}"),
+            EventKind::Pressure(value) => write!(f, "pressure {value}"),
+            EventKind::Sysex => write!(f, "sysex"),
+            EventKind::Start => write!(f, "start"),
+            EventKind::Stop => write!(f, "stop"),
+            EventKind::Reset => write!(f, "reset"),
+            EventKind::Continue => write!(f, "continue"),
+            EventKind::Clock => write!(f, "clock"),
+            EventKind::Ramp => write!(f, "ramp"),
+        }
+    }
+}
+
+/// Summary of an event emitted by a line, for the visualizations of the clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmittedEvent {
+    pub line_id: usize,
+    /// Beat at which the event was emitted
+    pub beat: f64,
+    /// Pitch of the event, if it plays a note
+    pub note: Option<i64>,
+    /// Duration in beats (0 for instantaneous events)
+    pub duration: f64,
+    pub kind: EventKind,
+}
+
+/// Note of a Dirt or generic event.
+fn map_note(args: &HashMap<String, VariableValue>) -> Option<i64> {
+    match args.get("note").or_else(|| args.get("n")) {
+        Some(VariableValue::Integer(note)) => Some(*note),
+        Some(VariableValue::Float(note)) => Some(note.round() as i64),
+        _ => None,
+    }
+}
+
+/// Sound of a Dirt or generic event.
+fn map_sound(args: &HashMap<String, VariableValue>) -> EventKind {
+    match args.get("s") {
+        Some(VariableValue::Str(sound)) => EventKind::Sound(Some(EventName::new(sound))),
+        _ => EventKind::Sound(None),
+    }
+}
+
+/// Duration in beats of a Dirt event, given by its sustain in seconds. Others are instantaneous.
+fn map_sustain(args: &HashMap<String, VariableValue>, clock: &Clock) -> f64 {
+    let seconds = match args.get("sustain") {
+        Some(VariableValue::Float(seconds)) => *seconds,
+        Some(VariableValue::Integer(seconds)) => *seconds as f64,
+        _ => return 0.0,
+    };
+    clock.micros_to_beats((seconds.max(0.0) * 1_000_000.0) as u64)
+}
+
+impl EmittedEvent {
+    /// Summarizes an event emitted by a line at a beat. Internal events are not summarized.
+    pub fn new(line_id: usize, event: &ConcreteEvent, beat: f64, clock: &Clock) -> Option<Self> {
+        let (note, duration, kind) = match event {
+            ConcreteEvent::Nop | ConcreteEvent::StartProgram(_) => return None,
+            ConcreteEvent::MidiNote(note, _, _, duration, _) => (
+                Some(*note as i64),
+                clock.micros_to_beats(*duration),
+                EventKind::Note(*note as i64),
+            ),
+            ConcreteEvent::Dirt { args, .. } => {
+                (map_note(args), map_sustain(args, clock), map_sound(args))
+            }
+            ConcreteEvent::Generic(VariableValue::Integer(note), duration, _, _) => (
+                Some(*note),
+                clock.micros_to_beats(*duration),
+                EventKind::Note(*note),
+            ),
+            ConcreteEvent::Generic(VariableValue::Map(args), duration, _, _) => (
+                map_note(args),
+                clock.micros_to_beats(*duration),
+                map_sound(args),
+            ),
+            ConcreteEvent::Generic(_, duration, _, _) => (
+                None,
+                clock.micros_to_beats(*duration),
+                EventKind::Sound(None),
+            ),
+            ConcreteEvent::Osc { message, .. } => {
+                (None, 0.0, EventKind::Osc(EventName::new(&message.addr)))
+            }
+            ConcreteEvent::MidiControl(control, value, _, _) => {
+                (None, 0.0, EventKind::Control(*control, *value))
+            }
+            ConcreteEvent::MidiControl14(control, value, _, _) => {
+                (None, 0.0, EventKind::Control14(*control, *value))
+            }
+            ConcreteEvent::MidiNrpn(param, value, _, _) => {
+                (None, 0.0, EventKind::Nrpn(*param, *value))
+            }
+            ConcreteEvent::MidiRpn(param, value, _, _) => (None, 0.0, EventKind::Rpn(*param, *value)),
+            ConcreteEvent::MidiPitchBend(value, _, _) => (None, 0.0, EventKind::Bend(*value)),
+            ConcreteEvent::MidiNoteExpression(note, _, _, _, _) => {
+                (None, 0.0, EventKind::Expression(*note))
+            }
+            ConcreteEvent::MidiProgram(program, _, _) => (None, 0.0, EventKind::Program(*program)),
+            ConcreteEvent::MidiAftertouch(note, value, _, _) => {
+                (None, 0.0, EventKind::Aftertouch(*note, *value))
+            }
+            ConcreteEvent::MidiChannelPressure(value, _, _) => {
+                (None, 0.0, EventKind::Pressure(*value))
+            }
+            ConcreteEvent::MidiSystemExclusive(_, _) => (None, 0.0, EventKind::Sysex),
+            ConcreteEvent::MidiStart(_) => (None, 0.0, EventKind::Start),
+            ConcreteEvent::MidiStop(_) => (None, 0.0, EventKind::Stop),
+            ConcreteEvent::MidiReset(_) => (None, 0.0, EventKind::Reset),
+            ConcreteEvent::MidiContinue(_) => (None, 0.0, EventKind::Continue),
+            ConcreteEvent::MidiClock(_) => (None, 0.0, EventKind::Clock),
+            ConcreteEvent::Ramp(_, _) => (None, 0.0, EventKind::Ramp),
+        };
+        Some(EmittedEvent {
+            line_id,
+            beat,
+            note,
+            duration,
+            kind,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockServer;
+    use std::sync::Arc;
+
+    #[test]
+    fn events_are_summarized() {
+        let clock = Clock::from(&Arc::new(ClockServer::new(120.0, 4.0)));
+        let note = ConcreteEvent::MidiNote(60, 90, 0, clock.beats_to_micros(0.5), 1);
+        let summary = EmittedEvent::new(2, &note, 4.0, &clock).unwrap();
+        assert_eq!((summary.line_id, summary.beat, summary.note), (2, 4.0, Some(60)));
+        assert!((summary.duration - 0.5).abs() < 1e-6);
+        assert_eq!(summary.kind.to_string(), "note 60");
+
+        // At 120 BPM, a sustain of one second lasts two beats
+        let args = HashMap::from([
+            ("s".to_owned(), VariableValue::Str("bd".to_owned())),
+            ("n".to_owned(), VariableValue::Float(3.4)),
+            ("sustain".to_owned(), VariableValue::Float(1.0)),
+        ]);
+        let dirt = ConcreteEvent::Dirt { args, device_id: 1 };
+        let summary = EmittedEvent::new(0, &dirt, 0.0, &clock).unwrap();
+        assert_eq!((summary.note, summary.kind.to_string()), (Some(3), "bd".to_owned()));
+        assert!((summary.duration - 2.0).abs() < 1e-6);
+        let dirt = ConcreteEvent::Dirt { args: HashMap::new(), device_id: 1 };
+        let summary = EmittedEvent::new(0, &dirt, 0.0, &clock).unwrap();
+        assert_eq!((summary.duration, summary.kind), (0.0, EventKind::Sound(None)));
+
+        let cc = ConcreteEvent::MidiControl(7, 100, 0, 1);
+        assert_eq!(EmittedEvent::new(0, &cc, 0.0, &clock).unwrap().kind.to_string(), "cc 7=100");
+        assert!(EmittedEvent::new(0, &ConcreteEvent::Nop, 0.0, &clock).is_none());
+    }
+
+    #[test]
+    fn names_are_truncated_to_characters() {
+        assert_eq!(EventName::new("/dirt/play").as_str(), "/dirt/play");
+        let long = "é".repeat(NAME_LEN);
+        assert_eq!(EventName::new(&long).as_str(), "é".repeat(NAME_LEN / 2));
+        assert_eq!(EventName::new(&format!("a{long}")).as_str().len(), NAME_LEN - 1);
+    }
+}
@@ -2,8 +2,10 @@ use crate::compiler::CompilationState;
 use crate::protocol::ProtocolPayload;
 use crate::scene::Frame;
 use crate::scene::script::Script;
-use crate::scene::{Scene, Line};
+use crate::scene::{Scene, Line, Modulator};
+use crate::theory::Tuning;
 use crate::schedule::action_timing::ActionTiming;
+use crate::vm::variable::{VariableScope, VariableValue};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +23,8 @@ pub enum SchedulerMessage {
     
     /// Set a frame at a specific index
     SetFrames(Vec<(usize, usize, Frame)>, ActionTiming),
+    /// Enable or disable frames (line, frame, enabled), leaving the rest of them untouched
+    EnableFrames(Vec<(usize, usize, bool)>, ActionTiming),
     /// Insert a frame with a given value at a specific position in a line.
     AddFrame(usize, usize, Frame, ActionTiming),
     /// Remove the frame at a specific position in a line.
@@ -28,6 +32,15 @@ pub enum SchedulerMessage {
 
     /// Set the script content and lang for specified frame
     SetScript(usize, usize, Script, ActionTiming),
+
+    /// Set the value of a named variable in the given scope
+    SetVariable(VariableScope, String, VariableValue, ActionTiming),
+    /// Set the root seed of the scene and restart all random streams (None: seeded from entropy)
+    SetSeed(Option<u64>, ActionTiming),
+    /// Define, replace or remove (None) a named scene modulator
+    SetModulator(String, Option<Modulator>, ActionTiming),
+    /// Set (or remove, with None) the microtonal tuning of the scene
+    SetTuning(Option<Tuning>, ActionTiming),
     
     /// Set the master tempo.
     SetTempo(f64, ActionTiming),
@@ -58,6 +71,7 @@ impl SchedulerMessage {
             | SchedulerMessage::AddLine(_, _, t)
             | SchedulerMessage::RemoveLine(_, t)
             | SchedulerMessage::SetFrames(_, t)
+            | SchedulerMessage::EnableFrames(_, t)
             | SchedulerMessage::AddFrame(_, _, _, t)
             | SchedulerMessage::RemoveFrame(_, _, t)
             | SchedulerMessage::SetTempo(_, t)
@@ -67,6 +81,10 @@ impl SchedulerMessage {
             | SchedulerMessage::DeviceMessage(_, _, t) 
             | SchedulerMessage::GoToFrame(_, _, t) 
             | SchedulerMessage::SetScript(_, _, _, t)
+            | SchedulerMessage::SetVariable(_, _, _, t)
+            | SchedulerMessage::SetSeed(_, t)
+            | SchedulerMessage::SetModulator(_, _, t)
+            | SchedulerMessage::SetTuning(_, t)
                 => *t,
             SchedulerMessage::CompilationUpdate(_, _, _, _)
             | SchedulerMessage::Shutdown => ActionTiming::Immediate,
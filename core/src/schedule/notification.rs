@@ -3,11 +3,15 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::compiler::CompilationState;
-use crate::vm::variable::VariableValue;
-use crate::scene::{Scene, Line, Frame};
+use crate::vm::RuntimeFault;
+use crate::vm::variable::{VariableScope, VariableValue};
+use crate::scene::{Scene, Line, Frame, Modulator};
+use crate::theory::Tuning;
 use crate::protocol::DeviceInfo;
 use crate::LogMessage;
 use crate::schedule::playback::PlaybackState;
+use crate::schedule::EmittedEvent;
+use crate::util::text_operation::{TextOperation, TextSelection};
 
 /// Enum representing notifications broadcast by the Scheduler.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -39,6 +43,10 @@ pub enum SovaNotification {
     PlaybackStateChanged(PlaybackState),
     /// Current frame position for each playing line (line_idx, frame_idx, repetition_idx)
     FramePositionChanged(Vec<(usize, usize)>),
+    /// Events emitted by the lines since the last notification
+    EventsEmitted(Vec<EmittedEvent>),
+    /// Runtime faults raised by the scripts since the last notification
+    RuntimeFaults(Vec<RuntimeFault>),
     /// List of connected clients changed.
     ClientListChanged(Vec<String>),
     /// A chat message was received from a client.
@@ -47,8 +55,27 @@ pub enum SovaNotification {
     PeerStartedEditingFrame(String, usize, usize),
     /// A peer stopped editing a specific frame.
     PeerStoppedEditingFrame(String, usize, usize),
+    /// The frame locks changed (line, frame, holder).
+    FrameLocksChanged(Vec<(usize, usize, String)>),
+    /// A peer edited the draft of a frame script (author, line, frame, new revision, operation).
+    ScriptEdited(String, usize, usize, u64, TextOperation),
+    /// A peer moved their cursor in a frame script.
+    PeerCursorMoved(String, usize, usize, Option<TextSelection>),
+    /// Shared draft of a frame script sent by a server to the client that asked for it
+    /// (line, frame, revision, content).
+    ScriptDraftLoaded(usize, usize, u64, String),
+    /// A server applied the last edit of this client to a script draft (line, frame, new revision).
+    ScriptEditAcknowledged(usize, usize, u64),
     /// The list of available/connected devices changed.
     DeviceListChanged(Vec<DeviceInfo>),
     /// Global variables have been updated
     GlobalVariablesChanged(HashMap<String, VariableValue>),
+    /// Visible variables changed since the last update, in any scope (scope, name, new value or None if removed)
+    VariablesChanged(Vec<(VariableScope, String, Option<VariableValue>)>),
+    /// The root seed of the scene random streams changed
+    SeedChanged(Option<u64>),
+    /// A scene modulator has been defined, replaced or removed (None)
+    ModulatorChanged(String, Option<Modulator>),
+    /// The microtonal tuning of the scene changed
+    TuningChanged(Option<Tuning>),
 }
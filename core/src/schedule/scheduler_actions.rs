@@ -1,5 +1,5 @@
 use crate::{
-    vm::LanguageCenter,
+    vm::{LanguageCenter, variable::{VariableScope, VariableStore, VariableValue}},
     scene::{Frame, Scene},
     schedule::{message::SchedulerMessage, notification::SovaNotification},
 };
@@ -24,6 +24,7 @@ impl ActionProcessor {
                 for (i, line) in lines {
                     upd_index.insert(i);
                     scene.set_line(i, line);
+                    scene.reseed_line(i);
                     languages.process_line(i, scene.line(i).unwrap(), feedback.clone());
                 }
                 for new in previous_len..scene.n_lines() {
@@ -39,7 +40,11 @@ impl ActionProcessor {
                 let previous_len = scene.n_lines();
                 for (i, line) in lines.iter() {
                     upd_index.insert(*i);
+                    let previous_seed = scene.line_mut(*i).seed;
                     scene.line_mut(*i).configure(line);
+                    if previous_seed != line.seed {
+                        scene.reseed_line(*i);
+                    }
                 }
                 for new in previous_len..scene.n_lines() {
                     if upd_index.contains(&new) {
@@ -51,6 +56,7 @@ impl ActionProcessor {
             }
             SchedulerMessage::AddLine(i, line, _) => {
                 scene.insert_line(i, line.clone());
+                scene.reseed_line(i);
                 languages.process_line(i, scene.line(i).unwrap(), feedback.clone());
                 let _ = update_notifier.send(SovaNotification::AddedLine(i, line));
             }
@@ -68,6 +74,20 @@ impl ActionProcessor {
             SchedulerMessage::SetFrames(frames, _) => {
                 Self::set_frames(scene, frames, update_notifier, languages, feedback);
             }
+            SchedulerMessage::EnableFrames(frames, _) => {
+                let mut updated = Vec::new();
+                for (line_id, frame_id, enabled) in frames {
+                    if !scene.has_frame(line_id, frame_id) {
+                        continue;
+                    }
+                    let frame = scene.get_frame_mut(line_id, frame_id);
+                    frame.enabled = enabled;
+                    updated.push((line_id, frame_id, frame.clone()));
+                }
+                if !updated.is_empty() {
+                    let _ = update_notifier.send(SovaNotification::UpdatedFrames(updated));
+                }
+            }
             SchedulerMessage::AddFrame(line_id, frame_id, frame, _) => {
                 let updated = frame.clone();
                 let line = scene.line_mut(line_id);
@@ -108,6 +128,18 @@ impl ActionProcessor {
                     frame.clone(),
                 )]));
             }
+            SchedulerMessage::SetVariable(scope, name, value, _) => {
+                Self::set_variable(scene, scope, name, value, update_notifier);
+            }
+            SchedulerMessage::SetSeed(seed, _) => {
+                scene.seed = seed;
+                scene.reseed();
+                let _ = update_notifier.send(SovaNotification::SeedChanged(seed));
+            }
+            SchedulerMessage::SetModulator(name, modulator, _) => {
+                scene.set_modulator(name.clone(), modulator.clone());
+                let _ = update_notifier.send(SovaNotification::ModulatorChanged(name, modulator));
+            }
             SchedulerMessage::CompilationUpdate(line_id, frame_id, id, state) => {
                 if !scene.has_frame(line_id, frame_id) {
                     return;
@@ -131,11 +163,39 @@ impl ActionProcessor {
             | SchedulerMessage::SetTempo(_, _)
             | SchedulerMessage::SetQuantum(_, _)
             | SchedulerMessage::SetScene(_, _)
+            | SchedulerMessage::SetTuning(_, _)
             | SchedulerMessage::DeviceMessage(_, _, _)
             | SchedulerMessage::Shutdown => (),
         }
     }
 
+    fn set_variable(
+        scene: &mut Scene,
+        scope: VariableScope,
+        name: String,
+        value: VariableValue,
+        update_notifier: &Sender<SovaNotification>,
+    ) {
+        match scope {
+            VariableScope::Global => {
+                scene.vars.insert(name, value);
+                let _ = update_notifier.send(SovaNotification::GlobalVariablesChanged(
+                    scene.vars.one_letter_vars().collect::<VariableStore>().into(),
+                ));
+            }
+            VariableScope::Line(line_id) => {
+                if line_id < scene.n_lines() {
+                    scene.line_mut(line_id).vars.insert(name, value);
+                }
+            }
+            VariableScope::Frame(line_id, frame_id) => {
+                if scene.has_frame(line_id, frame_id) {
+                    scene.get_frame_mut(line_id, frame_id).vars.insert(name, value);
+                }
+            }
+        }
+    }
+
     fn set_frames(
         scene: &mut Scene,
         frames: Vec<(usize, usize, Frame)>,
@@ -11,7 +11,10 @@ use std::{
 use tokio::time::Duration;
 use tokio::{
     io::{self, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
-    net::{TcpListener, TcpStream},
+    net::{
+        TcpListener, TcpStream, UdpSocket,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
     select, signal,
     sync::{Mutex, broadcast},
 };
@@ -19,15 +22,33 @@ use tokio::{
 use crate::{
     clock::{Clock, ClockServer, SyncTime},
     device_map::DeviceMap,
+    scene::script::Script,
     schedule::{SchedulerMessage, SovaNotification},
     {log_eprintln, log_println},
 };
 
+pub mod auth;
+use auth::{AuthConfig, Role, Secret};
+
 pub mod client;
+pub mod collab;
+use collab::{EditOutcome, ScriptDrafts};
+
+pub mod locks;
+use locks::FrameLocks;
+
+pub mod federation;
+use federation::MirrorConfig;
 
 mod message;
 pub use message::ServerMessage;
 
+mod subscription;
+pub use subscription::VariableSubscriptions;
+
+mod osc_control;
+mod websocket;
+
 /// Byte delimiter used to separate JSON messages in the TCP stream.
 pub const ENDING_BYTE: u8 = 0x07;
 /// Default name assigned to clients before they identify themselves.
@@ -58,6 +79,12 @@ pub struct ServerState {
     /// Handles compilers and interpreters
     pub languages: Arc<LanguageCenter>,
     pub is_playing: Arc<AtomicBool>,
+    /// Authentication settings, open (everyone is admin) by default
+    pub auth: Arc<AuthConfig>,
+    /// Shared drafts of the scripts edited collaboratively
+    pub drafts: Arc<Mutex<ScriptDrafts>>,
+    /// Frames locked by clients for exclusive edition
+    pub locks: Arc<Mutex<FrameLocks>>,
 }
 
 impl ServerState {
@@ -87,10 +114,19 @@ impl ServerState {
             clients: Arc::new(Mutex::new(Vec::new())),
             scene_image,
             languages,
-            is_playing: Arc::new(AtomicBool::new(false))
+            is_playing: Arc::new(AtomicBool::new(false)),
+            auth: Arc::new(AuthConfig::default()),
+            drafts: Arc::new(Mutex::new(ScriptDrafts::default())),
+            locks: Arc::new(Mutex::new(FrameLocks::default())),
         }
     }
 
+    /// Requires clients to authenticate with the given settings.
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = Arc::new(auth);
+        self
+    }
+
 }
 
 /// Represents the main Sova TCP server application.
@@ -102,6 +138,12 @@ pub struct SovaCoreServer {
     pub ip: String,
     /// The TCP port number the server will listen on (e.g., 8080).
     pub port: u16,
+    /// Port of the WebSocket listener (JSON or MessagePack), disabled if None.
+    pub websocket_port: Option<u16>,
+    /// UDP port of the OSC remote control, disabled if None.
+    pub osc_control_port: Option<u16>,
+    /// Lines of remote servers mirrored into the scene.
+    pub mirrors: Vec<MirrorConfig>,
     pub state: ServerState,
 }
 
@@ -123,6 +165,23 @@ pub struct Snapshot {
     pub devices: Option<Vec<crate::protocol::DeviceInfo>>,
 }
 
+/// Script of a frame in the scene image.
+async fn scene_script(state: &ServerState, line_id: usize, frame_id: usize) -> Option<Script> {
+    let scene = state.scene_image.lock().await;
+    scene
+        .line(line_id)
+        .and_then(|line| line.frame(frame_id))
+        .map(|frame| frame.script().clone())
+}
+
+/// Broadcasts the current holders of the frame locks.
+async fn broadcast_frame_locks(state: &ServerState) {
+    let holders = state.locks.lock().await.holders();
+    let _ = state
+        .update_sender
+        .send(SovaNotification::FrameLocksChanged(holders));
+}
+
 /// Processes a received `ClientMessage` and returns a direct `ServerMessage` response.
 ///
 /// This function handles the logic for each type of message a client can send.
@@ -139,6 +198,8 @@ pub struct Snapshot {
 /// * `state` - A reference to the shared `ServerState`.
 /// * `client_name` - A mutable reference to the name associated with this client connection.
 ///   This will be updated if the client sends `SetName`.
+/// * `subscriptions` - The variable subscriptions of this client connection.
+/// * `role` - The role of the client, checked against the role each message requires.
 ///
 /// # Returns
 /// The `ServerMessage` to be sent back directly to the requesting client.
@@ -146,10 +207,38 @@ async fn on_message(
     msg: ClientMessage,
     state: &ServerState,
     client_name: &mut String,
+    subscriptions: &mut VariableSubscriptions,
+    role: Role,
 ) -> ServerMessage {
     // Log the incoming request
     log_println!("[➡️ ] Client '{}' sent: {:?}", client_name, msg);
 
+    let required_role = msg.required_role();
+    if role < required_role {
+        log_eprintln!(
+            "[!] Client '{}' ({}) is not allowed to send {:?}",
+            client_name,
+            role,
+            msg
+        );
+        return ServerMessage::PermissionDenied(format!(
+            "This action requires the {} role.",
+            required_role
+        ));
+    }
+
+    let edited_parts = msg.edited_parts();
+    if !edited_parts.is_empty() {
+        let locks = state.locks.lock().await;
+        let allowed = edited_parts
+            .into_iter()
+            .try_for_each(|part| locks.check_part(part, client_name));
+        if let Err(e) = allowed {
+            log_eprintln!("[!] Client '{}' tried to edit a locked line or frame: {}", client_name, e);
+            return ServerMessage::InternalError(e);
+        }
+    }
+
     match msg {
         ClientMessage::Chat(chat_msg) => {
             // Broadcast user chat message
@@ -161,7 +250,19 @@ async fn on_message(
                 ));
             ServerMessage::Success
         }
+        ClientMessage::Login(_, _) => {
+            ServerMessage::InternalError("Already logged in.".to_string())
+        }
         ClientMessage::SetName(new_name) => {
+            // Names of users with a token can only be taken by logging in as them
+            let reserved = state.auth.is_reserved(&new_name)
+                || state.locks.lock().await.owns_lines(&new_name);
+            if new_name != *client_name && reserved {
+                return ServerMessage::PermissionDenied(format!(
+                    "The name '{}' is reserved.",
+                    new_name
+                ));
+            }
             // Update client name in shared list and broadcast the change
             let mut clients_guard = state.clients.lock().await;
             let old_name = client_name.clone();
@@ -186,6 +287,10 @@ async fn on_message(
                 );
                 clients_guard.push(new_name.clone());
             }
+            if state.locks.lock().await.rename(&old_name, &new_name) {
+                broadcast_frame_locks(state).await;
+            }
+            state.drafts.lock().await.rename(&old_name, &new_name);
             *client_name = new_name; // Update local name for this connection task
 
             let updated_clients = clients_guard.clone();
@@ -280,6 +385,7 @@ async fn on_message(
             ServerMessage::Snapshot(snapshot)
         }
         ClientMessage::StartedEditingFrame(line_idx, frame_idx) => {
+            state.drafts.lock().await.join(line_idx, frame_idx, client_name);
             // Broadcast notification that this client started editing
             let _ = state
                 .update_sender
@@ -291,6 +397,7 @@ async fn on_message(
             ServerMessage::Success // Acknowledge receipt
         }
         ClientMessage::StoppedEditingFrame(line_idx, frame_idx) => {
+            state.drafts.lock().await.leave(line_idx, frame_idx, client_name);
             // Broadcast notification that this client stopped editing
             let _ = state
                 .update_sender
@@ -301,6 +408,115 @@ async fn on_message(
                 ));
             ServerMessage::Success // Acknowledge receipt
         }
+        ClientMessage::GetScriptDraft(line_id, frame_id) => {
+            let content = scene_script(state, line_id, frame_id).await.map(|s| s.content().to_owned());
+            let mut drafts = state.drafts.lock().await;
+            drafts.join(line_id, frame_id, client_name);
+            match drafts.document(line_id, frame_id, content.as_deref()) {
+                Ok((revision, content)) => {
+                    ServerMessage::ScriptDraft(line_id, frame_id, revision, content)
+                }
+                Err(e) => ServerMessage::InternalError(e),
+            }
+        }
+        ClientMessage::EditScript(line_id, frame_id, revision, operation) => {
+            let content = scene_script(state, line_id, frame_id).await.map(|s| s.content().to_owned());
+            // The drafts stay locked while broadcasting, so that edits are sent in order
+            let mut drafts = state.drafts.lock().await;
+            drafts.join(line_id, frame_id, client_name);
+            match drafts.edit(line_id, frame_id, revision, operation, content.as_deref()) {
+                Ok(EditOutcome::Applied(revision, operation)) => {
+                    let _ = state.update_sender.send(SovaNotification::ScriptEdited(
+                        client_name.clone(),
+                        line_id,
+                        frame_id,
+                        revision,
+                        operation,
+                    ));
+                    ServerMessage::ScriptEditAck(line_id, frame_id, revision)
+                }
+                Ok(EditOutcome::OutOfSync(revision, content)) => {
+                    ServerMessage::ScriptDraft(line_id, frame_id, revision, content)
+                }
+                Err(e) => ServerMessage::InternalError(e),
+            }
+        }
+        ClientMessage::CommitScript(line_id, frame_id, timing) => {
+            let Some(mut script) = scene_script(state, line_id, frame_id).await else {
+                return ServerMessage::InternalError(format!(
+                    "No frame {frame_id} in line {line_id}"
+                ));
+            };
+            let Some(content) = state.drafts.lock().await.commit(line_id, frame_id) else {
+                return ServerMessage::InternalError("No draft for this frame.".to_string());
+            };
+            script.set_content(content);
+            if state
+                .sched_iface
+                .send(SchedulerMessage::SetScript(line_id, frame_id, script, timing))
+                .is_ok()
+            {
+                ServerMessage::Success
+            } else {
+                ServerMessage::InternalError("Failed to send script to scheduler.".to_string())
+            }
+        }
+        ClientMessage::FormatScript(line_id, frame_id, timing) => {
+            let Some(mut script) = scene_script(state, line_id, frame_id).await else {
+                return ServerMessage::InternalError(format!(
+                    "No frame {frame_id} in line {line_id}"
+                ));
+            };
+            let content = match state.languages.format_script(&script) {
+                Ok(content) => content,
+                Err(e) => return ServerMessage::InternalError(e),
+            };
+            if content == script.content() {
+                return ServerMessage::Success;
+            }
+            script.set_content(content);
+            if state
+                .sched_iface
+                .send(SchedulerMessage::SetScript(line_id, frame_id, script, timing))
+                .is_ok()
+            {
+                ServerMessage::Success
+            } else {
+                ServerMessage::InternalError("Failed to send script to scheduler.".to_string())
+            }
+        }
+        ClientMessage::LockFrame(line_id, frame_id) => {
+            let result = state.locks.lock().await.lock(line_id, frame_id, client_name);
+            match result {
+                Ok(()) => {
+                    broadcast_frame_locks(state).await;
+                    ServerMessage::Success
+                }
+                Err(e) => ServerMessage::InternalError(e),
+            }
+        }
+        ClientMessage::UnlockFrame(line_id, frame_id) => {
+            let result = state.locks.lock().await.unlock(line_id, frame_id, client_name);
+            match result {
+                Ok(()) => {
+                    broadcast_frame_locks(state).await;
+                    ServerMessage::Success
+                }
+                Err(e) => ServerMessage::InternalError(e),
+            }
+        }
+        ClientMessage::SetCursor(line_id, frame_id, selection) => {
+            if selection.is_some() {
+                state.drafts.lock().await.join(line_id, frame_id, client_name);
+            }
+            let _ = state.update_sender.send(SovaNotification::PeerCursorMoved(
+                client_name.clone(),
+                line_id,
+                frame_id,
+                selection,
+            ));
+            ServerMessage::Success
+        }
         ClientMessage::TransportStart(timing) => {
             if state
                 .sched_iface
@@ -540,6 +756,17 @@ async fn on_message(
             // Revert: No longer send immediate status based on atomic
             ServerMessage::Success
         },
+        ClientMessage::EnableFrames(frames, timing) => {
+            if state
+                .sched_iface
+                .send(SchedulerMessage::EnableFrames(frames, timing))
+                .is_err()
+            {
+                log_eprintln!("[!] Failed to send EnableFrames to scheduler.");
+                return ServerMessage::InternalError("Scheduler communication error.".to_string());
+            }
+            ServerMessage::Success
+        },
         ClientMessage::AddFrame(line_id, frame_id, frame, timing) => {
             if state
                 .sched_iface
@@ -552,6 +779,108 @@ async fn on_message(
             // Revert: No longer send immediate status based on atomic
             ServerMessage::Success
         },
+        ClientMessage::SetDeviceMpe(name, zone) => {
+            match state.devices.set_device_mpe(&name, zone) {
+                Ok(_) => {
+                    let updated_list = state.devices.device_list();
+                    let _ = state
+                        .update_sender
+                        .send(SovaNotification::DeviceListChanged(
+                            updated_list.clone(),
+                        ));
+                    ServerMessage::DeviceList(updated_list)
+                }
+                Err(e) => ServerMessage::InternalError(format!(
+                    "Failed to set MPE mode of '{}': {}",
+                    name, e
+                )),
+            }
+        }
+        ClientMessage::SetDeviceOverlap(name, policy) => {
+            match state.devices.set_device_overlap(&name, policy) {
+                Ok(_) => {
+                    let updated_list = state.devices.device_list();
+                    let _ = state
+                        .update_sender
+                        .send(SovaNotification::DeviceListChanged(
+                            updated_list.clone(),
+                        ));
+                    ServerMessage::DeviceList(updated_list)
+                }
+                Err(e) => ServerMessage::InternalError(format!(
+                    "Failed to set note overlap of '{}': {}",
+                    name, e
+                )),
+            }
+        }
+        ClientMessage::CreateSuperDirtDevice(name, ip, port) => {
+            match state.devices.create_superdirt_device(&name, &ip, port) {
+                Ok(_) => {
+                    let updated_list = state.devices.device_list();
+                    let _ = state
+                        .update_sender
+                        .send(SovaNotification::DeviceListChanged(
+                            updated_list.clone(),
+                        ));
+                    ServerMessage::DeviceList(updated_list)
+                }
+                Err(e) => ServerMessage::InternalError(format!(
+                    "Failed to create SuperDirt device '{}': {}",
+                    name, e
+                )),
+            }
+        }
+        ClientMessage::SetDirtProfile(device_name, profile) => {
+            match state.devices.set_device_dirt_profile(&device_name, profile) {
+                Ok(_) => {
+                    let updated_list = state.devices.device_list();
+                    let _ = state
+                        .update_sender
+                        .send(SovaNotification::DeviceListChanged(
+                            updated_list.clone(),
+                        ));
+                    ServerMessage::DeviceList(updated_list)
+                }
+                Err(e) => ServerMessage::InternalError(format!(
+                    "Failed to set SuperDirt profile of '{}': {}",
+                    device_name, e
+                )),
+            }
+        }
+        ClientMessage::SetDeviceTuningOutput(device_name, output) => {
+            match state.devices.set_device_tuning_output(&device_name, output) {
+                Ok(_) => {
+                    let updated_list = state.devices.device_list();
+                    let _ = state
+                        .update_sender
+                        .send(SovaNotification::DeviceListChanged(
+                            updated_list.clone(),
+                        ));
+                    ServerMessage::DeviceList(updated_list)
+                }
+                Err(e) => ServerMessage::InternalError(format!(
+                    "Failed to set tuning output of '{}': {}",
+                    device_name, e
+                )),
+            }
+        }
+        ClientMessage::SetSlotTransform(slot_id, transform) => {
+            match state.devices.set_slot_transform(slot_id, transform) {
+                Ok(_) => {
+                    let updated_list = state.devices.device_list();
+                    let _ = state
+                        .update_sender
+                        .send(SovaNotification::DeviceListChanged(
+                            updated_list.clone(),
+                        ));
+                    ServerMessage::DeviceList(updated_list)
+                }
+                Err(e) => ServerMessage::InternalError(format!(
+                    "Failed to set transform of slot {}: {}",
+                    slot_id, e
+                )),
+            }
+        }
         ClientMessage::RestoreDevices(devices) => {
             let missing_devices = state.devices.restore_from_snapshot(devices);
             // Broadcast updated device list after restoration
@@ -561,6 +890,61 @@ async fn on_message(
                 .send(SovaNotification::DeviceListChanged(updated_list));
             ServerMessage::DevicesRestored { missing_devices }
         },
+        ClientMessage::SubscribeVariables(patterns) => {
+            let mut new_patterns = VariableSubscriptions::default();
+            new_patterns.subscribe(patterns.clone());
+            subscriptions.subscribe(patterns);
+            let variables = state.scene_image.lock().await.visible_variables();
+            ServerMessage::VariablesUpdate(new_patterns.current_values(&variables))
+        },
+        ClientMessage::UnsubscribeVariables(patterns) => {
+            subscriptions.unsubscribe(&patterns);
+            ServerMessage::Success
+        },
+        ClientMessage::SetSeed(seed, timing) => {
+            if state
+                .sched_iface
+                .send(SchedulerMessage::SetSeed(seed, timing))
+                .is_err()
+            {
+                log_eprintln!("[!] Failed to send SetSeed to scheduler.");
+                return ServerMessage::InternalError("Scheduler communication error.".to_string());
+            }
+            ServerMessage::Success
+        },
+        ClientMessage::SetModulator(name, modulator, timing) => {
+            if state
+                .sched_iface
+                .send(SchedulerMessage::SetModulator(name, modulator, timing))
+                .is_err()
+            {
+                log_eprintln!("[!] Failed to send SetModulator to scheduler.");
+                return ServerMessage::InternalError("Scheduler communication error.".to_string());
+            }
+            ServerMessage::Success
+        },
+        ClientMessage::SetTuning(tuning, timing) => {
+            if state
+                .sched_iface
+                .send(SchedulerMessage::SetTuning(tuning, timing))
+                .is_err()
+            {
+                log_eprintln!("[!] Failed to send SetTuning to scheduler.");
+                return ServerMessage::InternalError("Scheduler communication error.".to_string());
+            }
+            ServerMessage::Success
+        },
+        ClientMessage::SetVariable(scope, name, value, timing) => {
+            if state
+                .sched_iface
+                .send(SchedulerMessage::SetVariable(scope, name, value, timing))
+                .is_err()
+            {
+                log_eprintln!("[!] Failed to send SetVariable to scheduler.");
+                return ServerMessage::InternalError("Scheduler communication error.".to_string());
+            }
+            ServerMessage::Success
+        },
     }
 }
 
@@ -637,14 +1021,39 @@ fn compress_message_intelligently(
 impl SovaCoreServer {
     /// Creates a new `SovaCoreServer` instance with the specified address and port.
     pub fn new(ip: String, port: u16, state: ServerState) -> Self {
-        SovaCoreServer { ip, port, state }
+        SovaCoreServer {
+            ip,
+            port,
+            websocket_port: None,
+            osc_control_port: None,
+            mirrors: Vec::new(),
+            state,
+        }
+    }
+
+    /// Also accepts WebSocket clients on the given port.
+    pub fn with_websocket(mut self, port: u16) -> Self {
+        self.websocket_port = Some(port);
+        self
+    }
+
+    /// Also accepts OSC control messages on the given UDP port.
+    pub fn with_osc_control(mut self, port: u16) -> Self {
+        self.osc_control_port = Some(port);
+        self
+    }
+
+    /// Also mirrors lines of a remote server, read-only for the local clients.
+    pub fn with_mirror(mut self, config: MirrorConfig) -> Self {
+        self.mirrors.push(config);
+        self
     }
 
     /// Starts the TCP server, listens for connections, and handles graceful shutdown.
     ///
     /// This function enters the main server loop, accepting new connections and
-    /// spawning `process_client` tasks. It also listens for a Ctrl+C signal
-    /// to initiate a shutdown.
+    /// spawning `process_client` tasks. If enabled, WebSocket clients are accepted
+    /// by a separate task. It also listens for a Ctrl+C signal to initiate a shutdown.
     ///
     /// # Arguments
     /// * `state` - The shared `ServerState` to be cloned for each client task.
@@ -652,6 +1061,38 @@ impl SovaCoreServer {
         let addr = format!("{}:{}", self.ip, self.port);
         let listener = TcpListener::bind(&addr).await?;
         log_println!("[+] Server listening on {}", addr);
+        let websocket_task = match self.websocket_port {
+            Some(port) => {
+                let ws_addr = format!("{}:{}", self.ip, port);
+                let ws_listener = TcpListener::bind(&ws_addr).await?;
+                log_println!("[+] WebSocket server listening on ws://{}", ws_addr);
+                Some(tokio::spawn(websocket::accept_websocket_clients(
+                    ws_listener,
+                    self.state.clone(),
+                )))
+            }
+            None => None,
+        };
+        let osc_control_task = match self.osc_control_port {
+            Some(port) => {
+                let osc_addr = format!("{}:{}", self.ip, port);
+                let socket = UdpSocket::bind(&osc_addr).await?;
+                log_println!("[+] OSC control listening on udp://{}", osc_addr);
+                Some(tokio::spawn(osc_control::serve_osc_control(
+                    socket,
+                    self.state.clone(),
+                )))
+            }
+            None => None,
+        };
+        let mut mirror_tasks = Vec::new();
+        for config in self.mirrors.iter() {
+            config.lock_lines(&mut *self.state.locks.lock().await);
+            mirror_tasks.push(tokio::spawn(federation::mirror_remote_lines(
+                config.clone(),
+                self.state.clone(),
+            )));
+        }
         self.start_image_maintainer(scheduler_notifications);
         loop {
             select! {
@@ -686,6 +1127,10 @@ impl SovaCoreServer {
             }
         }
 
+        let tasks = [websocket_task, osc_control_task].into_iter().flatten();
+        for task in tasks.chain(mirror_tasks) {
+            task.abort();
+        }
         Ok(())
     }
 
@@ -693,6 +1138,8 @@ impl SovaCoreServer {
         let scene_image = self.state.scene_image.clone();
         let update_sender = self.state.update_sender.clone();
         let is_playing = self.state.is_playing.clone();
+        let drafts = self.state.drafts.clone();
+        let locks = self.state.locks.clone();
         thread::spawn(move || {
             // Throttle FramePositionChanged broadcasts to ~30fps
             const POSITION_BROADCAST_INTERVAL: std::time::Duration = std::time::Duration::from_millis(33);
@@ -728,6 +1175,23 @@ impl SovaCoreServer {
                             SovaNotification::RemovedFrame(line_id, frame_id) => {
                                 guard.line_mut(*line_id).remove_frame(*frame_id);
                             }
+                            SovaNotification::VariablesChanged(changes) => {
+                                guard.apply_variable_changes(changes);
+                            }
+                            SovaNotification::SeedChanged(seed) => {
+                                guard.seed = *seed;
+                            }
+                            SovaNotification::ModulatorChanged(name, modulator) => {
+                                guard.set_modulator(name.clone(), modulator.clone());
+                            }
+                            SovaNotification::TuningChanged(tuning) => {
+                                guard.tuning = tuning.clone();
+                            }
+                            SovaNotification::RuntimeFaults(faults) => {
+                                for fault in faults {
+                                    log_eprintln!("[!] Runtime Error: {}", fault);
+                                }
+                            }
                             SovaNotification::PlaybackStateChanged(state) => {
                                 let playing = match state {
                                     PlaybackState::Stopped => false,
@@ -739,6 +1203,8 @@ impl SovaCoreServer {
                             _ => (),
                         };
                         drop(guard);
+                        Self::sync_drafts(&drafts, &p);
+                        let locks_moved = Self::sync_locks(&locks, &p);
 
                         // Throttle FramePositionChanged, pass all other notifications through
                         let should_broadcast = match &p {
@@ -757,6 +1223,10 @@ impl SovaCoreServer {
                         if should_broadcast {
                             let _ = update_sender.send(p);
                         }
+                        if locks_moved {
+                            let holders = locks.blocking_lock().holders();
+                            let _ = update_sender.send(SovaNotification::FrameLocksChanged(holders));
+                        }
                     }
                     Err(_) => break,
                 }
@@ -764,12 +1234,71 @@ impl SovaCoreServer {
         });
     }
 
+    /// Moves the locks of the frames moved in the scene. Returns whether frame locks changed.
+    fn sync_locks(locks: &Mutex<FrameLocks>, notification: &SovaNotification) -> bool {
+        match notification {
+            SovaNotification::UpdatedScene(scene) => locks.blocking_lock().retain_existing(scene),
+            SovaNotification::AddedLine(line_id, _) => locks.blocking_lock().insert_line(*line_id),
+            SovaNotification::RemovedLine(line_id) => locks.blocking_lock().remove_line(*line_id),
+            SovaNotification::AddedFrame(line_id, frame_id, _) => {
+                locks.blocking_lock().insert_frame(*line_id, *frame_id)
+            }
+            SovaNotification::RemovedFrame(line_id, frame_id) => {
+                locks.blocking_lock().remove_frame(*line_id, *frame_id)
+            }
+            _ => false,
+        }
+    }
+
+    /// Reloads the script drafts whose frames have been replaced or moved in the scene.
+    fn sync_drafts(drafts: &Mutex<ScriptDrafts>, notification: &SovaNotification) {
+        match notification {
+            SovaNotification::UpdatedFrames(frames) => {
+                let mut drafts = drafts.blocking_lock();
+                for (line_id, frame_id, frame) in frames {
+                    drafts.sync(*line_id, *frame_id, frame.script().content());
+                }
+            }
+            SovaNotification::UpdatedLines(lines) => {
+                let mut drafts = drafts.blocking_lock();
+                for (line_id, _) in lines {
+                    drafts.invalidate_line(*line_id);
+                }
+            }
+            SovaNotification::UpdatedScene(_)
+            | SovaNotification::AddedLine(_, _)
+            | SovaNotification::RemovedLine(_)
+            | SovaNotification::AddedFrame(_, _, _)
+            | SovaNotification::RemovedFrame(_, _) => drafts.blocking_lock().invalidate_all(),
+            _ => (),
+        }
+    }
 }
 
-/// Handles the lifecycle of a single client connection.
-///
-/// This function manages reading messages from the client, processing them via `on_message`,
-/// sending direct responses, listening for broadcast notifications, and handling disconnection.
+/// Receiving half of a client connection, whatever its transport.
+trait ClientReader {
+    /// Reads the next message. Returns Ok(None) if the connection was closed cleanly.
+    async fn read_message(&mut self, client_id_for_logging: &str) -> io::Result<Option<ClientMessage>>;
+}
+
+/// Sending half of a client connection, whatever its transport.
+trait ClientWriter {
+    async fn send_message(&mut self, msg: ServerMessage) -> io::Result<()>;
+}
+
+impl ClientReader for BufReader<OwnedReadHalf> {
+    async fn read_message(&mut self, client_id_for_logging: &str) -> io::Result<Option<ClientMessage>> {
+        read_message_internal(self, client_id_for_logging).await
+    }
+}
+
+impl ClientWriter for BufWriter<OwnedWriteHalf> {
+    async fn send_message(&mut self, msg: ServerMessage) -> io::Result<()> {
+        send_msg(self, msg).await
+    }
+}
+
+/// Handles the lifecycle of a single TCP client connection.
 ///
 /// # Arguments
 /// * `socket` - The `TcpStream` for the connected client.
@@ -780,19 +1309,43 @@ impl SovaCoreServer {
 async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<String> {
     socket.set_nodelay(true)?;
     let client_addr = socket.peer_addr()?;
-    let client_addr_str = client_addr.to_string(); // For logging before name is set
     let (reader, writer) = socket.into_split(); // Split into read/write halves
-    let mut reader = BufReader::with_capacity(32 * 1024, reader);
-    let mut writer = BufWriter::with_capacity(32 * 1024, writer);
+    let reader = BufReader::with_capacity(32 * 1024, reader);
+    let writer = BufWriter::with_capacity(32 * 1024, writer);
+    serve_client(reader, writer, client_addr.to_string(), state).await
+}
+
+/// Serves a connected client, whatever its transport.
+///
+/// This function manages the handshake, reading messages from the client, processing them
+/// via `on_message`, sending direct responses, listening for broadcast notifications,
+/// and handling disconnection.
+///
+/// # Returns
+/// An `io::Result` containing the final name of the client upon disconnection, or an `io::Error`.
+async fn serve_client(
+    mut reader: impl ClientReader,
+    mut writer: impl ClientWriter,
+    client_addr_str: String,
+    state: ServerState,
+) -> io::Result<String> {
     let mut client_name = DEFAULT_CLIENT_NAME.to_string(); // Start with default name
+    let mut subscriptions = VariableSubscriptions::default();
 
     let mut clock = Clock::from(&state.clock_server);
 
-    // --- Handshake: Expect SetName first ---
+    // --- Handshake: Expect SetName (or Login, with credentials) first ---
     let hello_msg: ServerMessage; // Declare hello_msg variable
+    let role: Role;
+
+    // A SetName handshake is a login without credentials
+    let first_msg = match reader.read_message(&client_addr_str).await {
+        Ok(Some(ClientMessage::SetName(new_name))) => Ok(Some(ClientMessage::Login(new_name, None))),
+        other => other,
+    };
 
-    match read_message_internal(&mut reader, &client_addr_str).await {
-        Ok(Some(ClientMessage::SetName(new_name))) => {
+    match first_msg {
+        Ok(Some(ClientMessage::Login(new_name, token))) => {
             // Validate name (e.g., non-empty, allowed characters, uniqueness)
             if new_name.is_empty() || new_name == DEFAULT_CLIENT_NAME {
                 log_eprintln!(
@@ -803,16 +1356,35 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                 let refuse_msg = ServerMessage::ConnectionRefused(
                     "Invalid username (empty or reserved).".to_string(),
                 );
-                let _ = send_msg(&mut writer, refuse_msg).await; // Attempt to notify client
+                let _ = writer.send_message(refuse_msg).await; // Attempt to notify client
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
                     "Invalid username",
                 ));
             }
 
+            // Check the credentials
+            role = match state.auth.authenticate(&new_name, token.as_ref().map(Secret::expose)) {
+                Ok(role) => role,
+                Err(reason) => {
+                    log_eprintln!(
+                        "[!] Connection rejected: Authentication of '{}' failed from {}",
+                        new_name,
+                        client_addr_str
+                    );
+                    let _ = writer.send_message(ServerMessage::ConnectionRefused(reason)).await;
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "Authentication failed",
+                    ));
+                }
+            };
+
             // Check for uniqueness
             let mut clients_guard = state.clients.lock().await;
-            if clients_guard.iter().any(|name| name == &new_name) {
+            let taken = clients_guard.iter().any(|name| name == &new_name)
+                || state.locks.lock().await.owns_lines(&new_name);
+            if taken {
                 log_eprintln!(
                     "[!] Connection rejected: Username '{}' already taken by {}",
                     new_name,
@@ -822,7 +1394,7 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                     "Username '{}' is already taken.",
                     new_name
                 ));
-                let _ = send_msg(&mut writer, refuse_msg).await; // Attempt to notify client
+                let _ = writer.send_message(refuse_msg).await; // Attempt to notify client
                 drop(clients_guard); // Release lock
                 return Err(io::Error::new(
                     io::ErrorKind::AlreadyExists,
@@ -833,9 +1405,10 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
             // Name is valid and unique, accept connection
             client_name = new_name; // Assign the validated name
             log_println!(
-                "[👤] Client {} identified as: {}",
+                "[👤] Client {} identified as: {} ({})",
                 client_addr_str,
-                client_name
+                client_name,
+                role
             );
             clients_guard.push(client_name.clone());
 
@@ -883,10 +1456,12 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                 link_state: initial_link_state,
                 is_playing: initial_is_playing,
                 available_languages,
+                role,
+                frame_locks: state.locks.lock().await.holders(),
             };
 
             // Send Hello
-            if send_msg(&mut writer, hello_msg).await.is_err() {
+            if writer.send_message(hello_msg).await.is_err() {
                 log_eprintln!("[!] Failed to send Hello to {}", client_name);
                 // Don't remove from list yet, cleanup will handle it
                 return Err(io::Error::new(
@@ -896,15 +1471,15 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
             }
         }
         Ok(Some(other_msg)) => {
-            // First message was not SetName
+            // First message was not SetName nor Login
             log_eprintln!(
-                "[!] Connection rejected: Expected SetName, received {:?} from {}",
+                "[!] Connection rejected: Expected SetName or Login, received {:?} from {}",
                 other_msg,
                 client_addr_str
             );
             let refuse_msg =
                 ServerMessage::ConnectionRefused("Invalid handshake sequence.".to_string());
-            let _ = send_msg(&mut writer, refuse_msg).await;
+            let _ = writer.send_message(refuse_msg).await;
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "Invalid handshake sequence",
@@ -938,19 +1513,19 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
             biased;
 
             // Branch for reading subsequent client data
-            read_result = read_message_internal(&mut reader, &client_name) => {
+            read_result = reader.read_message(&client_name) => {
                 match read_result {
                     Ok(Some(msg)) => {
                         // Handle SetName again? Or disallow after handshake?
                         // For now, let's allow name changes via the main handler.
-                        let response = on_message(msg, &state, &mut client_name).await;
+                        let response = on_message(msg, &state, &mut client_name, &mut subscriptions, role).await;
 
                         // Avoid sending Success for SetName handled during handshake?
                         // The `on_message` for SetName already handles broadcasting.
                         // Let's check if the response is just a placeholder Success from SetName
                         // If we modify on_message SetName to return something else (like NoResponse),
                         // we could skip sending here. For now, we send Success.
-                        if send_msg(&mut writer, response).await.is_err() {
+                        if writer.send_message(response).await.is_err() {
                             log_eprintln!("[!] Failed write direct response to {}", client_name);
                             break; // Assume connection broken
                         }
@@ -961,7 +1536,7 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                         break;
                     },
                     Err(_e) => {
-                        // Read error occurred and was logged by read_message_internal
+                        // Read error occurred and was logged by the reader
                         log_eprintln!("[!] Read error for client {}. Closing connection.", client_name);
                         break; // Break the loop on error
                     }
@@ -1011,6 +1586,12 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                     SovaNotification::FramePositionChanged(pos) => {
                         Some(ServerMessage::FramePosition(pos))
                     }
+                    SovaNotification::EventsEmitted(events) => {
+                        Some(ServerMessage::EmittedEvents(events))
+                    }
+                    SovaNotification::RuntimeFaults(faults) => {
+                        Some(ServerMessage::RuntimeFaults(faults))
+                    }
                     SovaNotification::Log(log_message) => {
                         Some(ServerMessage::Log(log_message))
                     }
@@ -1048,6 +1629,27 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                             None
                         }
                     }
+                    SovaNotification::FrameLocksChanged(locks) => {
+                        Some(ServerMessage::FrameLocks(locks))
+                    }
+                    SovaNotification::ScriptEdited(author, line_idx, frame_idx, revision, operation) => {
+                        // The author gets a ScriptEditAck instead
+                        if author != *client_name {
+                            Some(ServerMessage::ScriptEdited(author, line_idx, frame_idx, revision, operation))
+                        } else {
+                            None
+                        }
+                    }
+                    SovaNotification::PeerCursorMoved(sender_name, line_idx, frame_idx, selection) => {
+                        if sender_name != *client_name {
+                            Some(ServerMessage::PeerCursor(sender_name, line_idx, frame_idx, selection))
+                        } else {
+                            None
+                        }
+                    }
+                    // Answers to a single client, sent directly
+                    SovaNotification::ScriptDraftLoaded(_, _, _, _)
+                    | SovaNotification::ScriptEditAcknowledged(_, _, _) => None,
                     // Add handler for DeviceListChanged
                     SovaNotification::DeviceListChanged(devices) => {
                         log_println!("[ broadcast ] Sending updated device list ({} devices) to {}", devices.len(), client_name);
@@ -1056,6 +1658,23 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                     SovaNotification::GlobalVariablesChanged(vars) => {
                         Some(ServerMessage::GlobalVariablesUpdate(vars))
                     }
+                    SovaNotification::VariablesChanged(changes) => {
+                        let changes = subscriptions.filter(changes);
+                        if changes.is_empty() {
+                            None
+                        } else {
+                            Some(ServerMessage::VariablesUpdate(changes))
+                        }
+                    }
+                    SovaNotification::SeedChanged(seed) => {
+                        Some(ServerMessage::SeedChanged(seed))
+                    }
+                    SovaNotification::ModulatorChanged(name, modulator) => {
+                        Some(ServerMessage::ModulatorChanged(name, modulator))
+                    }
+                    SovaNotification::TuningChanged(tuning) => {
+                        Some(ServerMessage::TuningChanged(tuning))
+                    }
                     SovaNotification::CompilationUpdated(line_id, frame_id, script_id, state) => {
                         Some(ServerMessage::CompilationUpdate(line_id, frame_id, script_id, state))
                     }
@@ -1066,7 +1685,7 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
                 };
 
                 if let Some(broadcast_msg) = broadcast_msg_opt {
-                    let send_res = send_msg(&mut writer, broadcast_msg).await;
+                    let send_res = writer.send_message(broadcast_msg).await;
                     if send_res.is_err() {
                         break;
                     }
@@ -1077,6 +1696,24 @@ async fn process_client(socket: TcpStream, state: ServerState) -> io::Result<Str
 
     // --- Cleanup after loop breaks ---
     log_println!("[🔌] Cleaning up connection for client: {}", client_name);
+    if state.locks.lock().await.release_all(&client_name) {
+        log_println!("[🔒] Released the frame locks of {}.", client_name);
+        broadcast_frame_locks(&state).await;
+    }
+    // Peers drop the cursors of the client
+    for (line_id, frame_id) in state.drafts.lock().await.leave_all(&client_name) {
+        let _ = state.update_sender.send(SovaNotification::PeerCursorMoved(
+            client_name.clone(),
+            line_id,
+            frame_id,
+            None,
+        ));
+        let _ = state.update_sender.send(SovaNotification::PeerStoppedEditingFrame(
+            client_name.clone(),
+            line_id,
+            frame_id,
+        ));
+    }
     // Only remove the client if they successfully completed the handshake (i.e., name is not default)
     if client_name != DEFAULT_CLIENT_NAME {
         let mut clients_guard = state.clients.lock().await;
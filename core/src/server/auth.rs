@@ -0,0 +1,236 @@
+//! Authentication of the clients and access roles.
+//!
+//! A server is open by default: every client is an admin. Once a shared secret or user
+//! tokens are configured, clients authenticate during the handshake (`ClientMessage::Login`)
+//! and get a role, which limits the messages they may send.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Access role of a client, from the least to the most privileged.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Follows the performance: reads the scene, the clock and the variables, and chats
+    #[serde(alias = "audience")]
+    Viewer,
+    /// Edits the scene and controls the transport
+    Performer,
+    /// Also manages the devices of the server
+    #[default]
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Viewer => write!(f, "viewer"),
+            Role::Performer => write!(f, "performer"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "viewer" | "audience" => Ok(Role::Viewer),
+            "performer" => Ok(Role::Performer),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("Unknown role '{s}' (viewer, performer or admin)")),
+        }
+    }
+}
+
+/// A user token or shared secret sent by a client, hidden from the logs.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+/// Credentials of a named user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserCredentials {
+    pub token: String,
+    pub role: Role,
+}
+
+fn default_secret_role() -> Role {
+    Role::Performer
+}
+
+/// Authentication settings of a server, usually loaded from a JSON file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Shared secret, giving `secret_role` to whoever knows it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(default = "default_secret_role")]
+    pub secret_role: Role,
+    /// Personal tokens, by user name. These names are reserved to their owner.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub users: BTreeMap<String, UserCredentials>,
+    /// Role of the clients without credentials, None to refuse them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anonymous: Option<Role>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            secret: None,
+            secret_role: default_secret_role(),
+            users: BTreeMap::new(),
+            anonymous: None,
+        }
+    }
+}
+
+/// Compares secrets in a time independent of the position of the first difference.
+fn secrets_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+impl AuthConfig {
+    /// A server without secret nor tokens accepts everyone as admin.
+    pub fn is_open(&self) -> bool {
+        self.secret.is_none() && self.users.is_empty()
+    }
+
+    /// Whether a name can only be used by authenticating with its user token.
+    pub fn is_reserved(&self, name: &str) -> bool {
+        self.users.contains_key(name)
+    }
+
+    /// Role of a client connecting with the given name and credentials.
+    pub fn authenticate(&self, name: &str, token: Option<&str>) -> Result<Role, String> {
+        if self.is_open() {
+            return Ok(Role::Admin);
+        }
+        if let Some(user) = self.users.get(name) {
+            return match token {
+                Some(token) if secrets_match(token, &user.token) => Ok(user.role),
+                _ => Err(format!("Invalid credentials for '{name}'.")),
+            };
+        }
+        match (token, &self.secret) {
+            (Some(token), Some(secret)) if secrets_match(token, secret) => Ok(self.secret_role),
+            (Some(_), _) => Err("Invalid credentials.".to_owned()),
+            (None, _) => self
+                .anonymous
+                .ok_or_else(|| "Authentication required.".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_from_credentials() {
+        let auth = AuthConfig {
+            secret: Some("venue".to_owned()),
+            users: BTreeMap::from([(
+                "alice".to_owned(),
+                UserCredentials {
+                    token: "a1".to_owned(),
+                    role: Role::Admin,
+                },
+            )]),
+            anonymous: Some(Role::Viewer),
+            ..Default::default()
+        };
+        assert_eq!(auth.authenticate("alice", Some("a1")), Ok(Role::Admin));
+        assert!(auth.authenticate("alice", Some("venue")).is_err());
+        assert!(auth.authenticate("alice", None).is_err());
+        assert_eq!(auth.authenticate("bob", Some("venue")), Ok(Role::Performer));
+        assert!(auth.authenticate("bob", Some("guess")).is_err());
+        assert_eq!(auth.authenticate("bob", None), Ok(Role::Viewer));
+        assert_eq!(AuthConfig::default().authenticate("bob", None), Ok(Role::Admin));
+        assert!(Role::Viewer < Role::Performer && Role::Performer < Role::Admin);
+    }
+
+    #[test]
+    fn tokens_are_rejected_without_matching_credentials() {
+        let user = |token: &str, role| UserCredentials { token: token.to_owned(), role };
+        let auth = AuthConfig {
+            users: BTreeMap::from([
+                ("alice".to_owned(), user("a1", Role::Viewer)),
+                ("carol".to_owned(), user("c1", Role::Performer)),
+            ]),
+            ..Default::default()
+        };
+        assert!(!auth.is_open());
+        assert!(auth.is_reserved("alice") && !auth.is_reserved("bob"));
+        assert_eq!(auth.authenticate("alice", Some("a1")), Ok(Role::Viewer));
+        assert_eq!(auth.authenticate("carol", Some("c1")), Ok(Role::Performer));
+        // Tokens belong to their user, and neither prefixes nor empty tokens match
+        assert!(auth.authenticate("alice", Some("c1")).is_err());
+        assert!(auth.authenticate("alice", Some("a")).is_err());
+        assert!(auth.authenticate("alice", Some("a12")).is_err());
+        assert!(auth.authenticate("alice", Some("")).is_err());
+        // Without shared secret, other names need the anonymous role
+        assert!(auth.authenticate("bob", Some("a1")).is_err());
+        assert_eq!(auth.authenticate("bob", None), Err("Authentication required.".to_owned()));
+        let secret_only = AuthConfig {
+            secret: Some("venue".to_owned()),
+            secret_role: Role::Admin,
+            ..Default::default()
+        };
+        assert_eq!(secret_only.authenticate("bob", Some("venue")), Ok(Role::Admin));
+        assert!(secret_only.authenticate("bob", Some("Venue")).is_err());
+        assert!(secret_only.authenticate("bob", None).is_err());
+    }
+
+    #[test]
+    fn messages_require_a_role() {
+        use crate::schedule::{ActionTiming, SchedulerMessage};
+        use crate::server::client::ClientMessage;
+        let viewer = ClientMessage::GetScene;
+        let performer = ClientMessage::SetTempo(120.0, ActionTiming::Immediate);
+        let admin = ClientMessage::SchedulerControl(SchedulerMessage::Shutdown);
+        assert_eq!(viewer.required_role(), Role::Viewer);
+        assert_eq!(ClientMessage::Chat("hi".to_owned()).required_role(), Role::Viewer);
+        assert_eq!(performer.required_role(), Role::Performer);
+        assert_eq!(admin.required_role(), Role::Admin);
+        assert_eq!(ClientMessage::RemoveOscDevice("osc".to_owned()).required_role(), Role::Admin);
+        // Roles are ordered, a role may send the messages of the less privileged ones
+        assert!(Role::Performer >= viewer.required_role());
+        assert!(Role::Performer < admin.required_role());
+        assert!(Role::Viewer < performer.required_role());
+    }
+
+    #[test]
+    fn secrets_are_hidden_from_logs() {
+        let secret = Secret::from("hunter2".to_owned());
+        assert_eq!(secret.expose(), "hunter2");
+        assert!(!format!("{:?}", Some(secret)).contains("hunter2"));
+    }
+}
@@ -3,9 +3,17 @@
 use super::ServerMessage;
 use crate::log_eprintln;
 use crate::protocol::DeviceInfo;
-use crate::scene::{Frame, Line, Scene};
+use super::auth::{Role, Secret};
+use super::locks::ScenePart;
+use crate::util::text_operation::{TextOperation, TextSelection};
+use crate::protocol::midi::{MpeZone, NoteOverlap};
+use crate::protocol::superdirt::DirtProfile;
+use crate::protocol::transform::SlotTransform;
+use crate::theory::{Tuning, TuningOutput};
+use crate::scene::{Frame, Line, Modulator, Scene};
 use crate::schedule::ActionTiming;
 use crate::schedule::SchedulerMessage;
+use crate::vm::variable::{VariableScope, VariableValue};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncReadExt;
 use tokio::{
@@ -33,6 +41,9 @@ pub enum ClientMessage {
     SetTempo(f64, ActionTiming),
     /// Request to set the client name.
     SetName(String),
+    /// Handshake of an authenticated client: name and token (user token or shared secret).
+    /// The server answers `Hello` with the role of the client, or `ConnectionRefused`.
+    Login(String, Option<Secret>),
 
     /// Request the current scene data.
     GetScene,
@@ -54,6 +65,8 @@ pub enum ClientMessage {
     GetFrame(usize, usize),
     /// Replace specified frames
     SetFrames(Vec<(usize, usize, Frame)>, ActionTiming),
+    /// Enable or disable frames (line, frame, enabled), leaving the rest of them untouched
+    EnableFrames(Vec<(usize, usize, bool)>, ActionTiming),
     /// Insert a frame a specified index
     AddFrame(usize, usize, Frame, ActionTiming),
     /// Remove a frame at specified index
@@ -71,6 +84,20 @@ pub enum ClientMessage {
     StartedEditingFrame(usize, usize), // (line_idx, frame_idx)
     /// Informs the server the client stopped editing a specific frame.
     StoppedEditingFrame(usize, usize), // (line_idx, frame_idx)
+    /// Request the shared draft of a frame script, answered with `ScriptDraft`.
+    GetScriptDraft(usize, usize), // (line_idx, frame_idx)
+    /// Edit the shared draft of a frame script, from the given draft revision.
+    EditScript(usize, usize, u64, TextOperation), // (line_idx, frame_idx, revision, operation)
+    /// Play the shared draft of a frame script.
+    CommitScript(usize, usize, ActionTiming), // (line_idx, frame_idx, timing)
+    /// Reindent the script of a frame following the brackets of its language.
+    FormatScript(usize, usize, ActionTiming), // (line_idx, frame_idx, timing)
+    /// Request exclusive edition of a frame: other clients cannot replace it until it is unlocked.
+    LockFrame(usize, usize), // (line_idx, frame_idx)
+    /// Release the lock of a frame.
+    UnlockFrame(usize, usize), // (line_idx, frame_idx)
+    /// Informs the other editors of the cursor of the client in a frame script (None to hide it).
+    SetCursor(usize, usize, Option<TextSelection>), // (line_idx, frame_idx, selection)
     /// Request the transport to start playback.
     TransportStart(ActionTiming),
     /// Request the transport to stop playback.
@@ -92,8 +119,34 @@ pub enum ClientMessage {
     CreateOscDevice(String, String, u16), // name, ip_address, port
     /// Request removal of an OSC output device by its name.
     RemoveOscDevice(String), // name
+    /// Request creation of a SuperDirt output (removed with `RemoveOscDevice`).
+    CreateSuperDirtDevice(String, String, u16), // name, ip_address, port
+    /// Set the orbits and default parameters of a SuperDirt output.
+    SetDirtProfile(String, DirtProfile), // name, profile
     /// Restore devices from a saved configuration.
     RestoreDevices(Vec<DeviceInfo>),
+    /// Enable (with the given zone) or disable (None) MPE mode on a MIDI output.
+    SetDeviceMpe(String, Option<MpeZone>), // name, zone
+    /// Set the overlapping notes policy of a MIDI output.
+    SetDeviceOverlap(String, NoteOverlap), // name, policy
+    /// Set (or remove, with None) the note transformations of a device slot (1-N).
+    SetSlotTransform(usize, Option<SlotTransform>), // Slot ID, transform
+    /// Subscribe to the variables whose qualified name (e.g. `line.0.count`) matches
+    /// one of the patterns. The server answers with their current values.
+    SubscribeVariables(Vec<String>),
+    /// Remove variable subscriptions (all of them if the list is empty).
+    UnsubscribeVariables(Vec<String>),
+    /// Set the value of a named variable in the given scope.
+    SetVariable(VariableScope, String, VariableValue, ActionTiming),
+    /// Set the root seed of the scene random streams and restart them (None: non reproducible).
+    SetSeed(Option<u64>, ActionTiming),
+    /// Define, replace or remove (None) a named scene modulator.
+    SetModulator(String, Option<Modulator>, ActionTiming),
+    /// Set (or remove, with None) the microtonal tuning of the scene.
+    /// Clients build it from Scala files with `Tuning::from_scala`.
+    SetTuning(Option<Tuning>, ActionTiming),
+    /// Choose how a device realizes the tuning of the scene (None for its default).
+    SetDeviceTuningOutput(String, Option<TuningOutput>), // name, output
 }
 
 impl ClientMessage {
@@ -103,6 +156,8 @@ impl ClientMessage {
             // Real-time/frequent messages that should never be compressed
             ClientMessage::StartedEditingFrame(_, _)
             | ClientMessage::StoppedEditingFrame(_, _)
+            | ClientMessage::EditScript(_, _, _, _)
+            | ClientMessage::SetCursor(_, _, _)
             | ClientMessage::GetClock
             | ClientMessage::GetPeers
             | ClientMessage::GetScene
@@ -119,6 +174,117 @@ impl ClientMessage {
         }
     }
 
+    /// Parts of the scene this message changes, which must not be locked by other clients.
+    /// Inserting lines or frames changes none: locks follow the frames they hold.
+    pub fn edited_parts(&self) -> Vec<ScenePart> {
+        match self {
+            ClientMessage::SetScene(_, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::SetScene(_, _)) => {
+                vec![ScenePart::Scene]
+            }
+            ClientMessage::SetLines(lines, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::SetLines(lines, _)) => lines
+                .iter()
+                .map(|(line_id, _)| ScenePart::LineContent(*line_id))
+                .collect(),
+            ClientMessage::ConfigureLines(lines, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::ConfigureLines(lines, _)) => lines
+                .iter()
+                .map(|(line_id, _)| ScenePart::Line(*line_id))
+                .collect(),
+            ClientMessage::RemoveLine(line_id, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::RemoveLine(line_id, _)) => {
+                vec![ScenePart::LineContent(*line_id)]
+            }
+            ClientMessage::AddFrame(line_id, _, _, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::AddFrame(line_id, _, _, _)) => {
+                vec![ScenePart::Line(*line_id)]
+            }
+            ClientMessage::RemoveFrame(line_id, frame_id, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::RemoveFrame(line_id, frame_id, _)) => {
+                vec![ScenePart::Line(*line_id), ScenePart::Frame(*line_id, *frame_id)]
+            }
+            ClientMessage::SetFrames(ids, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::SetFrames(ids, _)) => ids
+                .iter()
+                .map(|(line_id, frame_id, _)| ScenePart::Frame(*line_id, *frame_id))
+                .collect(),
+            ClientMessage::EnableFrames(ids, _)
+            | ClientMessage::SchedulerControl(SchedulerMessage::EnableFrames(ids, _)) => ids
+                .iter()
+                .map(|(line_id, frame_id, _)| ScenePart::Frame(*line_id, *frame_id))
+                .collect(),
+            ClientMessage::SchedulerControl(SchedulerMessage::SetScript(line_id, frame_id, _, _))
+            | ClientMessage::EditScript(line_id, frame_id, _, _)
+            | ClientMessage::CommitScript(line_id, frame_id, _)
+            | ClientMessage::FormatScript(line_id, frame_id, _) => {
+                vec![ScenePart::Frame(*line_id, *frame_id)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Least role allowed to send this message.
+    pub fn required_role(&self) -> Role {
+        match self {
+            ClientMessage::SetName(_)
+            | ClientMessage::Login(_, _)
+            | ClientMessage::GetScene
+            | ClientMessage::GetLine(_)
+            | ClientMessage::GetFrame(_, _)
+            | ClientMessage::GetClock
+            | ClientMessage::GetPeers
+            | ClientMessage::Chat(_)
+            | ClientMessage::GetSnapshot
+            | ClientMessage::GetScriptDraft(_, _)
+            | ClientMessage::RequestDeviceList
+            | ClientMessage::SubscribeVariables(_)
+            | ClientMessage::UnsubscribeVariables(_) => Role::Viewer,
+
+            ClientMessage::SchedulerControl(SchedulerMessage::Shutdown) => Role::Admin,
+            ClientMessage::SchedulerControl(_)
+            | ClientMessage::SetTempo(_, _)
+            | ClientMessage::SetScene(_, _)
+            | ClientMessage::SetLines(_, _)
+            | ClientMessage::ConfigureLines(_, _)
+            | ClientMessage::AddLine(_, _, _)
+            | ClientMessage::RemoveLine(_, _)
+            | ClientMessage::SetFrames(_, _)
+            | ClientMessage::EnableFrames(_, _)
+            | ClientMessage::AddFrame(_, _, _, _)
+            | ClientMessage::RemoveFrame(_, _, _)
+            | ClientMessage::StartedEditingFrame(_, _)
+            | ClientMessage::StoppedEditingFrame(_, _)
+            | ClientMessage::EditScript(_, _, _, _)
+            | ClientMessage::CommitScript(_, _, _)
+            | ClientMessage::FormatScript(_, _, _)
+            | ClientMessage::SetCursor(_, _, _)
+            | ClientMessage::LockFrame(_, _)
+            | ClientMessage::UnlockFrame(_, _)
+            | ClientMessage::TransportStart(_)
+            | ClientMessage::TransportStop(_)
+            | ClientMessage::SetSlotTransform(_, _)
+            | ClientMessage::SetVariable(_, _, _, _)
+            | ClientMessage::SetSeed(_, _)
+            | ClientMessage::SetModulator(_, _, _)
+            | ClientMessage::SetTuning(_, _) => Role::Performer,
+
+            ClientMessage::ConnectMidiDeviceByName(_)
+            | ClientMessage::DisconnectMidiDeviceByName(_)
+            | ClientMessage::CreateVirtualMidiOutput(_)
+            | ClientMessage::AssignDeviceToSlot(_, _)
+            | ClientMessage::UnassignDeviceFromSlot(_)
+            | ClientMessage::CreateOscDevice(_, _, _)
+            | ClientMessage::RemoveOscDevice(_)
+            | ClientMessage::CreateSuperDirtDevice(_, _, _)
+            | ClientMessage::SetDirtProfile(_, _)
+            | ClientMessage::RestoreDevices(_)
+            | ClientMessage::SetDeviceMpe(_, _)
+            | ClientMessage::SetDeviceOverlap(_, _)
+            | ClientMessage::SetDeviceTuningOutput(_, _) => Role::Admin,
+        }
+    }
+
     /// Deserializes a MessagePack buffer into a ClientMessage
     pub fn deserialize(final_bytes: &[u8]) -> io::Result<Option<Self>> {
         match rmp_serde::from_slice::<ClientMessage>(final_bytes) {
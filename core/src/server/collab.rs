@@ -0,0 +1,293 @@
+//! Collaborative editing of frame scripts, by operational transformation.
+//!
+//! The server keeps a shared draft of each script being edited, with a revision number.
+//! Clients send their edits as `TextOperation`s based on the last revision they know
+//! (`ClientMessage::EditScript`). The server transforms them against the edits applied
+//! since, applies them, acknowledges them to their author (`ScriptEditAck`) and broadcasts
+//! them to the other clients (`ScriptEdited`), which transform their own pending edits
+//! the same way. The draft is played once a client commits it (`CommitScript`).
+//!
+//! A draft lives as long as clients edit it: once the last one stops editing the frame
+//! or disconnects, uncommitted edits are dropped.
+
+use std::collections::{BTreeSet, HashMap, VecDeque, hash_map::Entry};
+
+use crate::util::text_operation::TextOperation;
+
+/// Number of past edits kept to transform late operations. Older clients must resync.
+const HISTORY_LEN: usize = 256;
+
+/// Shared draft of a script.
+#[derive(Debug, Clone)]
+struct ScriptDraft {
+    /// None once the frame has been replaced, until the draft is reloaded from the scene
+    content: Option<String>,
+    revision: u64,
+    /// Last edits, the last one producing `revision`
+    history: VecDeque<TextOperation>,
+    /// Last content sent to the scene
+    committed: Option<String>,
+}
+
+/// Result of an edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// The edit has been transformed into the given operation, producing the given revision
+    Applied(u64, TextOperation),
+    /// The revision of the edit is unknown: the client must restart from this revision and content
+    OutOfSync(u64, String),
+}
+
+/// Shared drafts of the scripts being edited, by line and frame.
+#[derive(Debug, Default)]
+pub struct ScriptDrafts {
+    drafts: HashMap<(usize, usize), ScriptDraft>,
+    /// Clients editing each frame
+    editors: HashMap<(usize, usize), BTreeSet<String>>,
+}
+
+impl ScriptDrafts {
+    /// The draft of a frame, loaded from the scene content if needed.
+    fn draft(
+        &mut self,
+        line_id: usize,
+        frame_id: usize,
+        scene_content: Option<&str>,
+    ) -> Result<&mut ScriptDraft, String> {
+        let load = || {
+            scene_content
+                .map(str::to_owned)
+                .ok_or_else(|| format!("No frame {frame_id} in line {line_id}"))
+        };
+        match self.drafts.entry((line_id, frame_id)) {
+            Entry::Occupied(entry) => {
+                let draft = entry.into_mut();
+                if draft.content.is_none() {
+                    // Edits based on the replaced content are refused
+                    draft.content = Some(load()?);
+                    draft.revision += 1;
+                    draft.history.clear();
+                    draft.committed = None;
+                }
+                Ok(draft)
+            }
+            Entry::Vacant(entry) => Ok(entry.insert(ScriptDraft {
+                content: Some(load()?),
+                revision: 0,
+                history: VecDeque::new(),
+                committed: None,
+            })),
+        }
+    }
+
+    /// Registers a client as an editor of a frame.
+    pub fn join(&mut self, line_id: usize, frame_id: usize, client: &str) {
+        let editors = self.editors.entry((line_id, frame_id)).or_default();
+        if !editors.contains(client) {
+            editors.insert(client.to_owned());
+        }
+    }
+
+    /// A client stopped editing a frame. The draft is dropped if nobody edits it anymore.
+    pub fn leave(&mut self, line_id: usize, frame_id: usize, client: &str) {
+        let key = (line_id, frame_id);
+        let Some(editors) = self.editors.get_mut(&key) else {
+            return;
+        };
+        editors.remove(client);
+        if editors.is_empty() {
+            self.editors.remove(&key);
+            self.drafts.remove(&key);
+        }
+    }
+
+    /// A client disconnected: it leaves every frame it edited, which are returned.
+    pub fn leave_all(&mut self, client: &str) -> Vec<(usize, usize)> {
+        let frames: Vec<(usize, usize)> = self
+            .editors
+            .iter()
+            .filter(|(_, editors)| editors.contains(client))
+            .map(|(frame, _)| *frame)
+            .collect();
+        for (line_id, frame_id) in &frames {
+            self.leave(*line_id, *frame_id, client);
+        }
+        frames
+    }
+
+    /// Transfers the frames edited by a client to its new name.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) {
+        for editors in self.editors.values_mut() {
+            if editors.remove(old_name) {
+                editors.insert(new_name.to_owned());
+            }
+        }
+    }
+
+    /// Current revision and content of the draft of a frame.
+    pub fn document(
+        &mut self,
+        line_id: usize,
+        frame_id: usize,
+        scene_content: Option<&str>,
+    ) -> Result<(u64, String), String> {
+        let draft = self.draft(line_id, frame_id, scene_content)?;
+        Ok((draft.revision, draft.content.clone().unwrap_or_default()))
+    }
+
+    /// Applies an edit based on the given revision of the draft of a frame.
+    pub fn edit(
+        &mut self,
+        line_id: usize,
+        frame_id: usize,
+        revision: u64,
+        op: TextOperation,
+        scene_content: Option<&str>,
+    ) -> Result<EditOutcome, String> {
+        let draft = self.draft(line_id, frame_id, scene_content)?;
+        let content = draft.content.get_or_insert_default();
+        let oldest = draft.revision - draft.history.len() as u64;
+        if revision < oldest || revision > draft.revision {
+            return Ok(EditOutcome::OutOfSync(draft.revision, content.clone()));
+        }
+        let mut op = op;
+        for past in draft.history.iter().skip((revision - oldest) as usize) {
+            op = TextOperation::transform(&op, past)?.0;
+        }
+        *content = op.apply(content)?;
+        draft.revision += 1;
+        draft.history.push_back(op.clone());
+        if draft.history.len() > HISTORY_LEN {
+            draft.history.pop_front();
+        }
+        Ok(EditOutcome::Applied(draft.revision, op))
+    }
+
+    /// Content of the draft of a frame, to play it. None if the frame has no draft.
+    pub fn commit(&mut self, line_id: usize, frame_id: usize) -> Option<String> {
+        let draft = self.drafts.get_mut(&(line_id, frame_id))?;
+        draft.committed = draft.content.clone();
+        draft.committed.clone()
+    }
+
+    /// The script of a frame changed in the scene: unless it comes from a commit,
+    /// the draft is reloaded from the scene at the next edit.
+    pub fn sync(&mut self, line_id: usize, frame_id: usize, scene_content: &str) {
+        if let Some(draft) = self.drafts.get_mut(&(line_id, frame_id))
+            && draft.content.as_deref() != Some(scene_content)
+            && draft.committed.as_deref() != Some(scene_content)
+        {
+            draft.content = None;
+        }
+    }
+
+    /// A line has been replaced: its drafts are reloaded from the scene.
+    pub fn invalidate_line(&mut self, line_id: usize) {
+        for ((line, _), draft) in self.drafts.iter_mut() {
+            if *line == line_id {
+                draft.content = None;
+            }
+        }
+    }
+
+    /// Frames have been moved or replaced: every draft is reloaded from the scene.
+    pub fn invalidate_all(&mut self) {
+        for draft in self.drafts.values_mut() {
+            draft.content = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_edits_are_applied_in_order() {
+        let text = "d1 $ s \"bd\"";
+        let a = TextOperation::new().insert("-- ").retain(11);
+        let b = TextOperation::new()
+            .retain(8)
+            .delete(2)
+            .insert("sn")
+            .retain(1);
+        let mut drafts = ScriptDrafts::default();
+        assert_eq!(
+            drafts.edit(0, 0, 0, a, Some(text)),
+            Ok(EditOutcome::Applied(
+                1,
+                TextOperation::new().insert("-- ").retain(11)
+            ))
+        );
+        // Based on revision 0, transformed against the first edit
+        assert!(matches!(
+            drafts.edit(0, 0, 0, b, Some(text)),
+            Ok(EditOutcome::Applied(2, _))
+        ));
+        let edited = "-- d1 $ s \"sn\"".to_owned();
+        assert_eq!(drafts.document(0, 0, None), Ok((2, edited.clone())));
+        assert!(matches!(
+            drafts.edit(0, 0, 5, TextOperation::new(), None),
+            Ok(EditOutcome::OutOfSync(2, _))
+        ));
+        // Committed contents played by the scene are kept, other changes reload the draft
+        assert_eq!(drafts.commit(0, 0), Some(edited.clone()));
+        drafts.sync(0, 0, &edited);
+        assert_eq!(drafts.document(0, 0, None), Ok((2, edited.clone())));
+        drafts.sync(0, 0, "other");
+        assert_eq!(
+            drafts.document(0, 0, Some("new")),
+            Ok((3, "new".to_owned()))
+        );
+        assert!(drafts.document(0, 1, None).is_err());
+    }
+
+    #[test]
+    fn late_edits_must_resync() {
+        let mut drafts = ScriptDrafts::default();
+        for i in 0..HISTORY_LEN + 1 {
+            let op = TextOperation::new().retain(i).insert("a");
+            assert!(matches!(
+                drafts.edit(0, 0, i as u64, op, Some("")),
+                Ok(EditOutcome::Applied(_, _))
+            ));
+        }
+        // The first edit has been forgotten
+        let content = "a".repeat(HISTORY_LEN + 1);
+        assert_eq!(
+            drafts.edit(0, 0, 0, TextOperation::new().insert("b"), None),
+            Ok(EditOutcome::OutOfSync(HISTORY_LEN as u64 + 1, content))
+        );
+        assert!(matches!(
+            drafts.edit(0, 0, 1, TextOperation::new().insert("b").retain(1), None),
+            Ok(EditOutcome::Applied(_, _))
+        ));
+        // An operation on another length is refused
+        let last = HISTORY_LEN as u64 + 2;
+        assert!(drafts.edit(0, 0, last, TextOperation::new().retain(1000), None).is_err());
+    }
+
+    #[test]
+    fn drafts_are_dropped_without_editors() {
+        let mut drafts = ScriptDrafts::default();
+        drafts.join(0, 0, "alice");
+        drafts.join(0, 0, "bob");
+        drafts.join(1, 2, "alice");
+        drafts.edit(0, 0, 0, TextOperation::new().insert("x"), Some("")).unwrap();
+        drafts.edit(1, 2, 0, TextOperation::new().insert("y"), Some("")).unwrap();
+        drafts.rename("alice", "carol");
+        let mut frames = drafts.leave_all("carol");
+        frames.sort();
+        assert_eq!(frames, vec![(0, 0), (1, 2)]);
+        assert!(drafts.leave_all("alice").is_empty());
+        // Bob still edits the first draft, nobody edits the second one
+        assert_eq!(drafts.document(0, 0, None), Ok((1, "x".to_owned())));
+        assert!(drafts.commit(1, 2).is_none());
+        drafts.leave(0, 0, "bob");
+        assert!(drafts.commit(0, 0).is_none());
+        assert_eq!(
+            drafts.document(0, 0, Some("scene")),
+            Ok((0, "scene".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,361 @@
+//! Federation of servers: mirroring lines of remote servers.
+//!
+//! A mirror connects to a remote server as an ordinary client, keeps a copy of its scene
+//! from the `Hello` and the broadcasts it receives, and copies the selected remote lines
+//! into local lines. Mirrored lines are read-only for the local clients, and are played
+//! by the local scheduler on the local devices. Two servers mirroring each other's lines
+//! let an ensemble see and hear everyone's code, each player keeping their own server.
+//!
+//! Lines are designated by their indices, like in the other line messages. The local lines
+//! receiving mirrored lines are then followed by their locks, which move with them when
+//! lines are inserted or removed before them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use tokio::{select, sync::broadcast, sync::mpsc, time::Duration};
+
+use super::{
+    ServerMessage, ServerState, auth::Secret, client::ClientMessage, client::SovaClient,
+    locks::FrameLocks,
+};
+use crate::{
+    log_eprintln, log_println,
+    scene::Scene,
+    schedule::{ActionTiming, SchedulerMessage, SovaNotification},
+};
+
+/// Delay before reconnecting to a remote server.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Lines of a remote server to mirror.
+///
+/// Parsed from `[NAME[:TOKEN]@]HOST:PORT/LINES`, where `LINES` lists remote lines, each
+/// optionally followed by the local line receiving it (`0,1` or `0=4,1=5`). A `TOKEN` of the
+/// form `$VAR` names the environment variable holding the token, see `resolve_token`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    /// Name of the mirror on the remote server
+    pub name: String,
+    /// Token to log in with on the remote server (user token or shared secret)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    pub ip: String,
+    pub port: u16,
+    /// Local line of each mirrored remote line
+    pub lines: BTreeMap<usize, usize>,
+}
+
+impl MirrorConfig {
+    /// Name holding the lock of the local line receiving a remote line.
+    pub fn owner(&self, remote_id: usize) -> String {
+        format!("mirror of line {} of {}:{}", remote_id, self.ip, self.port)
+    }
+
+    /// Locks the local lines receiving the mirrored lines.
+    pub fn lock_lines(&self, locks: &mut FrameLocks) {
+        for (remote_id, local_id) in &self.lines {
+            locks.lock_line(*local_id, &self.owner(*remote_id));
+        }
+    }
+
+    /// Replaces a token given as `$VAR` by the value of the variable `VAR`, looked up with
+    /// `var` (`std::env::var` outside of tests), so that it does not appear on the command line.
+    pub fn resolve_token(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let Some(name) = self.token.as_deref().and_then(|t| t.strip_prefix('$')) else {
+            return Ok(());
+        };
+        let token = var(name).filter(|token| !token.is_empty()).ok_or_else(|| {
+            format!("No token in the environment variable '{name}' for mirror '{}'", self.name)
+        })?;
+        self.token = Some(token);
+        Ok(())
+    }
+
+    /// Current local line of each mirrored remote line (remote, local).
+    fn local_lines(&self, locks: &FrameLocks) -> Vec<(usize, usize)> {
+        self.lines
+            .keys()
+            .filter_map(|remote_id| Some((*remote_id, locks.line_of(&self.owner(*remote_id))?)))
+            .collect()
+    }
+}
+
+impl FromStr for MirrorConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (credentials, target) = match s.rsplit_once('@') {
+            Some((credentials, target)) => (Some(credentials), target),
+            None => (None, s),
+        };
+        let (address, lines) = target
+            .split_once('/')
+            .ok_or_else(|| format!("Missing mirrored lines in '{s}' (HOST:PORT/LINES)"))?;
+        let (ip, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Invalid address '{address}' (HOST:PORT)"))?;
+        let port: u16 = port.parse().map_err(|_| format!("Invalid port '{port}'"))?;
+        let index = |x: &str| {
+            x.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid line index '{x}'"))
+        };
+        let lines = lines
+            .split(',')
+            .map(|pair| match pair.split_once('=') {
+                Some((remote, local)) => Ok((index(remote)?, index(local)?)),
+                None => index(pair).map(|line| (line, line)),
+            })
+            .collect::<Result<BTreeMap<_, _>, String>>()?;
+        let (name, token) = match credentials.map(|c| c.split_once(':').unwrap_or((c, ""))) {
+            Some((name, token)) => (
+                name.to_owned(),
+                Some(token.to_owned()).filter(|t| !t.is_empty()),
+            ),
+            None => (format!("mirror@{ip}:{port}"), None),
+        };
+        Ok(MirrorConfig {
+            name,
+            token,
+            ip: ip.to_owned(),
+            port,
+            lines,
+        })
+    }
+}
+
+/// Applies a message of the remote server to the copy of its scene. Returns the remote
+/// lines that changed, None if they all may have.
+fn apply_remote(scene: &mut Scene, msg: ServerMessage) -> Option<Vec<usize>> {
+    match msg {
+        ServerMessage::Hello { scene: remote, .. } | ServerMessage::SceneValue(remote) => {
+            *scene = remote;
+            None
+        }
+        ServerMessage::AddLine(line_id, line) => {
+            scene.insert_line(line_id, line);
+            None
+        }
+        ServerMessage::RemoveLine(line_id) => {
+            scene.remove_line(line_id);
+            None
+        }
+        ServerMessage::LineValues(lines) => Some(
+            lines
+                .into_iter()
+                .map(|(line_id, line)| {
+                    scene.set_line(line_id, line);
+                    line_id
+                })
+                .collect(),
+        ),
+        ServerMessage::LineConfigurations(lines) => Some(
+            lines
+                .into_iter()
+                .map(|(line_id, line)| {
+                    scene.line_mut(line_id).configure(&line);
+                    line_id
+                })
+                .collect(),
+        ),
+        ServerMessage::FrameValues(frames) => Some(
+            frames
+                .into_iter()
+                .map(|(line_id, frame_id, frame)| {
+                    scene.line_mut(line_id).set_frame(frame_id, frame);
+                    line_id
+                })
+                .collect(),
+        ),
+        ServerMessage::AddFrame(line_id, frame_id, frame) => {
+            scene.line_mut(line_id).insert_frame(frame_id, frame);
+            Some(vec![line_id])
+        }
+        ServerMessage::RemoveFrame(line_id, frame_id) => {
+            scene.line_mut(line_id).remove_frame(frame_id);
+            Some(vec![line_id])
+        }
+        _ => Some(Vec::new()),
+    }
+}
+
+/// Copies remote lines into their local lines (all the mirrored lines if None).
+async fn push_lines(
+    config: &MirrorConfig,
+    remote: &Scene,
+    changed: Option<Vec<usize>>,
+    state: &ServerState,
+) {
+    let local_lines = config.local_lines(&*state.locks.lock().await);
+    let lines: Vec<_> = local_lines
+        .into_iter()
+        .filter(|(remote_id, _)| {
+            changed
+                .as_ref()
+                .is_none_or(|changed| changed.contains(remote_id))
+        })
+        .filter_map(|(remote_id, local_id)| Some((local_id, remote.line(remote_id)?.clone())))
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+    if state
+        .sched_iface
+        .send(SchedulerMessage::SetLines(lines, ActionTiming::Immediate))
+        .is_err()
+    {
+        log_eprintln!("[!] Failed to send mirrored lines to scheduler.");
+    }
+}
+
+/// Connects to the remote server and forwards its messages, until the connection is lost.
+async fn read_remote(
+    config: &MirrorConfig,
+    sender: mpsc::Sender<ServerMessage>,
+) -> Result<(), String> {
+    let mut client = SovaClient::new(config.ip.clone(), config.port);
+    client.connect().await.map_err(|e| e.to_string())?;
+    client
+        .send(ClientMessage::Login(
+            config.name.clone(),
+            config.token.clone().map(Secret::from),
+        ))
+        .await
+        .map_err(|e| e.to_string())?;
+    loop {
+        match client.read().await.map_err(|e| e.to_string())? {
+            ServerMessage::ConnectionRefused(reason) => return Err(reason),
+            msg => {
+                if sender.send(msg).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Mirrors lines of a remote server, reconnecting when the connection is lost,
+/// until the task is aborted.
+pub(super) async fn mirror_remote_lines(config: MirrorConfig, state: ServerState) {
+    let address = format!("{}:{}", config.ip, config.port);
+    let mut updates = state.update_sender.subscribe();
+    loop {
+        let (sender, mut receiver) = mpsc::channel(256);
+        // Polled in place, so that reading stops when the task is aborted
+        let reading = read_remote(&config, sender);
+        tokio::pin!(reading);
+        let mut remote: Option<Scene> = None;
+        let result = loop {
+            select! {
+                result = &mut reading => break result,
+                Some(msg) = receiver.recv() => {
+                    if let ServerMessage::Hello { .. } = msg {
+                        log_println!("[🔗] Mirroring lines {:?} of {}", config.lines, address);
+                        remote = Some(Scene::default());
+                    }
+                    if let Some(remote) = remote.as_mut() {
+                        let changed = apply_remote(remote, msg);
+                        push_lines(&config, remote, changed, &state).await;
+                    }
+                }
+                update = updates.recv() => {
+                    match update {
+                        // A local scene replacement erased the mirrored lines
+                        Ok(SovaNotification::UpdatedScene(_)) => {
+                            if let Some(remote) = remote.as_ref() {
+                                push_lines(&config, remote, None, &state).await;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                        _ => (),
+                    }
+                }
+            }
+        };
+        match result {
+            Ok(()) => log_eprintln!("[!] Mirror of {} disconnected.", address),
+            Err(e) => log_eprintln!("[!] Mirror of {} disconnected: {}", address, e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Frame, Line};
+
+    #[test]
+    fn parse_mirror() {
+        let config: MirrorConfig = "alice:secret@192.168.1.2:8080/0=4,1".parse().unwrap();
+        assert_eq!(config.name, "alice");
+        assert_eq!(config.token.as_deref(), Some("secret"));
+        assert_eq!((config.ip.as_str(), config.port), ("192.168.1.2", 8080));
+        assert_eq!(config.lines, BTreeMap::from([(0, 4), (1, 1)]));
+        let config: MirrorConfig = "laptop:8080/2".parse().unwrap();
+        assert_eq!(
+            (config.name.as_str(), config.token),
+            ("mirror@laptop:8080", None)
+        );
+        assert!("laptop:8080".parse::<MirrorConfig>().is_err());
+        assert!("laptop/0".parse::<MirrorConfig>().is_err());
+    }
+
+    #[test]
+    fn tokens_are_resolved_from_the_environment() {
+        let var = |name: &str| (name == "ALICE_TOKEN").then(|| "t0k".to_owned());
+        let mut config: MirrorConfig = "alice:$ALICE_TOKEN@laptop:8080/0".parse().unwrap();
+        assert_eq!(config.token.as_deref(), Some("$ALICE_TOKEN"));
+        config.resolve_token(var).unwrap();
+        assert_eq!(config.token.as_deref(), Some("t0k"));
+        let mut config: MirrorConfig = "bob:$BOB_TOKEN@laptop:8080/0".parse().unwrap();
+        assert!(config.resolve_token(var).is_err());
+        let mut config: MirrorConfig = "carol:plain@laptop:8080/0".parse().unwrap();
+        config.resolve_token(var).unwrap();
+        assert_eq!(config.token.as_deref(), Some("plain"));
+    }
+
+    #[test]
+    fn mirrored_lines_follow_local_changes() {
+        let config: MirrorConfig = "laptop:8080/0=1,2=3".parse().unwrap();
+        let mut locks = FrameLocks::default();
+        config.lock_lines(&mut locks);
+        locks.insert_line(0);
+        locks.remove_line(3);
+        assert_eq!(config.local_lines(&locks), vec![(0, 2), (2, 3)]);
+        // Clients can add lines, but neither replace the scene nor the mirrored lines
+        let timing = ActionTiming::Immediate;
+        let allowed = |msg: ClientMessage| {
+            msg.edited_parts()
+                .into_iter()
+                .try_for_each(|part| locks.check_part(part, "bob"))
+                .is_ok()
+        };
+        assert!(allowed(ClientMessage::AddLine(0, Line::default(), timing)));
+        assert!(allowed(ClientMessage::SetLines(vec![(1, Line::default())], timing)));
+        assert!(!allowed(ClientMessage::SetLines(vec![(2, Line::default())], timing)));
+        assert!(!allowed(ClientMessage::RemoveLine(3, timing)));
+        assert!(!allowed(ClientMessage::SetScene(Scene::default(), timing)));
+        assert!(!allowed(ClientMessage::AddFrame(2, 0, Frame::default(), timing)));
+        assert!(locks.owns_lines(&config.owner(2)));
+    }
+
+    #[test]
+    fn remote_changes_are_tracked() {
+        let mut scene = Scene::default();
+        let line = Line::new(vec![1.0, 2.0]);
+        assert_eq!(apply_remote(&mut scene, ServerMessage::AddLine(0, line.clone())), None);
+        assert_eq!(
+            apply_remote(&mut scene, ServerMessage::RemoveFrame(0, 1)),
+            Some(vec![0])
+        );
+        assert_eq!(scene.line(0).map(Line::n_frames), Some(1));
+        assert_eq!(
+            apply_remote(&mut scene, ServerMessage::LineValues(vec![(1, line)])),
+            Some(vec![1])
+        );
+        assert_eq!(scene.n_lines(), 2);
+        assert_eq!(apply_remote(&mut scene, ServerMessage::Success), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,292 @@
+//! Exclusive edition of frames.
+//!
+//! A client can lock a frame (`ClientMessage::LockFrame`): the other clients cannot replace
+//! its content nor its script, nor remove it, until it unlocks it or disconnects. Like the
+//! other frame messages, locks designate frames by their line and frame indices, and they
+//! follow their frames when lines or frames are inserted or removed before them.
+//!
+//! Whole lines can also be locked by the server itself, e.g. for the lines mirrored
+//! from another server.
+
+use std::{cmp::Ordering, collections::BTreeMap, mem};
+
+use crate::scene::Scene;
+
+/// Part of the scene changed by a message, see `ClientMessage::edited_parts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScenePart {
+    /// The content of a frame, or its removal
+    Frame(usize, usize),
+    /// The configuration of a line, or its list of frames
+    Line(usize),
+    /// A line with all its frames
+    LineContent(usize),
+    /// Every line
+    Scene,
+}
+
+/// Holders of the frame locks, by line and frame.
+#[derive(Debug, Default)]
+pub struct FrameLocks {
+    owners: BTreeMap<(usize, usize), String>,
+    line_owners: BTreeMap<usize, String>,
+}
+
+impl FrameLocks {
+    /// Checks that a client may edit a line, its configuration or its list of frames.
+    pub fn check_line(&self, line_id: usize, client: &str) -> Result<(), String> {
+        match self.line_owners.get(&line_id) {
+            Some(owner) if owner != client => {
+                Err(format!("Line {} is locked by {}.", line_id, owner))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that a client may edit a frame.
+    pub fn check(&self, line_id: usize, frame_id: usize, client: &str) -> Result<(), String> {
+        self.check_line(line_id, client)?;
+        match self.owners.get(&(line_id, frame_id)) {
+            Some(owner) if owner != client => Err(format!(
+                "Frame {} of line {} is locked by {}.",
+                frame_id, line_id, owner
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that a client may edit a part of the scene.
+    pub fn check_part(&self, part: ScenePart, client: &str) -> Result<(), String> {
+        match part {
+            ScenePart::Frame(line_id, frame_id) => self.check(line_id, frame_id, client),
+            ScenePart::Line(line_id) => self.check_line(line_id, client),
+            ScenePart::LineContent(line_id) => {
+                self.check_line(line_id, client)?;
+                self.owners
+                    .range((line_id, 0)..=(line_id, usize::MAX))
+                    .try_for_each(|((_, frame_id), _)| self.check(line_id, *frame_id, client))
+            }
+            ScenePart::Scene => {
+                self.line_owners
+                    .keys()
+                    .try_for_each(|line_id| self.check_line(*line_id, client))?;
+                self.owners
+                    .keys()
+                    .try_for_each(|(line_id, frame_id)| self.check(*line_id, *frame_id, client))
+            }
+        }
+    }
+
+    pub fn lock(&mut self, line_id: usize, frame_id: usize, client: &str) -> Result<(), String> {
+        self.check(line_id, frame_id, client)?;
+        self.owners.insert((line_id, frame_id), client.to_owned());
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, line_id: usize, frame_id: usize, client: &str) -> Result<(), String> {
+        self.check(line_id, frame_id, client)?;
+        self.owners.remove(&(line_id, frame_id));
+        Ok(())
+    }
+
+    /// Reserves a whole line to an owner, until the server stops.
+    pub fn lock_line(&mut self, line_id: usize, owner: &str) {
+        self.line_owners.insert(line_id, owner.to_owned());
+    }
+
+    /// Line locked by an owner, if any.
+    pub fn line_of(&self, owner: &str) -> Option<usize> {
+        self.line_owners
+            .iter()
+            .find(|(_, line_owner)| *line_owner == owner)
+            .map(|(line_id, _)| *line_id)
+    }
+
+    /// Whether a name holds line locks, and so cannot be taken by a client.
+    pub fn owns_lines(&self, name: &str) -> bool {
+        self.line_owners.values().any(|owner| owner == name)
+    }
+
+    /// Releases the locks of a client, returns whether it held any.
+    pub fn release_all(&mut self, client: &str) -> bool {
+        let count = self.owners.len();
+        self.owners.retain(|_, owner| owner != client);
+        count != self.owners.len()
+    }
+
+    /// Transfers the locks of a client to its new name, returns whether it held any.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> bool {
+        let mut renamed = false;
+        for owner in self.owners.values_mut().filter(|owner| *owner == old_name) {
+            *owner = new_name.to_owned();
+            renamed = true;
+        }
+        renamed
+    }
+
+    /// A line has been inserted: the locks of the next lines follow them.
+    pub fn insert_line(&mut self, line_id: usize) -> bool {
+        let moved = self.owners.keys().any(|(line, _)| *line >= line_id);
+        self.owners = shift(mem::take(&mut self.owners), |(line, frame)| {
+            Some(if line >= line_id { (line + 1, frame) } else { (line, frame) })
+        });
+        self.line_owners = shift(mem::take(&mut self.line_owners), |line| {
+            Some(if line >= line_id { line + 1 } else { line })
+        });
+        moved
+    }
+
+    /// A line has been removed with its locks: those of the next lines follow them.
+    pub fn remove_line(&mut self, line_id: usize) -> bool {
+        let moved = self.owners.keys().any(|(line, _)| *line >= line_id);
+        self.owners = shift(mem::take(&mut self.owners), |(line, frame)| match line.cmp(&line_id) {
+            Ordering::Less => Some((line, frame)),
+            Ordering::Equal => None,
+            Ordering::Greater => Some((line - 1, frame)),
+        });
+        self.line_owners = shift(mem::take(&mut self.line_owners), |line| match line.cmp(&line_id) {
+            Ordering::Less => Some(line),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(line - 1),
+        });
+        moved
+    }
+
+    /// A frame has been inserted: the locks of the next frames of its line follow them.
+    pub fn insert_frame(&mut self, line_id: usize, frame_id: usize) -> bool {
+        let moved = self.owners.range((line_id, frame_id)..=(line_id, usize::MAX)).next().is_some();
+        self.owners = shift(mem::take(&mut self.owners), |(line, frame)| {
+            Some(if line == line_id && frame >= frame_id { (line, frame + 1) } else { (line, frame) })
+        });
+        moved
+    }
+
+    /// A frame has been removed with its lock: those of the next frames of its line follow them.
+    pub fn remove_frame(&mut self, line_id: usize, frame_id: usize) -> bool {
+        let moved = self.owners.range((line_id, frame_id)..=(line_id, usize::MAX)).next().is_some();
+        self.owners = shift(mem::take(&mut self.owners), |(line, frame)| {
+            if line != line_id {
+                return Some((line, frame));
+            }
+            match frame.cmp(&frame_id) {
+                Ordering::Less => Some((line, frame)),
+                Ordering::Equal => None,
+                Ordering::Greater => Some((line, frame - 1)),
+            }
+        });
+        moved
+    }
+
+    /// The scene has been replaced: the locks of frames it lacks are released.
+    pub fn retain_existing(&mut self, scene: &Scene) -> bool {
+        let count = self.owners.len();
+        self.owners
+            .retain(|(line_id, frame_id), _| scene.get_frame(*line_id, *frame_id).is_some());
+        count != self.owners.len()
+    }
+
+    /// Locked frames and their holders (line, frame, client).
+    pub fn holders(&self) -> Vec<(usize, usize, String)> {
+        self.owners
+            .iter()
+            .map(|((line_id, frame_id), owner)| (*line_id, *frame_id, owner.clone()))
+            .collect()
+    }
+}
+
+/// Moves the keys of a map, dropping those mapped to None.
+fn shift<K: Ord>(
+    map: BTreeMap<K, String>,
+    move_key: impl Fn(K) -> Option<K>,
+) -> BTreeMap<K, String> {
+    map.into_iter()
+        .filter_map(|(key, owner)| Some((move_key(key)?, owner)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        scene::Line,
+        schedule::{ActionTiming, SchedulerMessage},
+        server::client::ClientMessage,
+    };
+
+    #[test]
+    fn locks_are_exclusive() {
+        let mut locks = FrameLocks::default();
+        assert!(locks.lock(0, 1, "alice").is_ok());
+        assert!(locks.lock(0, 1, "bob").is_err());
+        assert!(locks.check(0, 1, "bob").is_err());
+        assert!(locks.check(0, 2, "bob").is_ok());
+        assert!(locks.unlock(0, 1, "bob").is_err());
+        assert!(locks.rename("alice", "alice2"));
+        assert_eq!(locks.holders(), vec![(0, 1, "alice2".to_owned())]);
+        assert!(locks.release_all("alice2"));
+        assert!(locks.check(0, 1, "bob").is_ok());
+        assert!(!locks.release_all("alice2"));
+        locks.lock_line(2, "mirror");
+        assert!(locks.check_line(2, "bob").is_err());
+        assert!(locks.lock(2, 0, "bob").is_err());
+        assert!(locks.check(2, 0, "mirror").is_ok());
+    }
+
+    #[test]
+    fn locks_cover_every_edit_of_their_frames() {
+        let mut locks = FrameLocks::default();
+        locks.lock(1, 2, "alice").unwrap();
+        for part in [
+            ScenePart::Frame(1, 2),
+            ScenePart::LineContent(1),
+            ScenePart::Scene,
+        ] {
+            assert!(locks.check_part(part, "bob").is_err());
+            assert!(locks.check_part(part, "alice").is_ok());
+        }
+        assert!(locks.check_part(ScenePart::Line(1), "bob").is_ok());
+        assert!(locks.check_part(ScenePart::LineContent(0), "bob").is_ok());
+        let timing = ActionTiming::Immediate;
+        assert_eq!(
+            ClientMessage::RemoveFrame(1, 2, timing).edited_parts(),
+            vec![ScenePart::Line(1), ScenePart::Frame(1, 2)]
+        );
+        assert_eq!(
+            ClientMessage::SchedulerControl(SchedulerMessage::SetScene(Scene::default(), timing))
+                .edited_parts(),
+            vec![ScenePart::Scene]
+        );
+        assert_eq!(
+            ClientMessage::SetLines(vec![(1, Line::default())], timing).edited_parts(),
+            vec![ScenePart::LineContent(1)]
+        );
+        assert!(ClientMessage::AddLine(0, Line::default(), timing).edited_parts().is_empty());
+    }
+
+    #[test]
+    fn locks_follow_their_frames() {
+        let mut locks = FrameLocks::default();
+        locks.lock(1, 2, "alice").unwrap();
+        locks.lock(1, 0, "bob").unwrap();
+        locks.lock_line(2, "mirror");
+        assert!(locks.insert_frame(1, 1));
+        assert!(locks.insert_line(0));
+        assert_eq!(
+            locks.holders(),
+            vec![(2, 0, "bob".to_owned()), (2, 3, "alice".to_owned())]
+        );
+        assert!(locks.check_line(3, "bob").is_err());
+        assert!(locks.remove_frame(2, 0));
+        assert_eq!(locks.holders(), vec![(2, 2, "alice".to_owned())]);
+        assert!(!locks.remove_frame(2, 3));
+        assert!(locks.remove_line(1));
+        assert_eq!(locks.holders(), vec![(1, 2, "alice".to_owned())]);
+        assert!(locks.check_line(2, "bob").is_err());
+        // Locks of frames missing from a new scene are released
+        let lines = vec![Line::default(), Line::new(vec![1.0; 3])];
+        assert!(!locks.retain_existing(&Scene::new(lines)));
+        assert!(locks.retain_existing(&Scene::default()));
+        assert!(locks.holders().is_empty());
+        assert!(locks.check_part(ScenePart::Scene, "bob").is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{compiler::CompilationState, vm::variable::VariableValue, protocol::{log::LogMessage, DeviceInfo}, scene::{Frame, Line}, schedule::playback::PlaybackState, server::Snapshot};
+use crate::{compiler::CompilationState, vm::{RuntimeFault, variable::{VariableScope, VariableValue}}, protocol::{log::LogMessage, DeviceInfo}, scene::{Frame, Line, Modulator}, schedule::{EmittedEvent, playback::PlaybackState}, server::{Snapshot, auth::Role}, theory::Tuning, util::text_operation::{TextOperation, TextSelection}};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -28,6 +28,12 @@ pub enum ServerMessage {
         is_playing: bool,
         /// List of available languages names.
         available_languages: Vec<String>,
+        /// Role granted to the client.
+        #[serde(default)]
+        role: Role,
+        /// Locked frames and their holders (line, frame, client).
+        #[serde(default)]
+        frame_locks: Vec<(usize, usize, String)>,
     },
     /// Broadcast containing the updated list of connected client names.
     PeersUpdated(Vec<String>),
@@ -35,6 +41,16 @@ pub enum ServerMessage {
     PeerStartedEditing(String, usize, usize),
     /// Broadcasts that a peer stopped editing a specific frame.
     PeerStoppedEditing(String, usize, usize),
+    /// Broadcasts the locked frames and their holders (line, frame, client).
+    FrameLocks(Vec<(usize, usize, String)>),
+    /// Shared draft of a frame script: line, frame, revision and content.
+    ScriptDraft(usize, usize, u64, String),
+    /// The edit of the client has been applied to a script draft, producing the given revision.
+    ScriptEditAck(usize, usize, u64),
+    /// Broadcasts the edit of a script draft by a peer, producing the given revision.
+    ScriptEdited(String, usize, usize, u64, TextOperation),
+    /// Broadcasts the cursor of a peer in a frame script (None once hidden).
+    PeerCursor(String, usize, usize, Option<TextSelection>),
     /// Indicates a change of the PlaybackState.
     PlaybackStateChanged(PlaybackState),
     /// A log message originating from the server or scheduler.
@@ -47,6 +63,8 @@ pub enum ServerMessage {
     InternalError(String),
     /// Indicate connection refused (e.g., username taken).
     ConnectionRefused(String),
+    /// The role of the client does not allow the requested action.
+    PermissionDenied(String),
     /// A complete snapshot of the current server state (used for save/load?).
     Snapshot(Snapshot),
     /// Sends the full list of available/connected devices (can be requested).
@@ -71,8 +89,20 @@ pub enum ServerMessage {
     RemoveFrame(usize, usize),
     /// The current frame positions within each line (line_idx, frame_idx, repetition_idx)
     FramePosition(Vec<(usize, usize)>),
+    /// Events recently emitted by the lines
+    EmittedEvents(Vec<EmittedEvent>),
+    /// Runtime faults recently raised by the scripts, located in their scripts when possible
+    RuntimeFaults(Vec<RuntimeFault>),
     /// Update of global variables (single-letter variables A-Z)
     GlobalVariablesUpdate(HashMap<String, VariableValue>),
+    /// Changes of subscribed variables (scope, name, new value or None if removed)
+    VariablesUpdate(Vec<(VariableScope, String, Option<VariableValue>)>),
+    /// Broadcast a change of the scene random seed
+    SeedChanged(Option<u64>),
+    /// Broadcast the definition (or removal) of a scene modulator
+    ModulatorChanged(String, Option<Modulator>),
+    /// Broadcast a change of the scene microtonal tuning
+    TuningChanged(Option<Tuning>),
     /// Compilation status update for a frame
     CompilationUpdate(usize, usize, u64, CompilationState),
     /// Response after restoring devices, with list of missing device names.
@@ -87,8 +117,12 @@ impl ServerMessage {
             // Real-time/frequent messages that should never be compressed
             | ServerMessage::PeerStartedEditing(_, _, _)
             | ServerMessage::PeerStoppedEditing(_, _, _)
+            | ServerMessage::ScriptEditAck(_, _, _)
+            | ServerMessage::ScriptEdited(_, _, _, _, _)
+            | ServerMessage::PeerCursor(_, _, _, _)
             | ServerMessage::ClockState(_, _, _, _)
             | ServerMessage::FramePosition(_)
+            | ServerMessage::EmittedEvents(_)
             | ServerMessage::PlaybackStateChanged(_)
             | ServerMessage::GlobalVariablesUpdate(_) => CompressionStrategy::Never,
 
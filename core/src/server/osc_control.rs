@@ -0,0 +1,560 @@
+//! OSC remote control of the server, for control surfaces (TouchOSC, Open Stage Control, Max...).
+//!
+//! Address space:
+//! - `/sova/transport/start`, `/sova/transport/stop`
+//! - `/sova/tempo <bpm>`
+//! - `/sova/line/<N>/frame/<M>/enable [0|1]`: enables (without argument) or disables a frame
+//! - `/sova/var/<NAME> <value>`: sets a global variable
+//! - `/sova/subscribe [nonce]`, `/sova/unsubscribe`: starts or stops the replies. Without
+//!   nonce, the server answers `/sova/subscribe <nonce>`, to be sent back from the same port.
+//!   This proves the sender is not spoofed before it gets the state of the set. Subscribers
+//!   then receive the state with the same addresses, plus `/sova/transport/playing <0|1>` and
+//!   `/sova/line/<N>/position <frame> <repetition>`, on the port they send from.
+//!   Subscriptions expire after `SUBSCRIPTION_LIFETIME` unless they are renewed.
+//!
+//! OSC messages carry no credentials: they are handled with the role of anonymous clients
+//! (admin on an open server), and ignored if anonymous clients are refused.
+
+use rosc::{OscMessage, OscPacket, OscType};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::{net::UdpSocket, select, sync::broadcast};
+
+use super::{ServerMessage, ServerState, VariableSubscriptions, client::ClientMessage, on_message};
+use crate::{
+    clock::Clock,
+    log_eprintln, log_println,
+    scene::{Frame, Scene},
+    schedule::{ActionTiming, SovaNotification, playback::PlaybackState},
+    util::decimal_operations::float64_from_decimal,
+    vm::variable::{VariableScope, VariableValue},
+};
+
+/// Name under which OSC requests appear in the server logs.
+const OSC_CLIENT_NAME: &str = "OSC control";
+/// Largest number of subscribers, and of pending subscription requests.
+const MAX_SUBSCRIBERS: usize = 16;
+/// Time after which a subscription expires, unless it is renewed.
+const SUBSCRIPTION_LIFETIME: Duration = Duration::from_secs(600);
+/// Time given to answer a subscription request with its nonce.
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(10);
+
+/// A request decoded from an OSC message.
+enum OscRequest {
+    Client(Box<ClientMessage>),
+    Subscribe(Option<i32>),
+    Unsubscribe,
+}
+
+impl OscRequest {
+    fn client(msg: ClientMessage) -> Self {
+        OscRequest::Client(Box::new(msg))
+    }
+}
+
+fn osc_value(arg: &OscType) -> Option<VariableValue> {
+    match arg {
+        OscType::Int(i) => Some(VariableValue::Integer(*i as i64)),
+        OscType::Long(i) => Some(VariableValue::Integer(*i)),
+        OscType::Float(f) => Some(VariableValue::Float(*f as f64)),
+        OscType::Double(f) => Some(VariableValue::Float(*f)),
+        OscType::String(s) => Some(VariableValue::Str(s.clone())),
+        OscType::Bool(b) => Some(VariableValue::Bool(*b)),
+        OscType::Blob(b) => Some(VariableValue::Blob(b.clone())),
+        _ => None,
+    }
+}
+
+fn osc_arg(value: &VariableValue) -> Option<OscType> {
+    match value {
+        VariableValue::Integer(i) => Some(OscType::Int(*i as i32)),
+        VariableValue::Float(f) => Some(OscType::Float(*f as f32)),
+        VariableValue::Decimal(sign, num, den) => Some(OscType::Float(float64_from_decimal(
+            *sign, *num, *den,
+        ) as f32)),
+        VariableValue::Str(s) => Some(OscType::String(s.clone())),
+        VariableValue::Bool(b) => Some(OscType::Int(*b as i32)),
+        _ => None,
+    }
+}
+
+fn osc_number(arg: Option<&OscType>) -> Option<f64> {
+    match arg? {
+        OscType::Int(i) => Some(*i as f64),
+        OscType::Long(i) => Some(*i as f64),
+        OscType::Float(f) => Some(*f as f64),
+        OscType::Double(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// Toggle argument: none is on, numbers are on from 0.5 (faders and buttons send floats).
+fn osc_flag(arg: Option<&OscType>) -> Result<bool, String> {
+    match arg {
+        None => Ok(true),
+        Some(OscType::Bool(b)) => Ok(*b),
+        arg => osc_number(arg)
+            .map(|x| x >= 0.5)
+            .ok_or_else(|| "Expected a number or a boolean".to_owned()),
+    }
+}
+
+fn parse_index(segment: &str) -> Result<usize, String> {
+    segment
+        .parse()
+        .map_err(|_| format!("Invalid index '{segment}'"))
+}
+
+/// Maps an OSC message to a request.
+fn parse_request(msg: &OscMessage) -> Result<OscRequest, String> {
+    let segments: Vec<&str> = msg.addr.trim_start_matches('/').split('/').collect();
+    let arg = msg.args.first();
+    let request = match segments.as_slice() {
+        ["sova", "subscribe"] => OscRequest::Subscribe(match arg {
+            Some(OscType::Int(nonce)) => Some(*nonce),
+            _ => None,
+        }),
+        ["sova", "unsubscribe"] => OscRequest::Unsubscribe,
+        ["sova", "transport", "start"] => {
+            OscRequest::client(ClientMessage::TransportStart(ActionTiming::Immediate))
+        }
+        ["sova", "transport", "stop"] => {
+            OscRequest::client(ClientMessage::TransportStop(ActionTiming::Immediate))
+        }
+        ["sova", "tempo"] => {
+            let tempo = osc_number(arg).ok_or("Expected a tempo in BPM")?;
+            OscRequest::client(ClientMessage::SetTempo(tempo, ActionTiming::Immediate))
+        }
+        ["sova", "line", line, "frame", frame, "enable"] => {
+            let (line_id, frame_id) = (parse_index(line)?, parse_index(frame)?);
+            OscRequest::client(ClientMessage::EnableFrames(
+                vec![(line_id, frame_id, osc_flag(arg)?)],
+                ActionTiming::Immediate,
+            ))
+        }
+        ["sova", "var", name] if !name.is_empty() => {
+            let value = arg.and_then(osc_value).ok_or("Expected a value")?;
+            OscRequest::client(ClientMessage::SetVariable(
+                VariableScope::Global,
+                name.to_string(),
+                value,
+                ActionTiming::Immediate,
+            ))
+        }
+        _ => return Err(format!("Unknown address '{}'", msg.addr)),
+    };
+    Ok(request)
+}
+
+fn frame_state(line_id: usize, frame_id: usize, frame: &Frame) -> OscMessage {
+    OscMessage {
+        addr: format!("/sova/line/{line_id}/frame/{frame_id}/enable"),
+        args: vec![OscType::Int(frame.enabled as i32)],
+    }
+}
+
+fn variable_state(name: &str, value: &VariableValue) -> Option<OscMessage> {
+    Some(OscMessage {
+        addr: format!("/sova/var/{name}"),
+        args: vec![osc_arg(value)?],
+    })
+}
+
+/// State of the frames of a whole scene.
+fn scene_state(scene: &Scene) -> Vec<OscMessage> {
+    scene
+        .lines
+        .iter()
+        .enumerate()
+        .flat_map(|(line_id, line)| {
+            line.frames()
+                .iter()
+                .enumerate()
+                .map(move |(frame_id, frame)| frame_state(line_id, frame_id, frame))
+        })
+        .collect()
+}
+
+/// Answer to a subscription request.
+#[derive(Debug, PartialEq)]
+enum Subscription {
+    /// The sender has to send this nonce back
+    Challenge(i32),
+    /// The sender is subscribed, and gets the state of the set
+    Subscribed,
+    /// Too many subscribers or pending requests
+    Refused,
+}
+
+/// Remote control state: subscribers and their expiry date, pending subscription requests,
+/// and the last frame positions sent to the subscribers.
+#[derive(Default)]
+struct OscControl {
+    subscribers: BTreeMap<SocketAddr, Instant>,
+    challenges: BTreeMap<SocketAddr, (i32, Instant)>,
+    positions: Vec<(usize, usize)>,
+}
+
+impl OscControl {
+    /// Forgets the expired subscriptions and subscription requests.
+    fn prune(&mut self, now: Instant) {
+        self.subscribers.retain(|_, expiry| *expiry > now);
+        self.challenges.retain(|_, (_, expiry)| *expiry > now);
+    }
+
+    /// Handles a subscription request. A sender gets subscribed (or renews its subscription)
+    /// by sending back the nonce it was given.
+    fn subscribe(&mut self, from: SocketAddr, nonce: Option<i32>, now: Instant) -> Subscription {
+        self.prune(now);
+        if let Some((expected, _)) = self.challenges.get(&from)
+            && nonce == Some(*expected)
+        {
+            self.challenges.remove(&from);
+            if self.subscribers.len() >= MAX_SUBSCRIBERS && !self.subscribers.contains_key(&from) {
+                return Subscription::Refused;
+            }
+            self.subscribers.insert(from, now + SUBSCRIPTION_LIFETIME);
+            return Subscription::Subscribed;
+        }
+        if self.challenges.len() >= MAX_SUBSCRIBERS && !self.challenges.contains_key(&from) {
+            return Subscription::Refused;
+        }
+        let nonce = rand::random();
+        self.challenges.insert(from, (nonce, now + CHALLENGE_LIFETIME));
+        Subscription::Challenge(nonce)
+    }
+
+    /// Replies to send to the subscribers for a notification.
+    fn replies(&mut self, notification: &SovaNotification) -> Vec<OscMessage> {
+        match notification {
+            SovaNotification::TempoChanged(tempo) => vec![OscMessage {
+                addr: "/sova/tempo".to_owned(),
+                args: vec![OscType::Float(*tempo as f32)],
+            }],
+            SovaNotification::PlaybackStateChanged(state) => vec![OscMessage {
+                addr: "/sova/transport/playing".to_owned(),
+                args: vec![OscType::Int((*state == PlaybackState::Playing) as i32)],
+            }],
+            SovaNotification::UpdatedScene(scene) => scene_state(scene),
+            SovaNotification::UpdatedLines(lines) => lines
+                .iter()
+                .flat_map(|(line_id, line)| {
+                    line.frames()
+                        .iter()
+                        .enumerate()
+                        .map(|(frame_id, frame)| frame_state(*line_id, frame_id, frame))
+                })
+                .collect(),
+            SovaNotification::UpdatedFrames(frames) => frames
+                .iter()
+                .map(|(line_id, frame_id, frame)| frame_state(*line_id, *frame_id, frame))
+                .collect(),
+            SovaNotification::AddedFrame(line_id, frame_id, frame) => {
+                vec![frame_state(*line_id, *frame_id, frame)]
+            }
+            SovaNotification::GlobalVariablesChanged(vars) => vars
+                .iter()
+                .filter_map(|(name, value)| variable_state(name, value))
+                .collect(),
+            SovaNotification::FramePositionChanged(positions) => {
+                let replies = positions
+                    .iter()
+                    .enumerate()
+                    .filter(|(line_id, position)| self.positions.get(*line_id) != Some(*position))
+                    .map(|(line_id, (frame, repetition))| OscMessage {
+                        addr: format!("/sova/line/{line_id}/position"),
+                        args: vec![
+                            OscType::Int(*frame as i32),
+                            OscType::Int(*repetition as i32),
+                        ],
+                    })
+                    .collect();
+                self.positions = positions.clone();
+                replies
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// State of the set sent to a new subscriber.
+async fn subscriber_state(state: &ServerState) -> Vec<OscMessage> {
+    let mut replies = {
+        let scene = state.scene_image.lock().await;
+        let mut replies = scene_state(&scene);
+        replies.extend(
+            scene
+                .vars
+                .visible_vars()
+                .filter_map(|(name, value)| variable_state(name, value)),
+        );
+        replies
+    };
+    replies.push(OscMessage {
+        addr: "/sova/tempo".to_owned(),
+        args: vec![OscType::Float(Clock::from(&state.clock_server).tempo() as f32)],
+    });
+    replies.push(OscMessage {
+        addr: "/sova/transport/playing".to_owned(),
+        args: vec![OscType::Int(state.is_playing.load(Ordering::Relaxed) as i32)],
+    });
+    replies
+}
+
+async fn send_osc(socket: &UdpSocket, addr: SocketAddr, msg: OscMessage) {
+    match rosc::encoder::encode(&OscPacket::Message(msg)) {
+        Ok(buf) => {
+            if let Err(e) = socket.send_to(&buf, addr).await {
+                log_eprintln!("[!] Failed to send OSC reply to {}: {}", addr, e);
+            }
+        }
+        Err(e) => log_eprintln!("[!] Failed to encode OSC reply: {}", e),
+    }
+}
+
+/// Messages of a packet, bundles being flattened.
+fn packet_messages(packet: OscPacket, messages: &mut Vec<OscMessage>) {
+    match packet {
+        OscPacket::Message(msg) => messages.push(msg),
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                packet_messages(packet, messages);
+            }
+        }
+    }
+}
+
+/// Handles an OSC message, returning an error to report to its sender.
+async fn handle_message(
+    msg: OscMessage,
+    from: SocketAddr,
+    socket: &UdpSocket,
+    state: &ServerState,
+    control: &mut OscControl,
+    subscriptions: &mut VariableSubscriptions,
+) -> Result<(), String> {
+    let role = state
+        .auth
+        .authenticate(OSC_CLIENT_NAME, None)
+        .map_err(|_| "OSC control is disabled: anonymous clients are refused".to_owned())?;
+    let client_msg = match parse_request(&msg)? {
+        OscRequest::Client(client_msg) => *client_msg,
+        OscRequest::Subscribe(nonce) => {
+            let replies = match control.subscribe(from, nonce, Instant::now()) {
+                Subscription::Challenge(nonce) => vec![OscMessage {
+                    addr: "/sova/subscribe".to_owned(),
+                    args: vec![OscType::Int(nonce)],
+                }],
+                Subscription::Subscribed => {
+                    log_println!("[📡] OSC subscriber: {}", from);
+                    subscriber_state(state).await
+                }
+                Subscription::Refused => return Err("Too many OSC subscribers".to_owned()),
+            };
+            for reply in replies {
+                send_osc(socket, from, reply).await;
+            }
+            return Ok(());
+        }
+        OscRequest::Unsubscribe => {
+            control.subscribers.remove(&from);
+            return Ok(());
+        }
+    };
+    if let ClientMessage::EnableFrames(frames, _) = &client_msg {
+        let scene = state.scene_image.lock().await;
+        if let Some((line_id, frame_id, _)) = frames
+            .iter()
+            .find(|(line_id, frame_id, _)| !scene.has_frame(*line_id, *frame_id))
+        {
+            return Err(format!("No frame {frame_id} in line {line_id}"));
+        }
+    }
+    let mut client_name = OSC_CLIENT_NAME.to_owned();
+    match on_message(client_msg, state, &mut client_name, subscriptions, role).await {
+        ServerMessage::InternalError(e) | ServerMessage::PermissionDenied(e) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Receives OSC control messages on a bound socket, and replies to the subscribers,
+/// until the task is aborted.
+pub(super) async fn serve_osc_control(socket: UdpSocket, state: ServerState) {
+    let mut control = OscControl::default();
+    let mut subscriptions = VariableSubscriptions::default();
+    let mut updates = state.update_sender.subscribe();
+    let mut buf = [0u8; rosc::decoder::MTU];
+    loop {
+        select! {
+            received = socket.recv_from(&mut buf) => {
+                let (size, from) = match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        log_eprintln!("[!] OSC control receive error: {}", e);
+                        continue;
+                    }
+                };
+                let packet = match rosc::decoder::decode_udp(&buf[..size]) {
+                    Ok((_, packet)) => packet,
+                    Err(e) => {
+                        log_eprintln!("[!] Invalid OSC packet from {}: {}", from, e);
+                        continue;
+                    }
+                };
+                let mut messages = Vec::new();
+                packet_messages(packet, &mut messages);
+                for msg in messages {
+                    if let Err(e) =
+                        handle_message(msg, from, &socket, &state, &mut control, &mut subscriptions).await
+                    {
+                        log_eprintln!("[!] OSC control from {}: {}", from, e);
+                        let error = OscMessage {
+                            addr: "/sova/error".to_owned(),
+                            args: vec![OscType::String(e)],
+                        };
+                        send_osc(&socket, from, error).await;
+                    }
+                }
+            }
+            update = updates.recv() => {
+                let notification = match update {
+                    Ok(notification) => notification,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                control.prune(Instant::now());
+                if control.subscribers.is_empty() {
+                    continue;
+                }
+                for reply in control.replies(&notification) {
+                    for addr in control.subscribers.keys() {
+                        send_osc(&socket, *addr, reply.clone()).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn osc(addr: &str, args: Vec<OscType>) -> OscMessage {
+        OscMessage {
+            addr: addr.to_owned(),
+            args,
+        }
+    }
+
+    fn client_message(msg: OscMessage) -> Option<ClientMessage> {
+        match parse_request(&msg) {
+            Ok(OscRequest::Client(msg)) => Some(*msg),
+            _ => None,
+        }
+    }
+
+    fn address(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn address_space() {
+        let disable = osc("/sova/line/0/frame/1/enable", vec![OscType::Float(0.0)]);
+        assert!(matches!(
+            client_message(disable),
+            Some(ClientMessage::EnableFrames(frames, _)) if frames == vec![(0, 1, false)]
+        ));
+        let enable = osc("/sova/line/2/frame/3/enable", vec![]);
+        assert!(matches!(
+            client_message(enable),
+            Some(ClientMessage::EnableFrames(frames, _)) if frames == vec![(2, 3, true)]
+        ));
+        assert!(matches!(
+            client_message(osc("/sova/tempo", vec![OscType::Int(90)])),
+            Some(ClientMessage::SetTempo(t, _)) if t == 90.0
+        ));
+        assert!(matches!(
+            client_message(osc("/sova/var/A", vec![OscType::Float(0.5)])),
+            Some(ClientMessage::SetVariable(VariableScope::Global, name, _, _)) if name == "A"
+        ));
+        assert!(matches!(
+            client_message(osc("/sova/transport/start", vec![])),
+            Some(ClientMessage::TransportStart(_))
+        ));
+        assert!(parse_request(&osc("/sova/line/x/frame/0/enable", vec![])).is_err());
+        assert!(parse_request(&osc("/sova/line/0/frame/0/enable", vec![OscType::Nil])).is_err());
+        assert!(parse_request(&osc("/sova/tempo", vec![])).is_err());
+        assert!(parse_request(&osc("/sova/var/", vec![OscType::Int(1)])).is_err());
+        assert!(parse_request(&osc("/other", vec![])).is_err());
+        assert!(matches!(
+            parse_request(&osc("/sova/subscribe", vec![OscType::Int(7)])),
+            Ok(OscRequest::Subscribe(Some(7)))
+        ));
+    }
+
+    #[test]
+    fn positions_are_sent_for_the_lines_that_moved() {
+        let mut control = OscControl::default();
+        let moved = SovaNotification::FramePositionChanged(vec![(0, 0), (1, 0)]);
+        assert_eq!(control.replies(&moved).len(), 2);
+        let moved = SovaNotification::FramePositionChanged(vec![(0, 0), (2, 0)]);
+        assert_eq!(control.replies(&moved)[0].addr, "/sova/line/1/position");
+    }
+
+    #[test]
+    fn subscribers_send_back_their_nonce() {
+        let mut control = OscControl::default();
+        let now = Instant::now();
+        let Subscription::Challenge(nonce) = control.subscribe(address(9000), None, now) else {
+            panic!("Expected a challenge");
+        };
+        assert!(control.subscribers.is_empty());
+        // Another sender (or a wrong nonce) only gets a new challenge
+        assert!(matches!(
+            control.subscribe(address(9001), Some(nonce), now),
+            Subscription::Challenge(_)
+        ));
+        assert_eq!(
+            control.subscribe(address(9000), Some(nonce), now),
+            Subscription::Subscribed
+        );
+        assert!(control.subscribers.contains_key(&address(9000)));
+        assert!(!control.subscribers.contains_key(&address(9001)));
+        // A nonce is used once
+        assert!(matches!(
+            control.subscribe(address(9000), Some(nonce), now),
+            Subscription::Challenge(_)
+        ));
+    }
+
+    #[test]
+    fn subscriptions_are_bounded_and_expire() {
+        let mut control = OscControl::default();
+        let now = Instant::now();
+        for port in 0..MAX_SUBSCRIBERS as u16 {
+            let Subscription::Challenge(nonce) = control.subscribe(address(port), None, now) else {
+                panic!("Expected a challenge");
+            };
+            control.subscribe(address(port), Some(nonce), now);
+        }
+        assert_eq!(control.subscribers.len(), MAX_SUBSCRIBERS);
+        let late = address(MAX_SUBSCRIBERS as u16);
+        let Subscription::Challenge(nonce) = control.subscribe(late, None, now) else {
+            panic!("Expected a challenge");
+        };
+        assert_eq!(control.subscribe(late, Some(nonce), now), Subscription::Refused);
+
+        // Pending requests are bounded too
+        for port in 100..100 + MAX_SUBSCRIBERS as u16 {
+            control.subscribe(address(port), None, now);
+        }
+        assert_eq!(control.subscribe(address(200), None, now), Subscription::Refused);
+
+        control.prune(now + SUBSCRIPTION_LIFETIME);
+        assert!(control.subscribers.is_empty());
+        assert!(control.challenges.is_empty());
+    }
+}
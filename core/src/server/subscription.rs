@@ -0,0 +1,129 @@
+//! Per-client subscriptions to scene variables.
+
+use crate::vm::variable::{VariableScope, VariableStore, VariableValue};
+use std::collections::HashMap;
+
+/// Set of patterns a client subscribed to.
+///
+/// Patterns are matched against qualified variable names (see [`VariableScope::qualified_name`]),
+/// `*` matching any sequence of characters and `?` any single character.
+/// For example `global.*`, `line.0.*` or `*.count`.
+#[derive(Debug, Clone, Default)]
+pub struct VariableSubscriptions {
+    patterns: Vec<String>,
+}
+
+impl VariableSubscriptions {
+    pub fn subscribe(&mut self, patterns: Vec<String>) {
+        for pattern in patterns {
+            if !self.patterns.contains(&pattern) {
+                self.patterns.push(pattern);
+            }
+        }
+    }
+
+    /// Removes the given patterns, or every pattern if none is given.
+    pub fn unsubscribe(&mut self, patterns: &[String]) {
+        if patterns.is_empty() {
+            self.patterns.clear();
+        } else {
+            self.patterns.retain(|p| !patterns.contains(p));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn matches(&self, scope: &VariableScope, name: &str) -> bool {
+        let qualified = scope.qualified_name(name);
+        self.patterns.iter().any(|p| glob_match(p, &qualified))
+    }
+
+    /// Keeps only the changes matching at least one pattern.
+    pub fn filter(
+        &self,
+        changes: Vec<(VariableScope, String, Option<VariableValue>)>,
+    ) -> Vec<(VariableScope, String, Option<VariableValue>)> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        changes
+            .into_iter()
+            .filter(|(scope, name, _)| self.matches(scope, name))
+            .collect()
+    }
+
+    /// Current values of every subscribed variable, in the change format.
+    pub fn current_values(
+        &self,
+        variables: &HashMap<VariableScope, VariableStore>,
+    ) -> Vec<(VariableScope, String, Option<VariableValue>)> {
+        variables
+            .iter()
+            .flat_map(|(scope, store)| {
+                store
+                    .iter()
+                    .filter(|(name, _)| self.matches(scope, name))
+                    .map(|(name, value)| (*scope, name.clone(), Some(value.clone())))
+            })
+            .collect()
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("global.*", "global.A"));
+        assert!(glob_match("*.count", "line.0.count"));
+        assert!(glob_match("line.?.*", "line.3.x"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("line.?.*", "line.10.x"));
+        assert!(!glob_match("global.A", "global.AB"));
+        assert!(!glob_match("*.count", "line.0.counter"));
+    }
+
+    #[test]
+    fn changes_are_filtered_by_subscriptions() {
+        let mut subscriptions = VariableSubscriptions::default();
+        let changes = vec![
+            (VariableScope::Global, "A".to_string(), Some(1.into())),
+            (VariableScope::Line(0), "count".to_string(), None),
+            (VariableScope::Frame(0, 1), "count".to_string(), Some(2.into())),
+        ];
+        assert!(subscriptions.filter(changes.clone()).is_empty());
+        subscriptions.subscribe(vec!["line.*".to_string(), "global.A".to_string()]);
+        assert_eq!(subscriptions.filter(changes.clone()), changes[..2].to_vec());
+        subscriptions.unsubscribe(&["global.A".to_string()]);
+        assert_eq!(subscriptions.filter(changes.clone()), changes[1..2].to_vec());
+        subscriptions.unsubscribe(&[]);
+        assert!(subscriptions.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! WebSocket transport of the server, for browsers and quick scripts.
+//!
+//! It carries the same `ClientMessage`/`ServerMessage` enums as the TCP protocol, with the
+//! same handshake and broadcasts: JSON in text frames, or MessagePack in binary frames.
+//! The server answers in the encoding of the last frame it received from the client.
+
+use futures_util::{
+    SinkExt, StreamExt,
+    stream::{SplitSink, SplitStream},
+};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use tokio::{
+    io,
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::{WebSocketStream, tungstenite::Message};
+
+use super::{ClientReader, ClientWriter, ServerMessage, ServerState, client::ClientMessage, serve_client};
+use crate::{log_eprintln, log_println};
+
+struct WebSocketReader {
+    stream: SplitStream<WebSocketStream<TcpStream>>,
+    binary: Arc<AtomicBool>,
+}
+
+struct WebSocketWriter {
+    sink: SplitSink<WebSocketStream<TcpStream>, Message>,
+    binary: Arc<AtomicBool>,
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+impl ClientReader for WebSocketReader {
+    async fn read_message(&mut self, client_id_for_logging: &str) -> io::Result<Option<ClientMessage>> {
+        loop {
+            let frame = match self.stream.next().await {
+                Some(Ok(frame)) => frame,
+                Some(Err(e)) => {
+                    log_eprintln!("[!] WebSocket error from {}: {}", client_id_for_logging, e);
+                    return Err(io::Error::other(e));
+                }
+                None => return Ok(None),
+            };
+            let msg = match frame {
+                Message::Text(text) => {
+                    self.binary.store(false, Ordering::Relaxed);
+                    serde_json::from_str::<ClientMessage>(&text).map_err(invalid_data)
+                }
+                Message::Binary(bytes) => {
+                    self.binary.store(true, Ordering::Relaxed);
+                    ClientMessage::deserialize(&bytes).and_then(|msg| {
+                        msg.ok_or_else(|| invalid_data("Empty MessagePack message"))
+                    })
+                }
+                Message::Close(_) => {
+                    log_println!("[🔌] WebSocket closed by {}.", client_id_for_logging);
+                    return Ok(None);
+                }
+                // Pings are answered by tungstenite
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+            };
+            if let Err(e) = &msg {
+                log_eprintln!(
+                    "[!] Failed to deserialize WebSocket message from {}: {}",
+                    client_id_for_logging,
+                    e
+                );
+            }
+            return msg.map(Some);
+        }
+    }
+}
+
+impl ClientWriter for WebSocketWriter {
+    async fn send_message(&mut self, msg: ServerMessage) -> io::Result<()> {
+        let frame = if self.binary.load(Ordering::Relaxed) {
+            Message::Binary(rmp_serde::to_vec_named(&msg).map_err(invalid_data)?.into())
+        } else {
+            Message::Text(serde_json::to_string(&msg).map_err(invalid_data)?.into())
+        };
+        self.sink.send(frame).await.map_err(io::Error::other)
+    }
+}
+
+/// Upgrades a TCP connection to a WebSocket and serves the client.
+async fn process_websocket_client(socket: TcpStream, state: ServerState) -> io::Result<String> {
+    socket.set_nodelay(true)?;
+    let client_addr = socket.peer_addr()?;
+    let websocket = tokio_tungstenite::accept_async(socket)
+        .await
+        .map_err(io::Error::other)?;
+    let (sink, stream) = websocket.split();
+    let binary = Arc::new(AtomicBool::new(false));
+    let reader = WebSocketReader {
+        stream,
+        binary: Arc::clone(&binary),
+    };
+    let writer = WebSocketWriter { sink, binary };
+    serve_client(reader, writer, client_addr.to_string(), state).await
+}
+
+/// Accepts WebSocket clients on an already bound listener, until the task is aborted.
+pub(super) async fn accept_websocket_clients(listener: TcpListener, state: ServerState) {
+    loop {
+        let (socket, client_addr) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                log_eprintln!("[!] Failed to accept WebSocket connection: {}", e);
+                continue;
+            }
+        };
+        log_println!("[🔌] New WebSocket connection from {}", client_addr);
+        let client_state = state.clone();
+        tokio::spawn(async move {
+            match process_websocket_client(socket, client_state).await {
+                Ok(client_name) => {
+                    log_println!("[🔌] WebSocket client '{}' disconnected.", client_name);
+                }
+                Err(e) => {
+                    log_eprintln!("[!] Error handling WebSocket client {}: {}", client_addr, e);
+                }
+            }
+        });
+    }
+}
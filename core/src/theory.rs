@@ -0,0 +1,421 @@
+//! Music theory shared by the languages and the device layer.
+//!
+//! Notes are MIDI note numbers, kept as `i64` so that intermediate results may leave the
+//! MIDI range. Scales and chords are tables of semitones from their root.
+
+use serde::{Deserialize, Serialize};
+
+pub mod tuning;
+pub use tuning::{Tuning, TuningOutput};
+
+/// Largest number of voices of a chord built on a scale degree.
+pub const MAX_CHORD_VOICES: usize = 16;
+/// Largest number of octaves an arpeggio is spread over (the MIDI range).
+pub const MAX_ARPEGGIO_OCTAVES: usize = 11;
+
+/// Scale or mode, as a set of pitch classes relative to a root.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    #[default]
+    Major,
+    Minor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    HarmonicMinor,
+    MelodicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    WholeTone,
+    Chromatic,
+    /// Semitones from the root, in `[0, 11]`
+    Custom(Vec<u8>),
+}
+
+impl Scale {
+    /// Parses a scale name, accepting a few usual aliases.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let scale = match name {
+            "major" | "maj" | "ionian" => Scale::Major,
+            "minor" | "min" | "aeolian" => Scale::Minor,
+            "dorian" => Scale::Dorian,
+            "phrygian" => Scale::Phrygian,
+            "lydian" => Scale::Lydian,
+            "mixolydian" => Scale::Mixolydian,
+            "locrian" => Scale::Locrian,
+            "harmonic_minor" | "harmonic-minor" => Scale::HarmonicMinor,
+            "melodic_minor" | "melodic-minor" => Scale::MelodicMinor,
+            "major_pentatonic" | "major-pentatonic" | "pentatonic" => Scale::MajorPentatonic,
+            "minor_pentatonic" | "minor-pentatonic" => Scale::MinorPentatonic,
+            "blues" => Scale::Blues,
+            "whole_tone" | "whole-tone" => Scale::WholeTone,
+            "chromatic" => Scale::Chromatic,
+            _ => return None,
+        };
+        Some(scale)
+    }
+
+    /// Semitones of the degrees of the scale, from the root.
+    pub fn intervals(&self) -> &[u8] {
+        match self {
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            Scale::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+            Scale::WholeTone => &[0, 2, 4, 6, 8, 10],
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Custom(intervals) => intervals,
+        }
+    }
+
+    /// Moves a note to the nearest note of the scale built on `root` (a pitch class),
+    /// the lower one on ties.
+    pub fn quantize(&self, note: i64, root: i64) -> i64 {
+        let intervals = self.intervals();
+        if intervals.is_empty() {
+            return note;
+        }
+        let octave = note.saturating_sub(root).div_euclid(12);
+        let mut best = note;
+        let mut best_distance = u64::MAX;
+        // The candidates of the octave below and above are needed around the octave boundary
+        for o in [octave - 1, octave, octave + 1] {
+            for interval in intervals {
+                let candidate = o.saturating_mul(12).saturating_add(root + *interval as i64);
+                let distance = candidate.abs_diff(note);
+                if distance < best_distance || (distance == best_distance && candidate < best) {
+                    best = candidate;
+                    best_distance = distance;
+                }
+            }
+        }
+        best
+    }
+
+    /// Note of a degree (0 being the root) of the scale built on the note `root`.
+    /// Degrees beyond the scale continue in the next octaves, negative ones go below the root.
+    pub fn degree(&self, degree: i64, root: i64) -> i64 {
+        let intervals = self.intervals();
+        if intervals.is_empty() {
+            return root;
+        }
+        let len = intervals.len() as i64;
+        let octaves = degree.div_euclid(len).saturating_mul(12);
+        root.saturating_add(octaves)
+            .saturating_add(intervals[degree.rem_euclid(len) as usize] as i64)
+    }
+
+    /// Chord built by stacking thirds of the scale on a degree, with the given number of voices
+    /// (3 for a triad, 4 for a seventh chord, at most `MAX_CHORD_VOICES`).
+    pub fn degree_chord(&self, degree: i64, root: i64, voices: usize) -> Vec<i64> {
+        (0..voices.min(MAX_CHORD_VOICES) as i64)
+            .map(|i| self.degree(degree.saturating_add(2 * i), root))
+            .collect()
+    }
+
+    /// Notes of one octave of the scale built on `root`.
+    pub fn notes(&self, root: i64) -> Vec<i64> {
+        self.intervals().iter().map(|i| root + *i as i64).collect()
+    }
+}
+
+/// Chord quality, as a set of semitones from the root.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Chord {
+    #[default]
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Sus2,
+    Sus4,
+    Power,
+    Major6,
+    Minor6,
+    Major7,
+    Minor7,
+    Dominant7,
+    HalfDiminished7,
+    Diminished7,
+    MinorMajor7,
+    Add9,
+    Major9,
+    Minor9,
+    Dominant9,
+    /// Semitones from the root
+    Custom(Vec<u8>),
+}
+
+impl Chord {
+    /// Parses a chord name, in long form (`minor7`) or in the usual symbols (`m7`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let chord = match name {
+            "major" | "maj" | "M" => Chord::Major,
+            "minor" | "min" | "m" => Chord::Minor,
+            "diminished" | "dim" => Chord::Diminished,
+            "augmented" | "aug" => Chord::Augmented,
+            "sus2" => Chord::Sus2,
+            "sus4" | "sus" => Chord::Sus4,
+            "power" | "5" => Chord::Power,
+            "major6" | "6" => Chord::Major6,
+            "minor6" | "m6" => Chord::Minor6,
+            "major7" | "maj7" => Chord::Major7,
+            "minor7" | "min7" | "m7" => Chord::Minor7,
+            "dominant7" | "dom7" | "7" => Chord::Dominant7,
+            "half_diminished7" | "m7b5" => Chord::HalfDiminished7,
+            "diminished7" | "dim7" => Chord::Diminished7,
+            "minor_major7" | "mmaj7" => Chord::MinorMajor7,
+            "add9" => Chord::Add9,
+            "major9" | "maj9" => Chord::Major9,
+            "minor9" | "min9" | "m9" => Chord::Minor9,
+            "dominant9" | "dom9" | "9" => Chord::Dominant9,
+            _ => return None,
+        };
+        Some(chord)
+    }
+
+    pub fn intervals(&self) -> &[u8] {
+        match self {
+            Chord::Major => &[0, 4, 7],
+            Chord::Minor => &[0, 3, 7],
+            Chord::Diminished => &[0, 3, 6],
+            Chord::Augmented => &[0, 4, 8],
+            Chord::Sus2 => &[0, 2, 7],
+            Chord::Sus4 => &[0, 5, 7],
+            Chord::Power => &[0, 7],
+            Chord::Major6 => &[0, 4, 7, 9],
+            Chord::Minor6 => &[0, 3, 7, 9],
+            Chord::Major7 => &[0, 4, 7, 11],
+            Chord::Minor7 => &[0, 3, 7, 10],
+            Chord::Dominant7 => &[0, 4, 7, 10],
+            Chord::HalfDiminished7 => &[0, 3, 6, 10],
+            Chord::Diminished7 => &[0, 3, 6, 9],
+            Chord::MinorMajor7 => &[0, 3, 7, 11],
+            Chord::Add9 => &[0, 4, 7, 14],
+            Chord::Major9 => &[0, 4, 7, 11, 14],
+            Chord::Minor9 => &[0, 3, 7, 10, 14],
+            Chord::Dominant9 => &[0, 4, 7, 10, 14],
+            Chord::Custom(intervals) => intervals,
+        }
+    }
+
+    /// Notes of the chord built on `root`, in root position.
+    pub fn notes(&self, root: i64) -> Vec<i64> {
+        self.intervals().iter().map(|i| root.saturating_add(*i as i64)).collect()
+    }
+}
+
+/// Inverts a chord: each step up moves the lowest note an octave up, each step down
+/// (negative `inversion`) moves the highest note an octave down. The result is sorted.
+pub fn invert(notes: &[i64], inversion: i64) -> Vec<i64> {
+    let mut notes = notes.to_vec();
+    notes.sort();
+    if notes.is_empty() {
+        return notes;
+    }
+    // A whole round of inversions moves every note an octave
+    let len = notes.len() as i64;
+    let octaves = inversion.div_euclid(len).saturating_mul(12);
+    let steps = inversion.rem_euclid(len) as usize;
+    notes.rotate_left(steps);
+    let wrapped = notes.len() - steps;
+    for (i, note) in notes.iter_mut().enumerate() {
+        let shift = if i < wrapped { octaves } else { octaves.saturating_add(12) };
+        *note = note.saturating_add(shift);
+    }
+    notes.sort();
+    notes
+}
+
+/// Note with the pitch class of `note` that is the closest to `anchor`, the lower one on ties.
+fn closest_octave(note: i64, anchor: i64) -> i64 {
+    let below = anchor.saturating_sub(anchor.saturating_sub(note).rem_euclid(12));
+    if anchor.abs_diff(below) <= 6 { below } else { below.saturating_add(12) }
+}
+
+/// Voices the chord `to` so that it moves as little as possible from the chord `from`.
+///
+/// Every inversion of `to` is tried, each of its notes being placed in the octave closest
+/// to the corresponding voice of `from`, and the one with the smallest total motion is kept.
+/// The result is sorted.
+pub fn voice_lead(from: &[i64], to: &[i64]) -> Vec<i64> {
+    let mut from = from.to_vec();
+    from.sort();
+    let mut to = to.to_vec();
+    to.sort();
+    if from.is_empty() || to.is_empty() {
+        return to;
+    }
+    let mut best: Option<(u64, Vec<i64>)> = None;
+    for rotation in 0..to.len() {
+        let mut voicing = Vec::with_capacity(to.len());
+        let mut motion = 0u64;
+        for (i, note) in to.iter().cycle().skip(rotation).take(to.len()).enumerate() {
+            let anchor = from[i.min(from.len() - 1)];
+            let placed = closest_octave(*note, anchor);
+            motion = motion.saturating_add(placed.abs_diff(anchor));
+            voicing.push(placed);
+        }
+        if best.as_ref().is_none_or(|(best_motion, _)| motion < *best_motion) {
+            best = Some((motion, voicing));
+        }
+    }
+    let mut voicing = best.map(|(_, v)| v).unwrap_or_default();
+    voicing.sort();
+    voicing
+}
+
+/// Order in which an arpeggio plays the notes of a chord.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arpeggio {
+    #[default]
+    Up,
+    Down,
+    /// Up then down, the highest and lowest notes being played once
+    UpDown,
+    DownUp,
+    /// Alternately the lowest and highest remaining notes
+    Converge,
+    /// From the middle notes outwards
+    Diverge,
+}
+
+impl Arpeggio {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let arpeggio = match name {
+            "up" => Arpeggio::Up,
+            "down" => Arpeggio::Down,
+            "updown" | "up_down" | "up-down" => Arpeggio::UpDown,
+            "downup" | "down_up" | "down-up" => Arpeggio::DownUp,
+            "converge" => Arpeggio::Converge,
+            "diverge" => Arpeggio::Diverge,
+            _ => return None,
+        };
+        Some(arpeggio)
+    }
+
+    /// Notes of the arpeggio of a chord spread over the given number of octaves
+    /// (at most `MAX_ARPEGGIO_OCTAVES`).
+    pub fn notes(&self, chord: &[i64], octaves: usize) -> Vec<i64> {
+        let mut up: Vec<i64> = (0..octaves.clamp(1, MAX_ARPEGGIO_OCTAVES) as i64)
+            .flat_map(|octave| chord.iter().map(move |note| note.saturating_add(12 * octave)))
+            .collect();
+        up.sort();
+        up.dedup();
+        let down: Vec<i64> = up.iter().rev().copied().collect();
+        let inner = |notes: &[i64]| -> Vec<i64> {
+            notes
+                .get(1..notes.len().saturating_sub(1))
+                .map(|n| n.to_vec())
+                .unwrap_or_default()
+        };
+        match self {
+            Arpeggio::Up => up,
+            Arpeggio::Down => down,
+            Arpeggio::UpDown => [up.clone(), inner(&down)].concat(),
+            Arpeggio::DownUp => [down.clone(), inner(&up)].concat(),
+            Arpeggio::Converge | Arpeggio::Diverge => {
+                let mut converge = Vec::with_capacity(up.len());
+                let (mut low, mut high) = (0, up.len());
+                while low < high {
+                    converge.push(up[low]);
+                    low += 1;
+                    if low < high {
+                        high -= 1;
+                        converge.push(up[high]);
+                    }
+                }
+                if *self == Arpeggio::Diverge {
+                    converge.reverse();
+                }
+                converge
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantize_to_nearest_degree() {
+        // C major: C# goes down to C, F# goes down to F
+        assert_eq!(Scale::Major.quantize(61, 0), 60);
+        assert_eq!(Scale::Major.quantize(66, 0), 65);
+        assert_eq!(Scale::Major.quantize(64, 0), 64);
+        // A minor pentatonic: B goes up to C, G# is between G and A
+        assert_eq!(Scale::MinorPentatonic.quantize(71, 9), 72);
+        assert_eq!(Scale::MinorPentatonic.quantize(68, 9), 67);
+        // Around the octave boundary, B major 11 is kept
+        assert_eq!(Scale::Major.quantize(71, 0), 71);
+        assert_eq!(Scale::Major.quantize(-1, 0), -1);
+        // Extreme notes saturate instead of overflowing
+        assert_eq!(Scale::Major.quantize(i64::MAX, 0), i64::MAX);
+        assert_eq!(Scale::Major.quantize(i64::MIN, 0), i64::MIN);
+    }
+
+    #[test]
+    fn degrees_and_chords() {
+        assert_eq!(Scale::Major.degree(0, 60), 60);
+        assert_eq!(Scale::Major.degree(7, 60), 72);
+        assert_eq!(Scale::Minor.degree(-1, 60), 58);
+        assert_eq!(Scale::Major.degree_chord(1, 60, 3), vec![62, 65, 69]);
+        assert_eq!(Chord::from_name("m7").unwrap().notes(57), vec![57, 60, 64, 67]);
+        assert_eq!(invert(&[60, 64, 67], 1), vec![64, 67, 72]);
+        assert_eq!(invert(&[60, 64, 67], -1), vec![55, 60, 64]);
+        assert_eq!(invert(&[60, 64, 67], 4), vec![76, 79, 84]);
+        assert_eq!(invert(&[60, 64, 67], -3), vec![48, 52, 55]);
+    }
+
+    #[test]
+    fn sizes_are_bounded() {
+        // Large arguments are computed at once instead of looping
+        assert_eq!(invert(&[60, 64, 67], i64::MAX).len(), 3);
+        assert_eq!(invert(&[60, 64, 67], i64::MIN).len(), 3);
+        assert_eq!(Scale::Major.degree_chord(0, 60, usize::MAX).len(), MAX_CHORD_VOICES);
+        assert_eq!(Arpeggio::Up.notes(&[60], usize::MAX).len(), MAX_ARPEGGIO_OCTAVES);
+    }
+
+    #[test]
+    fn extreme_degrees_saturate() {
+        assert_eq!(Scale::Major.degree(i64::MAX, 60), i64::MAX);
+        assert!(Scale::Major.degree(i64::MIN, 60) < i64::MIN / 2);
+        assert_eq!(Scale::Major.degree(0, i64::MAX), i64::MAX);
+        assert_eq!(Scale::Major.degree_chord(i64::MAX, 60, 3), vec![i64::MAX; 3]);
+        assert_eq!(Scale::Major.degree_chord(i64::MIN, 60, 3).len(), 3);
+        assert_eq!(Chord::Major.notes(i64::MAX), vec![i64::MAX; 3]);
+        assert_eq!(voice_lead(&[i64::MIN, 0, i64::MAX], &[i64::MAX, i64::MIN]).len(), 2);
+        assert_eq!(Arpeggio::Up.notes(&[i64::MAX], 2), vec![i64::MAX]);
+    }
+
+    #[test]
+    fn voice_leading_minimizes_motion() {
+        // C major to F major: C is kept, E goes to F and G to A
+        assert_eq!(voice_lead(&[60, 64, 67], &[65, 69, 72]), vec![60, 65, 69]);
+        assert_eq!(voice_lead(&[60, 64, 67], &[67, 71, 74]), vec![59, 62, 67]);
+    }
+
+    #[test]
+    fn arpeggios() {
+        let chord = [60, 64, 67];
+        assert_eq!(Arpeggio::UpDown.notes(&chord, 1), vec![60, 64, 67, 64]);
+        assert_eq!(Arpeggio::Down.notes(&chord, 2), vec![79, 76, 72, 67, 64, 60]);
+        assert_eq!(Arpeggio::Converge.notes(&[60, 64, 67, 71], 1), vec![60, 71, 64, 67]);
+        assert_eq!(Arpeggio::Diverge.notes(&[60, 64, 67, 71], 1), vec![67, 64, 71, 60]);
+    }
+}
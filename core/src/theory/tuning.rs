@@ -0,0 +1,292 @@
+//! Microtonal tunings, loaded from Scala scale (`.scl`) and keyboard mapping (`.kbm`) files.
+//!
+//! A tuning gives a frequency to each note number (key). Scripts keep playing keys, and the
+//! devices realize the tuning according to their `TuningOutput`.
+
+use serde::{Deserialize, Serialize};
+
+/// Frequency of the A4 MIDI note (69), reference of the 12-TET note numbers.
+const A4_FREQUENCY: f64 = 440.0;
+const A4_NOTE: f64 = 69.0;
+/// Largest keyboard mapping pattern, one entry per MIDI key.
+const MAX_MAP_SIZE: usize = 128;
+
+/// How a device realizes the tuning of the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TuningOutput {
+    /// Notes are sent untuned
+    Off,
+    /// MIDI: nearest note and a pitch bend. Without MPE, the bend applies to the whole channel,
+    /// so this is meant for monophonic channels.
+    PitchBend,
+    /// MIDI: the key is retuned with a MIDI Tuning Standard single note tuning change
+    Mts,
+    /// OSC: the `note` argument is replaced by a `freq` argument, in Hz
+    Frequency,
+    /// OSC: the `note` argument becomes a fractional note number
+    Note,
+}
+
+/// Placement of a scale on the keys, as in a Scala `.kbm` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyboardMapping {
+    /// Number of keys of the repeating pattern, 0 for a linear mapping (each key is the next degree)
+    pub size: usize,
+    pub first_key: i64,
+    pub last_key: i64,
+    /// Key of the first degree of the scale
+    pub middle_key: i64,
+    pub reference_key: i64,
+    /// Frequency of the reference key, in Hz
+    pub reference_frequency: f64,
+    /// Degree of the formal octave, by which the pattern repeats (0 for the period of the scale)
+    pub octave_degree: usize,
+    /// Degree of each key of the pattern, None for unmapped keys
+    pub keys: Vec<Option<usize>>,
+}
+
+impl Default for KeyboardMapping {
+    fn default() -> Self {
+        KeyboardMapping {
+            size: 0,
+            first_key: 0,
+            last_key: 127,
+            middle_key: 60,
+            reference_key: A4_NOTE as i64,
+            reference_frequency: A4_FREQUENCY,
+            octave_degree: 0,
+            keys: Vec::new(),
+        }
+    }
+}
+
+/// Lines of a Scala file, without comments.
+fn scala_lines(source: &str) -> impl Iterator<Item = &str> {
+    source.lines().filter(|line| !line.starts_with('!'))
+}
+
+fn parse_number<T: std::str::FromStr>(line: Option<&str>, what: &str) -> Result<T, String> {
+    let line = line.ok_or_else(|| format!("Missing {what}"))?;
+    let token = line.split_whitespace().next().unwrap_or_default();
+    token
+        .parse()
+        .map_err(|_| format!("Invalid {what} : '{}'", line.trim()))
+}
+
+impl KeyboardMapping {
+    /// Parses the content of a Scala `.kbm` file.
+    pub fn parse(kbm: &str) -> Result<Self, String> {
+        let mut lines = scala_lines(kbm);
+        let size: usize = parse_number(lines.next(), "map size")?;
+        if size > MAX_MAP_SIZE {
+            return Err(format!("Map size {size} exceeds {MAX_MAP_SIZE} keys"));
+        }
+        let mut mapping = KeyboardMapping {
+            size,
+            first_key: parse_number(lines.next(), "first key")?,
+            last_key: parse_number(lines.next(), "last key")?,
+            middle_key: parse_number(lines.next(), "middle key")?,
+            reference_key: parse_number(lines.next(), "reference key")?,
+            reference_frequency: parse_number(lines.next(), "reference frequency")?,
+            octave_degree: parse_number(lines.next(), "octave degree")?,
+            keys: Vec::new(),
+        };
+        // Missing entries at the end of the pattern are unmapped
+        for line in lines.take(size) {
+            let token = line.split_whitespace().next().unwrap_or("x");
+            let degree = match token {
+                "x" | "X" => None,
+                token => Some(
+                    token
+                        .parse()
+                        .map_err(|_| format!("Invalid key mapping : '{}'", line.trim()))?,
+                ),
+            };
+            mapping.keys.push(degree);
+        }
+        mapping.keys.resize(size, None);
+        if mapping.reference_frequency <= 0.0 {
+            return Err("Reference frequency must be positive".to_owned());
+        }
+        Ok(mapping)
+    }
+}
+
+/// A scale of arbitrary pitches, and its placement on the keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tuning {
+    #[serde(default)]
+    pub description: String,
+    /// Pitches of the degrees following the root, in cents. The last one is the period
+    /// of the scale (1200 for octave-repeating scales).
+    pub pitches: Vec<f64>,
+    #[serde(default)]
+    pub mapping: KeyboardMapping,
+}
+
+impl Tuning {
+    /// Builds a tuning from the content of a Scala `.scl` file and, optionally, of a `.kbm` file.
+    /// Without mapping, the first degree is on C4 (60) and A4 (69) is at 440 Hz.
+    pub fn from_scala(scl: &str, kbm: Option<&str>) -> Result<Self, String> {
+        let mut lines = scala_lines(scl);
+        let description = lines.next().unwrap_or_default().trim().to_owned();
+        let count: usize = parse_number(lines.next(), "number of notes")?;
+        let pitches = lines
+            .take(count)
+            .map(Self::parse_pitch)
+            .collect::<Result<Vec<f64>, String>>()?;
+        if pitches.len() != count || count == 0 {
+            return Err(format!(
+                "Expected {count} pitches, found {}",
+                pitches.len()
+            ));
+        }
+        let mapping = match kbm {
+            Some(kbm) => KeyboardMapping::parse(kbm)?,
+            None => KeyboardMapping::default(),
+        };
+        Ok(Tuning {
+            description,
+            pitches,
+            mapping,
+        })
+    }
+
+    /// Parses a Scala pitch: cents if it contains a period, a ratio (`3/2`) or an integer otherwise.
+    fn parse_pitch(line: &str) -> Result<f64, String> {
+        let token = line.split_whitespace().next().unwrap_or_default();
+        let invalid = || format!("Invalid pitch : '{}'", line.trim());
+        if token.contains('.') {
+            return token.parse().map_err(|_| invalid());
+        }
+        let (num, den) = token.split_once('/').unwrap_or((token, "1"));
+        let num: f64 = num.parse().map_err(|_| invalid())?;
+        let den: f64 = den.parse().map_err(|_| invalid())?;
+        if num <= 0.0 || den <= 0.0 {
+            return Err(invalid());
+        }
+        Ok(1200.0 * (num / den).log2())
+    }
+
+    /// Cents of any degree of the scale above its root.
+    fn degree_cents(&self, degree: i64) -> f64 {
+        let Some(period) = self.pitches.last() else {
+            return 0.0;
+        };
+        let len = self.pitches.len() as i64;
+        let pitch = match degree.rem_euclid(len) {
+            0 => 0.0,
+            i => self.pitches[i as usize - 1],
+        };
+        degree.div_euclid(len) as f64 * period + pitch
+    }
+
+    /// Cents of a key above the first degree, None if the key is not mapped.
+    fn key_cents(&self, key: i64) -> Option<f64> {
+        let mapping = &self.mapping;
+        let steps = key - mapping.middle_key;
+        if mapping.size == 0 {
+            return Some(self.degree_cents(steps));
+        }
+        let size = mapping.size as i64;
+        let degree = mapping
+            .keys
+            .get(steps.rem_euclid(size) as usize)
+            .copied()
+            .flatten()?;
+        let octave = match mapping.octave_degree {
+            0 => self.pitches.last().copied().unwrap_or(1200.0),
+            degree => self.degree_cents(degree as i64),
+        };
+        Some(steps.div_euclid(size) as f64 * octave + self.degree_cents(degree as i64))
+    }
+
+    /// Frequency of a key in Hz, None if the key is outside the mapping or not mapped.
+    pub fn frequency(&self, key: i64) -> Option<f64> {
+        let mapping = &self.mapping;
+        if key < mapping.first_key || key > mapping.last_key {
+            return None;
+        }
+        let cents = self.key_cents(key)?;
+        // An unmapped reference key still gives its frequency to its place in the pattern
+        let reference = self
+            .key_cents(mapping.reference_key)
+            .unwrap_or_else(|| self.degree_cents(mapping.reference_key - mapping.middle_key));
+        Some(mapping.reference_frequency * 2f64.powf((cents - reference) / 1200.0))
+    }
+
+    /// Fractional 12-TET note number sounding at the frequency of a key.
+    pub fn note(&self, key: i64) -> Option<f64> {
+        self.frequency(key)
+            .map(|freq| A4_NOTE + 12.0 * (freq / A4_FREQUENCY).log2())
+    }
+
+    /// Nearest MIDI note of a key, and the 14-bit pitch bend reaching its frequency
+    /// with the given bend range (in semitones).
+    pub fn bend(&self, key: i64, range: f64) -> Option<(u8, u16)> {
+        let note = self.note(key)?;
+        let nearest = note.round().clamp(0.0, 127.0);
+        let bend = 8192.0 + (note - nearest) / range.max(f64::EPSILON) * 8192.0;
+        Some((nearest as u8, bend.round().clamp(0.0, 16383.0) as u16))
+    }
+
+    /// Data of the MIDI Tuning Standard real-time single note tuning change of a key
+    /// (without the SysEx start and end bytes).
+    pub fn mts_data(&self, key: i64) -> Option<Vec<u8>> {
+        if !(0..128).contains(&key) {
+            return None;
+        }
+        let note = self.note(key)?.clamp(0.0, 127.0 + 16383.0 / 16384.0);
+        let semitone = note.floor();
+        let fraction = ((note - semitone) * 16384.0).round().min(16383.0) as u16;
+        Some(vec![
+            0x7F, // Real-time universal SysEx
+            0x7F, // All devices
+            0x08, // MIDI Tuning Standard
+            0x02, // Single note tuning change
+            0x00, // Tuning program
+            0x01, // Number of changes
+            key as u8,
+            semitone as u8,
+            (fraction >> 7) as u8,
+            (fraction & 0x7F) as u8,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUST: &str = "! just.scl\n!\nJust major\n 7\n!\n9/8\n5/4\n4/3\n3/2\n5/3\n15/8\n2/1\n";
+
+    #[test]
+    fn equal_temperament_matches_midi() {
+        let scl = format!(
+            "12-TET\n12\n{}",
+            (1..=12).map(|i| format!("{}.0\n", i * 100)).collect::<String>()
+        );
+        let tuning = Tuning::from_scala(&scl, None).unwrap();
+        assert!((tuning.frequency(69).unwrap() - 440.0).abs() < 1e-9);
+        assert!((tuning.note(61).unwrap() - 61.0).abs() < 1e-9);
+        assert_eq!(tuning.bend(64, 2.0), Some((64, 8192)));
+    }
+
+    #[test]
+    fn just_intonation_with_mapping() {
+        // White keys only, the first degree on C4 at 261.63 Hz
+        let kbm = "12\n0\n127\n60\n60\n261.63\n7\n0\n x\n1\nx\n2\n3\nx\n4\nx\n5\nx\n6\n";
+        let tuning = Tuning::from_scala(JUST, Some(kbm)).unwrap();
+        assert!((tuning.frequency(67).unwrap() - 261.63 * 1.5).abs() < 1e-9);
+        assert!((tuning.frequency(72).unwrap() - 261.63 * 2.0).abs() < 1e-9);
+        assert_eq!(tuning.frequency(61), None);
+        // A just fifth is 2 cents above the tempered one
+        let (note, bend) = tuning.bend(67, 2.0).unwrap();
+        assert_eq!(note, 67);
+        assert!(bend > 8192 && bend < 8192 + 100);
+        assert!(Tuning::from_scala("bad\n2\n3/2\n", None).is_err());
+        let huge = "18446744073709551615\n0\n127\n60\n60\n440\n0\n";
+        assert!(KeyboardMapping::parse(huge).is_err());
+    }
+}
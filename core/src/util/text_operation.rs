@@ -0,0 +1,289 @@
+//! Edits of texts, as operations that can be transformed against concurrent ones
+//! (operational transformation), so that every copy of a text converges.
+//!
+//! Positions and lengths are counted in characters.
+
+use serde::{Deserialize, Serialize};
+
+/// A step of a `TextOperation`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEdit {
+    /// Keeps the next characters
+    Retain(usize),
+    /// Inserts text at the current position
+    Insert(String),
+    /// Removes the next characters
+    Delete(usize),
+}
+
+/// An edit of a whole text: its steps must cover every character of the text.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextOperation {
+    pub edits: Vec<TextEdit>,
+}
+
+/// A cursor or a selection in a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextSelection {
+    pub anchor: usize,
+    pub head: usize,
+}
+
+impl TextOperation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn retain(mut self, n: usize) -> Self {
+        if n == 0 {
+            return self;
+        }
+        match self.edits.last_mut() {
+            Some(TextEdit::Retain(m)) => *m = m.saturating_add(n),
+            _ => self.edits.push(TextEdit::Retain(n)),
+        }
+        self
+    }
+
+    pub fn insert(mut self, text: &str) -> Self {
+        if text.is_empty() {
+            return self;
+        }
+        // Inserts are kept before deletes, so that equivalent operations are equal
+        let len = self.edits.len();
+        match self.edits.as_mut_slice() {
+            [.., TextEdit::Insert(s)] => s.push_str(text),
+            [.., TextEdit::Insert(s), TextEdit::Delete(_)] => s.push_str(text),
+            [.., TextEdit::Delete(_)] => self
+                .edits
+                .insert(len - 1, TextEdit::Insert(text.to_owned())),
+            _ => self.edits.push(TextEdit::Insert(text.to_owned())),
+        }
+        self
+    }
+
+    pub fn delete(mut self, n: usize) -> Self {
+        if n == 0 {
+            return self;
+        }
+        match self.edits.last_mut() {
+            Some(TextEdit::Delete(m)) => *m = m.saturating_add(n),
+            _ => self.edits.push(TextEdit::Delete(n)),
+        }
+        self
+    }
+
+    /// Length of the texts the operation applies to, None if it does not fit in a `usize`
+    /// (operations may come from clients).
+    pub fn base_len(&self) -> Option<usize> {
+        self.edits.iter().try_fold(0usize, |len, edit| match edit {
+            TextEdit::Retain(n) | TextEdit::Delete(n) => len.checked_add(*n),
+            TextEdit::Insert(_) => Some(len),
+        })
+    }
+
+    /// Length of the texts the operation produces, None if it does not fit in a `usize`.
+    pub fn target_len(&self) -> Option<usize> {
+        self.edits.iter().try_fold(0usize, |len, edit| match edit {
+            TextEdit::Retain(n) => len.checked_add(*n),
+            TextEdit::Insert(s) => len.checked_add(s.chars().count()),
+            TextEdit::Delete(_) => Some(len),
+        })
+    }
+
+    /// Length of the texts the operation applies to, or an error if it overflows.
+    fn checked_base_len(&self) -> Result<usize, String> {
+        self.base_len()
+            .ok_or_else(|| "Operation on more characters than a text can hold".to_owned())
+    }
+
+    /// Whether the operation leaves texts unchanged.
+    pub fn is_noop(&self) -> bool {
+        self.edits
+            .iter()
+            .all(|edit| matches!(edit, TextEdit::Retain(_)))
+    }
+
+    pub fn apply(&self, text: &str) -> Result<String, String> {
+        let len = text.chars().count();
+        let base_len = self.checked_base_len()?;
+        if base_len != len {
+            return Err(format!(
+                "Operation on {} characters applied to a text of {}",
+                base_len, len
+            ));
+        }
+        let mut chars = text.chars();
+        let mut result = String::with_capacity(text.len());
+        for edit in &self.edits {
+            match edit {
+                TextEdit::Retain(n) => result.extend(chars.by_ref().take(*n)),
+                TextEdit::Insert(s) => result.push_str(s),
+                TextEdit::Delete(n) => {
+                    chars.by_ref().take(*n).for_each(drop);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Transforms two concurrent operations on the same text into `(a', b')`, such that
+    /// applying `a` then `b'` gives the same text as `b` then `a'`.
+    /// Text inserted at the same position by `a` comes first.
+    pub fn transform(
+        a: &TextOperation,
+        b: &TextOperation,
+    ) -> Result<(TextOperation, TextOperation), String> {
+        if a.checked_base_len()? != b.checked_base_len()? {
+            return Err("Concurrent operations on texts of different lengths".to_owned());
+        }
+        let (mut a_prime, mut b_prime) = (TextOperation::new(), TextOperation::new());
+        let mut a_edits = a.edits.iter().cloned();
+        let mut b_edits = b.edits.iter().cloned();
+        let (mut edit_a, mut edit_b) = (a_edits.next(), b_edits.next());
+        loop {
+            match (edit_a.take(), edit_b.take()) {
+                (None, None) => break,
+                (Some(TextEdit::Insert(s)), other) => {
+                    b_prime = b_prime.retain(s.chars().count());
+                    a_prime = a_prime.insert(&s);
+                    edit_a = a_edits.next();
+                    edit_b = other;
+                }
+                (other, Some(TextEdit::Insert(s))) => {
+                    a_prime = a_prime.retain(s.chars().count());
+                    b_prime = b_prime.insert(&s);
+                    edit_a = other;
+                    edit_b = b_edits.next();
+                }
+                (Some(x), Some(y)) => {
+                    let (n, m) = match (&x, &y) {
+                        (
+                            TextEdit::Retain(n) | TextEdit::Delete(n),
+                            TextEdit::Retain(m) | TextEdit::Delete(m),
+                        ) => (*n, *m),
+                        _ => unreachable!("inserts are handled above"),
+                    };
+                    let min = n.min(m);
+                    match (&x, &y) {
+                        (TextEdit::Retain(_), TextEdit::Retain(_)) => {
+                            a_prime = a_prime.retain(min);
+                            b_prime = b_prime.retain(min);
+                        }
+                        (TextEdit::Delete(_), TextEdit::Retain(_)) => a_prime = a_prime.delete(min),
+                        (TextEdit::Retain(_), TextEdit::Delete(_)) => b_prime = b_prime.delete(min),
+                        // Both deleted the same characters
+                        _ => (),
+                    }
+                    edit_a = shorten(x, min).or_else(|| a_edits.next());
+                    edit_b = shorten(y, min).or_else(|| b_edits.next());
+                }
+                _ => return Err("Concurrent operations on texts of different lengths".to_owned()),
+            }
+        }
+        Ok((a_prime, b_prime))
+    }
+
+    /// Position of a character index after the operation.
+    pub fn transform_index(&self, index: usize) -> usize {
+        let (mut position, mut new_index) = (0, index);
+        for edit in &self.edits {
+            if position > index {
+                break;
+            }
+            match edit {
+                TextEdit::Retain(n) => position = position.saturating_add(*n),
+                TextEdit::Insert(s) => new_index = new_index.saturating_add(s.chars().count()),
+                TextEdit::Delete(n) => {
+                    new_index -= (index - position).min(*n);
+                    position = position.saturating_add(*n);
+                }
+            }
+        }
+        new_index
+    }
+}
+
+impl TextSelection {
+    pub fn transform(&self, op: &TextOperation) -> Self {
+        TextSelection {
+            anchor: op.transform_index(self.anchor),
+            head: op.transform_index(self.head),
+        }
+    }
+}
+
+/// The rest of a retain or a delete, once `n` characters are consumed.
+fn shorten(edit: TextEdit, n: usize) -> Option<TextEdit> {
+    match edit {
+        TextEdit::Retain(m) if m > n => Some(TextEdit::Retain(m - n)),
+        TextEdit::Delete(m) if m > n => Some(TextEdit::Delete(m - n)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_edits_converge() {
+        let text = "d1 $ s \"bd\"";
+        // One inserts at the start, the other replaces "bd" by "sn"
+        let a = TextOperation::new().insert("-- ").retain(11);
+        let b = TextOperation::new()
+            .retain(8)
+            .delete(2)
+            .insert("sn")
+            .retain(1);
+        let (a_prime, b_prime) = TextOperation::transform(&a, &b).unwrap();
+        let ab = b_prime.apply(&a.apply(text).unwrap()).unwrap();
+        let ba = a_prime.apply(&b.apply(text).unwrap()).unwrap();
+        assert_eq!(ab, "-- d1 $ s \"sn\"");
+        assert_eq!(ab, ba);
+        assert_eq!(b.transform_index(10), 10);
+        assert_eq!(a.transform_index(0), 3);
+    }
+
+    #[test]
+    fn overlapping_deletes_converge() {
+        let text = "abcdef";
+        let a = TextOperation::new().retain(1).delete(3).retain(2);
+        let b = TextOperation::new().retain(2).delete(3).insert("é").retain(1);
+        let (a_prime, b_prime) = TextOperation::transform(&a, &b).unwrap();
+        let ab = b_prime.apply(&a.apply(text).unwrap()).unwrap();
+        let ba = a_prime.apply(&b.apply(text).unwrap()).unwrap();
+        assert_eq!(ab, "aéf");
+        assert_eq!(ab, ba);
+        assert!(TextOperation::transform(&a, &TextOperation::new().retain(2)).is_err());
+        assert!(a.apply("abc").is_err());
+        assert_eq!((b.base_len(), b.target_len()), (Some(6), Some(4)));
+    }
+
+    #[test]
+    fn overflowing_lengths_are_rejected() {
+        let op = TextOperation {
+            edits: vec![TextEdit::Retain(usize::MAX), TextEdit::Retain(2)],
+        };
+        assert_eq!(op.base_len(), None);
+        assert_eq!(op.target_len(), None);
+        assert!(op.apply("a").is_err());
+        assert!(TextOperation::transform(&op, &TextOperation::new().retain(1)).is_err());
+        assert!(TextOperation::transform(&op, &op).is_err());
+
+        let op = TextOperation {
+            edits: vec![TextEdit::Delete(usize::MAX), TextEdit::Delete(usize::MAX)],
+        };
+        assert!(op.apply("").is_err());
+        assert_eq!(TextOperation::new().retain(usize::MAX).retain(2).base_len(), Some(usize::MAX));
+    }
+
+    #[test]
+    fn selections_follow_edits() {
+        let op = TextOperation::new().retain(1).delete(2).insert("xyz").retain(3);
+        let selection = TextSelection { anchor: 0, head: 2 };
+        assert_eq!(selection.transform(&op), TextSelection { anchor: 0, head: 4 });
+        assert_eq!(op.transform_index(5), 6);
+        assert!(TextOperation::new().retain(3).is_noop());
+    }
+}
@@ -14,6 +14,8 @@ pub mod event;
 pub mod interpreter;
 /// Module defining the variable types and values used in the language.
 pub mod variable;
+/// Module defining the seedable random generators used by scripts.
+pub mod random;
 
 mod environment_func;
 pub use environment_func::*;
@@ -28,6 +30,9 @@ pub use language_center::LanguageCenter;
 mod evaluation_context;
 pub use evaluation_context::*;
 
+mod runtime_fault;
+pub use runtime_fault::*;
+
 /// Represents a single instruction in a program's execution flow.
 ///
 /// An instruction is the fundamental unit of execution. Programs are sequences of these instructions.
@@ -51,6 +56,75 @@ pub enum Instruction {
     Effect(Event, Variable),
 }
 
+/// Byte range of a script source text, as in [`crate::compiler::CompilationError`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceSpan {
+    pub from: usize,
+    pub to: usize,
+}
+
+impl SourceSpan {
+    pub fn new(from: usize, to: usize) -> Self {
+        SourceSpan { from, to }
+    }
+}
+
+/// Line and column of a script source text, both counted from 1 (columns in characters).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Source spans of the instructions of a program, indexed by instruction position,
+/// with the position where each span starts in the text.
+///
+/// Compilers which do not keep track of spans produce an empty map.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap(Vec<Option<(SourceSpan, SourcePosition)>>);
+
+impl SourceMap {
+    /// Maps the instructions to the given spans of `text`.
+    pub fn new(text: &str, spans: Vec<Option<SourceSpan>>) -> Self {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let locate = |offset: usize| {
+            let offset = offset.min(text.len());
+            let line = line_starts.partition_point(|start| *start <= offset) - 1;
+            let column = text
+                .get(line_starts[line]..offset)
+                .map_or(0, |prefix| prefix.chars().count());
+            SourcePosition {
+                line: line + 1,
+                column: column + 1,
+            }
+        };
+        SourceMap(
+            spans
+                .into_iter()
+                .map(|span| span.map(|span| (span, locate(span.from))))
+                .collect(),
+        )
+    }
+
+    /// Span of the source text the instruction at `position` was compiled from, if known.
+    pub fn span(&self, position: usize) -> Option<SourceSpan> {
+        self.0.get(position).copied().flatten().map(|(span, _)| span)
+    }
+
+    /// Where the source text of the instruction at `position` starts, if known.
+    pub fn position(&self, position: usize) -> Option<SourcePosition> {
+        self.0.get(position).copied().flatten().map(|(_, at)| at)
+    }
+}
+
 impl Instruction {
     /// Returns `true` if the instruction is a control flow instruction.
     pub fn is_control(&self) -> bool {
@@ -1,6 +1,6 @@
 use super::{
     Instruction, Program,
-    EvaluationContext,
+    EvaluationContext, FaultReporter,
     variable::{Variable, VariableValue},
 };
 use serde::{Deserialize, Serialize};
@@ -11,15 +11,10 @@ use crate::scene::script::ReturnInfo;
 use crate::log_eprintln;
 
 use std::collections::HashMap;
-use std::f64::consts::PI;
 
-// Import state keys
-use crate::vm::environment_func::{
-    ISAW_LAST_BEAT_KEY, ISAW_PHASE_KEY, RANDSTEP_LAST_BEAT_KEY, RANDSTEP_PHASE_KEY,
-    RANDSTEP_VALUE_KEY, SAW_LAST_BEAT_KEY, SAW_PHASE_KEY, SINE_LAST_BEAT_KEY, SINE_PHASE_KEY,
-    TRI_LAST_BEAT_KEY, TRI_PHASE_KEY,
-};
+use crate::scene::modulator::{Modulator, ModulatorShape};
 use crate::protocol::ProtocolDevice;
+use crate::theory::{self, Arpeggio, Chord, Scale};
 
 pub const DEFAULT_DEVICE : i64 = 1;
 pub const DEFAULT_CHAN : i64 = 1;
@@ -53,6 +48,15 @@ pub enum ControlASM {
     Min(Variable, Variable, Variable),
     Max(Variable, Variable, Variable),
     Quantize(Variable, Variable, Variable),
+    // Music theory. Scales and chords are given by name or as a vec of semitones,
+    // chords and results are vecs of notes.
+    ScaleDegree(Variable, Variable, Variable, Variable), // scale, root note, degree, dest
+    ScaleSnap(Variable, Variable, Variable, Variable),   // scale, root note, note, dest
+    DegreeChord(Variable, Variable, Variable, Variable, Variable), // scale, root note, degree, voices, dest
+    Chord(Variable, Variable, Variable),                 // chord, root note, dest
+    Invert(Variable, Variable, Variable),                // chord, inversion, dest
+    VoiceLead(Variable, Variable, Variable),             // previous chord, next chord, dest
+    Arpeggiate(Variable, Variable, Variable, Variable),  // chord, arpeggio, octaves, dest
     // Bitwise operations
     BitAnd(Variable, Variable, Variable),
     BitNot(Variable, Variable),
@@ -76,6 +80,8 @@ pub enum ControlASM {
     Pop(Variable),
     PushFront(Variable),
     PopFront(Variable),
+    // Randomness
+    Seed(Variable), // Restarts the random stream of the current frame with the given seed
     // Map operations
     MapInsert(Variable, Variable, Variable, Variable),
     MapGet(Variable, Variable, Variable),
@@ -132,12 +138,82 @@ impl ControlASM {
         }
     }
 
+    /// Scale given by name, or as a vec of semitones from the root.
+    fn evaluate_scale(ctx: &mut EvaluationContext, var: &Variable, faults: &mut FaultReporter) -> Scale {
+        match ctx.evaluate(var) {
+            VariableValue::Vec(intervals) => Scale::Custom(
+                intervals
+                    .iter()
+                    .map(|i| i.as_integer(ctx.clock, ctx.frame_len).rem_euclid(12) as u8)
+                    .collect(),
+            ),
+            value => {
+                let name = value.as_str(ctx.clock, ctx.frame_len);
+                Scale::from_name(&name).unwrap_or_else(|| {
+                    faults.report(|| format!("Unknown scale '{}', using major", name));
+                    Scale::Major
+                })
+            }
+        }
+    }
+
+    /// Chord given by name, or as a vec of semitones from the root.
+    fn evaluate_chord(ctx: &mut EvaluationContext, var: &Variable, faults: &mut FaultReporter) -> Chord {
+        match ctx.evaluate(var) {
+            VariableValue::Vec(intervals) => Chord::Custom(
+                intervals
+                    .iter()
+                    .map(|i| i.as_integer(ctx.clock, ctx.frame_len).clamp(0, 127) as u8)
+                    .collect(),
+            ),
+            value => {
+                let name = value.as_str(ctx.clock, ctx.frame_len);
+                Chord::from_name(&name).unwrap_or_else(|| {
+                    faults.report(|| format!("Unknown chord '{}', using major", name));
+                    Chord::Major
+                })
+            }
+        }
+    }
+
+    /// Notes of a vec, a single value being a chord of one note.
+    fn evaluate_notes(ctx: &mut EvaluationContext, var: &Variable) -> Vec<i64> {
+        match ctx.evaluate(var) {
+            VariableValue::Vec(notes) => notes
+                .iter()
+                .map(|n| n.as_integer(ctx.clock, ctx.frame_len))
+                .collect(),
+            value => vec![value.as_integer(ctx.clock, ctx.frame_len)],
+        }
+    }
+
+    fn notes_value(notes: Vec<i64>) -> VariableValue {
+        VariableValue::Vec(notes.into_iter().map(VariableValue::Integer).collect())
+    }
+
+    /// Evaluates an oscillator doing `speed` cycles per beat, at the logical date of the context.
+    fn oscillate(
+        &self,
+        ctx: &mut EvaluationContext,
+        speed_var: &Variable,
+        shape: ModulatorShape,
+        dest_var: &Variable,
+    ) -> ReturnInfo {
+        let speed = ctx.evaluate(speed_var).as_float(ctx.clock, ctx.frame_len);
+        let beat = ctx.clock.beat_at_date(ctx.logic_date);
+        let oscillator = Modulator::new(shape, 1.0 / speed).with_range(1.0, 127.0);
+        let value = oscillator.value_at(beat).round() as i64;
+        ctx.set_var(dest_var, VariableValue::Integer(value));
+        ReturnInfo::None
+    }
+
     pub fn execute(
         &self,
         ctx: &mut EvaluationContext,
         return_stack: &mut Vec<ReturnInfo>,
         instruction_position: usize,
         current_prog: &Program,
+        faults: &mut FaultReporter,
     ) -> ReturnInfo {
         match self {
             ControlASM::Nop => ReturnInfo::None,
@@ -151,7 +227,15 @@ impl ControlASM {
                 let mut y_value = ctx.evaluate(y);
 
                 // cast to correct types
-                x_value.compatible_cast(&mut y_value, ctx);
+                if !x_value.compatible_cast(&mut y_value, ctx) {
+                    faults.report(|| format!("Arithmetic on {:?} and {:?}", x, y));
+                }
+
+                if matches!(self, ControlASM::Div(_, _, _) | ControlASM::Mod(_, _, _))
+                    && y_value.is_zero()
+                {
+                    faults.report(|| "Division by zero".to_owned());
+                }
                     
                 // compute the result
                 let res_value = match self {
@@ -311,11 +395,16 @@ impl ControlASM {
                 ctx.stack.push_back(value);
                 ReturnInfo::None
             }
+            ControlASM::Seed(x) => {
+                let seed = ctx.evaluate(x).as_integer(ctx.clock, ctx.frame_len);
+                ctx.rng.reseed(seed as u64);
+                ReturnInfo::None
+            }
             ControlASM::Pop(x) => {
                 if let Some(value) = ctx.stack.pop_back() {
                     ctx.set_var(x, value);
                 } else {
-                    log_eprintln!("[!] Runtime Error: Pop from empty stack into Var {:?}", x);
+                    faults.report(|| format!("Pop from empty stack into Var {:?}", x));
                 }
                 ReturnInfo::None
             }
@@ -328,7 +417,7 @@ impl ControlASM {
                 if let Some(value) = ctx.stack.pop_front() {
                     ctx.set_var(x, value);
                 } else {
-                    log_eprintln!("[!] Runtime Error: Pop from empty stack into Var {:?}", x);
+                    faults.report(|| format!("Pop from empty stack into Var {:?}", x));
                 }
                 ReturnInfo::None
             }
@@ -341,10 +430,7 @@ impl ControlASM {
                     hash_map.insert(key_as_string, val_value);
                     ctx.set_var(res, VariableValue::Map(hash_map));
                 } else {
-                    log_eprintln!(
-                        "[!] Runtime Error: MapInsert expected a Map variable for {:?}, got {:?}",
-                        map, map_value
-                    );
+                    faults.report(|| format!("MapInsert expected a Map variable for {:?}, got {:?}", map, map_value));
                     ctx.set_var(res, VariableValue::Map(HashMap::new()));
                 }
                 ReturnInfo::None
@@ -356,7 +442,7 @@ impl ControlASM {
                 let value = if let Some(VariableValue::Map(map)) = map_value {
                     map.get(&key_value).cloned().unwrap_or_default()
                 } else {
-                    log_eprintln!("[!] Runtime Error: MapGet from a variable that is not a map ! {:?}", map_value);
+                    faults.report(|| format!("MapGet from a variable that is not a map ! {:?}", map_value));
                     VariableValue::default()
                 };
 
@@ -384,7 +470,7 @@ impl ControlASM {
                     let value = map.remove(&key_value).unwrap_or_default();
                     (VariableValue::Map(map), value)
                 } else {
-                    log_eprintln!("[!] Runtime Error: MapRemove from a variable that is not a map ! {:?}", map_value);
+                    faults.report(|| format!("MapRemove from a variable that is not a map ! {:?}", map_value));
                     (VariableValue::Map(HashMap::new()), VariableValue::default())
                 };
 
@@ -400,10 +486,7 @@ impl ControlASM {
                     vec.push(val_value);
                     ctx.set_var(res, VariableValue::Vec(vec));
                 } else {
-                    log_eprintln!(
-                        "[!] Runtime Error: VecPush expected a Vec variable for {:?}, got {:?}",
-                        vec, vec_value
-                    );
+                    faults.report(|| format!("VecPush expected a Vec variable for {:?}, got {:?}", vec, vec_value));
                     ctx.set_var(res, VariableValue::Vec(Vec::new()));
                 }
                 ReturnInfo::None
@@ -416,11 +499,11 @@ impl ControlASM {
                         let value = vec.pop().unwrap();
                         (VariableValue::Vec(vec), value)
                     } else {
-                        log_eprintln!("[!] Runtime Error: VecPop from empty vector !");
+                        faults.report(|| "VecPop from empty vector !".to_owned());
                         (VariableValue::Vec(vec), Default::default())
                     }
                 } else {
-                    log_eprintln!("[!] Runtime Error: VecPop from a variable that is not a vec ! {:?}", vec_value);
+                    faults.report(|| format!("VecPop from a variable that is not a vec ! {:?}", vec_value));
                     (VariableValue::Vec(Vec::new()), VariableValue::default())
                 };
 
@@ -433,7 +516,7 @@ impl ControlASM {
                 let len = if let Some(VariableValue::Vec(vec)) = vec_value {
                     vec.len() as i64
                 } else {
-                    log_eprintln!("[!] Runtime Error: VecLen from a variable that is not a vec ! {:?}", vec_value);
+                    faults.report(|| format!("VecLen from a variable that is not a vec ! {:?}", vec_value));
                     0
                 };
                 ctx.set_var(res, len.into());
@@ -449,10 +532,7 @@ impl ControlASM {
                     vec.insert(index, val_value);
                     ctx.set_var(res, VariableValue::Vec(vec));
                 } else {
-                    log_eprintln!(
-                        "[!] Runtime Error: VecInsert expected a Vec variable for {:?}, got {:?}",
-                        vec, vec_value
-                    );
+                    faults.report(|| format!("VecInsert expected a Vec variable for {:?}, got {:?}", vec, vec_value));
                     ctx.set_var(res, VariableValue::Vec(Vec::new()));
                 }
                 ReturnInfo::None
@@ -464,7 +544,7 @@ impl ControlASM {
                 let value = if let Some(VariableValue::Vec(vec)) = vec_value {
                     vec.get(key_value).cloned().unwrap_or_default()
                 } else {
-                    log_eprintln!("[!] Runtime Error: VecGet from a variable that is not a vec ! {:?}", vec_value);
+                    faults.report(|| format!("VecGet from a variable that is not a vec ! {:?}", vec_value));
                     VariableValue::default()
                 };
 
@@ -476,15 +556,15 @@ impl ControlASM {
                 let key_value = ctx.evaluate(at).as_integer(ctx.clock, ctx.frame_len) as usize;
 
                 let (vec, value) = if let VariableValue::Vec(mut vec) = vec_value {
-                    if key_value <= vec.len() {
+                    if key_value < vec.len() {
                         let value = vec.remove(key_value);
                         (VariableValue::Vec(vec), value)
                     } else {
-                        log_eprintln!("[!] Runtime Error: VecRemove index out of bounds ! {} > {}", key_value, vec.len());
+                        faults.report(|| format!("VecRemove index out of bounds ! {} >= {}", key_value, vec.len()));
                         (VariableValue::Vec(vec), Default::default())
                     }
                 } else {
-                    log_eprintln!("[!] Runtime Error: VecRemove from a variable that is not a vec ! {:?}", vec_value);
+                    faults.report(|| format!("VecRemove from a variable that is not a vec ! {:?}", vec_value));
                     (VariableValue::Vec(Vec::new()), VariableValue::default())
                 };
 
@@ -635,7 +715,10 @@ impl ControlASM {
                 let f_value = ctx.evaluate(f);
                 let next_prog = match f_value {
                     VariableValue::Func(p) => p,
-                    _ => vec![Instruction::Control(ControlASM::Return)],
+                    value => {
+                        faults.report(|| format!("Call of {:?}, which is not a function", value));
+                        vec![Instruction::Control(ControlASM::Return)]
+                    }
                 };
                 ReturnInfo::ProgChange(0, next_prog)
             }
@@ -706,176 +789,76 @@ impl ControlASM {
                 ctx.set_var(dest, VariableValue::Float(result));
                 ReturnInfo::None
             }
-            // Stateful Oscillators using Line Variables and Beat Delta
-            ControlASM::GetSine(speed_var, dest_var) => {
-                let speed_factor = ctx.evaluate(speed_var).as_float(ctx.clock, ctx.frame_len);
-                let current_beat = ctx.clock.beat();
-
-                let last_beat = ctx
-                    .line_vars
-                    .get(SINE_LAST_BEAT_KEY)
-                    .map_or(current_beat, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let current_phase = ctx
-                    .line_vars
-                    .get(SINE_PHASE_KEY)
-                    .map_or(0.0, |v| v.as_float(ctx.clock, ctx.frame_len));
-
-                let delta_beats = current_beat - last_beat;
-                let phase_increment = delta_beats * speed_factor;
-                let new_phase = (current_phase + phase_increment).fract();
-
-                ctx.line_vars.insert(
-                    SINE_PHASE_KEY.to_string(),
-                    VariableValue::Float(new_phase)
-                );
-                ctx.line_vars.insert(
-                    SINE_LAST_BEAT_KEY.to_string(),
-                    VariableValue::Float(current_beat)
-                );
-
-                let raw_value = (new_phase * 2.0 * PI).sin(); // Raw value [-1, 1]
-                let normalized_value = (raw_value + 1.0) / 2.0; // Normalize to [0, 1]
-                let scaled_to_midi = 1.0 + normalized_value * 126.0; // Scale to [1, 127]
-                let midi_int = scaled_to_midi.round().max(1.0).min(127.0) as i64;
-                ctx.set_var(dest_var, VariableValue::Integer(midi_int));
+            ControlASM::ScaleDegree(scale, root, degree, dest) => {
+                let scale = Self::evaluate_scale(ctx, scale, faults);
+                let root = ctx.evaluate(root).as_integer(ctx.clock, ctx.frame_len);
+                let degree = ctx.evaluate(degree).as_integer(ctx.clock, ctx.frame_len);
+                ctx.set_var(dest, scale.degree(degree, root).into());
                 ReturnInfo::None
             }
-            ControlASM::GetSaw(speed_var, dest_var) => {
-                let speed_factor = ctx.evaluate(speed_var).as_float(ctx.clock, ctx.frame_len);
-                let current_beat = ctx.clock.beat();
-                let last_beat = ctx
-                    .line_vars
-                    .get(SAW_LAST_BEAT_KEY)
-                    .map_or(current_beat, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let current_phase = ctx
-                    .line_vars
-                    .get(SAW_PHASE_KEY)
-                    .map_or(0.0, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let delta_beats = current_beat - last_beat;
-                let phase_increment = delta_beats * speed_factor;
-                let new_phase = (current_phase + phase_increment).fract();
-
-                ctx.line_vars.insert(
-                    SAW_PHASE_KEY.to_string(),
-                    VariableValue::Float(new_phase)
-                );
-                ctx.line_vars.insert(
-                    SAW_LAST_BEAT_KEY.to_string(),
-                    VariableValue::Float(current_beat)
-                );
-
-                let raw_value = new_phase * 2.0 - 1.0; // Raw value [-1, 1]
-                let normalized_value = (raw_value + 1.0) / 2.0; // Normalize to [0, 1]
-                let scaled_to_midi = 1.0 + normalized_value * 126.0; // Scale to [1, 127]
-                let midi_int = scaled_to_midi.round().max(1.0).min(127.0) as i64;
-                ctx.set_var(dest_var, VariableValue::Integer(midi_int));
+            ControlASM::ScaleSnap(scale, root, note, dest) => {
+                let scale = Self::evaluate_scale(ctx, scale, faults);
+                let root = ctx.evaluate(root).as_integer(ctx.clock, ctx.frame_len);
+                let note = ctx.evaluate(note).as_integer(ctx.clock, ctx.frame_len);
+                ctx.set_var(dest, scale.quantize(note, root.rem_euclid(12)).into());
                 ReturnInfo::None
             }
-            ControlASM::GetTriangle(speed_var, dest_var) => {
-                let speed_factor = ctx.evaluate(speed_var).as_float(ctx.clock, ctx.frame_len);
-                let current_beat = ctx.clock.beat();
-                let last_beat = ctx
-                    .line_vars
-                    .get(TRI_LAST_BEAT_KEY)
-                    .map_or(current_beat, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let current_phase = ctx
-                    .line_vars
-                    .get(TRI_PHASE_KEY)
-                    .map_or(0.0, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let delta_beats = current_beat - last_beat;
-                let phase_increment = delta_beats * speed_factor;
-                let new_phase = (current_phase + phase_increment).fract();
-
-                ctx.line_vars.insert(
-                    TRI_PHASE_KEY.to_string(),
-                    VariableValue::Float(new_phase)
-                );
-                ctx.line_vars.insert(
-                    TRI_LAST_BEAT_KEY.to_string(),
-                    VariableValue::Float(current_beat)
-                );
-
-                let raw_value = 1.0 - (new_phase * 2.0 - 1.0).abs() * 2.0; // Raw value [-1, 1]
-                let normalized_value = (raw_value + 1.0) / 2.0; // Normalize to [0, 1]
-                let scaled_to_midi = 1.0 + normalized_value * 126.0; // Scale to [1, 127]
-                let midi_int = scaled_to_midi.round().max(1.0).min(127.0) as i64;
-                ctx.set_var(dest_var, VariableValue::Integer(midi_int));
+            ControlASM::DegreeChord(scale, root, degree, voices, dest) => {
+                let scale = Self::evaluate_scale(ctx, scale, faults);
+                let root = ctx.evaluate(root).as_integer(ctx.clock, ctx.frame_len);
+                let degree = ctx.evaluate(degree).as_integer(ctx.clock, ctx.frame_len);
+                let voices = ctx.evaluate(voices).as_integer(ctx.clock, ctx.frame_len);
+                let chord = scale.degree_chord(degree, root, voices.max(0) as usize);
+                ctx.set_var(dest, Self::notes_value(chord));
                 ReturnInfo::None
             }
-            ControlASM::GetISaw(speed_var, dest_var) => {
-                let speed_factor = ctx.evaluate(speed_var).as_float(ctx.clock, ctx.frame_len);
-                let current_beat = ctx.clock.beat();
-                let last_beat = ctx
-                    .line_vars
-                    .get(ISAW_LAST_BEAT_KEY)
-                    .map_or(current_beat, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let current_phase = ctx
-                    .line_vars
-                    .get(ISAW_PHASE_KEY)
-                    .map_or(0.0, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let delta_beats = current_beat - last_beat;
-                let phase_increment = delta_beats * speed_factor;
-                let new_phase = (current_phase + phase_increment).fract();
-
-                ctx.line_vars.insert(
-                    ISAW_PHASE_KEY.to_string(),
-                    VariableValue::Float(new_phase)
-                );
-                ctx.line_vars.insert(
-                    ISAW_LAST_BEAT_KEY.to_string(),
-                    VariableValue::Float(current_beat)
-                );
-
-                let raw_value = 1.0 - (new_phase * 2.0); // Raw value [1, -1] (inverted saw)
-                let normalized_value = (raw_value + 1.0) / 2.0; // Normalize to [0, 1]
-                let scaled_to_midi = 1.0 + normalized_value * 126.0; // Scale to [1, 127]
-                let midi_int = scaled_to_midi.round().max(1.0).min(127.0) as i64;
-                ctx.set_var(dest_var, VariableValue::Integer(midi_int));
+            ControlASM::Chord(chord, root, dest) => {
+                let chord = Self::evaluate_chord(ctx, chord, faults);
+                let root = ctx.evaluate(root).as_integer(ctx.clock, ctx.frame_len);
+                ctx.set_var(dest, Self::notes_value(chord.notes(root)));
                 ReturnInfo::None
             }
-            ControlASM::GetRandStep(speed_var, dest_var) => {
-                let speed_factor = ctx.evaluate(speed_var).as_float(ctx.clock, ctx.frame_len);
-                let current_beat = ctx.clock.beat();
-                let last_beat = ctx
-                    .line_vars
-                    .get(RANDSTEP_LAST_BEAT_KEY)
-                    .map_or(current_beat, |v| v.as_float(ctx.clock, ctx.frame_len));
-                let current_phase = ctx
-                    .line_vars
-                    .get(RANDSTEP_PHASE_KEY)
-                    .map_or(0.0, |v| v.as_float(ctx.clock, ctx.frame_len));
-
-                let delta_beats = current_beat - last_beat;
-                let phase_increment = delta_beats * speed_factor;
-                let new_phase = (current_phase + phase_increment).fract();
-
-                let mut current_value = ctx
-                    .line_vars
-                    .get(RANDSTEP_VALUE_KEY)
-                    .map_or(0, |v| v.as_integer(ctx.clock, ctx.frame_len)); // Default to 0 if not set
-
-                // Check if phase wrapped around (cycle completed) or if it's the first run
-                if new_phase < current_phase || current_value == 0 {
-                    current_value = (rand::random::<u8>() % 127) as i64 + 1; // Generate new random value [1, 127]
-
-                    ctx.line_vars.insert(
-                        RANDSTEP_VALUE_KEY.to_string(),
-                        VariableValue::Integer(current_value)
-                    ); // Store it
-                }
-
-                ctx.line_vars.insert(
-                    RANDSTEP_PHASE_KEY.to_string(),
-                    VariableValue::Float(new_phase)
-                );
-                ctx.line_vars.insert(
-                    RANDSTEP_LAST_BEAT_KEY.to_string(),
-                    VariableValue::Float(current_beat)
-                );
-
-                ctx.set_var(dest_var, VariableValue::Integer(current_value)); // Return the current held value
+            ControlASM::Invert(chord, inversion, dest) => {
+                let chord = Self::evaluate_notes(ctx, chord);
+                let inversion = ctx.evaluate(inversion).as_integer(ctx.clock, ctx.frame_len);
+                ctx.set_var(dest, Self::notes_value(theory::invert(&chord, inversion)));
                 ReturnInfo::None
             }
+            ControlASM::VoiceLead(from, to, dest) => {
+                let from = Self::evaluate_notes(ctx, from);
+                let to = Self::evaluate_notes(ctx, to);
+                ctx.set_var(dest, Self::notes_value(theory::voice_lead(&from, &to)));
+                ReturnInfo::None
+            }
+            ControlASM::Arpeggiate(chord, arpeggio, octaves, dest) => {
+                let chord = Self::evaluate_notes(ctx, chord);
+                let name = ctx.evaluate(arpeggio).as_str(ctx.clock, ctx.frame_len);
+                let arpeggio = Arpeggio::from_name(&name).unwrap_or_else(|| {
+                    faults.report(|| format!("Unknown arpeggio '{}', using up", name));
+                    Arpeggio::Up
+                });
+                let octaves = ctx.evaluate(octaves).as_integer(ctx.clock, ctx.frame_len);
+                let notes = arpeggio.notes(&chord, octaves.max(1) as usize);
+                ctx.set_var(dest, Self::notes_value(notes));
+                ReturnInfo::None
+            }
+            // Beat-synced oscillators, scaled to [1, 127]
+            ControlASM::GetSine(speed_var, dest_var) => {
+                self.oscillate(ctx, speed_var, ModulatorShape::Sine, dest_var)
+            }
+            ControlASM::GetSaw(speed_var, dest_var) => {
+                self.oscillate(ctx, speed_var, ModulatorShape::Saw, dest_var)
+            }
+            ControlASM::GetTriangle(speed_var, dest_var) => {
+                self.oscillate(ctx, speed_var, ModulatorShape::Triangle, dest_var)
+            }
+            ControlASM::GetISaw(speed_var, dest_var) => {
+                self.oscillate(ctx, speed_var, ModulatorShape::InvertedSaw, dest_var)
+            }
+            ControlASM::GetRandStep(speed_var, dest_var) => {
+                let seed = ctx.rng.seed();
+                self.oscillate(ctx, speed_var, ModulatorShape::SampleHold(seed), dest_var)
+            }
             ControlASM::GetMidiCC(device_var, channel_var, ctrl_var, result_var) => {
                 // Resolve Device ID
                 let device_id = match device_var {
@@ -1,10 +1,10 @@
 use serde::Serialize;
 
-use crate::{clock::SyncTime, device_map::DeviceMap};
+use crate::{clock::SyncTime, device_map::DeviceMap, scene::Modulator};
 use crate::clock::Clock;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 
-use super::variable::{Variable, VariableStore, VariableValue};
+use super::{RuntimeFault, random::SovaRng, variable::{Variable, VariableStore, VariableValue}};
 
 /// Context that stores everything necessary for stateful script execution.
 #[derive(Serialize)]
@@ -22,6 +22,13 @@ pub struct EvaluationContext<'a> {
     pub clock: &'a Clock,
     #[serde(skip)]
     pub device_map: &'a DeviceMap,
+    /// Random stream of the executing frame
+    #[serde(skip)]
+    pub rng: &'a mut SovaRng,
+    pub modulators: &'a BTreeMap<String, Modulator>,
+    /// Runtime faults raised by the scripts, to be forwarded by the scheduler
+    #[serde(skip)]
+    pub faults: &'a mut Vec<RuntimeFault>,
 }
 
 impl<'a> EvaluationContext<'a> {
@@ -122,7 +129,10 @@ impl<'a> EvaluationContext<'a> {
             frame_len: len,
             structure: self.structure,
             clock: self.clock,
-            device_map: self.device_map
+            device_map: self.device_map,
+            rng: self.rng,
+            modulators: self.modulators,
+            faults: self.faults,
         }
     }
 
@@ -146,6 +156,9 @@ pub struct PartialContext<'a> {
     pub structure: Option<&'a Vec<Vec<f64>>>,
     pub clock: Option<&'a Clock>,
     pub device_map: Option<&'a DeviceMap>,
+    pub rng: Option<&'a mut SovaRng>,
+    pub modulators: Option<&'a BTreeMap<String, Modulator>>,
+    pub faults: Option<&'a mut Vec<RuntimeFault>>,
 }
 
 impl<'a> PartialContext<'a> {
@@ -169,7 +182,10 @@ impl<'a> PartialContext<'a> {
             self.frame_len.is_some() &&
             self.structure.is_some() &&
             self.clock.is_some() &&
-            self.device_map.is_some()
+            self.device_map.is_some() &&
+            self.rng.is_some() &&
+            self.modulators.is_some() &&
+            self.faults.is_some()
     }
 
     /// Creates another partial context sharing the same fields as its parent, but allowing override of some.
@@ -188,7 +204,10 @@ impl<'a> PartialContext<'a> {
             frame_len: self.frame_len,
             structure: self.structure,
             clock: self.clock, 
-            device_map: self.device_map
+            device_map: self.device_map,
+            rng: self.rng.as_deref_mut(),
+            modulators: self.modulators,
+            faults: self.faults.as_deref_mut(),
         }
     }
 
@@ -212,6 +231,9 @@ impl<'a> From<PartialContext<'a>> for EvaluationContext<'a> {
             structure: partial.structure.unwrap(),
             clock: partial.clock.unwrap(), 
             device_map: partial.device_map.unwrap(),
+            rng: partial.rng.unwrap(),
+            modulators: partial.modulators.unwrap(),
+            faults: partial.faults.unwrap(),
         }
     }
 }
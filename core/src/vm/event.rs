@@ -3,18 +3,35 @@ use std::collections::HashMap;
 
 use crate::clock::SyncTime;
 use crate::vm::Program;
+use crate::protocol::midi::NoteExpression;
 use crate::protocol::osc::OSCMessage;
+use crate::protocol::ramp::{DEFAULT_RAMP_RESOLUTION, Ramp, RampCurve, RampKind, RampTarget};
 
 use super::variable::VariableValue;
 use super::{EvaluationContext, variable::Variable};
 
+/// Clamps an integer to the 14-bit MIDI range before it is cast, so that
+/// negative values do not wrap around to the maximum.
+pub fn to_14_bits(value: i64) -> u64 {
+    value.clamp(0, 0x3FFF) as u64
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ConcreteEvent {
     Nop,
     MidiNote(u64, u64, u64, SyncTime, usize),
-    // TODO: MIDI Pitchbend
     MidiControl(u64, u64, u64, usize),
+    /// MidiPitchBend(value in [0, 16383], 8192 being the center, channel, device_id)
+    MidiPitchBend(u64, u64, usize),
+    /// MidiControl14(control in [0, 31], value in [0, 16383], channel, device_id)
+    MidiControl14(u64, u64, u64, usize),
+    /// MidiNrpn(parameter in [0, 16383], value in [0, 16383], channel, device_id)
+    MidiNrpn(u64, u64, u64, usize),
+    /// MidiRpn(parameter in [0, 16383], value in [0, 16383], channel, device_id)
+    MidiRpn(u64, u64, u64, usize),
+    /// MidiNoteExpression(note, expression, value, channel, device_id), per note in MPE mode
+    MidiNoteExpression(u64, NoteExpression, u64, u64, usize),
     MidiProgram(u64, u64, usize),
     MidiAftertouch(u64, u64, u64, usize),
     MidiChannelPressure(u64, u64, usize),
@@ -33,7 +50,8 @@ pub enum ConcreteEvent {
         device_id: usize,
     },
     StartProgram(Program),
-    Generic(VariableValue, SyncTime, String, usize)
+    Generic(VariableValue, SyncTime, String, usize),
+    Ramp(Ramp, usize),
 }
 
 impl ConcreteEvent {
@@ -41,6 +59,11 @@ impl ConcreteEvent {
         match self {
             ConcreteEvent::MidiNote(_, _, _, _, device_id) 
             | ConcreteEvent::MidiControl(_, _, _, device_id) 
+            | ConcreteEvent::MidiPitchBend(_, _, device_id) 
+            | ConcreteEvent::MidiControl14(_, _, _, device_id) 
+            | ConcreteEvent::MidiNrpn(_, _, _, device_id) 
+            | ConcreteEvent::MidiRpn(_, _, _, device_id) 
+            | ConcreteEvent::MidiNoteExpression(_, _, _, _, device_id) 
             | ConcreteEvent::MidiProgram(_, _, device_id) 
             | ConcreteEvent::MidiAftertouch(_, _, _, device_id) 
             | ConcreteEvent::MidiChannelPressure(_, _, device_id) 
@@ -53,6 +76,7 @@ impl ConcreteEvent {
             | ConcreteEvent::Dirt { args: _, device_id } 
             | ConcreteEvent::Osc { message: _, device_id } 
             | ConcreteEvent::Generic(_, _, _, device_id)
+            | ConcreteEvent::Ramp(_, device_id)
                 => Some(*device_id),
             ConcreteEvent::Nop 
             | ConcreteEvent::StartProgram(_) 
@@ -67,8 +91,17 @@ pub enum Event {
     Nop,
     /// MidiNote(note, velocity, channel, duration, device_id)
     MidiNote(Variable, Variable, Variable, Variable, Variable),
-    // TODO: MIDI Pitchbend
     MidiControl(Variable, Variable, Variable, Variable),
+    /// MidiPitchBend(value, channel, device_id)
+    MidiPitchBend(Variable, Variable, Variable),
+    /// MidiControl14(control, value, channel, device_id)
+    MidiControl14(Variable, Variable, Variable, Variable),
+    /// MidiNrpn(parameter, value, channel, device_id)
+    MidiNrpn(Variable, Variable, Variable, Variable),
+    /// MidiRpn(parameter, value, channel, device_id)
+    MidiRpn(Variable, Variable, Variable, Variable),
+    /// MidiNoteExpression(expression, note, value, channel, device_id)
+    MidiNoteExpression(NoteExpression, Variable, Variable, Variable, Variable),
     MidiProgram(Variable, Variable, Variable),
     MidiAftertouch(Variable, Variable, Variable, Variable),
     MidiChannelPressure(Variable, Variable, Variable),
@@ -89,6 +122,19 @@ pub enum Event {
         device_id: Variable,
     },
     StartProgram(Variable),
+    /// Continuous change of a parameter. `param` is the control number (MIDI CC)
+    /// or the address (OSC), ignored for pitch bend. `curve` is a curve name.
+    Ramp {
+        kind: RampKind,
+        param: Variable,
+        from: Variable,
+        to: Variable,
+        duration: Variable,
+        curve: Variable,
+        resolution: Variable,
+        channel: Variable,
+        device_id: Variable,
+    },
     
     /// ----- Generic events -----
 
@@ -118,6 +164,32 @@ impl Event {
                 let dev_id = ctx.evaluate(dev).as_integer(ctx.clock, ctx.frame_len) as usize;
                 ConcreteEvent::MidiControl(control, value, channel, dev_id)
             }
+            Event::MidiPitchBend(value, channel, dev) => {
+                let value = to_14_bits(ctx.evaluate(value).as_integer(ctx.clock, ctx.frame_len));
+                let channel = ctx.evaluate(channel).as_integer(ctx.clock, ctx.frame_len) as u64;
+                let dev_id = ctx.evaluate(dev).as_integer(ctx.clock, ctx.frame_len) as usize;
+                ConcreteEvent::MidiPitchBend(value, channel, dev_id)
+            }
+            Event::MidiControl14(param, value, channel, dev)
+            | Event::MidiNrpn(param, value, channel, dev)
+            | Event::MidiRpn(param, value, channel, dev) => {
+                let param = to_14_bits(ctx.evaluate(param).as_integer(ctx.clock, ctx.frame_len));
+                let value = to_14_bits(ctx.evaluate(value).as_integer(ctx.clock, ctx.frame_len));
+                let channel = ctx.evaluate(channel).as_integer(ctx.clock, ctx.frame_len) as u64;
+                let dev_id = ctx.evaluate(dev).as_integer(ctx.clock, ctx.frame_len) as usize;
+                match self {
+                    Event::MidiControl14(..) => ConcreteEvent::MidiControl14(param, value, channel, dev_id),
+                    Event::MidiNrpn(..) => ConcreteEvent::MidiNrpn(param, value, channel, dev_id),
+                    _ => ConcreteEvent::MidiRpn(param, value, channel, dev_id),
+                }
+            }
+            Event::MidiNoteExpression(expression, note, value, channel, dev) => {
+                let note = ctx.evaluate(note).as_integer(ctx.clock, ctx.frame_len) as u64;
+                let value = to_14_bits(ctx.evaluate(value).as_integer(ctx.clock, ctx.frame_len));
+                let channel = ctx.evaluate(channel).as_integer(ctx.clock, ctx.frame_len) as u64;
+                let dev_id = ctx.evaluate(dev).as_integer(ctx.clock, ctx.frame_len) as usize;
+                ConcreteEvent::MidiNoteExpression(note, *expression, value, channel, dev_id)
+            }
             Event::MidiProgram(program, channel, dev) => {
                 let program = ctx.evaluate(program).as_integer(ctx.clock, ctx.frame_len) as u64;
                 let channel = ctx.evaluate(channel).as_integer(ctx.clock, ctx.frame_len) as u64;
@@ -209,6 +281,43 @@ impl Event {
                     ConcreteEvent::StartProgram(Program::default())
                 }
             }
+            Event::Ramp {
+                kind,
+                param,
+                from,
+                to,
+                duration,
+                curve,
+                resolution,
+                channel,
+                device_id,
+            } => {
+                let channel = ctx.evaluate(channel).as_integer(ctx.clock, ctx.frame_len) as u64;
+                let target = match kind {
+                    RampKind::MidiControl => RampTarget::MidiControl {
+                        control: ctx.evaluate(param).as_integer(ctx.clock, ctx.frame_len) as u64,
+                        channel,
+                    },
+                    RampKind::PitchBend => RampTarget::PitchBend { channel },
+                    RampKind::Osc => RampTarget::OscFloat {
+                        address: ctx.evaluate(param).as_str(ctx.clock, ctx.frame_len),
+                    },
+                };
+                let resolution = match ctx.evaluate(resolution).as_dur().as_micros(ctx.clock, ctx.frame_len) {
+                    0 => DEFAULT_RAMP_RESOLUTION,
+                    res => res,
+                };
+                let ramp = Ramp {
+                    target,
+                    from: ctx.evaluate(from).as_float(ctx.clock, ctx.frame_len),
+                    to: ctx.evaluate(to).as_float(ctx.clock, ctx.frame_len),
+                    duration: ctx.evaluate(duration).as_dur().as_micros(ctx.clock, ctx.frame_len),
+                    curve: RampCurve::from_name(&ctx.evaluate(curve).as_str(ctx.clock, ctx.frame_len)),
+                    resolution,
+                };
+                let dev_id = ctx.evaluate(device_id).as_integer(ctx.clock, ctx.frame_len) as usize;
+                ConcreteEvent::Ramp(ramp, dev_id)
+            }
             Event::Generic(value, duration, channel, device) => {
                 ConcreteEvent::Generic(
                     ctx.evaluate(value), 
@@ -220,3 +329,16 @@ impl Event {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::to_14_bits;
+
+    #[test]
+    fn negative_14_bits_values_are_clamped() {
+        assert_eq!(to_14_bits(-1), 0);
+        assert_eq!(to_14_bits(i64::MIN), 0);
+        assert_eq!(to_14_bits(8192), 8192);
+        assert_eq!(to_14_bits(20000), 16383);
+    }
+}
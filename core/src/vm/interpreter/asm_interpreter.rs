@@ -1,4 +1,6 @@
-use crate::{clock::{NEVER, SyncTime}, compiler::CompilationState, vm::{Instruction, Program, EvaluationContext, event::ConcreteEvent, interpreter::Interpreter}, scene::script::{ReturnInfo, Script}};
+use std::sync::Arc;
+
+use crate::{clock::{NEVER, SyncTime}, compiler::CompilationState, vm::{FaultReporter, Instruction, Program, EvaluationContext, SourceMap, event::ConcreteEvent, interpreter::Interpreter}, scene::script::{ReturnInfo, Script}};
 
 pub const DEFAULT_INSTRUCTION_BATCH_SIZE : usize = 16;
 
@@ -7,6 +9,7 @@ pub struct ASMInterpreter {
     prog: Program,
     instruction_index: usize,
     return_stack: Vec<ReturnInfo>,
+    faults: FaultReporter,
     /// Optimization: allows to execute in the same iteration at most `instruction_block_size` control instructions
     pub instruction_batch_size: usize
 }
@@ -18,10 +21,17 @@ impl ASMInterpreter {
             prog, 
             instruction_index: 0, 
             return_stack: Vec::new(), 
+            faults: FaultReporter::default(),
             instruction_batch_size: DEFAULT_INSTRUCTION_BATCH_SIZE
         }
     }
 
+    /// Locates the runtime faults of the program in its script with `source_map`.
+    pub fn with_source_map(mut self, source_map: Arc<SourceMap>) -> Self {
+        self.faults = FaultReporter::new(source_map);
+        self
+    }
+
     #[inline]
     pub fn current_instruction(&self) -> &Instruction {
         &self.prog[self.instruction_index]
@@ -31,12 +41,25 @@ impl ASMInterpreter {
         let Instruction::Control(control) = &self.prog[self.instruction_index] else {
             return;
         };
-        match control.execute(
+        // Faults within functions are located at the call made by the script program
+        let site = self
+            .return_stack
+            .iter()
+            .find_map(|info| match info {
+                ReturnInfo::ProgChange(index, _) => Some(index - 1),
+                _ => None,
+            })
+            .unwrap_or(self.instruction_index);
+        self.faults.locate(site);
+        let info = control.execute(
             ctx,
             &mut self.return_stack,
             self.instruction_index,
             &self.prog,
-        ) {
+            &mut self.faults,
+        );
+        self.faults.flush(ctx);
+        match info {
             ReturnInfo::None => self.instruction_index += 1,
             ReturnInfo::IndexChange(index) => self.instruction_index = index,
             ReturnInfo::RelIndexChange(index_change) => {
@@ -111,7 +134,9 @@ impl ASMInterpreterFactory {
 
     pub fn make_instance(&self, script : &Script) -> Option<Box<dyn Interpreter>> {
         match &script.compiled {
-            CompilationState::Compiled(prog) => Some(Box::new(ASMInterpreter::new(prog.clone()))),
+            CompilationState::Compiled(prog, source_map) => Some(Box::new(
+                ASMInterpreter::new(prog.clone()).with_source_map(source_map.clone()),
+            )),
             _ => None
         }
     }
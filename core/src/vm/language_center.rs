@@ -1,8 +1,8 @@
-use std::thread;
+use std::{sync::Arc, thread};
 
 use crossbeam_channel::Sender;
 
-use crate::{Scene, compiler::CompilationState, vm::{Transcoder, interpreter::InterpreterDirectory}, scene::{Line, script::Script}, schedule::SchedulerMessage};
+use crate::{Scene, compiler::CompilationState, lang::format, vm::{Transcoder, interpreter::InterpreterDirectory}, scene::{Line, script::Script}, schedule::SchedulerMessage};
 
 #[derive(Debug, Default)]
 pub struct LanguageCenter {
@@ -26,9 +26,9 @@ impl LanguageCenter {
         let lang = script.lang();
         let state = if let Some(compiler) = self.transcoder.get_compiler(lang) {
             let script = script.clone();
-            match compiler.compile(script.content(), &script.args) {
-                Ok(prog) => 
-                    CompilationState::Compiled(prog),
+            match compiler.compile_with_source_map(script.content(), &script.args) {
+                Ok((prog, source_map)) => 
+                    CompilationState::Compiled(prog, Arc::new(source_map)),
                 Err(err) => 
                     CompilationState::Error(err),
             }
@@ -59,9 +59,9 @@ impl LanguageCenter {
         if let Some(compiler) = self.transcoder.get_compiler(lang) {
             let script = script.clone();
             thread::spawn(move || {
-                let state = match compiler.compile(script.content(), &script.args) {
-                    Ok(prog) => 
-                        CompilationState::Compiled(prog),
+                let state = match compiler.compile_with_source_map(script.content(), &script.args) {
+                    Ok((prog, source_map)) => 
+                        CompilationState::Compiled(prog, Arc::new(source_map)),
                     Err(err) => 
                         CompilationState::Error(err),
                 };
@@ -80,6 +80,12 @@ impl LanguageCenter {
         }
     }
 
+    /// Formats a script: Bali scripts are printed in their canonical layout, Boinx scripts
+    /// are reindented.
+    pub fn format_script(&self, script: &Script) -> Result<String, String> {
+        format::format_script(script.lang(), script.content(), format::INDENT)
+    }
+
     pub fn process_line(&self, line_id: usize, line : &Line, notifier: Sender<SchedulerMessage>) {
         for (frame_id, frame) in line.frames.iter().enumerate() {
             self.process_script(line_id, frame_id, frame.script(), notifier.clone());
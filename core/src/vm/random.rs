@@ -0,0 +1,147 @@
+//! Deterministic random number generation for scripts.
+//!
+//! Every frame owns its own random stream, derived from the seed of its line,
+//! itself derived from the seed of the scene. Given the same scene seed, a scene
+//! therefore always produces the same random values, whatever the other lines do.
+
+use rand::RngCore;
+
+/// Small, fast and portable generator (SplitMix64) : the produced sequence only depends on the seed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SovaRng {
+    seed: u64,
+    state: u64,
+}
+
+impl SovaRng {
+    pub fn new(seed: u64) -> Self {
+        SovaRng { seed, state: seed }
+    }
+
+    /// Creates a generator seeded from system entropy.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts the stream with a new seed.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    /// Restarts the stream from its current seed.
+    pub fn restart(&mut self) {
+        self.state = self.seed;
+    }
+
+    /// Seed of the sub-stream `index` of a stream seeded with `seed`.
+    pub fn derive(seed: u64, index: usize) -> u64 {
+        let mut sub = SovaRng::new(seed ^ (index as u64).wrapping_mul(0xD1B5_4A32_D192_ED03));
+        sub.next_u64()
+    }
+}
+
+impl Default for SovaRng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+impl RngCore for SovaRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        for chunk in dst.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SovaRng;
+    use rand::{Rng, RngCore};
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = SovaRng::new(42);
+        let mut b = SovaRng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn restart_replays_sequence() {
+        let mut rng = SovaRng::new(7);
+        let first: Vec<i64> = (0..10).map(|_| rng.random_range(0..128)).collect();
+        rng.restart();
+        let second: Vec<i64> = (0..10).map(|_| rng.random_range(0..128)).collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn derived_streams_differ() {
+        let a = SovaRng::derive(1234, 0);
+        let b = SovaRng::derive(1234, 1);
+        assert_ne!(a, b);
+        assert_eq!(a, SovaRng::derive(1234, 0));
+    }
+
+    #[test]
+    fn seeded_scene_is_reproducible() {
+        use crate::scene::{Line, Scene};
+        let draw = |scene: &mut Scene| -> Vec<u64> {
+            scene.reseed();
+            scene.lines.iter_mut()
+                .flat_map(|l| l.frames.iter_mut())
+                .map(|f| f.rng.next_u64())
+                .collect()
+        };
+        let mut scene = Scene::new(vec![Line::new(vec![1.0, 1.0]), Line::new(vec![2.0])]);
+        scene.seed = Some(99);
+        let first = draw(&mut scene);
+        assert_eq!(first, draw(&mut scene));
+        assert_ne!(first[0], first[1]);
+        assert_ne!(first[0], first[2]);
+    }
+
+    #[test]
+    fn cloned_frames_keep_their_stream() {
+        use crate::scene::{Line, Scene};
+        let mut scene = Scene::new(vec![Line::new(vec![1.0])]);
+        scene.seed = Some(5);
+        scene.reseed();
+        let frame = scene.get_frame_mut(0, 0);
+        frame.rng.next_u64();
+        let mut copy = frame.clone();
+        assert_eq!(copy.rng.next_u64(), frame.rng.next_u64());
+        let mut image = scene.clone();
+        assert_eq!(
+            image.get_frame_mut(0, 0).rng.next_u64(),
+            scene.get_frame_mut(0, 0).rng.next_u64()
+        );
+    }
+
+    #[test]
+    fn known_values() {
+        // SplitMix64 reference output for seed 0, keeps streams stable across versions.
+        let mut rng = SovaRng::new(0);
+        assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashSet, fmt::Display, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use super::{EvaluationContext, SourceMap, SourcePosition};
+
+/// Fault raised by an instruction while executing the script of a frame.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RuntimeFault {
+    pub line_index: usize,
+    pub frame_index: usize,
+    pub message: String,
+    /// Where the faulty instruction comes from in the script, if known
+    pub position: Option<SourcePosition>,
+}
+
+impl Display for RuntimeFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (line {}, frame {}", self.message, self.line_index, self.frame_index)?;
+        if let Some(position) = self.position {
+            write!(f, ", at {position}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Collects the runtime faults of a script execution, reporting each faulty instruction once.
+///
+/// Messages are only built for the faults which are reported, as faults are raised on the
+/// scheduler thread.
+#[derive(Debug, Default, Clone)]
+pub struct FaultReporter {
+    source_map: Arc<SourceMap>,
+    reported: HashSet<usize>,
+    /// Position in the script program of the instruction being executed
+    site: usize,
+    pending: Option<String>,
+}
+
+impl FaultReporter {
+    pub fn new(source_map: Arc<SourceMap>) -> Self {
+        FaultReporter {
+            source_map,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the position in the script program of the instruction about to be executed.
+    pub fn locate(&mut self, site: usize) {
+        self.site = site;
+    }
+
+    /// Raises a fault of the current instruction, unless it has already faulted.
+    pub fn report(&mut self, fault: impl FnOnce() -> String) {
+        if self.pending.is_none() && self.reported.insert(self.site) {
+            self.pending = Some(fault());
+        }
+    }
+
+    /// Hands the fault raised by the current instruction, if any, to the context.
+    pub fn flush(&mut self, ctx: &mut EvaluationContext) {
+        let Some(message) = self.pending.take() else {
+            return;
+        };
+        ctx.faults.push(RuntimeFault {
+            line_index: ctx.line_index,
+            frame_index: ctx.frame_index,
+            message,
+            position: self.source_map.position(self.site),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::SourceSpan;
+
+    #[test]
+    fn faulty_instructions_are_reported_once() {
+        let script = "(note 60)\n(é (note (/ 1 0)))";
+        let spans = vec![None, Some(SourceSpan::new(14, 28))];
+        let mut faults = FaultReporter::new(Arc::new(SourceMap::new(script, spans)));
+
+        faults.locate(1);
+        faults.report(|| "Division by zero".to_owned());
+        assert_eq!(faults.pending.take().as_deref(), Some("Division by zero"));
+        // The message of a fault already reported is not even built
+        faults.report(|| unreachable!());
+        assert!(faults.pending.is_none());
+
+        faults.locate(0);
+        faults.report(|| "Pop from empty stack".to_owned());
+        assert!(faults.pending.is_some());
+
+        assert_eq!(faults.source_map.position(0), None);
+        assert_eq!(
+            faults.source_map.position(1),
+            Some(SourcePosition { line: 2, column: 4 })
+        );
+    }
+}
@@ -76,15 +76,15 @@ impl Transcoder {
         let Some(compiler) = self.compilers.get(lang) else {
             return CompilationState::NotCompiled;
         };
-        match compiler.compile(content, args) {
-            Ok(prog) => CompilationState::Compiled(prog),
+        match compiler.compile_with_source_map(content, args) {
+            Ok((prog, source_map)) => CompilationState::Compiled(prog, Arc::new(source_map)),
             Err(err) => CompilationState::Error(err),
         }
     }
 
     pub fn compile_script(&self, script : &mut Script) -> bool {
-        if let CompilationState::Compiled(prog) = self.compile(script.content(), script.lang(), &script.args) {
-            script.compiled = CompilationState::Compiled(prog);
+        if let state @ CompilationState::Compiled(..) = self.compile(script.content(), script.lang(), &script.args) {
+            script.compiled = state;
             true
         } else {
             log_eprintln!(
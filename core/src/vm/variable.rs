@@ -332,7 +332,11 @@ impl VariableValue {
         }
     }
 
-    pub fn compatible_cast(&mut self, other: &mut VariableValue, ctx: &EvaluationContext) {
+    /// Casts two values to a common type to compute with them. Returns `false` when one of
+    /// them has no meaningful conversion, being neither numeric nor a map.
+    pub fn compatible_cast(&mut self, other: &mut VariableValue, ctx: &EvaluationContext) -> bool {
+        let castable = |value: &VariableValue| value.is_numeric() || value.is_map();
+        let compatible = castable(self) && castable(other);
         // cast to correct types
         match self {
             VariableValue::Integer(_) => {
@@ -369,6 +373,7 @@ impl VariableValue {
                 }
             },
         }
+        compatible
     }
 
     pub fn is_true(self, ctx: &EvaluationContext) -> bool {
@@ -936,13 +941,37 @@ impl VariableValue {
     pub fn is_blob(&self) -> bool {
         matches!(self, VariableValue::Blob(_))
     }
+
+    /// Returns `true` if the value can be cast to a number without being replaced by a
+    /// default: strings which are not numbers, functions, collections and blobs cannot.
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            VariableValue::Integer(_)
+            | VariableValue::Float(_)
+            | VariableValue::Decimal(_, _, _)
+            | VariableValue::Bool(_)
+            | VariableValue::Dur(_) => true,
+            VariableValue::Str(s) => s.parse::<f64>().is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the value is a number equal to zero.
+    pub fn is_zero(&self) -> bool {
+        match self {
+            VariableValue::Integer(i) => *i == 0,
+            VariableValue::Float(f) => *f == 0.0,
+            VariableValue::Decimal(_, num, _) => *num == 0,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Variable {
     Environment(EnvironmentFunc),
     Global(String),
-    Line(String), // not fully handled
+    Line(String),
     Frame(String),
     Instance(String),
     Constant(VariableValue),
@@ -970,11 +999,36 @@ impl Variable {
     }
 }
 
+/// Identifies the store in which a named variable lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VariableScope {
+    Global,
+    /// Line index
+    Line(usize),
+    /// Line index, frame index
+    Frame(usize, usize),
+}
+
+impl VariableScope {
+    /// Dot separated name identifying a variable across all scopes,
+    /// e.g. `global.A`, `line.0.count` or `frame.1.2.x`.
+    pub fn qualified_name(&self, name: &str) -> String {
+        match self {
+            VariableScope::Global => format!("global.{name}"),
+            VariableScope::Line(l) => format!("line.{l}.{name}"),
+            VariableScope::Frame(l, f) => format!("frame.{l}.{f}.{name}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct VariableStore {
     content: HashMap<String, VariableValue>,
     delta: Vec<String>,
     watchers: Vec<usize>,
+    /// Set by every mutation, cleared by `take_modified`
+    #[serde(skip)]
+    modified: bool,
 }
 
 impl VariableStore {
@@ -990,6 +1044,7 @@ impl VariableStore {
         if self.watchers.len() > 0 {
             self.delta.push(key.clone());
         }
+        self.modified = true;
         self.content.insert(key, value)
     }
 
@@ -1017,12 +1072,14 @@ impl VariableStore {
 
     pub fn get_create(&mut self, key: &str) -> &VariableValue {
         if !self.content.contains_key(key) {
+            self.modified = true;
             self.content.insert(key.to_owned(), VariableValue::default());
         }
         self.content.get(key).unwrap()
     }
 
     pub fn get_mut(&mut self, key: &str) -> Option<&mut VariableValue> {
+        self.modified = true;
         self.content.get_mut(key)
     }
 
@@ -1031,6 +1088,7 @@ impl VariableStore {
     }
 
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut VariableValue)> {
+        self.modified = true;
         self.content.iter_mut()
     }
 
@@ -1038,11 +1096,43 @@ impl VariableStore {
         self.iter().filter(|(k, _)| k.len() == 1)
     }
 
+    /// Variables meant to be seen by users : internal state keys start with an underscore.
+    pub fn visible_vars(&self) -> impl Iterator<Item = (&String, &VariableValue)> {
+        self.iter().filter(|(k, _)| !k.starts_with('_'))
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<VariableValue> {
+        self.modified = true;
+        self.content.remove(key)
+    }
+
+    /// Tells whether the store may have changed since the last call.
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::take(&mut self.modified)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
 
+    /// Lists what changed since `previous` : new or updated values, and removed keys as `None`.
+    pub fn diff(&self, previous: &VariableStore) -> Vec<(String, Option<VariableValue>)> {
+        let mut changes: Vec<(String, Option<VariableValue>)> = self
+            .iter()
+            .filter(|(k, v)| previous.get(k) != Some(*v))
+            .map(|(k, v)| (k.clone(), Some(v.clone())))
+            .collect();
+        changes.extend(
+            previous
+                .iter()
+                .filter(|(k, _)| !self.content.contains_key(*k))
+                .map(|(k, _)| (k.clone(), None)),
+        );
+        changes
+    }
+
     pub fn clear(&mut self) {
+        self.modified = true;
         self.content.clear();
         self.reset_changes();
     }
@@ -1139,3 +1229,44 @@ impl<T : Into<VariableValue>> From<T> for Variable {
         Variable::Constant(value.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lists_updates_and_removals() {
+        let mut previous = VariableStore::new();
+        previous.insert("kept".to_string(), 1.into());
+        previous.insert("changed".to_string(), 2.into());
+        previous.insert("removed".to_string(), 3.into());
+        let mut current = previous.clone();
+        current.insert("changed".to_string(), 4.into());
+        current.insert("added".to_string(), 5.into());
+        current.remove("removed");
+        let mut diff = current.diff(&previous);
+        diff.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            diff,
+            vec![
+                ("added".to_string(), Some(5.into())),
+                ("changed".to_string(), Some(4.into())),
+                ("removed".to_string(), None),
+            ]
+        );
+        assert!(current.diff(&current).is_empty());
+    }
+
+    #[test]
+    fn mutations_mark_the_store_modified() {
+        let mut store = VariableStore::new();
+        assert!(!store.take_modified());
+        store.insert("x".to_string(), 1.into());
+        assert!(store.take_modified());
+        assert!(!store.take_modified());
+        let _ = store.get("x");
+        assert!(!store.take_modified());
+        store.remove("x");
+        assert!(store.take_modified());
+    }
+}
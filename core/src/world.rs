@@ -1,4 +1,5 @@
 use crossbeam_channel::{self, Receiver, RecvTimeoutError, Sender};
+use rosc::OscTime;
 
 use std::{
     collections::BinaryHeap,
@@ -13,7 +14,9 @@ use crate::{
     clock::{Clock, ClockServer, SyncTime},
     protocol::{
         TimedMessage,
+        ProtocolDevice,
         ProtocolPayload,
+        ramp::Ramp,
     },
     log_println,
 };
@@ -95,6 +98,10 @@ impl World {
     }
 
     fn handle_timed_message(&mut self, mut timed_message: TimedMessage) {
+        if let ProtocolPayload::Ramp(ramp) = &timed_message.message.payload {
+            self.expand_ramp(ramp, &timed_message.message.device, timed_message.time);
+            return;
+        }
         // Regular message - add to queue for timed execution
         let offset = match &timed_message.message.payload {
             ProtocolPayload::LOG(_) => 0,
@@ -110,6 +117,24 @@ impl World {
         self.queue.push(timed_message);
     }
 
+    /// Splits a ramp into the timed messages of each of its steps.
+    /// OSC steps are timetagged, taking the latency of the device into account, but still
+    /// queued until their own date (minus the lookahead), as receivers may ignore timetags.
+    fn expand_ramp(&mut self, ramp: &Ramp, device: &Arc<ProtocolDevice>, date: SyncTime) {
+        for (mut payload, offset) in ramp.expand() {
+            let step_date = date + offset;
+            if let (ProtocolPayload::OSC(osc), Some(out)) = (&mut payload, device.osc_out()) {
+                let latency = (out.latency * 1_000_000.0) as SyncTime;
+                let time = self.clock.to_system_time(step_date + latency);
+                osc.timetag = OscTime::try_from(time).ok().map(Into::into);
+                let step_date = step_date.saturating_sub(self.non_midi_lookahead);
+                self.queue.push(payload.with_device(Arc::clone(device)).timed(step_date));
+                continue;
+            }
+            self.handle_timed_message(payload.with_device(Arc::clone(device)).timed(step_date));
+        }
+    }
+
     fn refresh_next_timeout(&mut self) {
         let Some(next_msg) = self.queue.peek() else {
             self.next_timeout = Duration::MAX;
@@ -0,0 +1,228 @@
+//! Open documents, and what the server knows about their languages.
+
+use std::{
+    collections::BTreeMap,
+    panic::{self, AssertUnwindSafe},
+};
+
+use serde_json::{Value, json};
+use sova_core::{
+    compiler::{CompilationError, Compiler},
+    lang::{
+        bali::{BaliCompiler, bali_forms},
+        boinx::{BOINX_FUNCTIONS, parse_boinx},
+    },
+};
+
+/// LSP kind of the completion items of functions.
+const COMPLETION_FUNCTION: u32 = 3;
+
+/// LSP severity of errors.
+const SEVERITY_ERROR: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Bali,
+    Boinx,
+}
+
+impl Language {
+    /// Language of a document, from its language identifier or the extension of its URI.
+    pub fn detect(language_id: &str, uri: &str) -> Option<Self> {
+        match language_id {
+            "bali" => Some(Language::Bali),
+            "boinx" => Some(Language::Boinx),
+            _ => match uri.rsplit_once('.').map(|(_, ext)| ext) {
+                Some("bali") => Some(Language::Bali),
+                Some("boinx") => Some(Language::Boinx),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Bali => "bali",
+            Language::Boinx => "boinx",
+        }
+    }
+
+    /// Built-in names of the language: (name, signature, description).
+    pub fn builtins(&self) -> &'static [(&'static str, &'static str, &'static str)] {
+        match self {
+            Language::Bali => bali_forms(),
+            Language::Boinx => BOINX_FUNCTIONS,
+        }
+    }
+
+    pub fn is_word_char(&self, c: char) -> bool {
+        match self {
+            Language::Bali => c.is_ascii_alphanumeric() || c == '-' || c == '#',
+            Language::Boinx => c.is_ascii_alphanumeric() || c == '_',
+        }
+    }
+
+    /// Compiles a script, catching the panics of the compilers.
+    fn check(&self, text: &str) -> Result<(), CompilationError> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| match self {
+            Language::Bali => BaliCompiler.compile(text, &BTreeMap::new()).map(|_| ()),
+            Language::Boinx => parse_boinx(text).map(|_| ()),
+        }));
+        result.unwrap_or_else(|_| {
+            Err(CompilationError {
+                lang: self.name().to_owned(),
+                info: "The compiler crashed on this script".to_owned(),
+                from: 0,
+                to: 0,
+            })
+        })
+    }
+}
+
+pub struct Document {
+    pub language: Option<Language>,
+    pub text: String,
+}
+
+/// LSP position (line, UTF-16 character) of a byte offset in a text, clamped to the text.
+pub fn position(text: &str, offset: usize) -> Value {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({ "line": line, "character": character })
+}
+
+/// Byte offset of an LSP position in a text, clamped to its line.
+pub fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    let line_start = text
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Range of the whole text.
+pub fn full_range(text: &str) -> Value {
+    json!({ "start": { "line": 0, "character": 0 }, "end": position(text, text.len()) })
+}
+
+impl Document {
+    /// Diagnostics of the compilation of the document.
+    pub fn diagnostics(&self) -> Vec<Value> {
+        let Some(language) = self.language else {
+            return Vec::new();
+        };
+        match language.check(&self.text) {
+            Ok(()) => Vec::new(),
+            Err(error) => vec![json!({
+                "range": {
+                    "start": position(&self.text, error.from),
+                    "end": position(&self.text, error.to.max(error.from)),
+                },
+                "severity": SEVERITY_ERROR,
+                "source": language.name(),
+                "message": error.info,
+            })],
+        }
+    }
+
+    /// Byte range of the word at a byte offset, if any.
+    fn word_at(&self, language: Language, offset: usize) -> Option<(usize, usize)> {
+        let offset = offset.min(self.text.len());
+        let start = self.text[..offset]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| language.is_word_char(*c))
+            .last()
+            .map_or(offset, |(i, _)| i);
+        let end = self.text[offset..]
+            .char_indices()
+            .find(|(_, c)| !language.is_word_char(*c))
+            .map_or(self.text.len(), |(i, _)| offset + i);
+        (start < end).then_some((start, end))
+    }
+
+    /// Built-in names of the language of the document.
+    pub fn completion(&self) -> Vec<Value> {
+        let Some(language) = self.language else {
+            return Vec::new();
+        };
+        language
+            .builtins()
+            .iter()
+            .map(|(name, signature, description)| {
+                json!({
+                    "label": name,
+                    "kind": COMPLETION_FUNCTION,
+                    "detail": signature,
+                    "documentation": description,
+                })
+            })
+            .collect()
+    }
+
+    /// Documentation of the built-in name at a byte offset.
+    pub fn hover(&self, offset: usize) -> Option<Value> {
+        let language = self.language?;
+        let (start, end) = self.word_at(language, offset)?;
+        let word = &self.text[start..end];
+        let (_, signature, description) = language
+            .builtins()
+            .iter()
+            .find(|(name, _, _)| *name == word)?;
+        Some(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```{}\n{}\n```\n{}", language.name(), signature, description),
+            },
+            "range": { "start": position(&self.text, start), "end": position(&self.text, end) },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_count_utf16_units() {
+        let text = "(note 60)\n; é𝄞 x";
+        let offset_x = text.find('x').unwrap();
+        assert_eq!(
+            position(text, offset_x),
+            json!({ "line": 1, "character": 6 })
+        );
+        assert_eq!(offset(text, &position(text, offset_x)), offset_x);
+    }
+
+    #[test]
+    fn hover_finds_builtin_names() {
+        let document = Document {
+            language: Some(Language::Bali),
+            text: "(> 1 (note-bend 60 100))".to_owned(),
+        };
+        let hover = document.hover(10).unwrap();
+        assert!(
+            hover["contents"]["value"]
+                .as_str()
+                .unwrap()
+                .contains("(note-bend")
+        );
+        assert!(document.hover(1).is_none());
+    }
+}
@@ -0,0 +1,215 @@
+//! Language server for the Bali and Boinx languages of Sova, speaking the Language Server
+//! Protocol over the standard input and output.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, Write},
+};
+
+use crossbeam_channel::Receiver;
+use serde_json::{Value, json};
+use sova_core::{LogMessage, Severity, lang::format};
+
+use crate::{
+    document::{Document, Language},
+    transport::{read_message, write_message},
+};
+
+pub mod document;
+pub mod transport;
+
+/// JSON-RPC error code of unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// LSP synchronization of documents by sending their full text.
+const SYNC_FULL: u32 = 1;
+
+struct Server<W: Write> {
+    output: W,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        write_message(&mut self.output, &message)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Forwards the logs of the compilers to the client.
+    fn forward_logs(&mut self, logs: &Receiver<LogMessage>) -> io::Result<()> {
+        for log in logs.try_iter() {
+            let kind = match log.level {
+                Severity::Fatal | Severity::Error => 1,
+                Severity::Warn => 2,
+                Severity::Info => 3,
+                Severity::Debug => 4,
+            };
+            self.notify(
+                "window/logMessage",
+                json!({ "type": kind, "message": log.msg }),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let diagnostics = self
+            .documents
+            .get(uri)
+            .map(Document::diagnostics)
+            .unwrap_or_default();
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    /// Result of a request, or the code and message of its error.
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let document = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri));
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": SYNC_FULL,
+                    "completionProvider": { "triggerCharacters": ["("] },
+                    "hoverProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "sova-lsp", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/completion" => Ok(json!(
+                document.map(Document::completion).unwrap_or_default()
+            )),
+            "textDocument/hover" => Ok(document
+                .and_then(|document| {
+                    let offset = document::offset(&document.text, &params["position"]);
+                    document.hover(offset)
+                })
+                .unwrap_or(Value::Null)),
+            "textDocument/formatting" => {
+                let Some(document) = document else {
+                    return Ok(Value::Null);
+                };
+                let Some(language) = document.language else {
+                    return Ok(Value::Null);
+                };
+                let options = &params["options"];
+                let indent = if options["insertSpaces"].as_bool().unwrap_or(true) {
+                    " ".repeat(options["tabSize"].as_u64().unwrap_or(2) as usize)
+                } else {
+                    "\t".to_owned()
+                };
+                // Scripts which do not parse are left as they are
+                let Ok(formatted) = format::format_script(language.name(), &document.text, &indent)
+                else {
+                    return Ok(Value::Null);
+                };
+                if formatted == document.text {
+                    return Ok(json!([]));
+                }
+                Ok(json!([{
+                    "range": document::full_range(&document.text),
+                    "newText": formatted,
+                }]))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method {method}"))),
+        }
+    }
+
+    /// Handles a notification. Returns whether the server should exit.
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<bool> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        match method {
+            "exit" => return Ok(true),
+            "textDocument/didOpen" => {
+                let item = &params["textDocument"];
+                let language_id = item["languageId"].as_str().unwrap_or_default();
+                let document = Document {
+                    language: Language::detect(language_id, &uri),
+                    text: item["text"].as_str().unwrap_or_default().to_owned(),
+                };
+                self.documents.insert(uri.clone(), document);
+                self.publish_diagnostics(&uri)?;
+            }
+            "textDocument/didChange" => {
+                // Full synchronization: the last change holds the whole text
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let (Some(document), Some(text)) = (self.documents.get_mut(&uri), text) {
+                    document.text = text.to_owned();
+                }
+                self.publish_diagnostics(&uri)?;
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )?;
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle(&mut self, message: Value) -> io::Result<bool> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+        if method.is_empty() {
+            // Response to a request of the server, which sends none
+            return Ok(false);
+        }
+        let response = match self.request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        self.send(response)?;
+        Ok(false)
+    }
+}
+
+fn main() -> io::Result<()> {
+    // The standard output carries the protocol: logs go through a channel instead
+    let (log_tx, log_rx) = sova_core::create_log_channel();
+    sova_core::init_embedded(log_tx);
+
+    let mut input = BufReader::new(io::stdin().lock());
+    let mut server = Server {
+        output: io::stdout().lock(),
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+    while let Some(message) = read_message(&mut input)? {
+        let exit = server.handle(message)?;
+        server.forward_logs(&log_rx)?;
+        if exit {
+            break;
+        }
+    }
+    if !server.shutdown {
+        std::process::exit(1);
+    }
+    Ok(())
+}
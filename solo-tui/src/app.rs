@@ -1,16 +1,16 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::{HashMap, VecDeque}, sync::Arc};
 
 use crate::{
-    event::{AppEvent, Event, EventHandler, TICK_FPS}, notification::Notification, page::Page, popup::{Popup, PopupValue}, widgets::{configure_widget::ConfigureWidget, devices_widget::DevicesWidget, edit_widget::EditWidget, log_widget::LogWidget, scene_widget::SceneWidget, time_widget::TimeWidget}
+    backend::Backend, event::{AppEvent, Event, EventHandler, TICK_FPS}, notification::Notification, page::Page, popup::{Popup, PopupValue}, widgets::{configure_widget::ConfigureWidget, devices_widget::DevicesWidget, edit_widget::EditWidget, log_widget::LogWidget, scene_widget::SceneWidget, time_widget::TimeWidget, timeline_widget::EVENT_WINDOW, vars_widget::VarsWidget}
 };
 use arboard::Clipboard;
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::Receiver;
 use ratatui::{
     DefaultTerminal,
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
 };
 use sova_core::{
-    LogMessage, Scene, clock::{Clock, ClockServer}, device_map::DeviceMap, vm::{LanguageCenter, variable::VariableValue}, protocol::DeviceInfo, scene::Frame, schedule::{ActionTiming, SchedulerMessage, SovaNotification, playback::PlaybackState}
+    LogMessage, Scene, Severity, clock::{Clock, ClockServer}, vm::variable::VariableValue, protocol::DeviceInfo, scene::Frame, schedule::{ActionTiming, EmittedEvent, SchedulerMessage, SovaNotification, playback::PlaybackState}
 };
 
 pub struct AppState {
@@ -19,13 +19,16 @@ pub struct AppState {
     pub global_vars: HashMap<String, VariableValue>,
     pub playing: PlaybackState,
     pub positions: Vec<(usize, usize)>,
+    /// Beat at which the position of each line was last received
+    pub position_beats: Vec<f64>,
+    /// Events emitted in the last `EVENT_WINDOW` beats
+    pub emitted_events: VecDeque<EmittedEvent>,
     pub clock: Clock,
     pub devices: Vec<DeviceInfo>,
     pub page: Page,
     pub selected: (usize, usize),
     pub events: EventHandler,
-    pub device_map: Arc<DeviceMap>,
-    pub languages: Arc<LanguageCenter>,
+    pub backend: Backend,
     pub clipboard: Option<Clipboard>,
 }
 
@@ -35,18 +38,20 @@ impl AppState {
     }
 
     pub fn refresh_devices(&mut self) {
-        self.devices = self.device_map.device_list();
+        if let Some(devices) = self.backend.device_list() {
+            self.devices = devices;
+        }
     }
 }
 
 /// Application.
 pub struct App {
-    pub sched_iface: Sender<SchedulerMessage>,
     pub state: AppState,
     pub scene_widget: SceneWidget,
     pub edit_widget: EditWidget,
     pub devices_widget: DevicesWidget,
     pub log_widget: LogWidget,
+    pub vars_widget: VarsWidget,
     pub popup: Popup,
     pub notification: Notification,
     frame_counter: u16
@@ -55,33 +60,33 @@ pub struct App {
 impl App {
     /// Constructs a new instance of [`App`].
     pub fn new(
-        sched_iface: Sender<SchedulerMessage>,
+        backend: Backend,
         sched_update: Receiver<SovaNotification>,
         log_rx: Receiver<LogMessage>,
         clock_server: Arc<ClockServer>,
-        device_map: Arc<DeviceMap>,
-        languages: Arc<LanguageCenter>
     ) -> Self {
         App {
-            sched_iface,
             state: AppState {
                 running: Default::default(),
                 scene_image: Default::default(),
                 global_vars: Default::default(),
                 playing: Default::default(),
                 positions: Default::default(),
+                position_beats: Default::default(),
+                emitted_events: Default::default(),
                 clock: clock_server.into(),
                 devices: Default::default(),
                 page: Default::default(),
                 selected: Default::default(),
                 events: EventHandler::new(sched_update, log_rx),
                 clipboard: Clipboard::new().map(|x| Some(x)).unwrap_or_default(),
-                device_map, languages
+                backend
             },
             scene_widget: SceneWidget::default(),
             edit_widget: EditWidget::default(),
             devices_widget: DevicesWidget::default(),
             log_widget: LogWidget::default(),
+            vars_widget: VarsWidget::default(),
             popup: Popup::default(),
             notification: Notification::new(),
             frame_counter: 0
@@ -119,7 +124,7 @@ impl App {
     pub fn handle_app_event(&mut self, event: AppEvent) -> color_eyre::Result<()> {
         match event {
             AppEvent::SchedulerControl(msg) => {
-                let _ = self.sched_iface.send(msg);
+                self.state.backend.send(msg);
             }
             AppEvent::Right => self.state.page.right(),
             AppEvent::Left => self.state.page.left(),
@@ -127,7 +132,7 @@ impl App {
             AppEvent::Down => self.state.page.down(),
             AppEvent::Popup(title, content, value, callback) => 
                 self.popup.open(title, content, value, callback),
-            AppEvent::ChangeScript => self.edit_widget.open(&self.state),
+            AppEvent::ChangeScript => self.edit_widget.open(&mut self.state),
             AppEvent::Info(text) => self.notification.info(text),
             AppEvent::Positive(text) => self.notification.positive(text),
             AppEvent::Negative(text) => self.notification.negative(text),
@@ -184,14 +189,64 @@ impl App {
                 *frame.compilation_state_mut() = state;
             }
             SovaNotification::PlaybackStateChanged(state) => self.state.playing = state,
-            SovaNotification::FramePositionChanged(positions) => self.state.positions = positions,
+            SovaNotification::FramePositionChanged(positions) => {
+                let beat = self.state.clock.beat();
+                self.state.position_beats.resize(positions.len(), beat);
+                for (i, position) in positions.iter().enumerate() {
+                    if self.state.positions.get(i) != Some(position) {
+                        self.state.position_beats[i] = beat;
+                    }
+                }
+                self.state.positions = positions
+            }
+            SovaNotification::EventsEmitted(events) => self.state.emitted_events.extend(events),
+            SovaNotification::RuntimeFaults(faults) => {
+                // A server logs the faults of its scripts, and its logs come to the app
+                if !self.state.backend.is_remote() {
+                    if let Some(fault) = faults.first() {
+                        self.state.events.send(AppEvent::Negative(fault.to_string()));
+                    }
+                    for fault in faults {
+                        self.log(LogMessage::error(format!("[!] Runtime Error: {fault}")));
+                    }
+                }
+            }
             SovaNotification::GlobalVariablesChanged(values) => self.state.global_vars = values,
-            SovaNotification::Log(msg) => self.log(msg),
+            SovaNotification::VariablesChanged(changes) => {
+                self.state.scene_image.apply_variable_changes(&changes)
+            }
+            SovaNotification::SeedChanged(seed) => self.state.scene_image.seed = seed,
+            SovaNotification::ModulatorChanged(name, modulator) => {
+                self.state.scene_image.set_modulator(name, modulator)
+            }
+            SovaNotification::TuningChanged(tuning) => self.state.scene_image.tuning = tuning,
+            SovaNotification::Log(msg) => {
+                // Errors of the server would otherwise go unnoticed
+                if self.state.backend.is_remote() && matches!(msg.level, Severity::Fatal | Severity::Error) {
+                    self.state.events.send(AppEvent::Negative(msg.msg.clone()));
+                }
+                self.log(msg)
+            }
             SovaNotification::DeviceListChanged(devices) => self.state.devices = devices,
+            SovaNotification::ScriptDraftLoaded(line_id, frame_id, revision, content) => self
+                .edit_widget
+                .load_draft(&mut self.state, line_id, frame_id, revision, content),
+            SovaNotification::ScriptEditAcknowledged(line_id, frame_id, revision) => self
+                .edit_widget
+                .acknowledged(&mut self.state, line_id, frame_id, revision),
+            SovaNotification::ScriptEdited(_, line_id, frame_id, revision, operation) => self
+                .edit_widget
+                .peer_edit(&mut self.state, line_id, frame_id, revision, operation),
+            SovaNotification::PeerCursorMoved(name, line_id, frame_id, selection) => self
+                .edit_widget
+                .peer_selection(name, line_id, frame_id, selection),
+            SovaNotification::PeerStoppedEditingFrame(name, line_id, frame_id) => self
+                .edit_widget
+                .peer_selection(name, line_id, frame_id, None),
             SovaNotification::ClientListChanged(_)
             | SovaNotification::ChatReceived(_, _)
             | SovaNotification::PeerStartedEditingFrame(_, _, _)
-            | SovaNotification::PeerStoppedEditingFrame(_, _, _) => (),
+            | SovaNotification::FrameLocksChanged(_) => (),
         }
         Ok(())
     }
@@ -260,7 +315,10 @@ impl App {
                     .process_event(key_event),
                 Page::Configure => 
                     ConfigureWidget::process_event(&mut self.state, key_event),
-                _ => (),
+                Page::Vars => self
+                    .vars_widget
+                    .process_event(&mut self.state, key_event),
+                Page::Timeline => (),
             }
         }
         Ok(())
@@ -272,6 +330,8 @@ impl App {
     /// needs to be updated at a fixed frame rate. E.g. polling a server, updating an animation.
     pub fn tick(&mut self) {
         self.state.clock.capture_app_state();
+        let window_start = self.state.clock.beat() - EVENT_WINDOW;
+        self.state.emitted_events.retain(|e| e.beat + e.duration >= window_start);
         if self.frame_counter == 0 {
             self.state.refresh_devices();
         }
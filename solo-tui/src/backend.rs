@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use crossbeam_channel::Sender;
+use sova_core::{
+    device_map::DeviceMap, protocol::DeviceInfo, schedule::SchedulerMessage,
+    server::client::ClientMessage, vm::LanguageCenter,
+};
+
+use crate::remote::RemoteServer;
+
+/// Where the scene is played: by a scheduler in this process, or by a Sova server.
+pub enum Backend {
+    Local {
+        sched_iface: Sender<SchedulerMessage>,
+        device_map: Arc<DeviceMap>,
+        languages: Arc<LanguageCenter>,
+    },
+    Remote(RemoteServer),
+}
+
+impl Backend {
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Backend::Remote(_))
+    }
+
+    /// The server playing the scene, if any.
+    pub fn remote(&self) -> Option<&RemoteServer> {
+        match self {
+            Backend::Local { .. } => None,
+            Backend::Remote(server) => Some(server),
+        }
+    }
+
+    pub fn send(&self, msg: SchedulerMessage) {
+        match self {
+            Backend::Local { sched_iface, .. } => {
+                let _ = sched_iface.send(msg);
+            }
+            Backend::Remote(server) => {
+                let _ = server.send(ClientMessage::SchedulerControl(msg));
+            }
+        }
+    }
+
+    pub fn languages(&self) -> Vec<String> {
+        match self {
+            Backend::Local { languages, .. } => languages.languages().map(str::to_owned).collect(),
+            Backend::Remote(server) => server.languages.clone(),
+        }
+    }
+
+    /// Current devices of a local scheduler. Those of a server come with its notifications.
+    pub fn device_list(&self) -> Option<Vec<DeviceInfo>> {
+        match self {
+            Backend::Local { device_map, .. } => Some(device_map.device_list()),
+            Backend::Remote(_) => None,
+        }
+    }
+
+    pub fn assign_slot(&self, slot_id: usize, name: &str) -> Result<(), String> {
+        match self {
+            Backend::Local { device_map, .. } => device_map.assign_slot(slot_id, name),
+            Backend::Remote(server) => {
+                server.send(ClientMessage::AssignDeviceToSlot(slot_id, name.to_owned()))
+            }
+        }
+    }
+
+    pub fn unassign_slot(&self, slot_id: usize) -> Result<(), String> {
+        match self {
+            Backend::Local { device_map, .. } => device_map.unassign_slot(slot_id),
+            Backend::Remote(server) => server.send(ClientMessage::UnassignDeviceFromSlot(slot_id)),
+        }
+    }
+
+    pub fn connect_midi(&self, name: &str) -> Result<(), String> {
+        match self {
+            Backend::Local { device_map, .. } => device_map.connect_midi_by_name(name),
+            Backend::Remote(server) => {
+                server.send(ClientMessage::ConnectMidiDeviceByName(name.to_owned()))
+            }
+        }
+    }
+
+    /// Creates an OSC output, or a SuperDirt one if `dirt` is set.
+    pub fn create_osc_output(
+        &self,
+        name: &str,
+        ip: &str,
+        port: u16,
+        dirt: bool,
+    ) -> Result<(), String> {
+        match self {
+            Backend::Local { device_map, .. } if dirt => {
+                device_map.create_superdirt_device(name, ip, port)
+            }
+            Backend::Local { device_map, .. } => {
+                device_map.create_osc_output_device(name, ip, port)
+            }
+            Backend::Remote(server) => {
+                let (name, ip) = (name.to_owned(), ip.to_owned());
+                server.send(if dirt {
+                    ClientMessage::CreateSuperDirtDevice(name, ip, port)
+                } else {
+                    ClientMessage::CreateOscDevice(name, ip, port)
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Client side of the collaborative edition of frame scripts on a server.
+//!
+//! One edit at a time is sent to the server, based on the last revision known. Local edits
+//! made meanwhile wait for its acknowledgement, and the edits of peers are transformed
+//! against the ones the server did not apply yet, as the server transforms them.
+
+use std::collections::VecDeque;
+
+use sova_core::{server::client::ClientMessage, util::text_operation::TextOperation};
+
+/// State of the edition of a frame script shared with a server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptSession {
+    pub line_id: usize,
+    pub frame_id: usize,
+    /// Last revision of the draft received from the server
+    revision: u64,
+    /// Edit sent to the server, waiting for its acknowledgement
+    sent: Option<TextOperation>,
+    /// Local edits made since, each based on the previous one
+    pending: VecDeque<TextOperation>,
+}
+
+impl ScriptSession {
+    pub fn new(line_id: usize, frame_id: usize, revision: u64) -> Self {
+        ScriptSession {
+            line_id,
+            frame_id,
+            revision,
+            sent: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn edits(&self, line_id: usize, frame_id: usize) -> bool {
+        (self.line_id, self.frame_id) == (line_id, frame_id)
+    }
+
+    fn edit_message(&self, op: TextOperation) -> ClientMessage {
+        ClientMessage::EditScript(self.line_id, self.frame_id, self.revision, op)
+    }
+
+    /// A local edit of the text. Returns the message to send, unless an edit is in flight.
+    pub fn local_edit(&mut self, op: TextOperation) -> Option<ClientMessage> {
+        if op.is_noop() {
+            return None;
+        }
+        if self.sent.is_some() {
+            self.pending.push_back(op);
+            return None;
+        }
+        self.sent = Some(op.clone());
+        Some(self.edit_message(op))
+    }
+
+    /// The server applied the edit in flight. Returns the message sending the next one.
+    pub fn acknowledged(&mut self, revision: u64) -> Option<ClientMessage> {
+        self.sent.take()?;
+        self.revision = revision;
+        let op = self.pending.pop_front()?;
+        self.sent = Some(op.clone());
+        Some(self.edit_message(op))
+    }
+
+    /// An edit of a peer, producing the given revision. Returns the operation to apply
+    /// to the local text, or an error if the draft must be reloaded from the server.
+    pub fn remote_edit(&mut self, revision: u64, op: TextOperation) -> Result<TextOperation, String> {
+        if revision != self.revision + 1 {
+            return Err(format!(
+                "Received revision {revision} after revision {}",
+                self.revision
+            ));
+        }
+        let mut op = op;
+        // The server applied the peer edit first: ours are transformed against it
+        for local in self.sent.iter_mut().chain(self.pending.iter_mut()) {
+            let (remote, transformed) = TextOperation::transform(&op, local)?;
+            *local = transformed;
+            op = remote;
+        }
+        self.revision = revision;
+        Ok(op)
+    }
+}
+
+/// Operation turning a text into another, replacing the part in which they differ.
+pub fn diff(old: &str, new: &str) -> TextOperation {
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let inserted: String = new[prefix..new.len() - suffix].iter().collect();
+    TextOperation::new()
+        .retain(prefix)
+        .delete(old.len() - prefix - suffix)
+        .insert(&inserted)
+        .retain(suffix)
+}
+
+/// Index in characters of a position (row, column) in the lines of a text.
+pub fn char_index(lines: &[String], (row, col): (usize, usize)) -> usize {
+    lines
+        .iter()
+        .take(row)
+        .map(|line| line.chars().count() + 1)
+        .sum::<usize>()
+        + col
+}
+
+/// Position (row, column) of an index in characters in a text.
+pub fn char_position(text: &str, index: usize) -> (usize, usize) {
+    let (mut row, mut col) = (0, 0);
+    for c in text.chars().take(index) {
+        if c == '\n' {
+            row += 1;
+            col = 0;
+        } else {
+            col += 1;
+        }
+    }
+    (row, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_replace_the_changed_part() {
+        let op = diff("d1 $ s \"bd\"", "d1 $ s \"sn\"");
+        assert_eq!(
+            op,
+            TextOperation::new()
+                .retain(8)
+                .delete(2)
+                .insert("sn")
+                .retain(1)
+        );
+        assert_eq!(op.apply("d1 $ s \"bd\"").unwrap(), "d1 $ s \"sn\"");
+        assert!(diff("même", "même").is_noop());
+        assert_eq!(diff("aa", "aaa").apply("aa").unwrap(), "aaa");
+        let lines = vec!["é1".to_owned(), "b".to_owned()];
+        assert_eq!(char_index(&lines, (1, 1)), 4);
+        assert_eq!(char_position("é1\nb", 4), (1, 1));
+    }
+
+    #[test]
+    fn edits_wait_for_their_acknowledgement() {
+        let mut session = ScriptSession::new(0, 1, 3);
+        let first = TextOperation::new().insert("a");
+        assert!(matches!(
+            session.local_edit(first.clone()),
+            Some(ClientMessage::EditScript(0, 1, 3, op)) if op == first
+        ));
+        let second = TextOperation::new().retain(1).insert("b");
+        assert!(session.local_edit(second.clone()).is_none());
+        assert!(session.local_edit(TextOperation::new().retain(2)).is_none());
+        assert!(matches!(
+            session.acknowledged(4),
+            Some(ClientMessage::EditScript(0, 1, 4, op)) if op == second
+        ));
+        assert!(session.acknowledged(5).is_none());
+        // Nothing is in flight anymore
+        assert!(session.acknowledged(6).is_none());
+        assert_eq!(session.revision, 5);
+    }
+
+    #[test]
+    fn peer_edits_converge_with_local_ones() {
+        // Local text "x", the server draft is "x" at revision 0
+        let mut session = ScriptSession::new(0, 0, 0);
+        let local = TextOperation::new().retain(1).insert("a");
+        session.local_edit(local.clone());
+        let pending = TextOperation::new().retain(2).insert("b");
+        session.local_edit(pending.clone());
+        let local_text = pending.apply(&local.apply("x").unwrap()).unwrap();
+        // A peer inserted at the start, applied first by the server
+        let peer = TextOperation::new().insert("p").retain(1);
+        let server_text = peer.apply("x").unwrap();
+        let op = session.remote_edit(1, peer).unwrap();
+        let local_text = op.apply(&local_text).unwrap();
+        // Then the server applies our edits, transformed
+        let Some(ClientMessage::EditScript(_, _, 2, sent)) = session.acknowledged(2) else {
+            panic!("The pending edit should be sent");
+        };
+        let server_text = TextOperation::new()
+            .retain(2)
+            .insert("a")
+            .apply(&server_text)
+            .unwrap();
+        let server_text = sent.apply(&server_text).unwrap();
+        assert_eq!(local_text, "pxab");
+        assert_eq!(server_text, local_text);
+        // Revisions must follow each other
+        assert!(session.remote_edit(5, TextOperation::new().retain(4)).is_err());
+    }
+}
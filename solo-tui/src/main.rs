@@ -1,5 +1,6 @@
-use std::{sync::Arc};
+use std::{path::PathBuf, sync::Arc};
 
+use clap::Parser;
 use crossbeam_channel::unbounded;
 use sova_core::{
     Scene, clock::ClockServer, device_map::DeviceMap, init, vm::{
@@ -11,20 +12,65 @@ use sova_core::{
     lang::{boinx::BoinxInterpreterFactory, bali::BaliCompiler}
 };
 
-use crate::app::App;
+use crate::{app::App, backend::Backend, remote::RemoteServer};
 
 pub mod app;
+pub mod backend;
+pub mod collab;
 pub mod event;
 pub mod page;
 pub mod ui;
 pub mod widgets;
 pub mod popup;
 pub mod notification;
+pub mod remote;
+pub mod syntax;
 
 const DEFAULT_TEMPO: f64 = 120.0;
 const DEFAULT_QUANTUM: f64 = 4.0;
 const DEFAULT_MIDI_OUT: &str = "SovaOut";
 
+#[derive(Parser, Debug)]
+#[command(version, about = "Sova terminal interface")]
+struct Cli {
+    /// Connect to a Sova server (HOST:PORT) instead of running a local scheduler
+    #[arg(short, long, value_name = "ADDRESS")]
+    connect: Option<String>,
+
+    /// Client name on the server
+    #[arg(short, long)]
+    name: Option<String>,
+
+    /// Token to log in to the server with (user token or shared secret). It is visible to
+    /// the other users of the machine: prefer --token-file or the SOVA_TOKEN environment variable.
+    #[arg(short, long)]
+    token: Option<String>,
+
+    /// File holding the token to log in to the server with (on its first line)
+    #[arg(long, value_name = "FILE", conflicts_with = "token")]
+    token_file: Option<PathBuf>,
+}
+
+/// Environment variable holding the login token, when not given on the command line.
+const TOKEN_VAR: &str = "SOVA_TOKEN";
+
+/// Token to log in with, from the command line, a file or the environment.
+fn login_token(cli: &Cli) -> Result<Option<String>, String> {
+    let token = if let Some(path) = &cli.token_file {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+        content.lines().next().map(|line| line.trim().to_owned())
+    } else if cli.token.is_some() {
+        cli.token.clone()
+    } else {
+        std::env::var(TOKEN_VAR).ok()
+    };
+    if token.as_deref().is_some_and(str::is_empty) {
+        return Err("The login token is empty.".to_owned());
+    }
+    Ok(token)
+}
+
 fn create_language_center() -> Arc<LanguageCenter> {
     let mut transcoder = Transcoder::default();
     transcoder.add_compiler(BaliCompiler);
@@ -37,10 +83,31 @@ fn create_language_center() -> Arc<LanguageCenter> {
 }
 
 fn main() -> color_eyre::Result<()> {
+    let cli = Cli::parse();
     let (log_tx, log_rx) = unbounded();
     sova_core::logger::init_embedded(log_tx);
 
     let clock_server = Arc::new(ClockServer::new(DEFAULT_TEMPO, DEFAULT_QUANTUM));
+
+    if let Some(address) = cli.connect.clone() {
+        let token = login_token(&cli).map_err(|e| color_eyre::eyre::eyre!(e))?;
+        let name = cli
+            .name
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_else(|| "solo-tui".to_owned());
+        let (server, notifications) = RemoteServer::connect(&address, name, token)
+            .map_err(|e| color_eyre::eyre::eyre!("Failed to connect to {address}: {e}"))?;
+        // The clock of the server is shared through Link
+        clock_server.link.enable(true);
+
+        color_eyre::install()?;
+        let terminal = ratatui::init();
+        let result = App::new(Backend::Remote(server), notifications, log_rx, clock_server)
+            .run(terminal);
+        ratatui::restore();
+        return result;
+    }
+
     let languages = create_language_center();
     let devices = Arc::new(DeviceMap::new());
 
@@ -59,13 +126,15 @@ fn main() -> color_eyre::Result<()> {
         ActionTiming::Immediate,
     ));
 
+    let backend = Backend::Local {
+        sched_iface: sched_iface.clone(),
+        device_map: devices.clone(),
+        languages,
+    };
+
     color_eyre::install()?;
     let terminal = ratatui::init();
-    let result = App::new(
-        sched_iface.clone(), 
-        sched_updates, 
-        log_rx, clock_server, devices.clone(), languages.clone()
-    ).run(terminal);
+    let result = App::new(backend, sched_updates, log_rx, clock_server).run(terminal);
     ratatui::restore();
 
     devices.panic_all_midi_outputs();
@@ -75,3 +144,21 @@ fn main() -> color_eyre::Result<()> {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_read_from_files() {
+        let path = std::env::temp_dir().join(format!("sova-token-{}", std::process::id()));
+        std::fs::write(&path, "t0k \nignored").unwrap();
+        let cli = Cli::parse_from(["solo-tui", "--token-file", path.to_str().unwrap()]);
+        assert_eq!(login_token(&cli), Ok(Some("t0k".to_owned())));
+        std::fs::write(&path, "\n").unwrap();
+        assert!(login_token(&cli).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(login_token(&cli).is_err());
+        assert!(Cli::try_parse_from(["solo-tui", "-t", "a", "--token-file", "f"]).is_err());
+    }
+}
@@ -1,5 +1,5 @@
 // Map
-// T C
+// T C P
 // D S E
 //   L V
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -12,6 +12,7 @@ pub enum Page {
     Time,
     Logs,
     Vars,
+    Timeline,
 }
 
 impl Page {
@@ -24,6 +25,7 @@ impl Page {
             Page::Time => Page::Time,
             Page::Logs => Page::Logs,
             Page::Vars => Page::Logs,
+            Page::Timeline => Page::Configure,
         }
     }
 
@@ -32,10 +34,11 @@ impl Page {
             Page::Scene => Page::Edit,
             Page::Devices => Page::Scene,
             Page::Edit => Page::Edit,
-            Page::Configure => Page::Configure,
+            Page::Configure => Page::Timeline,
             Page::Time => Page::Configure,
             Page::Logs => Page::Vars,
             Page::Vars => Page::Vars,
+            Page::Timeline => Page::Timeline,
         }
     }
 
@@ -43,11 +46,12 @@ impl Page {
         *self = match self {
             Page::Scene => Page::Configure,
             Page::Devices => Page::Time,
-            Page::Edit => Page::Edit,
+            Page::Edit => Page::Timeline,
             Page::Configure => Page::Configure,
             Page::Time => Page::Time,
             Page::Logs => Page::Scene,
             Page::Vars => Page::Edit,
+            Page::Timeline => Page::Timeline,
         }
     }
 
@@ -60,6 +64,7 @@ impl Page {
             Page::Time => Page::Devices,
             Page::Logs => Page::Logs,
             Page::Vars => Page::Vars,
+            Page::Timeline => Page::Edit,
         }
     }
 }
@@ -0,0 +1,325 @@
+//! Connection to a Sova server, for the `--connect` mode.
+//!
+//! The scene is then played by the server: scheduler messages are sent to it as
+//! `ClientMessage::SchedulerControl`, and its messages are turned back into scheduler
+//! notifications, so that the rest of the app works as with a local scheduler.
+
+use std::thread;
+
+use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
+use sova_core::{
+    LogMessage,
+    schedule::{SovaNotification, playback::PlaybackState},
+    server::{
+        ServerMessage,
+        auth::Secret,
+        client::{ClientMessage, SovaClient},
+    },
+};
+use tokio::{select, sync::mpsc};
+
+/// An open connection to a Sova server.
+pub struct RemoteServer {
+    pub address: String,
+    /// Languages available on the server
+    pub languages: Vec<String>,
+    sender: mpsc::UnboundedSender<ClientMessage>,
+}
+
+impl RemoteServer {
+    /// Connects and logs in to a server. Returns the connection, and the notifications
+    /// built from the server messages.
+    pub fn connect(
+        address: &str,
+        name: String,
+        token: Option<String>,
+    ) -> Result<(Self, Receiver<SovaNotification>), String> {
+        let (ip, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Invalid address '{address}' (HOST:PORT)"))?;
+        let port: u16 = port.parse().map_err(|_| format!("Invalid port '{port}'"))?;
+        let client = SovaClient::new(ip.to_owned(), port);
+        let (sender, messages) = mpsc::unbounded_channel();
+        let (notifier, notifications) = unbounded();
+        let (hello_sender, hello) = bounded(1);
+        thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = hello_sender.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            runtime.block_on(run_connection(
+                client,
+                name,
+                token,
+                messages,
+                notifier,
+                hello_sender,
+            ));
+        });
+        let languages = hello
+            .recv()
+            .map_err(|_| "Connection thread stopped".to_owned())??;
+        let server = RemoteServer {
+            address: address.to_owned(),
+            languages,
+            sender,
+        };
+        Ok((server, notifications))
+    }
+
+    pub fn send(&self, msg: ClientMessage) -> Result<(), String> {
+        self.sender
+            .send(msg)
+            .map_err(|_| format!("Disconnected from {}", self.address))
+    }
+}
+
+/// Notifications equivalent to a server message.
+fn notifications(msg: ServerMessage) -> Vec<SovaNotification> {
+    let notification = match msg {
+        ServerMessage::Hello {
+            scene,
+            devices,
+            peers,
+            is_playing,
+            frame_locks,
+            ..
+        } => {
+            let playing = if is_playing {
+                PlaybackState::Playing
+            } else {
+                PlaybackState::Stopped
+            };
+            return vec![
+                SovaNotification::UpdatedScene(scene),
+                SovaNotification::DeviceListChanged(devices),
+                SovaNotification::ClientListChanged(peers),
+                SovaNotification::PlaybackStateChanged(playing),
+                SovaNotification::FrameLocksChanged(frame_locks),
+            ];
+        }
+        ServerMessage::SceneValue(scene) => SovaNotification::UpdatedScene(scene),
+        ServerMessage::LineValues(lines) => SovaNotification::UpdatedLines(lines),
+        ServerMessage::LineConfigurations(lines) => {
+            SovaNotification::UpdatedLineConfigurations(lines)
+        }
+        ServerMessage::AddLine(line_id, line) => SovaNotification::AddedLine(line_id, line),
+        ServerMessage::RemoveLine(line_id) => SovaNotification::RemovedLine(line_id),
+        ServerMessage::FrameValues(frames) => SovaNotification::UpdatedFrames(frames),
+        ServerMessage::AddFrame(line_id, frame_id, frame) => {
+            SovaNotification::AddedFrame(line_id, frame_id, frame)
+        }
+        ServerMessage::RemoveFrame(line_id, frame_id) => {
+            SovaNotification::RemovedFrame(line_id, frame_id)
+        }
+        ServerMessage::CompilationUpdate(line_id, frame_id, script_id, state) => {
+            SovaNotification::CompilationUpdated(line_id, frame_id, script_id, state)
+        }
+        ServerMessage::PlaybackStateChanged(state) => SovaNotification::PlaybackStateChanged(state),
+        ServerMessage::FramePosition(positions) => {
+            SovaNotification::FramePositionChanged(positions)
+        }
+        ServerMessage::EmittedEvents(events) => SovaNotification::EventsEmitted(events),
+        ServerMessage::RuntimeFaults(faults) => SovaNotification::RuntimeFaults(faults),
+        ServerMessage::GlobalVariablesUpdate(vars) => {
+            SovaNotification::GlobalVariablesChanged(vars)
+        }
+        ServerMessage::VariablesUpdate(changes) => SovaNotification::VariablesChanged(changes),
+        ServerMessage::SeedChanged(seed) => SovaNotification::SeedChanged(seed),
+        ServerMessage::ModulatorChanged(name, modulator) => {
+            SovaNotification::ModulatorChanged(name, modulator)
+        }
+        ServerMessage::TuningChanged(tuning) => SovaNotification::TuningChanged(tuning),
+        ServerMessage::DeviceList(devices) => SovaNotification::DeviceListChanged(devices),
+        ServerMessage::PeersUpdated(peers) => SovaNotification::ClientListChanged(peers),
+        ServerMessage::Chat(name, text) => SovaNotification::ChatReceived(name, text),
+        ServerMessage::PeerStartedEditing(name, line_id, frame_id) => {
+            SovaNotification::PeerStartedEditingFrame(name, line_id, frame_id)
+        }
+        ServerMessage::PeerStoppedEditing(name, line_id, frame_id) => {
+            SovaNotification::PeerStoppedEditingFrame(name, line_id, frame_id)
+        }
+        ServerMessage::FrameLocks(locks) => SovaNotification::FrameLocksChanged(locks),
+        ServerMessage::ScriptDraft(line_id, frame_id, revision, content) => {
+            SovaNotification::ScriptDraftLoaded(line_id, frame_id, revision, content)
+        }
+        ServerMessage::ScriptEditAck(line_id, frame_id, revision) => {
+            SovaNotification::ScriptEditAcknowledged(line_id, frame_id, revision)
+        }
+        ServerMessage::ScriptEdited(name, line_id, frame_id, revision, operation) => {
+            SovaNotification::ScriptEdited(name, line_id, frame_id, revision, operation)
+        }
+        ServerMessage::PeerCursor(name, line_id, frame_id, selection) => {
+            SovaNotification::PeerCursorMoved(name, line_id, frame_id, selection)
+        }
+        ServerMessage::Log(msg) => SovaNotification::Log(msg),
+        ServerMessage::InternalError(e)
+        | ServerMessage::PermissionDenied(e)
+        | ServerMessage::ConnectionRefused(e) => SovaNotification::Log(LogMessage::error(e)),
+        // The clock follows the server through Link
+        _ => return Vec::new(),
+    };
+    vec![notification]
+}
+
+/// Logs in, then forwards the messages of the app to the server and the notifications
+/// of the server to the app, until one of them disconnects.
+async fn run_connection(
+    mut client: SovaClient,
+    name: String,
+    token: Option<String>,
+    mut messages: mpsc::UnboundedReceiver<ClientMessage>,
+    notifier: Sender<SovaNotification>,
+    hello_sender: Sender<Result<Vec<String>, String>>,
+) {
+    let hello = async {
+        client.connect().await?;
+        client.send(ClientMessage::Login(name, token.map(Secret::from))).await?;
+        client.read().await
+    };
+    let msg = match hello.await {
+        Ok(msg) => msg,
+        Err(e) => {
+            let _ = hello_sender.send(Err(e.to_string()));
+            return;
+        }
+    };
+    let languages = match &msg {
+        ServerMessage::Hello {
+            available_languages,
+            ..
+        } => available_languages.clone(),
+        ServerMessage::ConnectionRefused(reason) => {
+            let _ = hello_sender.send(Err(reason.clone()));
+            return;
+        }
+        msg => {
+            let _ = hello_sender.send(Err(format!("Unexpected answer: {msg:?}")));
+            return;
+        }
+    };
+    let _ = hello_sender.send(Ok(languages));
+    for notification in notifications(msg) {
+        let _ = notifier.send(notification);
+    }
+    // Every variable, as with a local scheduler
+    let _ = client
+        .send(ClientMessage::SubscribeVariables(vec!["*".to_owned()]))
+        .await;
+    loop {
+        select! {
+            // Waiting for data does not consume it, unlike a partial read
+            ready = client.ready() => {
+                if !ready {
+                    break;
+                }
+                match client.read().await {
+                    Ok(msg) => {
+                        for notification in notifications(msg) {
+                            if notifier.send(notification).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+            msg = messages.recv() => {
+                let Some(msg) = msg else {
+                    let _ = client.disconnect().await;
+                    return;
+                };
+                if client.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = notifier.send(SovaNotification::Log(LogMessage::error(
+        "Disconnected from the server".to_owned(),
+    )));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sova_core::{
+        Scene,
+        schedule::{EmittedEvent, EventKind},
+        server::auth::Role,
+        util::text_operation::TextOperation,
+        vm::{RuntimeFault, SourcePosition},
+    };
+
+    #[test]
+    fn server_messages_become_notifications() {
+        let hello = ServerMessage::Hello {
+            username: "alice".to_owned(),
+            scene: Scene::default(),
+            devices: Vec::new(),
+            peers: vec!["bob".to_owned()],
+            link_state: (120.0, 0.0, 0.0, 1, true),
+            is_playing: true,
+            available_languages: vec!["bali".to_owned()],
+            role: Role::Performer,
+            frame_locks: vec![(0, 1, "bob".to_owned())],
+        };
+        let hello = notifications(hello);
+        assert_eq!(hello.len(), 5);
+        assert!(matches!(&hello[0], SovaNotification::UpdatedScene(_)));
+        assert!(matches!(&hello[2], SovaNotification::ClientListChanged(peers) if peers == &["bob"]));
+        assert!(matches!(
+            hello[3],
+            SovaNotification::PlaybackStateChanged(PlaybackState::Playing)
+        ));
+        assert!(matches!(&hello[4], SovaNotification::FrameLocksChanged(locks) if locks.len() == 1));
+
+        let op = TextOperation::new().insert("a");
+        assert!(matches!(
+            notifications(ServerMessage::ScriptEdited("bob".to_owned(), 0, 1, 4, op.clone()))[..],
+            [SovaNotification::ScriptEdited(_, 0, 1, 4, ref edit)] if *edit == op
+        ));
+        assert!(matches!(
+            notifications(ServerMessage::ScriptDraft(0, 1, 4, "a".to_owned()))[..],
+            [SovaNotification::ScriptDraftLoaded(0, 1, 4, _)]
+        ));
+        let event = EmittedEvent {
+            line_id: 1,
+            beat: 2.0,
+            note: Some(60),
+            duration: 0.5,
+            kind: EventKind::Note(60),
+        };
+        assert!(matches!(
+            &notifications(ServerMessage::EmittedEvents(vec![event.clone()]))[..],
+            [SovaNotification::EventsEmitted(events)] if events[..] == [event]
+        ));
+        let fault = RuntimeFault {
+            line_index: 0,
+            frame_index: 1,
+            message: "Division by zero".to_owned(),
+            position: Some(SourcePosition { line: 2, column: 3 }),
+        };
+        assert!(matches!(
+            &notifications(ServerMessage::RuntimeFaults(vec![fault.clone()]))[..],
+            [SovaNotification::RuntimeFaults(faults)] if faults[..] == [fault]
+        ));
+        assert!(matches!(
+            notifications(ServerMessage::RemoveFrame(2, 3))[..],
+            [SovaNotification::RemovedFrame(2, 3)]
+        ));
+        // Errors of the server are logged
+        assert!(matches!(
+            &notifications(ServerMessage::PermissionDenied("No".to_owned()))[..],
+            [SovaNotification::Log(msg)] if msg.msg == "No"
+        ));
+        // The clock follows the server through Link
+        assert!(notifications(ServerMessage::ClockState(120.0, 0.0, 0, 4.0)).is_empty());
+    }
+}
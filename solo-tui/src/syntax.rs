@@ -0,0 +1,316 @@
+//! Syntax highlighting of the script languages.
+//!
+//! Lines are split into tokens following the terminals of the grammars of the languages
+//! (`bali_grammar.lalrpop` and `boinx.pest`), without parsing them: a line being edited
+//! is highlighted even when it does not compile.
+
+use std::ops::Range;
+
+use sova_core::lang::bali::bali_forms;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    Function,
+    Parameter,
+    Number,
+    Duration,
+    Note,
+    Text,
+    Comment,
+    Operator,
+    Delimiter,
+    Name,
+}
+
+/// A token of a line, as a byte range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub range: Range<usize>,
+    pub kind: TokenKind,
+}
+
+/// Bali forms structuring a program.
+const BALI_KEYWORDS: &[&str] = &[
+    "alt", "at", "binloop", "def", "eucloop", "for", "fun", "if", "loop", "pick", "seq",
+    "spread", "with",
+];
+
+/// Bali operators written as words.
+const BALI_WORD_OPERATORS: &[&str] = &["and", "geq", "gt", "leq", "lt", "not", "or"];
+
+/// Whether a word names a Bali form producing events or values, as read from the grammar.
+fn is_bali_builtin(word: &str) -> bool {
+    !BALI_KEYWORDS.contains(&word)
+        && !BALI_WORD_OPERATORS.contains(&word)
+        && bali_forms().iter().any(|(name, _, _)| *name == word)
+}
+
+/// Tokens of a line of a script. Languages without highlighting have no tokens.
+pub fn tokenize(lang: &str, line: &str) -> Vec<Token> {
+    match lang {
+        "bali" => tokenize_bali(line),
+        "boinx" => tokenize_boinx(line),
+        _ => Vec::new(),
+    }
+}
+
+/// End of the run of characters matching `f` from `start`.
+fn scan(line: &str, start: usize, f: impl Fn(char) -> bool) -> usize {
+    line[start..]
+        .char_indices()
+        .find(|(_, c)| !f(*c))
+        .map_or(line.len(), |(i, _)| start + i)
+}
+
+/// End of a string literal starting at `start`, or of the line if it is not closed.
+fn scan_string(line: &str, start: usize, quote: char, escapes: bool) -> usize {
+    let mut escaped = false;
+    for (i, c) in line[start + 1..].char_indices() {
+        if escaped {
+            escaped = false;
+        } else if escapes && c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return start + 1 + i + c.len_utf8();
+        }
+    }
+    line.len()
+}
+
+/// End of a number starting at `start`: `-?[0-9]+` or `-?[0-9]*\.[0-9]+`.
+fn scan_number(line: &str, start: usize) -> usize {
+    let start = if line[start..].starts_with('-') { start + 1 } else { start };
+    let end = scan(line, start, |c| c.is_ascii_digit());
+    if line[end..].starts_with('.') {
+        let decimals = scan(line, end + 1, |c| c.is_ascii_digit());
+        if decimals > end + 1 {
+            return decimals;
+        }
+    }
+    end
+}
+
+fn is_bali_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '#'
+}
+
+fn tokenize_bali(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    // Whether the previous token opened a form, so that a name is its head
+    let mut form_head = false;
+    while let Some((start, c)) = chars.next() {
+        let (end, kind) = match c {
+            ';' => (line.len(), TokenKind::Comment),
+            '"' => (scan_string(line, start, '"', true), TokenKind::Text),
+            '(' | ')' | '[' | ']' | '{' | '}' => (start + 1, TokenKind::Delimiter),
+            ':' => {
+                let end = scan(line, start + 1, |c| c.is_ascii_alphanumeric() || c == '_');
+                (end, TokenKind::Parameter)
+            }
+            '-' if line[start + 1..].starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+                (scan_number(line, start), TokenKind::Number)
+            }
+            '.' | '0'..='9' => (scan_number(line, start), TokenKind::Number),
+            c if c.is_ascii_alphabetic() => {
+                let end = scan(line, start, is_bali_name);
+                let word = &line[start..end];
+                if line[end..].starts_with(':') {
+                    // Named arguments: `dur:`, `ch:`, `dev:`, `v:`, `sh:`
+                    (end + 1, TokenKind::Parameter)
+                } else if form_head && BALI_KEYWORDS.contains(&word) {
+                    (end, TokenKind::Keyword)
+                } else if form_head && BALI_WORD_OPERATORS.contains(&word) {
+                    (end, TokenKind::Operator)
+                } else if form_head || is_bali_builtin(word) {
+                    (end, TokenKind::Function)
+                } else if word == "linear" {
+                    (end, TokenKind::Keyword)
+                } else {
+                    (end, TokenKind::Name)
+                }
+            }
+            c if c.is_whitespace() => continue,
+            _ => {
+                let end = scan(line, start, |c| "+-*/%<>=!?".contains(c));
+                (end.max(start + c.len_utf8()), TokenKind::Operator)
+            }
+        };
+        form_head = c == '(';
+        while chars.peek().is_some_and(|(i, _)| *i < end) {
+            chars.next();
+        }
+        tokens.push(Token {
+            range: start..end,
+            kind,
+        });
+    }
+    tokens
+}
+
+fn is_boinx_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// End of a note (`[A-G][#b]*[0-9]*` not followed by a letter) starting at `start`.
+fn scan_note(line: &str, start: usize) -> Option<usize> {
+    if !line[start..].starts_with(|c: char| ('A'..='G').contains(&c)) {
+        return None;
+    }
+    let end = scan(line, start + 1, |c| c == '#' || c == 'b');
+    let end = scan(line, end, |c| c.is_ascii_digit());
+    (!line[end..].starts_with(|c: char| c.is_ascii_alphabetic())).then_some(end)
+}
+
+fn tokenize_boinx(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let (end, kind) = match c {
+            '/' if line[start..].starts_with("//") => (line.len(), TokenKind::Comment),
+            '"' | '\'' => (scan_string(line, start, c, false), TokenKind::Text),
+            '(' | ')' | '[' | ']' | '{' | '}' => (start + 1, TokenKind::Delimiter),
+            '0'..='9' => {
+                let end = scan_number(line, start);
+                let rest = &line[end..];
+                if rest.starts_with('u') {
+                    (end + 1, TokenKind::Duration)
+                } else if rest.starts_with("''") {
+                    (end + 2, TokenKind::Duration)
+                } else if rest.starts_with('\'') {
+                    (end + 1, TokenKind::Duration)
+                } else {
+                    (end, TokenKind::Number)
+                }
+            }
+            '.' => (start + 1, TokenKind::Keyword),
+            '_' if !line[start + 1..].starts_with(is_boinx_name) => {
+                (start + 1, TokenKind::Keyword)
+            }
+            '$' | '_' => {
+                // Qualified names: `$x`, `$l_x`, `$f_x` and `_x`
+                let end = scan(line, start + 1, is_boinx_name);
+                (end, TokenKind::Name)
+            }
+            c if c.is_ascii_alphanumeric() => match scan_note(line, start) {
+                Some(end) => (end, TokenKind::Note),
+                None => {
+                    let end = scan(line, start, is_boinx_name);
+                    let kind = if line[end..].starts_with('(') {
+                        TokenKind::Function
+                    } else {
+                        TokenKind::Name
+                    };
+                    (end, kind)
+                }
+            },
+            c if c.is_whitespace() => continue,
+            _ => (start + c.len_utf8(), TokenKind::Operator),
+        };
+        while chars.peek().is_some_and(|(i, _)| *i < end) {
+            chars.next();
+        }
+        tokens.push(Token {
+            range: start..end,
+            kind,
+        });
+    }
+    tokens
+}
+
+/// Position (line, character) of a byte offset in a text, clamped to the text.
+pub fn position(text: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds<'a>(lang: &str, line: &'a str) -> Vec<(&'a str, TokenKind)> {
+        tokenize(lang, line)
+            .into_iter()
+            .map(|token| (&line[token.range], token.kind))
+            .collect()
+    }
+
+    #[test]
+    fn bali_tokens() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("bali", "(loop 4 (note 60 dur:-0.5)) ; bass"),
+            vec![
+                ("(", Delimiter),
+                ("loop", Keyword),
+                ("4", Number),
+                ("(", Delimiter),
+                ("note", Function),
+                ("60", Number),
+                ("dur:", Parameter),
+                ("-0.5", Number),
+                (")", Delimiter),
+                (")", Delimiter),
+                ("; bass", Comment),
+            ]
+        );
+        // Keywords and operators only head forms
+        assert_eq!(
+            kinds("bali", "(and loop x) (<= a \"é\\\"\")"),
+            vec![
+                ("(", Delimiter),
+                ("and", Operator),
+                ("loop", Name),
+                ("x", Name),
+                (")", Delimiter),
+                ("(", Delimiter),
+                ("<=", Operator),
+                ("a", Name),
+                ("\"é\\\"\"", Text),
+                (")", Delimiter),
+            ]
+        );
+        // Unclosed strings run to the end of the line
+        assert_eq!(kinds("bali", "\"open"), vec![("\"open", Text)]);
+    }
+
+    #[test]
+    fn boinx_tokens() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("boinx", "C#4 2'' $x _ f(3u) // end"),
+            vec![
+                ("C#4", Note),
+                ("2''", Duration),
+                ("$x", Name),
+                ("_", Keyword),
+                ("f", Function),
+                ("(", Delimiter),
+                ("3u", Duration),
+                (")", Delimiter),
+                ("// end", Comment),
+            ]
+        );
+        assert_eq!(kinds("boinx", "Cat 'a'"), vec![("Cat", Name), ("'a'", Text)]);
+        assert!(tokenize("other", "(note 60)").is_empty());
+    }
+
+    #[test]
+    fn positions_count_characters() {
+        let text = "(note 60)\n(dirt \"é\" 1)";
+        assert_eq!(position(text, 0), (0, 0));
+        assert_eq!(position(text, 10), (1, 0));
+        // After the two bytes of "é"
+        assert_eq!(position(text, 19), (1, 8));
+        // Inside a character, and past the end
+        assert_eq!(position(text, 18), (1, 7));
+        assert_eq!(position(text, 100), (1, 12));
+    }
+}
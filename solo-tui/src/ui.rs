@@ -7,7 +7,7 @@ use ratatui::{
 use crate::{
     app::App,
     page::Page,
-    widgets::{configure_widget::ConfigureWidget, footer::Footer, header::Header, time_widget::TimeWidget},
+    widgets::{configure_widget::ConfigureWidget, footer::Footer, header::Header, time_widget::TimeWidget, timeline_widget::TimelineWidget},
 };
 
 impl Widget for &mut App {
@@ -50,7 +50,15 @@ impl Widget for &mut App {
                 self.log_widget.render(content_area, buf);
                 "logs"
             }
-            Page::Vars => "variables",
+            Page::Vars => {
+                self.vars_widget
+                    .render(content_area, buf, &mut self.state);
+                "variables"
+            }
+            Page::Timeline => {
+                TimelineWidget.render(content_area, buf, &mut self.state);
+                "timeline"
+            }
         };
 
         Header::default().render(header_area, buf, &mut self.state);
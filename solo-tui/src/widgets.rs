@@ -5,4 +5,6 @@ pub mod header;
 pub mod log_widget;
 pub mod scene_widget;
 pub mod time_widget;
+pub mod timeline_widget;
 pub mod configure_widget;
+pub mod vars_widget;
@@ -1,6 +1,6 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{buffer::Buffer, layout::{Constraint, Flex, Layout, Rect}, widgets::{Paragraph, StatefulWidget, Widget}};
-use sova_core::{schedule::{ActionTiming, SchedulerMessage}, server::Snapshot};
+use sova_core::{schedule::{ActionTiming, SchedulerMessage}, server::Snapshot, theory::Tuning};
 
 use crate::{app::AppState, event::AppEvent, popup::PopupValue};
 
@@ -11,6 +11,8 @@ impl ConfigureWidget {
         "\
         C-S: Save \n\
         C-L: Load \n\
+        C-R: Random seed \n\
+        C-T: Tuning \n\
         "
     }
 
@@ -74,6 +76,67 @@ impl ConfigureWidget {
                     })
                 ));
             } 
+            KeyCode::Char('r') if event.modifiers == KeyModifiers::CONTROL => {
+                let current = state.scene_image.seed.map(|s| s.to_string()).unwrap_or_default();
+                state.events.send(AppEvent::Popup(
+                    "Random seed".to_owned(),
+                    "Seed of the scene (empty for non reproducible randomness)".to_owned(),
+                    PopupValue::Text(current),
+                    Box::new(|state, x| {
+                        let input = String::from(x);
+                        let input = input.trim();
+                        let seed = if input.is_empty() {
+                            None
+                        } else if let Ok(seed) = input.parse::<u64>() {
+                            Some(seed)
+                        } else {
+                            state.events.send(AppEvent::Negative("Invalid seed !".to_owned()));
+                            return;
+                        };
+                        state.events.send(
+                            AppEvent::SchedulerControl(SchedulerMessage::SetSeed(seed, ActionTiming::Immediate))
+                        );
+                    })
+                ));
+            }
+            KeyCode::Char('t') if event.modifiers == KeyModifiers::CONTROL => {
+                state.events.send(AppEvent::Popup(
+                    "Tuning".to_owned(),
+                    "Path of a .scl file, optionally followed by a .kbm file (empty for 12-TET)".to_owned(),
+                    PopupValue::Text(String::new()),
+                    Box::new(|state, x| {
+                        let input = String::from(x);
+                        let mut paths = input.split_whitespace();
+                        let tuning = match paths.next() {
+                            None => None,
+                            Some(scl_path) => {
+                                let Ok(scl) = std::fs::read_to_string(scl_path) else {
+                                    state.events.send(AppEvent::Negative("Failed to read scale file !".to_owned()));
+                                    return;
+                                };
+                                let kbm = match paths.next().map(std::fs::read_to_string) {
+                                    Some(Ok(kbm)) => Some(kbm),
+                                    Some(Err(_)) => {
+                                        state.events.send(AppEvent::Negative("Failed to read mapping file !".to_owned()));
+                                        return;
+                                    }
+                                    None => None,
+                                };
+                                match Tuning::from_scala(&scl, kbm.as_deref()) {
+                                    Ok(tuning) => Some(tuning),
+                                    Err(e) => {
+                                        state.events.send(AppEvent::Negative(format!("Invalid tuning : {e}")));
+                                        return;
+                                    }
+                                }
+                            }
+                        };
+                        state.events.send(
+                            AppEvent::SchedulerControl(SchedulerMessage::SetTuning(tuning, ActionTiming::Immediate))
+                        );
+                    })
+                ));
+            }
             _ => ()
         }
     }
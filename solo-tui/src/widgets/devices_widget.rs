@@ -37,7 +37,7 @@ impl DevicesWidget {
                     format!("Which slot to assign device {} ?", dev.name), 
                     PopupValue::Int(1), 
                     Box::new(move |state, x| {
-                        let _ = state.device_map.assign_slot(i64::from(x) as usize, &name);
+                        let _ = state.backend.assign_slot(i64::from(x) as usize, &name);
                         state.refresh_devices();
                     })
                 ));
@@ -48,11 +48,14 @@ impl DevicesWidget {
                 };
                 let dev = &state.devices[selected];
                 if let Some(id) = dev.slot_id {
-                    let _ = state.device_map.unassign_slot(id);
+                    let _ = state.backend.unassign_slot(id);
                 }
             }
             KeyCode::Char('o') => {
-                Self::create_osc_out(state);
+                Self::create_osc_out(state, false);
+            }
+            KeyCode::Char('d') => {
+                Self::create_osc_out(state, true);
             }
             KeyCode::Char('m') => {
                 let Some(selected) = self.state.selected() else {
@@ -68,31 +71,35 @@ impl DevicesWidget {
         "\
         A: Assign      O: Create OSC Out\n\
         U: Unassign    M: Connect Midi Out\n\
+        D: Create SuperDirt Out\n\
         "
     }
 
     pub fn connect_midi(selected : usize, state: &mut AppState) {
         let dev = &state.devices[selected];
-        if let Err(s) = state.device_map.connect_midi_by_name(&dev.name) {
+        if let Err(s) = state.backend.connect_midi(&dev.name) {
             state.events.send(AppEvent::Negative(s));
         } else {
             state.events.send(AppEvent::Positive(format!("Connected MIDI device {}", dev.name)));
         }
     }
 
-    pub fn create_osc_out(state: &mut AppState) {
+    pub fn create_osc_out(state: &mut AppState, dirt: bool) {
+        let kind = if dirt { "SuperDirt" } else { "OSC" };
         let ev = AppEvent::Popup(
-            "Create OSC Out".to_owned(), 
-            "Configure a new OSC Output (name:ip:port)".to_owned(), 
+            format!("Create {kind} Out"),
+            format!("Configure a new {kind} Output (name:ip:port)"),
             PopupValue::Text(String::default()), 
-            Box::new(|state, x| {
+            Box::new(move |state, x| {
                 let input = String::from(x);
                 let vec : Vec<&str> = input.split(":").collect();
                 if vec.len() != 3 {
                     state.events.send(AppEvent::Negative("Wrong address format !".to_owned()));
                     return;
                 }
-                match state.device_map.create_osc_output_device(vec[0], vec[1], vec[2].parse().unwrap_or_default()) {
+                let port = vec[2].parse().unwrap_or_default();
+                let res = state.backend.create_osc_output(vec[0], vec[1], port, dirt);
+                match res {
                     Ok(_) => {
                         state.events.send(AppEvent::Positive("Created device !".to_owned()));
                         state.refresh_devices();
@@ -1,13 +1,48 @@
+use std::{cell::Cell, collections::HashMap};
+
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use ratatui::{buffer::Buffer, layout::{Constraint, Layout, Rect}, style::{Style, Stylize}, widgets::{StatefulWidget, Widget}};
-use sova_core::{scene::script::Script, schedule::{ActionTiming, SchedulerMessage}};
+use ratatui::{buffer::Buffer, layout::{Constraint, Layout, Rect}, style::{Color, Modifier, Style, Stylize}, text::{Line, Span}, widgets::{Paragraph, StatefulWidget, Widget, Wrap}};
+use sova_core::{lang::format, scene::script::Script, schedule::{ActionTiming, SchedulerMessage}, server::client::ClientMessage, util::text_operation::{TextOperation, TextSelection}};
 use tui_textarea::{CursorMove, TextArea};
 
-use crate::{app::AppState, event::AppEvent, popup::PopupValue};
+use crate::{app::AppState, collab::{self, ScriptSession}, event::AppEvent, popup::PopupValue, syntax::{self, TokenKind}};
 
 #[derive(Default)]
 pub struct EditWidget {
-    text_area: TextArea<'static>
+    text_area: TextArea<'static>,
+    /// First visible line and column, kept from one render to the next
+    scroll: Cell<(usize, usize)>,
+    /// Edition shared with the server, once its draft is loaded
+    session: Option<ScriptSession>,
+    /// Selections of the peers editing the same script
+    peer_selections: HashMap<String, TextSelection>,
+}
+
+fn token_style(kind: TokenKind) -> Style {
+    match kind {
+        TokenKind::Keyword => Style::default().magenta().bold(),
+        TokenKind::Function => Style::default().blue(),
+        TokenKind::Parameter => Style::default().light_blue(),
+        TokenKind::Number => Style::default().yellow(),
+        TokenKind::Duration => Style::default().light_yellow(),
+        TokenKind::Note => Style::default().cyan(),
+        TokenKind::Text => Style::default().green(),
+        TokenKind::Comment => Style::default().dark_gray().italic(),
+        TokenKind::Operator => Style::default().light_red(),
+        TokenKind::Delimiter => Style::default().gray(),
+        TokenKind::Name => Style::default(),
+    }
+}
+
+/// Scrolls the least to keep the cursor visible.
+fn next_scroll(top: usize, cursor: usize, len: usize) -> usize {
+    if cursor < top {
+        cursor
+    } else if top + len <= cursor {
+        cursor + 1 - len
+    } else {
+        top
+    }
 }
 
 fn upload_script(state: &mut AppState, script: Script) {
@@ -25,6 +60,14 @@ fn upload_script(state: &mut AppState, script: Script) {
     );
 }
 
+fn send_remote(state: &mut AppState, msg: ClientMessage) {
+    if let Some(server) = state.backend.remote()
+        && let Err(e) = server.send(msg)
+    {
+        state.events.send(AppEvent::Negative(e));
+    }
+}
+
 fn upload_content(state: &mut AppState, content: String) {
     let Some(frame) = state.selected_frame() else {
         return;
@@ -45,13 +88,131 @@ fn upload_lang(state: &mut AppState, lang: String) {
 
 impl EditWidget {
 
-    pub fn open(&mut self, state: &AppState) {
+    pub fn open(&mut self, state: &mut AppState) {
         let Some(frame) = state.selected_frame() else {
             return;
         };
         let content = frame.script().content();
         self.text_area = content.lines().into();
-        self.text_area.set_line_number_style(Style::default().dark_gray());
+        self.scroll.set((0, 0));
+        if state.backend.is_remote() {
+            // Edits are sent once the shared draft is loaded
+            let (line_id, frame_id) = state.selected;
+            if let Some(session) = self.session.take() {
+                send_remote(state, ClientMessage::StoppedEditingFrame(session.line_id, session.frame_id));
+            }
+            self.peer_selections.clear();
+            send_remote(state, ClientMessage::StartedEditingFrame(line_id, frame_id));
+            send_remote(state, ClientMessage::GetScriptDraft(line_id, frame_id));
+        }
+    }
+
+    /// The shared draft of a script, sent by the server when asked for or out of sync.
+    pub fn load_draft(&mut self, state: &mut AppState, line_id: usize, frame_id: usize, revision: u64, content: String) {
+        if state.selected != (line_id, frame_id) {
+            return;
+        }
+        let cursor = self.text_area.cursor();
+        self.set_text(&content, None);
+        self.text_area.move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+        self.session = Some(ScriptSession::new(line_id, frame_id, revision));
+    }
+
+    /// The server applied the last edit sent.
+    pub fn acknowledged(&mut self, state: &mut AppState, line_id: usize, frame_id: usize, revision: u64) {
+        let Some(session) = self.session.as_mut().filter(|s| s.edits(line_id, frame_id)) else {
+            return;
+        };
+        if let Some(msg) = session.acknowledged(revision) {
+            send_remote(state, msg);
+        }
+    }
+
+    /// A peer edited the shared draft.
+    pub fn peer_edit(&mut self, state: &mut AppState, line_id: usize, frame_id: usize, revision: u64, op: TextOperation) {
+        let Some(session) = self.session.as_mut().filter(|s| s.edits(line_id, frame_id)) else {
+            return;
+        };
+        let op = match session.remote_edit(revision, op) {
+            Ok(op) => op,
+            Err(e) => {
+                // The draft is reloaded, local edits not applied yet are lost
+                state.events.send(AppEvent::Negative(format!("Reloading the script: {e}")));
+                self.session = None;
+                send_remote(state, ClientMessage::GetScriptDraft(line_id, frame_id));
+                return;
+            }
+        };
+        let content = self.get_content();
+        let Ok(edited) = op.apply(&content) else {
+            self.session = None;
+            send_remote(state, ClientMessage::GetScriptDraft(line_id, frame_id));
+            return;
+        };
+        let selection = self.selection().transform(&op);
+        self.set_text(&edited, Some(selection));
+        for selection in self.peer_selections.values_mut() {
+            *selection = selection.transform(&op);
+        }
+    }
+
+    /// A peer moved their cursor, or left the script (None).
+    pub fn peer_selection(&mut self, name: String, line_id: usize, frame_id: usize, selection: Option<TextSelection>) {
+        if !self.session.as_ref().is_some_and(|s| s.edits(line_id, frame_id)) {
+            return;
+        }
+        match selection {
+            Some(selection) => self.peer_selections.insert(name, selection),
+            None => self.peer_selections.remove(&name),
+        };
+    }
+
+    /// Replaces the text, placing the cursor and the selection at the given character indices.
+    fn set_text(&mut self, content: &str, selection: Option<TextSelection>) {
+        self.text_area = content.split('\n').collect::<Vec<_>>().into();
+        let Some(selection) = selection else {
+            return;
+        };
+        let jump = |index| {
+            let (row, col) = collab::char_position(content, index);
+            CursorMove::Jump(row as u16, col as u16)
+        };
+        self.text_area.move_cursor(jump(selection.anchor));
+        if selection.anchor != selection.head {
+            self.text_area.start_selection();
+        }
+        self.text_area.move_cursor(jump(selection.head));
+    }
+
+    /// Selection of the text area, in character indices.
+    fn selection(&self) -> TextSelection {
+        let lines = self.text_area.lines();
+        let cursor = self.text_area.cursor();
+        let anchor = match self.text_area.selection_range() {
+            Some((from, to)) if from == cursor => to,
+            Some((from, _)) => from,
+            None => cursor,
+        };
+        TextSelection {
+            anchor: collab::char_index(lines, anchor),
+            head: collab::char_index(lines, cursor),
+        }
+    }
+
+    /// Sends the local edits of the shared draft and the moves of the cursor.
+    fn share_changes(&mut self, state: &mut AppState, content: String, selection: TextSelection) {
+        let op = collab::diff(&content, &self.get_content());
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        let (line_id, frame_id) = (session.line_id, session.frame_id);
+        if let Some(msg) = session.local_edit(op) {
+            send_remote(state, msg);
+        }
+        let new_selection = self.selection();
+        if new_selection != selection {
+            send_remote(state, ClientMessage::SetCursor(line_id, frame_id, Some(new_selection)));
+        }
     }
 
     pub fn get_help() -> &'static str {
@@ -59,14 +220,33 @@ impl EditWidget {
         C-S: Upload \n\
         C-L: Change language \n\
         C-A: Select all \n\
+        C-F: Format \n\
         "
     }
 
-    pub fn process_event(&mut self, state: &mut AppState, mut event: KeyEvent) { 
+    pub fn process_event(&mut self, state: &mut AppState, event: KeyEvent) {
+        let before = self.session.is_some().then(|| (self.get_content(), self.selection()));
+        self.process_key(state, event);
+        if let Some((content, selection)) = before {
+            self.share_changes(state, content, selection);
+        }
+    }
+
+    fn process_key(&mut self, state: &mut AppState, mut event: KeyEvent) {
         match event.code {
             KeyCode::Char('s') if event.modifiers == KeyModifiers::CONTROL => {
-                upload_content(state, self.get_content());
-            } 
+                match &self.session {
+                    Some(session) => {
+                        send_remote(state, ClientMessage::CommitScript(
+                            session.line_id,
+                            session.frame_id,
+                            ActionTiming::Immediate,
+                        ));
+                        state.events.send(AppEvent::Positive("Sent script".to_owned()));
+                    }
+                    None => upload_content(state, self.get_content()),
+                }
+            }
             KeyCode::Char('a') if event.modifiers == KeyModifiers::CONTROL => {
                 self.text_area.select_all();
             }
@@ -74,7 +254,7 @@ impl EditWidget {
                 let Some(frame) = state.selected_frame() else {
                     return;
                 };
-                let langs : Vec<String> = state.languages.languages().map(str::to_owned).collect();
+                let langs : Vec<String> = state.backend.languages();
                 let i = langs.iter().position(|l| l == frame.script().lang()).unwrap_or_default();
                 state.events.send(AppEvent::Popup(
                     "Script language".to_owned(), 
@@ -84,6 +264,9 @@ impl EditWidget {
                         upload_lang(state, x.into());
                     })));
             }
+            KeyCode::Char('f') if event.modifiers == KeyModifiers::CONTROL => {
+                self.format(state);
+            }
             KeyCode::Char('w') if event.modifiers == KeyModifiers::CONTROL => {
                 self.text_area.start_selection();
                 self.text_area.move_cursor(CursorMove::WordForward);
@@ -125,19 +308,118 @@ impl EditWidget {
         }
     }
 
+    /// Formats the text as the server would (Bali is printed in its canonical layout,
+    /// Boinx is reindented), keeping it undoable.
+    fn format(&mut self, state: &mut AppState) {
+        let Some(lang) = state.selected_frame().map(|f| f.script().lang().to_owned()) else {
+            return;
+        };
+        let indent = " ".repeat(self.text_area.tab_length() as usize);
+        let content = self.get_content();
+        let formatted = match format::format_script(&lang, &content, &indent) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                state.events.send(AppEvent::Negative(e));
+                return;
+            }
+        };
+        if formatted.trim_end() != content {
+            self.text_area.select_all();
+            self.text_area.insert_str(formatted.trim_end());
+        }
+        state.events.send(
+            AppEvent::Positive("Script formatted".to_owned())
+        );
+    }
+
     pub fn get_content(&self) -> String {
         self.text_area.lines().join("\n")
     }
 
 }
 
+impl EditWidget {
+    /// Renders the text with the highlighting of its language, the selection, the cursors
+    /// of the peers and the span of the compilation error, if any.
+    fn render_text(&self, area: Rect, buf: &mut Buffer, lang: &str, error_span: Option<((usize, usize), (usize, usize))>) {
+        let lines = self.text_area.lines();
+        let number_width = lines.len().to_string().len() + 2;
+        let text_width = (area.width as usize).saturating_sub(number_width);
+        let cursor = self.text_area.cursor();
+        let (top, left) = self.scroll.get();
+        let top = next_scroll(top, cursor.0, area.height as usize);
+        let left = next_scroll(left, cursor.1, text_width.max(1));
+        self.scroll.set((top, left));
+        let selection = self.text_area.selection_range();
+        let content = self.get_content();
+        let peers: Vec<(usize, usize)> = self
+            .peer_selections
+            .values()
+            .map(|selection| collab::char_position(&content, selection.head))
+            .collect();
+        let within = |range: Option<((usize, usize), (usize, usize))>, pos: (usize, usize)| {
+            range.is_some_and(|(from, to)| from <= pos && pos < to)
+        };
+        for (row, line) in lines.iter().enumerate().skip(top).take(area.height as usize) {
+            let y = area.y + (row - top) as u16;
+            let number = format!("{:>width$} ", row + 1, width = number_width - 1);
+            buf.set_string(area.x, y, number, Style::default().dark_gray());
+            let mut styles = vec![Style::default(); line.len()];
+            for token in syntax::tokenize(lang, line) {
+                styles[token.range.clone()].fill(token_style(token.kind));
+            }
+            let mut spans = Vec::new();
+            let chars = line.char_indices().map(|(i, c)| (i, Some(c))).chain([(line.len(), None)]);
+            for (col, (i, c)) in chars.enumerate().skip(left) {
+                let mut style = styles.get(i).copied().unwrap_or_default();
+                if within(selection, (row, col)) {
+                    style = style.bg(Color::LightBlue);
+                }
+                if within(error_span, (row, col)) {
+                    style = style.add_modifier(Modifier::UNDERLINED).underline_color(Color::Red);
+                }
+                if peers.contains(&(row, col)) {
+                    style = style.bg(Color::Magenta);
+                }
+                if (row, col) == cursor {
+                    style = style.add_modifier(Modifier::REVERSED);
+                } else if c.is_none() && !peers.contains(&(row, col)) {
+                    break;
+                }
+                spans.push(Span::styled(c.unwrap_or(' ').to_string(), style));
+            }
+            let x = area.x + number_width as u16;
+            buf.set_line(x, y, &Line::from(spans), text_width as u16);
+        }
+    }
+}
+
 impl StatefulWidget for &EditWidget {
     type State = AppState;
 
-    fn render(self, area: Rect, buf: &mut Buffer, _state: &mut Self::State) {
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         use Constraint::*;
         let layout = Layout::vertical([Min(0), Length(2)]);
-        let [main_area, _tools_area] = layout.areas(area);
-        self.text_area.render(main_area, buf);
+        let [main_area, tools_area] = layout.areas(area);
+        let Some(script) = state.selected_frame().map(|frame| frame.script()) else {
+            self.render_text(main_area, buf, "", None);
+            return;
+        };
+        // Positions of the error in the uploaded script, which only match an unchanged text
+        let error = script.compilation_state().error();
+        let unchanged = self.text_area.lines().iter().map(String::as_str).eq(script.content().lines());
+        let error_span = error.filter(|error| unchanged && error.to > 0).map(|error| {
+            let from = syntax::position(script.content(), error.from);
+            let to = syntax::position(script.content(), error.to);
+            // A position rather than a span marks the character at it
+            (from, to.max((from.0, from.1 + 1)))
+        });
+        self.render_text(main_area, buf, script.lang(), error_span);
+        if let Some(error) = error {
+            Paragraph::new(error.to_string())
+                .red()
+                .wrap(Wrap { trim: true })
+                .render(tools_area, buf);
+        }
     }
 }
@@ -7,7 +7,7 @@ use ratatui::{
 };
 use sova_core::compiler::CompilationState;
 
-use crate::{app::AppState, page::Page, widgets::{configure_widget::ConfigureWidget, devices_widget::DevicesWidget, edit_widget::EditWidget, scene_widget::SceneWidget, time_widget::TimeWidget}};
+use crate::{app::AppState, page::Page, widgets::{configure_widget::ConfigureWidget, devices_widget::DevicesWidget, edit_widget::EditWidget, scene_widget::SceneWidget, time_widget::TimeWidget, timeline_widget::TimelineWidget, vars_widget::VarsWidget}};
 
 #[derive(Default)]
 pub struct Footer;
@@ -24,7 +24,7 @@ fn format_compilation_state(state: &CompilationState) -> &str {
     match state {
         CompilationState::NotCompiled => "_",
         CompilationState::Compiling => "...",
-        CompilationState::Compiled(_) | CompilationState::Parsed(_) => "✓",
+        CompilationState::Compiled(..) | CompilationState::Parsed(_) => "✓",
         CompilationState::Error(_) => "❌",
     }
 }
@@ -48,7 +48,7 @@ impl StatefulWidget for Footer {
                 Span::from(" "),
                 Span::styled("C", map_style(state, Page::Configure)),
                 Span::from(" "),
-                Span::styled(" ", Style::default()),
+                Span::styled("P", map_style(state, Page::Timeline)),
             ]),
             Line::from(vec![
                 Span::styled("D", map_style(state, Page::Devices)),
@@ -89,6 +89,8 @@ impl StatefulWidget for Footer {
             Page::Devices => DevicesWidget::get_help(),
             Page::Time => TimeWidget::get_help(),
             Page::Configure => ConfigureWidget::get_help(),
+            Page::Vars => VarsWidget::get_help(),
+            Page::Timeline => TimelineWidget::get_help(),
             _ => ""
         };
         Paragraph::new(help).render(middle.inner(Margin {
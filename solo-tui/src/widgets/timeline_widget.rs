@@ -0,0 +1,144 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Paragraph, StatefulWidget, Widget},
+};
+use sova_core::{scene::Line, schedule::EmittedEvent};
+
+use crate::app::AppState;
+
+/// Number of beats shown in the event strips.
+pub const EVENT_WINDOW: f64 = 4.0;
+
+/// Rows used by each line: frames, events and a spacer.
+const LINE_HEIGHT: u16 = 3;
+
+const LABEL_WIDTH: u16 = 12;
+
+#[derive(Default)]
+pub struct TimelineWidget;
+
+impl TimelineWidget {
+    pub fn get_help() -> &'static str {
+        "\
+        Frames of each line, with the playhead  \n\
+        Events emitted in the last beats        \n\
+        Follows the line selected in the scene  \n\
+        "
+    }
+
+    /// Draws the frames of a line proportionally to their durations, and the playhead.
+    fn render_frames(line: &Line, position: Option<(usize, usize, f64)>, area: Rect, buf: &mut Buffer) {
+        let start = line.get_effective_start_frame();
+        let frames = line.get_effective_frames();
+        let total: f64 = frames.iter().map(|f| f.effective_duration()).sum();
+        if total <= 0.0 {
+            return;
+        }
+        let width = area.width as f64;
+        let x_at = |beats: f64| area.x + ((beats / total * width) as u16).min(area.width - 1);
+        let mut elapsed = 0.0;
+        let mut playhead = None;
+        for (i, frame) in frames.iter().enumerate() {
+            let frame_id = start + i;
+            let (x0, x1) = (x_at(elapsed), x_at(elapsed + frame.effective_duration()));
+            let current = position.is_some_and(|(f, _, _)| f == frame_id);
+            let color = if current {
+                Color::Green
+            } else if !frame.enabled {
+                Color::DarkGray
+            } else if i % 2 == 0 {
+                Color::Blue
+            } else {
+                Color::Cyan
+            };
+            let style = Style::default().bg(color).fg(Color::Black);
+            buf.set_style(Rect::new(x0, area.y, (x1 - x0).max(1), 1), style);
+            buf.set_stringn(x0, area.y, frame_id.to_string(), (x1 - x0) as usize, style);
+            if let Some((_, repetition, progress)) = position.filter(|_| current) {
+                playhead = Some(elapsed + frame.duration * repetition as f64 + progress);
+            }
+            elapsed += frame.effective_duration();
+        }
+        if let Some(beats) = playhead {
+            let x = x_at(beats);
+            buf[(x, area.y)].set_char('┃').set_fg(Color::Yellow);
+        }
+    }
+
+    /// Draws the events of a line emitted in the last `EVENT_WINDOW` beats, lighting up
+    /// those still sounding.
+    fn render_events<'a>(events: impl Iterator<Item = &'a EmittedEvent>, beat: f64, area: Rect, buf: &mut Buffer) {
+        let window_start = beat - EVENT_WINDOW;
+        let width = area.width as f64;
+        let x_at = |b: f64| {
+            let x = ((b - window_start) / EVENT_WINDOW * width).max(0.0) as u16;
+            area.x + x.min(area.width - 1)
+        };
+        for event in events.filter(|e| e.beat + e.duration >= window_start) {
+            let sounding = event.beat <= beat && beat < event.beat + event.duration;
+            let color = match (event.note.is_some(), sounding) {
+                (true, true) => Color::LightGreen,
+                (false, true) => Color::LightMagenta,
+                (true, false) => Color::Green,
+                (false, false) => Color::Magenta,
+            };
+            let x = x_at(event.beat);
+            let end = x_at((event.beat + event.duration).min(beat));
+            for x in x + 1..=end {
+                buf[(x, area.y)].set_char('━').set_fg(color);
+            }
+            let symbol = if event.note.is_some() { '●' } else { '◆' };
+            buf[(x, area.y)].set_char(symbol).set_fg(color);
+        }
+    }
+}
+
+impl StatefulWidget for TimelineWidget {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        use Constraint::*;
+        if state.scene_image.is_empty() {
+            Paragraph::new("No line").centered().render(area, buf);
+            return;
+        }
+        let [labels_area, timeline_area] = Layout::horizontal([Length(LABEL_WIDTH), Min(0)]).areas(area);
+        if timeline_area.width == 0 {
+            return;
+        }
+        let beat = state.clock.beat();
+        let playing = state.playing.is_playing();
+        let visible = (area.height / LINE_HEIGHT).max(1) as usize;
+        let first = state.selected.0.saturating_sub(visible - 1);
+        let lines = state.scene_image.lines.iter().enumerate().skip(first).take(visible);
+        for (row, (line_id, line)) in lines.enumerate() {
+            let y = area.y + row as u16 * LINE_HEIGHT;
+            if y + 1 >= area.bottom() {
+                break;
+            }
+            let label_style = if line_id == state.selected.0 {
+                Style::default().bold()
+            } else {
+                Style::default()
+            };
+            buf.set_stringn(labels_area.x, y, format!("Line {line_id}"), LABEL_WIDTH as usize - 1, label_style);
+            // Progress in the current frame, from the beat at which its position was received
+            let position = state.positions.get(line_id).filter(|_| playing).and_then(|&(frame_id, repetition)| {
+                let duration = line.frame(frame_id)?.duration;
+                let since = state.position_beats.get(line_id).copied().unwrap_or(beat);
+                let progress = ((beat - since) * line.speed_factor).rem_euclid(duration.max(f64::EPSILON));
+                Some((frame_id, repetition, progress))
+            });
+            let frames_area = Rect::new(timeline_area.x, y, timeline_area.width, 1);
+            Self::render_frames(line, position, frames_area, buf);
+            let line_events = state.emitted_events.iter().filter(|e| e.line_id == line_id);
+            if let Some(last) = line_events.clone().next_back() {
+                buf.set_stringn(labels_area.x, y + 1, last.kind.to_string(), LABEL_WIDTH as usize - 1, Style::default().dark_gray());
+            }
+            let events_area = Rect::new(timeline_area.x, y + 1, timeline_area.width, 1);
+            Self::render_events(line_events, beat, events_area, buf);
+        }
+    }
+}
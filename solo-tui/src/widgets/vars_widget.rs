@@ -0,0 +1,169 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{buffer::Buffer, layout::{Constraint, Rect}, style::{Color, Style, Stylize}, widgets::{Cell, HighlightSpacing, Row, StatefulWidget, Table, TableState}};
+use sova_core::{schedule::{ActionTiming, SchedulerMessage}, vm::variable::{VariableScope, VariableValue}};
+
+use crate::{app::AppState, event::AppEvent, popup::PopupValue};
+
+#[derive(Debug, Default)]
+pub struct VarsWidget {
+    state: TableState,
+}
+
+impl VarsWidget {
+
+    /// Lists the displayed variables : globals first, then the variables of the selected line and frame.
+    fn variables(state: &AppState) -> Vec<(VariableScope, String, VariableValue)> {
+        let (line_index, frame_index) = state.selected;
+        let scene = &state.scene_image;
+        let stores = [
+            (VariableScope::Global, Some(&scene.vars)),
+            (VariableScope::Line(line_index), scene.line(line_index).map(|l| &l.vars)),
+            (VariableScope::Frame(line_index, frame_index), state.selected_frame().map(|f| &f.vars)),
+        ];
+        let mut res = Vec::new();
+        for (scope, store) in stores {
+            let Some(store) = store else {
+                continue;
+            };
+            let mut vars: Vec<_> = store.visible_vars()
+                .map(|(k, v)| (scope, k.clone(), v.clone()))
+                .collect();
+            vars.sort_by(|a, b| a.1.cmp(&b.1));
+            res.append(&mut vars);
+        }
+        res
+    }
+
+    pub fn process_event(&mut self, state: &mut AppState, event: KeyEvent) {
+        match event.code {
+            KeyCode::Up => self.state.select_previous(),
+            KeyCode::Down => self.state.select_next(),
+            KeyCode::Enter => {
+                let Some(selected) = self.state.selected() else {
+                    return;
+                };
+                let vars = Self::variables(state);
+                let Some((scope, name, value)) = vars.get(selected).cloned() else {
+                    return;
+                };
+                state.events.send(AppEvent::Popup(
+                    "Set variable".to_owned(),
+                    format!("New value of {name}"),
+                    PopupValue::Text(format_value(&value)),
+                    Box::new(move |state, x| {
+                        let value = parse_value(&String::from(x));
+                        state.events.send(
+                            SchedulerMessage::SetVariable(scope, name, value, ActionTiming::Immediate).into()
+                        );
+                    })
+                ));
+            }
+            KeyCode::Char('g') => Self::create_variable(state, VariableScope::Global),
+            KeyCode::Char('l') => Self::create_variable(state, VariableScope::Line(state.selected.0)),
+            _ => ()
+        }
+    }
+
+    pub fn get_help() -> &'static str {
+        "\
+        Enter: Edit value    G: New global\n\
+                             L: New line variable\n\
+        "
+    }
+
+    pub fn create_variable(state: &mut AppState, scope: VariableScope) {
+        let ev = AppEvent::Popup(
+            "New variable".to_owned(),
+            "Declare a new variable (name=value)".to_owned(),
+            PopupValue::Text(String::default()),
+            Box::new(move |state, x| {
+                let input = String::from(x);
+                let Some((name, value)) = input.split_once('=') else {
+                    state.events.send(AppEvent::Negative("Wrong variable format !".to_owned()));
+                    return;
+                };
+                let name = name.trim();
+                if name.is_empty() {
+                    state.events.send(AppEvent::Negative("Empty variable name !".to_owned()));
+                    return;
+                }
+                state.events.send(SchedulerMessage::SetVariable(
+                    scope, name.to_owned(), parse_value(value), ActionTiming::Immediate
+                ).into());
+            })
+        );
+        state.events.send(ev);
+    }
+
+}
+
+/// Parses user input into the most specific variable value it represents.
+fn parse_value(input: &str) -> VariableValue {
+    let input = input.trim();
+    if let Ok(i) = input.parse::<i64>() {
+        VariableValue::Integer(i)
+    } else if let Ok(f) = input.parse::<f64>() {
+        VariableValue::Float(f)
+    } else if let Ok(b) = input.parse::<bool>() {
+        VariableValue::Bool(b)
+    } else {
+        VariableValue::Str(input.to_owned())
+    }
+}
+
+fn format_value(value: &VariableValue) -> String {
+    match value {
+        VariableValue::Integer(i) => i.to_string(),
+        VariableValue::Float(f) => f.to_string(),
+        VariableValue::Bool(b) => b.to_string(),
+        VariableValue::Str(s) => s.clone(),
+        other => format!("{other:?}"),
+    }
+}
+
+impl StatefulWidget for &mut VarsWidget {
+    type State = AppState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let vars = VarsWidget::variables(state);
+        if vars.is_empty() {
+            self.state.select(None);
+        } else if self.state.selected().is_none_or(|i| i >= vars.len()) {
+            self.state.select(Some(0));
+        }
+        let header_style = Style::default()
+            .fg(Color::White)
+            .bold();
+        let selected_row_style = Style::default()
+            .fg(Color::White)
+            .bg(Color::LightMagenta)
+            .bold();
+        let header = [ "Scope", "Name", "Value"]
+            .into_iter()
+            .map(Cell::from)
+            .collect::<Row>()
+            .style(header_style)
+            .height(1);
+        let rows : Vec<Row> = vars.iter().map(|(scope, name, value)| {
+            let scope = Cell::from(match scope {
+                VariableScope::Global => "global".to_owned(),
+                VariableScope::Line(i) => format!("line {i}"),
+                VariableScope::Frame(l, f) => format!("frame {l}:{f}"),
+            });
+            Row::new([scope, Cell::from(name.clone()), Cell::from(format_value(value))])
+        }).collect();
+        let t = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(16),
+                Constraint::Min(0),
+            ],
+        )
+            .header(header)
+            .row_highlight_style(selected_row_style)
+            .highlight_symbol(" > ")
+            .highlight_spacing(HighlightSpacing::Always);
+        t.render(area, buf, &mut self.state);
+    }
+}